@@ -0,0 +1,968 @@
+//! Bot navigation files (`maps/*.aas`) in the EAAS version 5 format of bspc.
+//!
+//! Spans of diagnostics and records are byte ranges in the file.
+
+use std::fmt;
+
+use crate::{
+    binary::{index, int, short, span, vector},
+    bsp::{Bsp, Lump},
+    diagnostic::Diagnostic,
+    span::RawSpan,
+};
+
+/// Magic at the start of the file.
+pub const AAS_IDENT: &[u8; 4] = b"EAAS";
+/// Version written by bspc, with an obfuscated header.
+pub const AAS_VERSION: i32 = 5;
+/// Version with a plain header, still read by the engine.
+pub const AAS_VERSION_OLD: i32 = 4;
+/// Number of lumps in the directory.
+pub const AAS_LUMPS: usize = 14;
+
+/// `AREA_GROUNDED`, players can stand in the area.
+pub const AREA_GROUNDED: i32 = 1;
+/// `AREA_LADDER`
+pub const AREA_LADDER: i32 = 2;
+/// `AREA_LIQUID`, water, slime or lava.
+pub const AREA_LIQUID: i32 = 4;
+/// `AREA_DISABLED`
+pub const AREA_DISABLED: i32 = 8;
+/// `AREA_BRIDGE`, on top of a mover.
+pub const AREA_BRIDGE: i32 = 16;
+
+/// Mask of the travel type, the other bits are `TRAVELFLAG_*`.
+const TRAVELTYPE_MASK: i32 = 0x00ff_ffff;
+/// Size of the ident, version, BSP checksum and lump directory.
+const HEADER_SIZE: usize = 12 + AAS_LUMPS * 8;
+/// Slack for rounding when checking area bounds against the world's.
+const BOUNDS_EPSILON: f32 = 1.0;
+
+/// Lumps in directory order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LumpKind {
+    BoundingBoxes,
+    Vertexes,
+    Planes,
+    Edges,
+    EdgeIndex,
+    Faces,
+    FaceIndex,
+    Areas,
+    AreaSettings,
+    Reachabilities,
+    Nodes,
+    Portals,
+    PortalIndex,
+    Clusters,
+}
+
+impl LumpKind {
+    /// Lumps in directory order.
+    pub const ALL: [Self; AAS_LUMPS] = [
+        Self::BoundingBoxes,
+        Self::Vertexes,
+        Self::Planes,
+        Self::Edges,
+        Self::EdgeIndex,
+        Self::Faces,
+        Self::FaceIndex,
+        Self::Areas,
+        Self::AreaSettings,
+        Self::Reachabilities,
+        Self::Nodes,
+        Self::Portals,
+        Self::PortalIndex,
+        Self::Clusters,
+    ];
+
+    /// Get the size of a record.
+    pub const fn record_size(self) -> usize {
+        match self {
+            Self::BoundingBoxes => 32,
+            Self::Vertexes | Self::Nodes => 12,
+            Self::Planes | Self::Portals => 20,
+            Self::Edges => 8,
+            Self::EdgeIndex | Self::FaceIndex | Self::PortalIndex => 4,
+            Self::Faces => 24,
+            Self::Areas => 48,
+            Self::AreaSettings => 28,
+            Self::Reachabilities => 44,
+            Self::Clusters => 16,
+        }
+    }
+}
+
+impl fmt::Display for LumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// How a bot gets from one area to another, `TRAVEL_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TravelType {
+    Invalid = 1,
+    Walk,
+    Crouch,
+    BarrierJump,
+    Jump,
+    Ladder,
+    WalkOffLedge,
+    Swim,
+    WaterJump,
+    Teleport,
+    Elevator,
+    RocketJump,
+    BfgJump,
+    GrappleHook,
+    DoubleJump,
+    RampJump,
+    StrafeJump,
+    JumpPad,
+    FuncBob,
+}
+
+impl TravelType {
+    /// Travel types by value.
+    pub const ALL: [Self; 19] = [
+        Self::Invalid,
+        Self::Walk,
+        Self::Crouch,
+        Self::BarrierJump,
+        Self::Jump,
+        Self::Ladder,
+        Self::WalkOffLedge,
+        Self::Swim,
+        Self::WaterJump,
+        Self::Teleport,
+        Self::Elevator,
+        Self::RocketJump,
+        Self::BfgJump,
+        Self::GrappleHook,
+        Self::DoubleJump,
+        Self::RampJump,
+        Self::StrafeJump,
+        Self::JumpPad,
+        Self::FuncBob,
+    ];
+
+    /// Get the travel type of a reachability's `traveltype`, ignoring the travel flags.
+    pub fn from_raw(value: i32) -> Option<Self> {
+        let value = value & TRAVELTYPE_MASK;
+        Self::ALL.iter().copied().find(|kind| *kind as i32 == value)
+    }
+}
+
+impl fmt::Display for TravelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Read and validated bot navigation file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aas {
+    version: i32,
+    bsp_checksum: i32,
+    lumps: Vec<Lump>,
+    areas: Vec<Area>,
+    area_settings: Vec<AreaSettings>,
+    reachabilities: Vec<Reachability>,
+    portals: Vec<Portal>,
+    clusters: Vec<Cluster>,
+}
+
+impl Aas {
+    /// Reads and validates a navigation file, nothing but the diagnostics if the header is invalid.
+    pub fn parse(data: &[u8]) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut aas = Self::default();
+
+        if data.len() < HEADER_SIZE {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "file of {} bytes is too short for the {} bytes header",
+                    data.len(),
+                    HEADER_SIZE
+                ),
+                span(0, data.len()),
+            ));
+            return (aas, diagnostics);
+        }
+        if &data[..4] != AAS_IDENT {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "expect ident 'EAAS', found '{}'",
+                    String::from_utf8_lossy(&data[..4])
+                ),
+                span(0, 4),
+            ));
+            return (aas, diagnostics);
+        }
+        aas.version = int(data, 4);
+        if aas.version != AAS_VERSION && aas.version != AAS_VERSION_OLD {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "unsupported version {}, expect {} or {}",
+                    aas.version, AAS_VERSION, AAS_VERSION_OLD
+                ),
+                span(4, 8),
+            ));
+            return (aas, diagnostics);
+        }
+
+        // `AAS_DData` of everything after the version
+        let mut header = data[..HEADER_SIZE].to_vec();
+        if aas.version == AAS_VERSION {
+            for (number, byte) in header[8..].iter_mut().enumerate() {
+                *byte ^= (number as u8).wrapping_mul(119);
+            }
+        }
+        aas.bsp_checksum = int(&header, 8);
+
+        for (number, kind) in LumpKind::ALL.iter().enumerate() {
+            let entry = 12 + number * 8;
+            let mut lump = Lump::new(
+                index(&header, entry) as u32,
+                index(&header, entry + 4) as u32,
+                span(entry, entry + 8),
+            );
+            let end = lump.offset() as usize + lump.length() as usize;
+            if end > data.len() {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "{} lump {}..{} is outside of the file's {} bytes",
+                        kind,
+                        lump.offset(),
+                        end,
+                        data.len()
+                    ),
+                    lump.span(),
+                ));
+                lump = Lump::new(0, 0, lump.span());
+            } else if !(lump.length() as usize).is_multiple_of(kind.record_size()) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "{} lump length {} isn't a multiple of {}",
+                        kind,
+                        lump.length(),
+                        kind.record_size()
+                    ),
+                    lump.span(),
+                ));
+            }
+            aas.lumps.push(lump);
+        }
+
+        aas.areas = aas
+            .records(data, LumpKind::Areas)
+            .map(|(span, record)| Area {
+                number: int(record, 0),
+                face_count: index(record, 4),
+                first_face: index(record, 8),
+                mins: vector(record, 12),
+                maxs: vector(record, 24),
+                center: vector(record, 36),
+                span,
+            })
+            .collect();
+        aas.area_settings = aas
+            .records(data, LumpKind::AreaSettings)
+            .map(|(span, record)| AreaSettings {
+                contents: int(record, 0),
+                flags: int(record, 4),
+                presence_type: int(record, 8),
+                cluster: int(record, 12),
+                cluster_area: int(record, 16),
+                reachability_count: index(record, 20),
+                first_reachability: index(record, 24),
+                span,
+            })
+            .collect();
+        aas.reachabilities = aas
+            .records(data, LumpKind::Reachabilities)
+            .map(|(span, record)| Reachability {
+                area: index(record, 0),
+                face: int(record, 4),
+                edge: int(record, 8),
+                start: vector(record, 12),
+                end: vector(record, 24),
+                travel_type: int(record, 36),
+                travel_time: short(record, 40) as u16,
+                span,
+            })
+            .collect();
+        aas.portals = aas
+            .records(data, LumpKind::Portals)
+            .map(|(span, record)| Portal {
+                area: index(record, 0),
+                front_cluster: index(record, 4),
+                back_cluster: index(record, 8),
+                span,
+            })
+            .collect();
+        aas.clusters = aas
+            .records(data, LumpKind::Clusters)
+            .map(|(span, record)| Cluster {
+                area_count: index(record, 0),
+                reachability_area_count: index(record, 4),
+                portal_count: index(record, 8),
+                first_portal: index(record, 12),
+                span,
+            })
+            .collect();
+
+        aas.check_indexes(&mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (aas, diagnostics)
+    }
+
+    fn records<'a>(
+        &self,
+        data: &'a [u8],
+        kind: LumpKind,
+    ) -> impl Iterator<Item = (RawSpan, &'a [u8])> {
+        let lump = self.lump(kind);
+        let size = kind.record_size();
+        data[std::ops::Range::from(lump.data_span())]
+            .chunks_exact(size)
+            .enumerate()
+            .map(move |(index, record)| {
+                let start = lump.offset() as usize + index * size;
+                (span(start, start + size), record)
+            })
+    }
+
+    fn check_indexes(&self, diagnostics: &mut Vec<Diagnostic>) {
+        if self.area_settings.len() != self.areas.len() {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "{} area settings for {} areas",
+                    self.area_settings.len(),
+                    self.areas.len()
+                ),
+                self.lump(LumpKind::AreaSettings).span(),
+            ));
+        }
+
+        // `count` is `None` for a single index
+        let mut check = |what: &str, index: usize, count: Option<usize>, len, span| {
+            let end = index.checked_add(count.unwrap_or(1));
+            if end.is_none_or(|end| end > len) {
+                let range = match count {
+                    Some(count) => format!("{}..{}", index, index.saturating_add(count)),
+                    None => index.to_string(),
+                };
+                diagnostics.push(Diagnostic::error(
+                    format!("{} {} out of {}", what, range, len),
+                    span,
+                ));
+            }
+        };
+        let face_indexes = self.count(LumpKind::FaceIndex);
+        for area in &self.areas {
+            let faces = area.faces();
+            check(
+                "face indexes",
+                faces.start,
+                Some(faces.len()),
+                face_indexes,
+                area.span,
+            );
+        }
+        for settings in &self.area_settings {
+            check(
+                "reachabilities",
+                settings.first_reachability,
+                Some(settings.reachability_count),
+                self.reachabilities.len(),
+                settings.span,
+            );
+            // Negative for portal areas, the portal's number
+            let cluster = settings.cluster;
+            if cluster >= 0 {
+                check(
+                    "cluster",
+                    cluster as usize,
+                    None,
+                    self.clusters.len(),
+                    settings.span,
+                );
+            } else {
+                check(
+                    "portal",
+                    cluster.unsigned_abs() as usize,
+                    None,
+                    self.portals.len(),
+                    settings.span,
+                );
+            }
+        }
+        for reachability in &self.reachabilities {
+            check(
+                "area",
+                reachability.area,
+                None,
+                self.areas.len(),
+                reachability.span,
+            );
+        }
+        for portal in &self.portals {
+            check("area", portal.area, None, self.areas.len(), portal.span);
+            check(
+                "cluster",
+                portal.front_cluster,
+                None,
+                self.clusters.len(),
+                portal.span,
+            );
+            check(
+                "cluster",
+                portal.back_cluster,
+                None,
+                self.clusters.len(),
+                portal.span,
+            );
+        }
+        let portal_indexes = self.count(LumpKind::PortalIndex);
+        for cluster in &self.clusters {
+            check(
+                "portal indexes",
+                cluster.first_portal,
+                Some(cluster.portal_count),
+                portal_indexes,
+                cluster.span,
+            );
+        }
+    }
+
+    /// Checks that the file was compiled from the map, like the engine does when loading it.
+    pub fn check_bsp(&self, bsp: &Bsp) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.bsp_checksum != bsp.checksum() {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "out of date, BSP checksum {} isn't the map's {}",
+                    self.bsp_checksum,
+                    bsp.checksum()
+                ),
+                span(8, 12),
+            ));
+        }
+
+        // Areas are the empty space a player's origin can be in, so all of them are inside of
+        // the world's bounds; area 0 is the unused dummy
+        if let Some(world) = bsp.models().first() {
+            let outside = self
+                .areas
+                .iter()
+                .skip(1)
+                .filter(|area| {
+                    (0..3).any(|axis| {
+                        area.mins[axis] < world.mins()[axis] - BOUNDS_EPSILON
+                            || area.maxs[axis] > world.maxs()[axis] + BOUNDS_EPSILON
+                    })
+                })
+                .count();
+            if outside > 0 {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{} areas are outside of the world's bounds", outside),
+                    self.lump(LumpKind::Areas).span(),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    /// Get the format version, [`AAS_VERSION`] or [`AAS_VERSION_OLD`].
+    pub const fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Get the checksum of the map the file was compiled from, see [`Bsp::checksum`].
+    pub const fn bsp_checksum(&self) -> i32 {
+        self.bsp_checksum
+    }
+
+    /// Get the directory entry of the lump.
+    pub fn lump(&self, kind: LumpKind) -> Lump {
+        self.lumps.get(kind as usize).copied().unwrap_or_default()
+    }
+
+    /// Get the number of records in the lump.
+    pub fn count(&self, kind: LumpKind) -> usize {
+        self.lump(kind).length() as usize / kind.record_size()
+    }
+
+    /// Get the areas, the first being an unused dummy.
+    pub fn areas(&self) -> &[Area] {
+        &self.areas
+    }
+
+    /// Get the settings of the areas by area number.
+    pub fn area_settings(&self) -> &[AreaSettings] {
+        &self.area_settings
+    }
+
+    /// Get the reachabilities, the ways from an area to its neighbors.
+    pub fn reachabilities(&self) -> &[Reachability] {
+        &self.reachabilities
+    }
+
+    /// Get the portals, the areas connecting clusters, the first being an unused dummy.
+    pub fn portals(&self) -> &[Portal] {
+        &self.portals
+    }
+
+    /// Get the clusters of areas routed as a whole, the first being an unused dummy.
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// Get the numbers of areas, clusters, portals and reachabilities, without the dummies.
+    pub fn summary(&self) -> Summary {
+        let areas_with = |flag| {
+            self.area_settings
+                .iter()
+                .skip(1)
+                .filter(|settings| settings.flags & flag != 0)
+                .count()
+        };
+        let mut reachabilities: Vec<(TravelType, usize)> = Vec::new();
+        for kind in self
+            .reachabilities
+            .iter()
+            .filter_map(Reachability::travel_type)
+        {
+            match reachabilities.iter_mut().find(|(other, _)| *other == kind) {
+                Some((_, count)) => *count += 1,
+                None => reachabilities.push((kind, 1)),
+            }
+        }
+        reachabilities.sort();
+        Summary {
+            areas: self.areas.len().saturating_sub(1),
+            grounded_areas: areas_with(AREA_GROUNDED),
+            ladder_areas: areas_with(AREA_LADDER),
+            liquid_areas: areas_with(AREA_LIQUID),
+            clusters: self.clusters.len().saturating_sub(1),
+            portals: self.portals.len().saturating_sub(1),
+            reachabilities,
+        }
+    }
+}
+
+/// `aas_area_t`, a convex region of space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Area {
+    number: i32,
+    face_count: usize,
+    first_face: usize,
+    mins: [f32; 3],
+    maxs: [f32; 3],
+    center: [f32; 3],
+    span: RawSpan,
+}
+
+impl Area {
+    /// Get the area's number.
+    pub const fn number(&self) -> i32 {
+        self.number
+    }
+
+    /// Get the range of the area's faces in the face index lump.
+    pub const fn faces(&self) -> std::ops::Range<usize> {
+        self.first_face..self.first_face.saturating_add(self.face_count)
+    }
+
+    /// Get the minimums of the bounding box.
+    pub const fn mins(&self) -> [f32; 3] {
+        self.mins
+    }
+
+    /// Get the maximums of the bounding box.
+    pub const fn maxs(&self) -> [f32; 3] {
+        self.maxs
+    }
+
+    /// Get the center.
+    pub const fn center(&self) -> [f32; 3] {
+        self.center
+    }
+
+    /// Get the span of the record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `aas_areasettings_t`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaSettings {
+    contents: i32,
+    flags: i32,
+    presence_type: i32,
+    cluster: i32,
+    cluster_area: i32,
+    reachability_count: usize,
+    first_reachability: usize,
+    span: RawSpan,
+}
+
+impl AreaSettings {
+    /// Get the `AREACONTENTS_*` flags.
+    pub const fn contents(&self) -> i32 {
+        self.contents
+    }
+
+    /// Get the `AREA_*` flags like [`AREA_GROUNDED`].
+    pub const fn flags(&self) -> i32 {
+        self.flags
+    }
+
+    /// Get the `PRESENCE_*` flags, whether players fit standing or crouched.
+    pub const fn presence_type(&self) -> i32 {
+        self.presence_type
+    }
+
+    /// Get the area's cluster, or the negated number of its portal.
+    pub const fn cluster(&self) -> i32 {
+        self.cluster
+    }
+
+    /// Get the area's number in its cluster.
+    pub const fn cluster_area(&self) -> i32 {
+        self.cluster_area
+    }
+
+    /// Get the range of the reachabilities leaving the area.
+    pub const fn reachabilities(&self) -> std::ops::Range<usize> {
+        self.first_reachability
+            ..self
+                .first_reachability
+                .saturating_add(self.reachability_count)
+    }
+
+    /// Get the span of the record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `aas_reachability_t`, a way to reach an area.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reachability {
+    area: usize,
+    face: i32,
+    edge: i32,
+    start: [f32; 3],
+    end: [f32; 3],
+    travel_type: i32,
+    travel_time: u16,
+    span: RawSpan,
+}
+
+impl Reachability {
+    /// Get the number of the area reached.
+    pub const fn area(&self) -> usize {
+        self.area
+    }
+
+    /// Get the number of the face crossed, if any.
+    pub const fn face(&self) -> i32 {
+        self.face
+    }
+
+    /// Get the number of the edge crossed, if any.
+    pub const fn edge(&self) -> i32 {
+        self.edge
+    }
+
+    /// Get the start point in the area left.
+    pub const fn start(&self) -> [f32; 3] {
+        self.start
+    }
+
+    /// Get the end point in the area reached.
+    pub const fn end(&self) -> [f32; 3] {
+        self.end
+    }
+
+    /// Get the travel type, `None` if it is unknown.
+    pub fn travel_type(&self) -> Option<TravelType> {
+        TravelType::from_raw(self.travel_type)
+    }
+
+    /// Get the `traveltype` with the `TRAVELFLAG_*` flags.
+    pub const fn raw_travel_type(&self) -> i32 {
+        self.travel_type
+    }
+
+    /// Get the travel time in hundredths of a second.
+    pub const fn travel_time(&self) -> u16 {
+        self.travel_time
+    }
+
+    /// Get the span of the record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `aas_portal_t`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Portal {
+    area: usize,
+    front_cluster: usize,
+    back_cluster: usize,
+    span: RawSpan,
+}
+
+impl Portal {
+    /// Get the number of the portal's area.
+    pub const fn area(&self) -> usize {
+        self.area
+    }
+
+    /// Get the clusters in front of and behind the portal.
+    pub const fn clusters(&self) -> [usize; 2] {
+        [self.front_cluster, self.back_cluster]
+    }
+
+    /// Get the span of the record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `aas_cluster_t`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    area_count: usize,
+    reachability_area_count: usize,
+    portal_count: usize,
+    first_portal: usize,
+    span: RawSpan,
+}
+
+impl Cluster {
+    /// Get the number of areas, including the portals.
+    pub const fn area_count(&self) -> usize {
+        self.area_count
+    }
+
+    /// Get the number of areas with reachabilities.
+    pub const fn reachability_area_count(&self) -> usize {
+        self.reachability_area_count
+    }
+
+    /// Get the range of the cluster's portals in the portal index lump.
+    pub const fn portals(&self) -> std::ops::Range<usize> {
+        self.first_portal..self.first_portal.saturating_add(self.portal_count)
+    }
+
+    /// Get the span of the record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Numbers of a navigation file, see [`Aas::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub areas: usize,
+    pub grounded_areas: usize,
+    pub ladder_areas: usize,
+    pub liquid_areas: usize,
+    pub clusters: usize,
+    pub portals: usize,
+    /// Reachabilities by travel type, in [`TravelType`] order.
+    pub reachabilities: Vec<(TravelType, usize)>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} areas, {} grounded, {} ladder, {} liquid",
+            self.areas, self.grounded_areas, self.ladder_areas, self.liquid_areas
+        )?;
+        writeln!(f, "{} clusters, {} portals", self.clusters, self.portals)?;
+        let total: usize = self.reachabilities.iter().map(|(_, count)| count).sum();
+        write!(f, "{} reachabilities", total)?;
+        for (index, (kind, count)) in self.reachabilities.iter().enumerate() {
+            let separator = if index == 0 { ": " } else { ", " };
+            write!(f, "{}{} {}", separator, count, kind)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        binary::{floats, ints},
+        bsp,
+    };
+
+    use super::*;
+
+    /// Writes an obfuscated version 5 file with the lumps in directory order.
+    fn write(checksum: i32, lumps: &[(LumpKind, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(AAS_IDENT);
+        data.extend_from_slice(&AAS_VERSION.to_le_bytes());
+        data.extend_from_slice(&checksum.to_le_bytes());
+        data.resize(HEADER_SIZE, 0);
+        for (kind, lump) in lumps {
+            let entry = 12 + *kind as usize * 8;
+            let offset = data.len() as u32;
+            data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+            data[entry + 4..entry + 8].copy_from_slice(&(lump.len() as u32).to_le_bytes());
+            data.extend_from_slice(lump);
+        }
+        for (number, byte) in data[8..HEADER_SIZE].iter_mut().enumerate() {
+            *byte ^= (number as u8).wrapping_mul(119);
+        }
+        data
+    }
+
+    fn area(number: i32, mins: [f32; 3], maxs: [f32; 3]) -> Vec<u8> {
+        let mut record = ints(&[number, 0, 0]);
+        record.extend(floats(&mins));
+        record.extend(floats(&maxs));
+        record.extend(floats(&[0.0; 3]));
+        record
+    }
+
+    fn reachability(area: i32, travel_type: i32) -> Vec<u8> {
+        let mut record = ints(&[area, 0, 0]);
+        record.extend(floats(&[0.0; 6]));
+        record.extend(ints(&[travel_type, 100]));
+        record
+    }
+
+    /// Writes a map with only the world model.
+    fn write_bsp() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(bsp::BSP_IDENT);
+        data.extend_from_slice(&bsp::BSP_VERSION.to_le_bytes());
+        data.resize(8 + bsp::HEADER_LUMPS * 8, 0);
+        let entry = 8 + bsp::LumpKind::Models as usize * 8;
+        let offset = data.len() as u32;
+        data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+        data[entry + 4..entry + 8].copy_from_slice(&40u32.to_le_bytes());
+        data.extend(floats(&[-256.0, -256.0, -64.0, 256.0, 256.0, 64.0]));
+        data.extend(ints(&[0; 4]));
+        data
+    }
+
+    /// Writes two areas of a cluster, reachable from each other.
+    fn write_areas(checksum: i32) -> Vec<u8> {
+        let mut areas = area(0, [0.0; 3], [0.0; 3]);
+        areas.extend(area(1, [-128.0, -128.0, 0.0], [0.0, 128.0, 32.0]));
+        areas.extend(area(2, [0.0, -128.0, 0.0], [128.0, 128.0, 32.0]));
+        let mut settings = ints(&[0; 7]);
+        settings.extend(ints(&[1, AREA_GROUNDED, 2, 1, 0, 1, 1]));
+        settings.extend(ints(&[1, AREA_GROUNDED | AREA_LIQUID, 2, 1, 1, 2, 2]));
+        let mut reachabilities = reachability(0, 0);
+        reachabilities.extend(reachability(2, TravelType::Walk as i32));
+        reachabilities.extend(reachability(1, TravelType::Jump as i32 | 0x0100_0000));
+        reachabilities.extend(reachability(1, TravelType::Walk as i32));
+        write(
+            checksum,
+            &[
+                (LumpKind::Areas, areas),
+                (LumpKind::AreaSettings, settings),
+                (LumpKind::Reachabilities, reachabilities),
+                (LumpKind::Portals, ints(&[0; 5])),
+                (LumpKind::Clusters, ints(&[0, 0, 0, 0, 2, 2, 0, 0])),
+            ],
+        )
+    }
+
+    #[test]
+    fn read() {
+        let (bsp, _) = Bsp::parse(&write_bsp());
+        let (aas, diagnostics) = Aas::parse(&write_areas(bsp.checksum()));
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(aas.version(), AAS_VERSION);
+        assert_eq!(aas.bsp_checksum(), bsp.checksum());
+        assert_eq!(aas.areas()[2].mins(), [0.0, -128.0, 0.0]);
+        assert_eq!(aas.area_settings()[2].reachabilities(), 2..4);
+        assert_eq!(
+            aas.reachabilities()[2].travel_type(),
+            Some(TravelType::Jump)
+        );
+        assert_eq!(aas.clusters()[1].area_count(), 2);
+        assert_eq!(aas.check_bsp(&bsp), &[]);
+        assert_eq!(
+            aas.summary().to_string(),
+            "2 areas, 2 grounded, 0 ladder, 1 liquid\n\
+             1 clusters, 0 portals\n\
+             3 reachabilities: 2 Walk, 1 Jump"
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let mut data = write(0, &[]);
+        data[4] = 6;
+        let (_, diagnostics) = Aas::parse(&data);
+        assert_eq!(
+            diagnostics[0].message(),
+            "unsupported version 6, expect 5 or 4"
+        );
+
+        let mut settings = ints(&[0; 7]);
+        settings.extend(ints(&[1, AREA_GROUNDED, 2, -3, 0, 2, 1]));
+        let data = write(
+            0,
+            &[
+                (LumpKind::Areas, area(1, [-512.0; 3], [0.0; 3])),
+                (LumpKind::AreaSettings, settings),
+                (LumpKind::Reachabilities, reachability(4, 2)),
+                (LumpKind::Clusters, ints(&[0, 0, 1, 0])),
+            ],
+        );
+        let (aas, diagnostics) = Aas::parse(&data);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "2 area settings for 1 areas",
+                "reachabilities 1..3 out of 1",
+                "portal 3 out of 0",
+                "area 4 out of 1",
+                "portal indexes 0..1 out of 0",
+            ]
+        );
+
+        let (bsp, _) = Bsp::parse(&write_bsp());
+        let messages: Vec<_> = aas
+            .check_bsp(&bsp)
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            &[format!(
+                "out of date, BSP checksum 0 isn't the map's {}",
+                bsp.checksum()
+            )]
+        );
+
+        let mut areas = area(0, [0.0; 3], [0.0; 3]);
+        areas.extend(area(1, [-128.0; 3], [0.0; 3]));
+        areas.extend(area(2, [0.0; 3], [256.5, 257.5, 64.0]));
+        let (aas, _) = Aas::parse(&write(bsp.checksum(), &[(LumpKind::Areas, areas)]));
+        let messages: Vec<_> = aas
+            .check_bsp(&bsp)
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(messages, &["2 areas are outside of the world's bounds"]);
+    }
+}
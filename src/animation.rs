@@ -0,0 +1,699 @@
+//! Player animations (`models/players/*/animation.cfg`).
+//!
+//! Follows cgame's `CG_ParseAnimationFile`: header keywords first, then four numbers per
+//! animation in the fixed `animNumber_t` order.
+
+use std::fmt;
+
+use crate::{
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    parse,
+    span::RawSpan,
+    syntax::{self, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+/// Animation names in file order, `animNumber_t`.
+pub const ANIMATION_NAMES: [&str; 31] = [
+    "BOTH_DEATH1",
+    "BOTH_DEAD1",
+    "BOTH_DEATH2",
+    "BOTH_DEAD2",
+    "BOTH_DEATH3",
+    "BOTH_DEAD3",
+    "TORSO_GESTURE",
+    "TORSO_ATTACK",
+    "TORSO_ATTACK2",
+    "TORSO_DROP",
+    "TORSO_RAISE",
+    "TORSO_STAND",
+    "TORSO_STAND2",
+    "LEGS_WALKCR",
+    "LEGS_WALK",
+    "LEGS_RUN",
+    "LEGS_BACK",
+    "LEGS_SWIM",
+    "LEGS_JUMP",
+    "LEGS_LAND",
+    "LEGS_JUMPB",
+    "LEGS_LANDB",
+    "LEGS_IDLE",
+    "LEGS_IDLECR",
+    "LEGS_TURN",
+    "TORSO_GETFLAG",
+    "TORSO_GUARDBASE",
+    "TORSO_PATROL",
+    "TORSO_FOLLOWME",
+    "TORSO_AFFIRMATIVE",
+    "TORSO_NEGATIVE",
+];
+
+/// Number of animations every model needs, up to `LEGS_TURN`.
+pub const REQUIRED_ANIMATIONS: usize = 25;
+
+const TORSO_GESTURE: usize = 6;
+const LEGS_WALKCR: usize = 13;
+
+/// Parsed and validated `animation.cfg`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimationConfig {
+    sex: Sex,
+    footsteps: Footsteps,
+    head_offset: [f64; 3],
+    fixed_legs: bool,
+    fixed_torso: bool,
+    animations: Vec<Animation>,
+}
+
+impl AnimationConfig {
+    /// Parses and validates an animation config.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(
+            source,
+            Lexer::new(source).collect(),
+            parse::animation::animation_file,
+        );
+        let mut diagnostics = parse.errors().to_vec();
+
+        let mut config = Self::default();
+        for node in parse.syntax().children().flat_map(|file| file.children()) {
+            let tokens: Vec<_> = child_tokens(node)
+                .filter(|token| !token.kind().is_trivia())
+                .map(|token| {
+                    let span = RawSpan::from(token.text_range());
+                    (unquote(&source[span]), span)
+                })
+                .collect();
+            match node.kind() {
+                SyntaxKind::Directive if !config.animations.is_empty() => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("header keyword '{}' after the first animation", tokens[0].0),
+                        tokens[0].1,
+                    ));
+                }
+                SyntaxKind::Directive => config.header(&tokens, &mut diagnostics),
+                SyntaxKind::Animation => {
+                    config.push_animation(node, source, &tokens, &mut diagnostics)
+                }
+                _ => {}
+            }
+        }
+
+        let end = RawSpan::new(source.len() as u32, source.len() as u32);
+        if let Some(name) = ANIMATION_NAMES[..REQUIRED_ANIMATIONS].get(config.animations.len()) {
+            diagnostics.push(Diagnostic::error(
+                format!("missing animation '{}', the engine rejects the file", name),
+                end,
+            ));
+        }
+        config.check_overlaps(&mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (config, diagnostics)
+    }
+
+    fn header(&mut self, tokens: &[(&str, RawSpan)], diagnostics: &mut Vec<Diagnostic>) {
+        let (keyword, span) = tokens[0];
+        let argument = tokens.get(1).copied();
+        let used = match keyword.to_ascii_lowercase().as_str() {
+            "sex" => {
+                let first = argument.and_then(|(text, _)| text.bytes().next());
+                self.sex = match first.map(|byte| byte.to_ascii_lowercase()) {
+                    Some(b'f') => Sex::Female,
+                    Some(b'n') => Sex::Neuter,
+                    _ => Sex::Male,
+                };
+                if first.is_none() {
+                    diagnostics.push(Diagnostic::warning(
+                        "missing parameter for 'sex', the engine uses m",
+                        argument.map_or(span, |(_, span)| span),
+                    ));
+                }
+                2
+            }
+            "footsteps" => {
+                match argument.map(|(text, _)| text.to_ascii_lowercase()).as_deref() {
+                    Some("default") | Some("normal") => self.footsteps = Footsteps::Normal,
+                    Some("boot") => self.footsteps = Footsteps::Boot,
+                    Some("flesh") => self.footsteps = Footsteps::Flesh,
+                    Some("mech") => self.footsteps = Footsteps::Mech,
+                    Some("energy") => self.footsteps = Footsteps::Energy,
+                    _ => diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "bad footsteps parm '{}', expect default, normal, boot, flesh, mech or energy",
+                            argument.map(|(text, _)| text).unwrap_or_default()
+                        ),
+                        argument.map_or(span, |(_, span)| span),
+                    )),
+                }
+                2
+            }
+            "headoffset" => {
+                for (index, offset) in self.head_offset.iter_mut().enumerate() {
+                    match tokens.get(index + 1) {
+                        Some((text, span)) => *offset = number(atof, text, *span, diagnostics),
+                        None => {
+                            diagnostics.push(Diagnostic::error(
+                                "missing parameter for 'headoffset'",
+                                span,
+                            ));
+                            break;
+                        }
+                    }
+                }
+                4
+            }
+            "fixedlegs" => {
+                self.fixed_legs = true;
+                1
+            }
+            "fixedtorso" => {
+                self.fixed_torso = true;
+                1
+            }
+            _ => {
+                diagnostics.push(Diagnostic::warning(
+                    format!("unknown token '{}'", keyword),
+                    span,
+                ));
+                tokens.len()
+            }
+        };
+        for (text, span) in tokens.iter().skip(used) {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "unexpected parameter '{}' for '{}' is ignored",
+                    text, keyword
+                ),
+                *span,
+            ));
+        }
+    }
+
+    fn push_animation(
+        &mut self,
+        node: &SyntaxNode,
+        source: &str,
+        tokens: &[(&str, RawSpan)],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let span = RawSpan::new(tokens[0].1.start(), tokens[tokens.len() - 1].1.end());
+        let Some(name) = ANIMATION_NAMES.get(self.animations.len()) else {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "more than {} animations, the rest is ignored",
+                    ANIMATION_NAMES.len()
+                ),
+                node.text_range().into(),
+            ));
+            return;
+        };
+        if tokens.len() != 4 {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "animation '{}' has {} numbers, expect first frame, number of frames, looping frames and fps",
+                    name,
+                    tokens.len()
+                ),
+                span,
+            ));
+        }
+        let mut frames = [0i64; 3];
+        for (frame, (text, span)) in frames.iter_mut().zip(tokens) {
+            *frame = number(atoi, text, *span, diagnostics);
+        }
+        let [first_frame, num_frames, loop_frames] = frames;
+        let fps = tokens
+            .get(3)
+            .map_or(0.0, |(text, span)| number(atof, text, *span, diagnostics));
+
+        // A trailing `// NAME` comment documents which animation the line is
+        let line_end = source[span.end() as usize..]
+            .find('\n')
+            .map_or(source.len(), |end| span.end() as usize + end);
+        let comment = source[span.end() as usize..line_end]
+            .trim()
+            .strip_prefix("//")
+            .map(|comment| comment.trim().to_owned());
+        if let Some(word) = comment
+            .as_deref()
+            .and_then(|comment| comment.split_whitespace().next())
+        {
+            if !word.eq_ignore_ascii_case(name) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "comment names '{}', but this line is animation '{}'",
+                        word, name
+                    ),
+                    span,
+                ));
+            }
+        }
+
+        if loop_frames > num_frames.abs() {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "animation '{}' loops {} frames but only has {}",
+                    name,
+                    loop_frames,
+                    num_frames.abs()
+                ),
+                span,
+            ));
+        }
+        if fps == 0.0 {
+            diagnostics.push(Diagnostic::warning(
+                format!("animation '{}' has 0 fps, the engine uses 1", name),
+                span,
+            ));
+        } else if fps < 0.0 {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "animation '{}' has {} fps, a negative time between frames",
+                    name, fps
+                ),
+                span,
+            ));
+        }
+
+        // Legs frames in the file count the torso only frames, which lower.md3 doesn't have
+        let index = self.animations.len();
+        let offset = match index {
+            LEGS_WALKCR..=24 => self
+                .animations
+                .get(LEGS_WALKCR)
+                .map_or(first_frame, |walk| walk.first_frame)
+                .saturating_sub(self.animations[TORSO_GESTURE].first_frame),
+            _ => 0,
+        };
+        let model_first_frame = first_frame - offset;
+        if model_first_frame < 0 {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "animation '{}' starts before LEGS_WALKCR, its frame in the legs model is {}",
+                    name, model_first_frame
+                ),
+                span,
+            ));
+        }
+
+        self.animations.push(Animation {
+            name,
+            first_frame,
+            model_first_frame,
+            num_frames,
+            loop_frames,
+            fps,
+            comment,
+            span,
+        });
+    }
+
+    fn check_overlaps(&self, diagnostics: &mut Vec<Diagnostic>) {
+        for (model, part) in [("upper", Part::Torso), ("lower", Part::Legs)] {
+            let animations: Vec<_> = self
+                .animations
+                .iter()
+                .filter(|animation| animation.part().is_in(part) && animation.num_frames != 0)
+                .collect();
+            for (index, animation) in animations.iter().enumerate() {
+                // Dead poses are the last frame of their death, and both models share `BOTH_*`
+                if let Some(other) = animations[..index].iter().find(|other| {
+                    other.frames().start < animation.frames().end
+                        && animation.frames().start < other.frames().end
+                        && !(animation.name.starts_with("BOTH_DEAD")
+                            && other.name == format!("BOTH_DEATH{}", &animation.name[9..]))
+                        && !(part == Part::Legs
+                            && animation.part() == Part::Both
+                            && other.part() == Part::Both)
+                }) {
+                    diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "frames of '{}' overlap '{}' in the {} model",
+                            animation.name, other.name, model
+                        ),
+                        animation.span,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Checks the animations fit the frame counts of `upper.md3` and `lower.md3`.
+    pub fn check_frame_counts(&self, upper_frames: u32, lower_frames: u32) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for (model, part, frames) in [
+            ("upper", Part::Torso, upper_frames),
+            ("lower", Part::Legs, lower_frames),
+        ] {
+            for animation in self
+                .animations
+                .iter()
+                .filter(|animation| animation.part().is_in(part))
+            {
+                let end = animation.frames().end;
+                if end > i64::from(frames) {
+                    diagnostics.push(Diagnostic::error(
+                        format!(
+                            "animation '{}' uses frames up to {}, but the {} model has {}",
+                            animation.name,
+                            end - 1,
+                            model,
+                            frames
+                        ),
+                        animation.span,
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Get the sex used for gender specific sounds and chats.
+    pub const fn sex(&self) -> Sex {
+        self.sex
+    }
+
+    /// Get the footstep sounds.
+    pub const fn footsteps(&self) -> Footsteps {
+        self.footsteps
+    }
+
+    /// Get the `headoffset` for the status bar head.
+    pub const fn head_offset(&self) -> [f64; 3] {
+        self.head_offset
+    }
+
+    /// Legs don't turn separately from the torso, `fixedlegs`.
+    pub const fn fixed_legs(&self) -> bool {
+        self.fixed_legs
+    }
+
+    /// Torso doesn't pitch, `fixedtorso`.
+    pub const fn fixed_torso(&self) -> bool {
+        self.fixed_torso
+    }
+
+    /// Get the animations in file order.
+    pub fn animations(&self) -> &[Animation] {
+        &self.animations
+    }
+
+    /// Get the animation with the given name, e.g. `LEGS_RUN`.
+    ///
+    /// Missing Team Arena torso animations fall back to `TORSO_GESTURE` like in the engine.
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        let index = ANIMATION_NAMES
+            .iter()
+            .position(|known| known.eq_ignore_ascii_case(name))?;
+        match self.animations.get(index) {
+            None if index >= REQUIRED_ANIMATIONS => self.animations.get(TORSO_GESTURE),
+            animation => animation,
+        }
+    }
+}
+
+/// `sex m`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Sex {
+    #[default]
+    Male,
+    Female,
+    Neuter,
+}
+
+impl fmt::Display for Sex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Male => write!(f, "m"),
+            Self::Female => write!(f, "f"),
+            Self::Neuter => write!(f, "n"),
+        }
+    }
+}
+
+/// `footsteps boot`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Footsteps {
+    #[default]
+    Normal,
+    Boot,
+    Flesh,
+    Mech,
+    Energy,
+}
+
+/// Model an animation plays on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Part {
+    /// `BOTH_*`, on the upper and the lower model.
+    Both,
+    /// `TORSO_*`, on `upper.md3`.
+    Torso,
+    /// `LEGS_*`, on `lower.md3`.
+    Legs,
+}
+
+impl Part {
+    const fn is_in(self, model: Self) -> bool {
+        matches!(
+            (self, model),
+            (Self::Both, _) | (Self::Torso, Self::Torso) | (Self::Legs, Self::Legs)
+        )
+    }
+}
+
+/// `0 30 0 25 // BOTH_DEATH1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    name: &'static str,
+    first_frame: i64,
+    model_first_frame: i64,
+    num_frames: i64,
+    loop_frames: i64,
+    fps: f64,
+    comment: Option<String>,
+    span: RawSpan,
+}
+
+impl Animation {
+    /// Get the animation's name, e.g. `BOTH_DEATH1`.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the model the animation plays on.
+    pub fn part(&self) -> Part {
+        match self.name.split('_').next() {
+            Some("BOTH") => Part::Both,
+            Some("LEGS") => Part::Legs,
+            _ => Part::Torso,
+        }
+    }
+
+    /// Get the first frame as written, counting torso and legs frames together.
+    pub const fn first_frame(&self) -> i64 {
+        self.first_frame
+    }
+
+    /// Get the first frame in the model, legs frames without the torso only frames.
+    pub const fn model_first_frame(&self) -> i64 {
+        self.model_first_frame
+    }
+
+    /// Get the number of frames.
+    pub const fn num_frames(&self) -> i64 {
+        self.num_frames.abs()
+    }
+
+    /// Animation plays backwards, a negative number of frames.
+    pub const fn is_reversed(&self) -> bool {
+        self.num_frames < 0
+    }
+
+    /// Get the number of frames looped at the end.
+    pub const fn loop_frames(&self) -> i64 {
+        self.loop_frames
+    }
+
+    /// Get the frames per second, 0 meaning 1.
+    pub const fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Get the trailing comment.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Get the span of the animation's numbers.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the frames in the model.
+    pub const fn frames(&self) -> std::ops::Range<i64> {
+        self.model_first_frame..self.model_first_frame + self.num_frames()
+    }
+}
+
+/// Reads a number like `atoi` or `atof`, which stop at the first character that doesn't fit and
+/// read 0 when none does.
+fn number<T: Default>(
+    read: fn(&str) -> Option<T>,
+    text: &str,
+    span: RawSpan,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> T {
+    read(text).unwrap_or_else(|| {
+        diagnostics.push(Diagnostic::warning(
+            format!("invalid number '{}', the engine reads 0", text),
+            span,
+        ));
+        T::default()
+    })
+}
+
+/// Length of the leading digits.
+fn digits(text: &str) -> usize {
+    text.bytes().take_while(u8::is_ascii_digit).count()
+}
+
+/// Length of the optional sign.
+fn sign(text: &str) -> usize {
+    usize::from(text.starts_with(['+', '-']))
+}
+
+/// Reads the leading integer like `atoi`, clamped to an `int`, `None` without any digit.
+fn atoi(text: &str) -> Option<i64> {
+    let text = text.trim_start();
+    let end = sign(text) + digits(&text[sign(text)..]);
+    let number = &text[..end];
+    let value = match number.parse() {
+        Ok(value) => value,
+        Err(_) if end > sign(text) => {
+            if number.starts_with('-') {
+                i64::MIN
+            } else {
+                i64::MAX
+            }
+        }
+        Err(_) => return None,
+    };
+    Some(value.clamp(i32::MIN.into(), i32::MAX.into()))
+}
+
+/// Reads the leading decimal number like `atof`, `None` without any digit.
+fn atof(text: &str) -> Option<f64> {
+    let text = text.trim_start();
+    let mut end = sign(text);
+    let integer = digits(&text[end..]);
+    end += integer;
+    let mut fraction = 0;
+    if text[end..].starts_with('.') {
+        fraction = digits(&text[end + 1..]);
+        end += 1 + fraction;
+    }
+    if integer + fraction == 0 {
+        return None;
+    }
+    if text[end..].starts_with(['e', 'E']) {
+        let exponent = sign(&text[end + 1..]);
+        let exponent_digits = digits(&text[end + 1 + exponent..]);
+        if exponent_digits > 0 {
+            end += 1 + exponent + exponent_digits;
+        }
+    }
+    text[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SARGE: &str = "sex m\nfootsteps boot\nheadoffset 0 0 0\n\n\
+0\t30\t0\t25\t\t// BOTH_DEATH1\n\
+29\t1\t0\t25\t\t// BOTH_DEAD1\n\
+30\t30\t0\t25\t\t// BOTH_DEATH2\n\
+59\t1\t0\t25\t\t// BOTH_DEAD2\n\
+60\t30\t0\t25\t\t// BOTH_DEATH3\n\
+89\t1\t0\t25\t\t// BOTH_DEAD3\n\
+90\t40\t0\t20\t\t// TORSO_GESTURE\n\
+130\t6\t0\t15\t\t// TORSO_ATTACK\n\
+136\t6\t0\t15\t\t// TORSO_ATTACK2\n\
+142\t5\t0\t20\t\t// TORSO_DROP\n\
+147\t4\t0\t20\t\t// TORSO_RAISE\n\
+151\t1\t0\t15\t\t// TORSO_STAND\n\
+152\t1\t0\t15\t\t// TORSO_STAND2\n\
+153\t8\t8\t20\t\t// LEGS_WALKCR\n\
+161\t12\t12\t20\t\t// LEGS_WALK\n\
+173\t9\t9\t18\t\t// LEGS_RUN\n\
+182\t10\t10\t20\t\t// LEGS_BACK\n\
+192\t10\t10\t15\t\t// LEGS_SWIM\n\
+202\t8\t0\t15\t\t// LEGS_JUMP\n\
+210\t1\t0\t15\t\t// LEGS_LAND\n\
+211\t8\t0\t15\t\t// LEGS_JUMPB\n\
+219\t1\t0\t15\t\t// LEGS_LANDB\n\
+220\t10\t10\t15\t\t// LEGS_IDLE\n\
+230\t10\t10\t15\t\t// LEGS_IDLECR\n\
+240\t7\t7\t15\t\t// LEGS_TURN\n";
+
+    #[test]
+    fn animation_config() {
+        let (config, diagnostics) = AnimationConfig::parse(SARGE);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(config.footsteps(), Footsteps::Boot);
+        assert_eq!(config.animations().len(), REQUIRED_ANIMATIONS);
+
+        let walk = config.animation("legs_walkcr").unwrap();
+        assert_eq!(walk.first_frame(), 153);
+        assert_eq!(walk.model_first_frame(), 90);
+        assert_eq!(
+            config.animation("TORSO_GETFLAG").unwrap().name(),
+            "TORSO_GESTURE"
+        );
+
+        assert_eq!(config.check_frame_counts(153, 184), &[]);
+        let messages: Vec<_> = config
+            .check_frame_counts(153, 180)
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            &["animation 'LEGS_TURN' uses frames up to 183, but the lower model has 180"]
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let source = SARGE
+            .replace("sex m", "sex \"\"")
+            .replace("footsteps boot", "footsteps boots")
+            .replace("0\t30\t0\t25", "0\t30\t0\t25.5")
+            .replace("29\t1\t0\t25", "29\t1abc\t0\t-5")
+            .replace("130\t6\t0\t15", "125\t6\t0\t15")
+            .replace("// LEGS_RUN", "// LEGS_BACK")
+            .replace("240\t7\t7\t15\t\t// LEGS_TURN\n", "");
+        let (config, diagnostics) = AnimationConfig::parse(&source);
+        assert_eq!(config.animations()[0].fps(), 25.5);
+        assert_eq!(config.animations()[1].num_frames(), 1);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "missing parameter for 'sex', the engine uses m",
+                "bad footsteps parm 'boots', expect default, normal, boot, flesh, mech or energy",
+                "animation 'BOTH_DEAD1' has -5 fps, a negative time between frames",
+                "frames of 'TORSO_ATTACK' overlap 'TORSO_GESTURE' in the upper model",
+                "comment names 'LEGS_BACK', but this line is animation 'LEGS_RUN'",
+                "missing animation 'LEGS_TURN', the engine rejects the file",
+            ]
+        );
+
+        let (config, _) = AnimationConfig::parse("0 -99999999999999999999 0 25\n");
+        assert_eq!(config.animations()[0].num_frames(), 2147483648);
+        assert!(config.animations()[0].is_reversed());
+    }
+}
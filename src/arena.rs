@@ -0,0 +1,218 @@
+//! Arena definitions (`scripts/arenas.txt`, `scripts/*.arena`).
+
+use crate::{
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    parse,
+    q3string::Q3String,
+    span::RawSpan,
+    syntax::{self, child_nodes, SyntaxKind, SyntaxNode},
+};
+
+pub mod database;
+pub mod rules;
+pub mod single_player;
+
+/// Parsed arena file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArenaFile {
+    arenas: Vec<Arena>,
+}
+
+impl ArenaFile {
+    /// Parses an arena file.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(source, Lexer::new(source).collect(), parse::arenas);
+        let diagnostics = parse.errors().to_vec();
+
+        let arenas = parse
+            .syntax()
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Arena))
+            .map(|node| Arena::from_node(node, source))
+            .collect();
+
+        (Self { arenas }, diagnostics)
+    }
+
+    /// Get the arenas in file order.
+    pub fn arenas(&self) -> &[Arena] {
+        &self.arenas
+    }
+}
+
+/// `{ map "q3dm1" longname "Arena Gate" … }`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Arena {
+    pairs: Vec<KeyValue>,
+    span: RawSpan,
+}
+
+impl Arena {
+    fn from_node(node: &SyntaxNode, source: &str) -> Self {
+        let pairs = child_nodes(node, SyntaxKind::KeyValuePair)
+            .filter_map(|pair| KeyValue::from_node(pair, source))
+            .collect();
+        Self {
+            pairs,
+            span: node.text_range().into(),
+        }
+    }
+
+    /// Get the key/value pairs in file order.
+    pub fn pairs(&self) -> &[KeyValue] {
+        &self.pairs
+    }
+
+    /// Get the pair for `key`, ignoring case; the last one wins since `G_ParseInfos` stores
+    /// the pairs with `Info_SetValueForKey`, which drops the earlier value. A raw info string
+    /// is read first wins instead, see [`InfoString::pair`](crate::info::InfoString::pair).
+    pub fn pair(&self, key: &str) -> Option<&KeyValue> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|pair| pair.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Get the value for `key`, see [`pair`](Self::pair).
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pair(key).map(|pair| pair.value().as_str())
+    }
+
+    /// Get the `map` name, the BSP without `maps/` and `.bsp`.
+    pub fn map(&self) -> Option<&str> {
+        self.get("map")
+    }
+
+    /// Get the `longname` shown in menus.
+    pub fn long_name(&self) -> Option<&str> {
+        self.get("longname")
+    }
+
+    /// Get the names in `bots`.
+    pub fn bots(&self) -> impl Iterator<Item = &str> {
+        self.get("bots").unwrap_or_default().split_whitespace()
+    }
+
+    /// Get the game types in `type`, e.g. `single` or `ctf`.
+    pub fn types(&self) -> impl Iterator<Item = &str> {
+        self.get("type").unwrap_or_default().split_whitespace()
+    }
+
+    /// Arena lists the game type, ignoring case.
+    pub fn has_type(&self, game_type: &str) -> bool {
+        self.types()
+            .any(|listed| listed.eq_ignore_ascii_case(game_type))
+    }
+
+    /// Get the `fraglimit`.
+    pub fn fraglimit(&self) -> Option<u32> {
+        self.get("fraglimit")?.trim().parse().ok()
+    }
+
+    /// Get the `timelimit` in minutes.
+    pub fn timelimit(&self) -> Option<u32> {
+        self.get("timelimit")?.trim().parse().ok()
+    }
+
+    /// Get the single player `special`, `training` or `final`.
+    pub fn special(&self) -> Option<&str> {
+        self.get("special")
+    }
+
+    /// Get the arena's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `longname "Arena Gate"`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyValue {
+    key: String,
+    key_span: RawSpan,
+    value: Q3String,
+    value_span: RawSpan,
+}
+
+impl KeyValue {
+    /// Creates a pair read from other syntax, e.g. info strings.
+    pub(crate) fn new(key: String, key_span: RawSpan, value: String, value_span: RawSpan) -> Self {
+        Self {
+            key,
+            key_span,
+            value: value.into(),
+            value_span,
+        }
+    }
+
+    /// Creates a pair from a [`SyntaxKind::KeyValuePair`] node, `None` if incomplete.
+    pub(crate) fn from_node(node: &SyntaxNode, source: &str) -> Option<Self> {
+        let text = |node: &SyntaxNode| {
+            let span = RawSpan::from(node.text_range());
+            (unquote(&source[span]).to_owned(), span)
+        };
+        let (key, key_span) = text(child_nodes(node, SyntaxKind::Key).next()?);
+        let (value, value_span) = text(child_nodes(node, SyntaxKind::Value).next()?);
+        Some(Self {
+            key,
+            key_span,
+            value: value.into(),
+            value_span,
+        })
+    }
+
+    /// Get the key without quotes.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the span of the key.
+    pub const fn key_span(&self) -> RawSpan {
+        self.key_span
+    }
+
+    /// Get the value without quotes, with its colour escapes.
+    pub fn value(&self) -> &Q3String {
+        &self.value
+    }
+
+    /// Get the span of the value, including quotes.
+    pub const fn value_span(&self) -> RawSpan {
+        self.value_span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arenas() {
+        let source = include_str!("../fixtures/arenas.txt");
+        let (file, diagnostics) = ArenaFile::parse(source);
+        assert_eq!(diagnostics, &[]);
+
+        let arena = &file.arenas()[0];
+        assert_eq!(arena.map(), Some("oa_rpg3dm2"));
+        assert_eq!(arena.long_name(), Some("Trial by Error"));
+        assert_eq!(
+            arena.bots().collect::<Vec<_>>(),
+            &["kyonshi", "jenna", "sarge"]
+        );
+        assert!(arena.has_type("FFA"));
+        assert!(!arena.has_type("ctf"));
+        assert_eq!(arena.fraglimit(), Some(15));
+        assert_eq!(
+            &source[arena.pair("map").unwrap().value_span()],
+            "\"oa_rpg3dm2\""
+        );
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let (file, _) = ArenaFile::parse("{ map \"q3dm1\" MAP \"q3dm17\" }");
+        assert_eq!(file.arenas()[0].map(), Some("q3dm17"));
+    }
+}
@@ -0,0 +1,353 @@
+//! Arenas of a game as the UI loads them, `scripts/arenas.txt` and then every `scripts/*.arena`.
+
+use std::collections::HashMap;
+
+use crate::{diagnostic::Diagnostic, resolve::FileResolver, span::RawSpan};
+
+use super::{Arena, ArenaFile};
+
+/// Arena file loaded first, unless `g_arenasFile` names another.
+pub const ARENAS_FILE: &str = "scripts/arenas.txt";
+/// `MAX_ARENAS`, arenas beyond are dropped.
+pub const MAX_ARENAS: usize = 1024;
+/// `MAX_ARENAS_TEXT`, files this long or longer are skipped.
+pub const MAX_ARENAS_TEXT: usize = 8192;
+
+/// Arenas of several files in load order.
+///
+/// Duplicate maps are all kept like in the UI, which lists each of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArenaDatabase {
+    files: Vec<String>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    file: usize,
+    arena: Arena,
+}
+
+impl ArenaDatabase {
+    /// Creates a new empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the arenas like `UI_LoadArenas`, from `arenas_file` or [`ARENAS_FILE`] and then
+    /// every `scripts/*.arena` listed by `resolver`.
+    ///
+    /// Returns the problems by file, including duplicate maps.
+    pub fn load(
+        resolver: &dyn FileResolver,
+        arenas_file: Option<&str>,
+    ) -> (Self, Vec<(String, Diagnostic)>) {
+        let mut database = Self::new();
+        let mut problems = Vec::new();
+
+        let arenas_file = arenas_file.filter(|file| !file.is_empty());
+        let mut paths = vec![arenas_file.unwrap_or(ARENAS_FILE).to_owned()];
+        paths.extend(resolver.list("scripts", ".arena"));
+        for path in paths {
+            let source = match resolver.read_to_string(&path) {
+                Ok(source) => source,
+                Err(_) => {
+                    problems.push((
+                        path.clone(),
+                        Diagnostic::error(format!("file not found: {}", path), RawSpan::default()),
+                    ));
+                    continue;
+                }
+            };
+            if source.len() >= MAX_ARENAS_TEXT {
+                let span = RawSpan::new(0, source.len() as u32);
+                problems.push((
+                    path.clone(),
+                    Diagnostic::error(
+                        format!(
+                            "file of {} bytes is skipped, the maximum is {}",
+                            source.len(),
+                            MAX_ARENAS_TEXT - 1
+                        ),
+                        span,
+                    ),
+                ));
+                continue;
+            }
+            let diagnostics = database.add(path.clone(), &source);
+            problems.extend(
+                diagnostics
+                    .into_iter()
+                    .map(|diagnostic| (path.clone(), diagnostic)),
+            );
+        }
+
+        for duplicate in database.duplicates() {
+            for repeated in duplicate.repeated() {
+                let span = repeated
+                    .arena()
+                    .pair("map")
+                    .map_or(repeated.arena().span(), |pair| pair.value_span());
+                problems.push((
+                    repeated.file().to_owned(),
+                    Diagnostic::warning(
+                        format!(
+                            "map '{}' is already defined in '{}'",
+                            repeated.arena().map().unwrap_or_default(),
+                            duplicate.first().file()
+                        ),
+                        span,
+                    ),
+                ));
+            }
+        }
+        (database, problems)
+    }
+
+    /// Parses and adds the arenas of the file at `path`, after all files added before.
+    ///
+    /// Arenas beyond [`MAX_ARENAS`] are dropped with a diagnostic.
+    pub fn add(&mut self, path: impl Into<String>, source: &str) -> Vec<Diagnostic> {
+        let (file, mut diagnostics) = ArenaFile::parse(source);
+        let room = MAX_ARENAS.saturating_sub(self.entries.len());
+        if let Some(dropped) = file.arenas().get(room) {
+            diagnostics.push(Diagnostic::error(
+                format!("more than {} arenas, the rest is dropped", MAX_ARENAS),
+                dropped.span(),
+            ));
+        }
+        let file_index = self.files.len();
+        self.files.push(path.into());
+        self.entries
+            .extend(file.arenas.into_iter().take(room).map(|arena| Entry {
+                file: file_index,
+                arena,
+            }));
+        diagnostics
+    }
+
+    /// Get the files in load order.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Iterates over all arenas in load order.
+    pub fn arenas(&self) -> impl Iterator<Item = ArenaDefinition<'_>> {
+        self.entries.iter().map(move |entry| self.definition(entry))
+    }
+
+    /// Get the arenas of the `map`, ignoring case, in load order.
+    pub fn by_map<'a>(&'a self, map: &'a str) -> impl Iterator<Item = ArenaDefinition<'a>> {
+        self.arenas().filter(move |definition| {
+            definition
+                .arena
+                .map()
+                .is_some_and(|name| name.eq_ignore_ascii_case(map))
+        })
+    }
+
+    /// Get the arenas listing the game type, e.g. `ctf`.
+    pub fn by_type<'a>(&'a self, game_type: &'a str) -> impl Iterator<Item = ArenaDefinition<'a>> {
+        self.arenas()
+            .filter(move |definition| definition.arena.has_type(game_type))
+    }
+
+    /// Get the arenas listing the bot in `bots`, ignoring case.
+    pub fn by_bot<'a>(&'a self, bot: &'a str) -> impl Iterator<Item = ArenaDefinition<'a>> {
+        self.arenas().filter(move |definition| {
+            definition
+                .arena
+                .bots()
+                .any(|name| name.eq_ignore_ascii_case(bot))
+        })
+    }
+
+    /// Get the maps defined more than once, ignoring case.
+    pub fn duplicates(&self) -> Vec<Duplicate<'_>> {
+        let mut duplicates: Vec<Duplicate> = Vec::new();
+        let mut firsts = HashMap::new();
+        let mut positions = HashMap::new();
+        for definition in self.arenas() {
+            let key = match definition.arena.map() {
+                Some(map) => map.to_ascii_lowercase(),
+                None => continue,
+            };
+            let first = *firsts.entry(key.clone()).or_insert(definition);
+            if std::ptr::eq(first.arena, definition.arena) {
+                continue;
+            }
+            let position = *positions.entry(key).or_insert_with(|| {
+                duplicates.push(Duplicate {
+                    first,
+                    repeated: Vec::new(),
+                });
+                duplicates.len() - 1
+            });
+            duplicates[position].repeated.push(definition);
+        }
+        duplicates
+    }
+
+    fn definition<'a>(&'a self, entry: &'a Entry) -> ArenaDefinition<'a> {
+        ArenaDefinition {
+            file: &self.files[entry.file],
+            arena: &entry.arena,
+        }
+    }
+}
+
+/// Arena and the file defining it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaDefinition<'a> {
+    file: &'a str,
+    arena: &'a Arena,
+}
+
+impl<'a> ArenaDefinition<'a> {
+    /// Get the path of the defining file.
+    pub const fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// Get the arena.
+    pub const fn arena(&self) -> &'a Arena {
+        self.arena
+    }
+
+    /// Get the arena's span in the file.
+    pub const fn span(&self) -> RawSpan {
+        self.arena.span
+    }
+}
+
+/// Map defined more than once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate<'a> {
+    first: ArenaDefinition<'a>,
+    repeated: Vec<ArenaDefinition<'a>>,
+}
+
+impl<'a> Duplicate<'a> {
+    /// Get the definition loaded first.
+    pub const fn first(&self) -> &ArenaDefinition<'a> {
+        &self.first
+    }
+
+    /// Get the later definitions.
+    pub fn repeated(&self) -> &[ArenaDefinition<'a>] {
+        &self.repeated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> HashMap<String, String> {
+        [
+            (
+                "scripts/arenas.txt",
+                "{\nmap \"q3dm17\"\ntype \"single ffa\"\nbots \"sarge\"\n}\n\
+                 {\nmap \"q3ctf1\"\ntype \"ctf\"\n}\n",
+            ),
+            (
+                "scripts/custom.arena",
+                "{\nmap \"Q3DM17\"\ntype \"ffa\"\nbots \"Sarge grunt\"\n}\n",
+            ),
+            ("scripts/tourney.arena", "{\nmap \"q3tourney2\"\n}\n"),
+            ("scripts/readme.txt", "{\nmap \"ignored\"\n}\n"),
+            ("mod/arenas.txt", "{\nmap \"modmap\"\n}\n"),
+        ]
+        .iter()
+        .map(|(path, source)| (path.to_string(), source.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn load() {
+        let files = files();
+        let (database, problems) = ArenaDatabase::load(&files, None);
+        assert_eq!(
+            database.files(),
+            &[
+                "scripts/arenas.txt",
+                "scripts/custom.arena",
+                "scripts/tourney.arena"
+            ]
+        );
+        let messages: Vec<_> = problems
+            .iter()
+            .map(|(path, diagnostic)| (path.as_str(), diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[(
+                "scripts/custom.arena",
+                "map 'Q3DM17' is already defined in 'scripts/arenas.txt'"
+            )]
+        );
+        let (path, diagnostic) = &problems[0];
+        assert_eq!(
+            &files[path][std::ops::Range::from(diagnostic.span())],
+            "\"Q3DM17\""
+        );
+
+        let maps = |definitions: Vec<ArenaDefinition>| -> Vec<String> {
+            definitions
+                .iter()
+                .map(|definition| {
+                    format!(
+                        "{}:{}",
+                        definition.file(),
+                        definition.arena().map().unwrap()
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            maps(database.by_map("q3dm17").collect()),
+            &["scripts/arenas.txt:q3dm17", "scripts/custom.arena:Q3DM17"]
+        );
+        assert_eq!(
+            maps(database.by_type("ctf").collect()),
+            &["scripts/arenas.txt:q3ctf1"]
+        );
+        assert_eq!(
+            maps(database.by_bot("grunt").collect()),
+            &["scripts/custom.arena:Q3DM17"]
+        );
+        assert_eq!(
+            database.duplicates()[0].first().file(),
+            "scripts/arenas.txt"
+        );
+    }
+
+    #[test]
+    fn arenas_file() {
+        let mut files = files();
+        files.insert(
+            "scripts/large.arena".to_owned(),
+            "{\nmap \"q3dm1\"\n}\n".repeat(600),
+        );
+        let (database, problems) = ArenaDatabase::load(&files, Some("mod/arenas.txt"));
+        assert_eq!(database.files()[0], "mod/arenas.txt");
+        assert_eq!(
+            database.arenas().next().unwrap().arena().map(),
+            Some("modmap")
+        );
+        let messages: Vec<_> = problems
+            .iter()
+            .map(|(path, diagnostic)| (path.as_str(), diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[(
+                "scripts/large.arena",
+                "file of 9600 bytes is skipped, the maximum is 8191"
+            )]
+        );
+
+        let (_, problems) = ArenaDatabase::load(&files, Some("missing.txt"));
+        assert_eq!(problems[0].1.message(), "file not found: missing.txt");
+    }
+}
@@ -0,0 +1,366 @@
+//! Lint rules for arenas, identified like in SARIF output, see `fixtures/arenas.sarif`.
+
+use crate::{
+    aas::Aas,
+    arena::Arena,
+    bsp::Bsp,
+    diagnostic::{Diagnostic, Severity},
+    map::Entity,
+    resolve::FileResolver,
+    shader::index::image_exists,
+    span::RawSpan,
+};
+
+/// Check of arenas with an id and name for reports.
+pub trait Rule {
+    /// Get the rule's id, e.g. `MAP003`.
+    fn id(&self) -> &'static str;
+
+    /// Get the rule's name, e.g. `ArenaDependsOnFiles`.
+    fn name(&self) -> &'static str;
+
+    /// Checks the arenas of a file.
+    fn check(&self, arenas: &[Arena]) -> Vec<Diagnostic>;
+}
+
+/// `MAP003`: the files of the arena's map exist, `maps/<map>.bsp`, `levelshots/<map>.tga` or
+/// `.jpg`, and the bots' `maps/<map>.aas`.
+///
+/// A navigation file is also checked against the map's checksum like the engine does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaDependsOnFiles<R> {
+    resolver: R,
+}
+
+impl<R: FileResolver> ArenaDependsOnFiles<R> {
+    /// Creates a new rule looking files up with the resolver, e.g. a directory or pk3 set.
+    pub fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+
+    fn check_aas(&self, map: &str, span: RawSpan) -> Vec<Diagnostic> {
+        let path = format!("maps/{}.aas", map);
+        let data = match self.resolver.read(&path) {
+            Ok(data) => data,
+            Err(_) => {
+                return vec![Diagnostic::note(
+                    format!("missing '{}', bots can't play the map", path),
+                    span,
+                )]
+            }
+        };
+        let (aas, errors) = Aas::parse(&data);
+        let mut diagnostics: Vec<_> = errors
+            .iter()
+            .filter(|error| error.severity() == Severity::Error)
+            .map(|error| Diagnostic::error(format!("in '{}': {}", path, error.message()), span))
+            .collect();
+        if aas.version() == 0 {
+            return diagnostics;
+        }
+        if let Ok(data) = self.resolver.read(&format!("maps/{}.bsp", map)) {
+            let (bsp, _) = Bsp::parse(&data);
+            diagnostics.extend(aas.check_bsp(&bsp).iter().map(|diagnostic| {
+                Diagnostic::new(
+                    diagnostic.severity(),
+                    format!("in '{}': {}", path, diagnostic.message()),
+                    span,
+                )
+            }));
+        }
+        diagnostics
+    }
+}
+
+impl<R: FileResolver> Rule for ArenaDependsOnFiles<R> {
+    fn id(&self) -> &'static str {
+        "MAP003"
+    }
+
+    fn name(&self) -> &'static str {
+        "ArenaDependsOnFiles"
+    }
+
+    fn check(&self, arenas: &[Arena]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for arena in arenas {
+            let (map, span) = match arena.pair("map") {
+                Some(pair) => (pair.value(), pair.value_span()),
+                None => continue,
+            };
+            let bsp = format!("maps/{}.bsp", map);
+            if !self.resolver.exists(&bsp) {
+                diagnostics.push(Diagnostic::error(format!("missing '{}'", bsp), span));
+            }
+            let levelshot = format!("levelshots/{}", map);
+            if !image_exists(&self.resolver, &levelshot) {
+                diagnostics.push(Diagnostic::warning(
+                    format!("missing '{0}.tga' or '{0}.jpg'", levelshot),
+                    span,
+                ));
+            }
+            diagnostics.extend(self.check_aas(map, span));
+        }
+        diagnostics
+    }
+}
+
+/// Entities a game type can't be played without, e.g. the flags of `ctf`.
+const REQUIRED_ENTITIES: &[(&str, &[&str])] = &[
+    ("ctf", &["team_CTF_redflag", "team_CTF_blueflag"]),
+    (
+        "oneflag",
+        &[
+            "team_CTF_redflag",
+            "team_CTF_blueflag",
+            "team_CTF_neutralflag",
+        ],
+    ),
+    ("overload", &["team_redobelisk", "team_blueobelisk"]),
+    (
+        "harvester",
+        &["team_redobelisk", "team_blueobelisk", "team_neutralobelisk"],
+    ),
+];
+
+/// Game types spawning players at their team's spawn points.
+///
+/// Team deathmatch (`team`) spawns at deathmatch spawn points like `ffa`.
+const TEAM_GAME_TYPES: &[&str] = &["ctf", "oneflag", "overload", "harvester"];
+
+/// Spawn points of each team, the game falls back to deathmatch spawn points without them.
+const TEAM_SPAWNS: &[&str] = &["team_CTF_redspawn", "team_CTF_bluespawn"];
+
+/// Classes spawning as `info_player_deathmatch`.
+const DEATHMATCH_SPAWNS: &[&str] = &["info_player_deathmatch", "info_player_start"];
+
+/// `MAP004`: the arena's `type` flags match the entities of `maps/<map>.bsp`.
+///
+/// Arenas without a map or whose BSP is missing are left to other rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameTypeHasEntities<R> {
+    resolver: R,
+}
+
+impl<R: FileResolver> GameTypeHasEntities<R> {
+    /// Creates a new rule reading compiled maps with the resolver.
+    pub fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+}
+
+impl<R: FileResolver> Rule for GameTypeHasEntities<R> {
+    fn id(&self) -> &'static str {
+        "MAP004"
+    }
+
+    fn name(&self) -> &'static str {
+        "GameTypeHasEntities"
+    }
+
+    fn check(&self, arenas: &[Arena]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for arena in arenas {
+            let (map, span) = match arena.pair("map") {
+                Some(pair) => (pair.value(), pair.value_span()),
+                None => continue,
+            };
+            let path = format!("maps/{}.bsp", map);
+            let data = match self.resolver.read(&path) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            let (bsp, errors) = Bsp::parse(&data);
+            diagnostics.extend(
+                errors
+                    .iter()
+                    .filter(|error| error.severity() == Severity::Error)
+                    .map(|error| {
+                        Diagnostic::error(format!("in '{}': {}", path, error.message()), span)
+                    }),
+            );
+            diagnostics.extend(check_entities(arena, bsp.entities()));
+        }
+        diagnostics
+    }
+}
+
+/// Checks the arena's `type` flags against the entities of its map.
+pub fn check_entities(arena: &Arena, entities: &[Entity]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let span = arena
+        .pair("type")
+        .map_or(arena.span(), |pair| pair.value_span());
+    let count = |classes: &[&str]| {
+        entities
+            .iter()
+            .filter(|entity| {
+                entity
+                    .classname()
+                    .is_some_and(|class| classes.contains(&class))
+            })
+            .count()
+    };
+
+    let spawns = count(DEATHMATCH_SPAWNS);
+    if spawns == 0 {
+        diagnostics.push(Diagnostic::error(
+            "map has no 'info_player_deathmatch' to spawn at",
+            span,
+        ));
+    }
+    for game_type in arena.types().map(str::to_ascii_lowercase) {
+        for (_, classes) in REQUIRED_ENTITIES
+            .iter()
+            .filter(|(required, _)| *required == game_type)
+        {
+            for class in classes.iter().filter(|class| count(&[class]) == 0) {
+                diagnostics.push(Diagnostic::error(
+                    format!("type '{}' needs a '{}'", game_type, class),
+                    span,
+                ));
+            }
+        }
+        if TEAM_GAME_TYPES.contains(&game_type.as_str()) {
+            for class in TEAM_SPAWNS.iter().filter(|class| count(&[class]) == 0) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "type '{}' wants a '{}', players spawn at deathmatch spawn points",
+                        game_type, class
+                    ),
+                    span,
+                ));
+            }
+        }
+        if game_type == "single" {
+            let bots = arena.bots().count();
+            if spawns > 0 && spawns <= bots {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "type 'single' has {} deathmatch spawns for the player and {} bots",
+                        spawns, bots
+                    ),
+                    span,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// `MAP005`: no value ends with a `^`, which starts no colour escape and is likely a typo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValueHasDanglingEscape;
+
+impl Rule for ValueHasDanglingEscape {
+    fn id(&self) -> &'static str {
+        "MAP005"
+    }
+
+    fn name(&self) -> &'static str {
+        "ValueHasDanglingEscape"
+    }
+
+    fn check(&self, arenas: &[Arena]) -> Vec<Diagnostic> {
+        arenas
+            .iter()
+            .flat_map(Arena::pairs)
+            .filter(|pair| pair.value().has_dangling_escape())
+            .map(|pair| {
+                Diagnostic::warning(
+                    format!("'{}' ends with a dangling '^'", pair.key()),
+                    pair.value_span(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{arena::ArenaFile, map::MapFile};
+
+    use super::*;
+
+    #[test]
+    fn dangling_escape() {
+        let source = "{ map \"q3dm1\" longname \"^1Arena Gate^\" bots \"^^7sarge\" }";
+        let (file, _) = ArenaFile::parse(source);
+        let diagnostics = ValueHasDanglingEscape.check(file.arenas());
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (&source[diagnostic.span()], diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[("\"^1Arena Gate^\"", "'longname' ends with a dangling '^'")]
+        );
+    }
+
+    #[test]
+    fn dependencies() {
+        let source = "{ map \"q3dm17\" }\n{ map \"q3dm1\" }\n";
+        let (file, _) = ArenaFile::parse(source);
+        let files: HashMap<_, _> = [
+            ("maps/q3dm17.bsp", "IBSP"),
+            ("maps/q3dm17.aas", "EAAS"),
+            ("levelshots/q3dm17.jpg", ""),
+        ]
+        .iter()
+        .map(|(path, data)| (path.to_string(), data.to_string()))
+        .collect();
+        let diagnostics = ArenaDependsOnFiles::new(files).check(file.arenas());
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (&source[diagnostic.span()], diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                (
+                    "\"q3dm17\"",
+                    "in 'maps/q3dm17.aas': file of 4 bytes is too short for the 124 bytes header"
+                ),
+                ("\"q3dm1\"", "missing 'maps/q3dm1.bsp'"),
+                (
+                    "\"q3dm1\"",
+                    "missing 'levelshots/q3dm1.tga' or 'levelshots/q3dm1.jpg'"
+                ),
+                (
+                    "\"q3dm1\"",
+                    "missing 'maps/q3dm1.aas', bots can't play the map"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn game_types() {
+        let (file, _) = ArenaFile::parse(
+            "{ map \"q3ctf1\" bots \"sarge grunt\" type \"single ctf team\" }\n{ map \"q3dm0\" }\n",
+        );
+        let (map, _) = MapFile::parse(
+            "{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"info_player_deathmatch\"\n}\n{\n\"classname\" \"info_player_start\"\n}\n{\n\"classname\" \"team_CTF_redflag\"\n}\n{\n\"classname\" \"team_CTF_redspawn\"\n}\n",
+        );
+        let messages: Vec<_> = check_entities(&file.arenas()[0], map.entities())
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                "type 'single' has 2 deathmatch spawns for the player and 2 bots",
+                "type 'ctf' needs a 'team_CTF_blueflag'",
+                "type 'ctf' wants a 'team_CTF_bluespawn', players spawn at deathmatch spawn points",
+            ]
+        );
+        let diagnostics = check_entities(&file.arenas()[1], &map.entities()[..1]);
+        assert_eq!(
+            diagnostics[0].message(),
+            "map has no 'info_player_deathmatch' to spawn at"
+        );
+        assert_eq!(diagnostics[0].span(), file.arenas()[1].span());
+    }
+}
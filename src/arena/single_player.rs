@@ -0,0 +1,318 @@
+//! Single player progression as the UI builds it from the arena database, tiers of
+//! [`ARENAS_PER_TIER`] arenas between the `training` and `final` arenas.
+
+use crate::{diagnostic::Diagnostic, span::RawSpan};
+
+use super::database::{ArenaDatabase, ArenaDefinition, ARENAS_FILE};
+
+/// `ARENAS_PER_TIER`, the arenas played against the same bots to unlock the next tier.
+pub const ARENAS_PER_TIER: usize = 4;
+/// `special` value of the arena played before the first tier.
+pub const TRAINING: &str = "training";
+/// `special` value of the arena played once every tier is completed.
+pub const FINAL: &str = "final";
+
+/// Tiers of the single player arenas, in the order the menu shows them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SinglePlayer<'a> {
+    training: Option<ArenaDefinition<'a>>,
+    tiers: Vec<Tier<'a>>,
+    final_arena: Option<ArenaDefinition<'a>>,
+    ignored: Vec<ArenaDefinition<'a>>,
+}
+
+impl<'a> SinglePlayer<'a> {
+    /// Assigns the arenas to tiers like `UI_LoadArenas`.
+    ///
+    /// Arenas whose `type` contains `single` are single player arenas in load order, and arenas
+    /// of any type with a `special` value are apart like in `UI_GetSpecialArenaInfo`. The arenas
+    /// left over after the last full tier are ignored.
+    /// Returns the problems by file, a missing training or final arena by the first file loaded.
+    pub fn new(database: &'a ArenaDatabase) -> (Self, Vec<(String, Diagnostic)>) {
+        let mut single_player = Self::default();
+        let mut problems = Vec::new();
+        let mut arenas = Vec::new();
+        let mut specials: Vec<(&str, ArenaDefinition)> = Vec::new();
+
+        for definition in database.arenas() {
+            let arena = definition.arena();
+            let special = match arena.special().filter(|special| !special.is_empty()) {
+                Some(special) => special,
+                None => {
+                    if arena.get("type").unwrap_or_default().contains("single") {
+                        arenas.push(definition);
+                    }
+                    continue;
+                }
+            };
+            let span = arena
+                .pair("special")
+                .map_or(arena.span(), |pair| pair.value_span());
+            if let Some((_, first)) = specials
+                .iter()
+                .find(|(tag, _)| tag.eq_ignore_ascii_case(special))
+            {
+                problems.push((
+                    definition.file().to_owned(),
+                    Diagnostic::warning(
+                        format!(
+                            "special '{}' is already the map '{}' in '{}'",
+                            special,
+                            first.arena().map().unwrap_or_default(),
+                            first.file()
+                        ),
+                        span,
+                    ),
+                ));
+                continue;
+            }
+            if special.eq_ignore_ascii_case(TRAINING) {
+                single_player.training = Some(definition);
+            } else if special.eq_ignore_ascii_case(FINAL) {
+                single_player.final_arena = Some(definition);
+            } else {
+                problems.push((
+                    definition.file().to_owned(),
+                    Diagnostic::warning(
+                        format!(
+                            "unknown special '{}', expect '{}' or '{}'",
+                            special, TRAINING, FINAL
+                        ),
+                        span,
+                    ),
+                ));
+            }
+            specials.push((special, definition));
+        }
+
+        let count = arenas.len() - arenas.len() % ARENAS_PER_TIER;
+        single_player.ignored = arenas.split_off(count);
+        if let Some(first) = single_player.ignored.first() {
+            problems.push((
+                first.file().to_owned(),
+                Diagnostic::warning(
+                    format!(
+                        "{} arenas ignored to make count divisible by {}",
+                        single_player.ignored.len(),
+                        ARENAS_PER_TIER
+                    ),
+                    first.span(),
+                ),
+            ));
+        }
+        single_player.tiers = arenas
+            .chunks(ARENAS_PER_TIER)
+            .map(|arenas| Tier {
+                arenas: arenas.to_vec(),
+            })
+            .collect();
+
+        let file = database.files().first().map_or(ARENAS_FILE, String::as_str);
+        if single_player.tiers.is_empty() {
+            problems.push((
+                file.to_owned(),
+                Diagnostic::error(
+                    format!(
+                        "no tier, {} single player arenas of at least {} needed",
+                        single_player.ignored.len(),
+                        ARENAS_PER_TIER
+                    ),
+                    RawSpan::default(),
+                ),
+            ));
+        }
+        for (special, arena) in [
+            (TRAINING, single_player.training),
+            (FINAL, single_player.final_arena),
+        ] {
+            if arena.is_none() {
+                problems.push((
+                    file.to_owned(),
+                    Diagnostic::error(
+                        format!("no arena with special '{}'", special),
+                        RawSpan::default(),
+                    ),
+                ));
+            }
+        }
+        (single_player, problems)
+    }
+
+    /// Get the `training` arena.
+    pub const fn training(&self) -> Option<&ArenaDefinition<'a>> {
+        self.training.as_ref()
+    }
+
+    /// Get the tiers, the first played first.
+    pub fn tiers(&self) -> &[Tier<'a>] {
+        &self.tiers
+    }
+
+    /// Get the `final` arena.
+    pub const fn final_arena(&self) -> Option<&ArenaDefinition<'a>> {
+        self.final_arena.as_ref()
+    }
+
+    /// Get the single player arenas after the last full tier.
+    pub fn ignored(&self) -> &[ArenaDefinition<'a>] {
+        &self.ignored
+    }
+
+    /// Get the index of the tier with the `map`, ignoring case.
+    pub fn tier_of(&self, map: &str) -> Option<usize> {
+        self.tiers
+            .iter()
+            .position(|tier| tier.maps().any(|name| name.eq_ignore_ascii_case(map)))
+    }
+
+    /// Get the number of unlocked tiers given the completed maps, the first tier is always
+    /// unlocked and each completed tier unlocks the next one.
+    pub fn unlocked_tiers(&self, completed: impl Fn(&str) -> bool) -> usize {
+        let completed_tiers = self
+            .tiers
+            .iter()
+            .take_while(|tier| tier.maps().all(&completed))
+            .count();
+        (completed_tiers + 1).min(self.tiers.len())
+    }
+
+    /// The final arena is unlocked once every tier is completed.
+    pub fn is_final_unlocked(&self, completed: impl Fn(&str) -> bool) -> bool {
+        self.tiers.iter().all(|tier| tier.maps().all(&completed))
+    }
+}
+
+/// Arenas unlocked together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tier<'a> {
+    arenas: Vec<ArenaDefinition<'a>>,
+}
+
+impl<'a> Tier<'a> {
+    /// Get the arenas in load order.
+    pub fn arenas(&self) -> &[ArenaDefinition<'a>] {
+        &self.arenas
+    }
+
+    /// Get the maps of the arenas.
+    pub fn maps(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.arenas
+            .iter()
+            .map(|definition| definition.arena().map().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers() {
+        let mut database = ArenaDatabase::new();
+        let source = std::fs::read_to_string("fixtures/arenas.txt").unwrap();
+        database.add(ARENAS_FILE, &source);
+        database.add(
+            "scripts/sp.arena",
+            "{ map \"q3dm0\" type \"single\" special \"training\" }\n\
+             { map \"q3tourney6\" type \"single\" special \"final\" }\n\
+             { map \"extra\" type \"single ffa\" }\n",
+        );
+        let (single_player, problems) = SinglePlayer::new(&database);
+        assert_eq!(
+            single_player
+                .training()
+                .and_then(|arena| arena.arena().map()),
+            Some("q3dm0")
+        );
+        assert_eq!(
+            single_player
+                .final_arena()
+                .and_then(|arena| arena.arena().map()),
+            Some("q3tourney6")
+        );
+        assert_eq!(
+            single_player.tiers()[0].maps().collect::<Vec<_>>(),
+            &["oa_rpg3dm2", "ce1m7", "aggressor", "oa_dm1"]
+        );
+        assert_eq!(single_player.tier_of("OA_DM2"), Some(1));
+
+        assert_eq!(single_player.tiers().len(), 6);
+        assert_eq!(single_player.ignored()[0].arena().map(), Some("extra"));
+        let messages: Vec<_> = problems
+            .iter()
+            .map(|(path, diagnostic)| (path.as_str(), diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[(
+                "scripts/sp.arena",
+                "1 arenas ignored to make count divisible by 4"
+            )]
+        );
+
+        let first_tier: Vec<_> = single_player.tiers()[0].maps().collect();
+        assert_eq!(single_player.unlocked_tiers(|_| false), 1);
+        assert_eq!(
+            single_player.unlocked_tiers(|map| first_tier.contains(&map)),
+            2
+        );
+        assert!(!single_player.is_final_unlocked(|map| first_tier.contains(&map)));
+        assert!(single_player.is_final_unlocked(|_| true));
+    }
+
+    #[test]
+    fn missing() {
+        let mut database = ArenaDatabase::new();
+        database.add(
+            "scripts/custom.arena",
+            "{ map \"a\" type \"single\" }\n\
+             { map \"b\" type \"single\" special \"Training\" }\n\
+             { map \"c\" type \"single\" special \"training\" }\n\
+             { map \"d\" type \"ffa\" special \"final\" }\n",
+        );
+        let (single_player, problems) = SinglePlayer::new(&database);
+        assert!(single_player.tiers().is_empty());
+        assert_eq!(
+            single_player
+                .training()
+                .and_then(|arena| arena.arena().map()),
+            Some("b")
+        );
+        assert_eq!(
+            single_player
+                .final_arena()
+                .and_then(|arena| arena.arena().map()),
+            Some("d")
+        );
+        let messages: Vec<_> = problems
+            .iter()
+            .map(|(_, diagnostic)| diagnostic.message())
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                "special 'training' is already the map 'b' in 'scripts/custom.arena'",
+                "1 arenas ignored to make count divisible by 4",
+                "no tier, 1 single player arenas of at least 4 needed",
+            ]
+        );
+
+        let database = ArenaDatabase::new();
+        let (_, problems) = SinglePlayer::new(&database);
+        let messages: Vec<_> = problems
+            .iter()
+            .map(|(path, diagnostic)| (path.as_str(), diagnostic.message()))
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                (
+                    ARENAS_FILE,
+                    "no tier, 0 single player arenas of at least 4 needed"
+                ),
+                (ARENAS_FILE, "no arena with special 'training'"),
+                (ARENAS_FILE, "no arena with special 'final'"),
+            ]
+        );
+    }
+}
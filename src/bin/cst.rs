@@ -1,4 +1,4 @@
-use std::{fmt::Write as _, path::PathBuf};
+use std::path::PathBuf;
 
 use clap::Parser as _;
 
@@ -19,7 +19,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let lexer = Lexer::new(&content);
     let tokens: Vec<_> = lexer.collect();
 
-    let mut parser = Parser::new(&tokens[..]);
+    let parser = Parser::new(&tokens[..]);
     let (events, errors) = parser.parse(arenas);
 
     let sink = Sink::new(&content, tokens, events);
@@ -0,0 +1,149 @@
+//! Little endian reading of binary game files, e.g. compiled maps and models.
+
+use std::borrow::Cow;
+
+use crate::span::RawSpan;
+
+/// Size of a path in records, `MAX_QPATH`.
+pub(crate) const MAX_QPATH: usize = 64;
+
+pub(crate) fn span(start: usize, end: usize) -> RawSpan {
+    RawSpan::new(start as u32, end as u32)
+}
+
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(&data[offset..offset + N]);
+    bytes
+}
+
+pub(crate) fn int(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(bytes(data, offset))
+}
+
+/// Little endian `int` read as unsigned, so negative indexes and counts are out of range.
+pub(crate) fn index(data: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(bytes(data, offset)) as usize
+}
+
+pub(crate) fn short(data: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes(bytes(data, offset))
+}
+
+pub(crate) fn float(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes(data, offset))
+}
+
+/// Three little endian floats, `vec3_t`.
+pub(crate) fn vector(data: &[u8], offset: usize) -> [f32; 3] {
+    [
+        float(data, offset),
+        float(data, offset + 4),
+        float(data, offset + 8),
+    ]
+}
+
+/// NUL terminated string filling a fixed size field, `None` if it isn't terminated.
+pub(crate) fn name(field: &[u8]) -> Option<Cow<'_, str>> {
+    let end = field.iter().position(|byte| *byte == 0)?;
+    Some(String::from_utf8_lossy(&field[..end]))
+}
+
+/// `count` records of `size` bytes at `offset`, `None` if they're outside of `data`.
+pub(crate) fn region(data: &[u8], offset: usize, count: usize, size: usize) -> Option<&[u8]> {
+    let end = count.checked_mul(size)?.checked_add(offset)?;
+    data.get(offset..end)
+}
+
+/// `Com_BlockChecksum`, the words of the data's MD4 digest xored, e.g. a map's `sv_mapChecksum`.
+pub(crate) fn block_checksum(data: &[u8]) -> i32 {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_le_bytes());
+
+    // Function, constant, order of the words and shifts of each round
+    type Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in message.chunks_exact(64) {
+        let mut words = [0; 16];
+        for (number, word) in words.iter_mut().enumerate() {
+            *word = index(block, number * 4) as u32;
+        }
+        let rounds: [Round; 3] = [
+            (
+                |x, y, z| (x & y) | (!x & z),
+                0,
+                [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+                [3, 7, 11, 19],
+            ),
+            (
+                |x, y, z| (x & y) | (x & z) | (y & z),
+                0x5a82_7999,
+                [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+                [3, 5, 9, 13],
+            ),
+            (
+                |x, y, z| x ^ y ^ z,
+                0x6ed9_eba1,
+                [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+                [3, 9, 11, 15],
+            ),
+        ];
+        let [mut a, mut b, mut c, mut d] = state;
+        for (function, constant, order, shifts) in &rounds {
+            for (step, word) in order.iter().enumerate() {
+                let value = a
+                    .wrapping_add(function(b, c, d))
+                    .wrapping_add(words[*word])
+                    .wrapping_add(*constant)
+                    .rotate_left(shifts[step % 4]);
+                a = d;
+                d = c;
+                c = b;
+                b = value;
+            }
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    (state[0] ^ state[1] ^ state[2] ^ state[3]) as i32
+}
+
+/// Little endian `int`s of a test file.
+#[cfg(test)]
+pub(crate) fn ints(values: &[i32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Little endian `float`s of a test file.
+#[cfg(test)]
+pub(crate) fn floats(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum() {
+        // MD4 digests 31d6cfe0d16ae931b73c59d7e0c089c0 and a448017aaf21d8525fc10ae87aa6729d
+        assert_eq!(block_checksum(b""), 0xc6f6_40b7u32 as i32);
+        assert_eq!(block_checksum(b"abc"), 0x5da1_0e2e);
+        // More than a block, e33b4ddc9c38f2199c3e7b164fcc0536
+        assert_eq!(
+            block_checksum("1234567890".repeat(8).as_bytes()),
+            0xe5c1_f1acu32 as i32
+        );
+    }
+}
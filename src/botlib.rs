@@ -0,0 +1,81 @@
+//! Bot library (`botlib`) script formats below `botfiles/`.
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::ScriptLexer,
+    parser::{CompletedMarker, Parser},
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{self, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+use self::precomp::Defines;
+
+pub mod chat;
+pub mod match_template;
+pub mod precomp;
+pub mod reply_chat;
+pub mod structure;
+pub mod synonym;
+pub mod weight;
+
+/// Botlib script parsed with one of the [`parse`](crate::parse) grammars.
+#[derive(Debug)]
+pub(crate) struct Script {
+    pub(crate) root: SyntaxNode,
+    pub(crate) defines: Defines,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl Script {
+    /// Lexes and parses `source`, collecting its defines and any `#include`d ones.
+    pub(crate) fn parse(
+        source: &str,
+        resolver: Option<&dyn FileResolver>,
+        defines: Defines,
+        grammar: impl FnOnce(&mut Parser) -> Option<CompletedMarker>,
+    ) -> Self {
+        let tokens: Vec<_> = ScriptLexer::new(source).collect();
+
+        let mut defines = defines;
+        let mut diagnostics = defines.collect(&tokens, resolver);
+
+        let parse = syntax::parse_tokens(source, tokens, grammar);
+        diagnostics.extend(parse.errors().iter().cloned());
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        Self {
+            root: parse.syntax(),
+            defines,
+            diagnostics,
+        }
+    }
+}
+
+/// Evaluates an expression node, reporting empty expressions and undefined names.
+pub(crate) fn evaluate(
+    source: &str,
+    defines: &Defines,
+    node: &SyntaxNode,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<f64> {
+    let span = RawSpan::from(node.text_range());
+    let text = &source[span];
+    if text.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("expect expression", span));
+        return None;
+    }
+    let value = defines.evaluate(text);
+    if value.is_none() {
+        let undefined = child_tokens(node)
+            .filter(|token| token.kind() == SyntaxKind::Name)
+            .map(|token| &source[RawSpan::from(token.text_range())])
+            .find(|name| !defines.contains(name) && !name.starts_with("eval"));
+        let message = match undefined {
+            Some(name) => format!("undefined name '{}'", name),
+            None => format!("invalid expression '{}'", text.trim()),
+        };
+        diagnostics.push(Diagnostic::error(message, span));
+    }
+    value
+}
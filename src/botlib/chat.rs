@@ -0,0 +1,682 @@
+//! Bot chat files (`*_t.c`) and random strings (`rnd.c`).
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+use super::{
+    precomp::{self, Defines, Directive},
+    Script,
+};
+
+/// Number of variables a chat message can refer to, `MAX_MATCHVARIABLES` in `be_ai_chat.c`.
+pub const MAX_MATCH_VARIABLES: u32 = 8;
+
+/// Character that delimits variables and random strings in a built chat message.
+pub const ESCAPE_CHAR: char = '\x01';
+
+/// Maximum depth of random strings referring to random strings when expanding.
+pub const MAX_EXPAND_DEPTH: usize = 8;
+
+/// Bot chat file, e.g. `botfiles/bots/sarge_t.c`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatFile {
+    chats: Vec<Chat>,
+    defines: Defines,
+}
+
+impl ChatFile {
+    /// Parses a chat file, resolving `#include`d headers and chat types through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(source, resolver, Defines::new(), parse::chat::chat_file);
+        let mut diagnostics = script.diagnostics.clone();
+
+        let chats = script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Chat))
+            .map(|node| Chat::from_node(node, source, resolver, &script.defines, &mut diagnostics))
+            .collect();
+
+        let file = Self {
+            chats,
+            defines: script.defines,
+        };
+        (file, diagnostics)
+    }
+
+    /// Get the chats of this file.
+    pub fn chats(&self) -> &[Chat] {
+        &self.chats
+    }
+
+    /// Get the chat with the given name.
+    pub fn chat(&self, name: &str) -> Option<&Chat> {
+        self.chats.iter().find(|chat| chat.name == name)
+    }
+
+    /// Get the defines of this file and its includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    /// Index of the variable defined as `name`, e.g. `NETNAME`.
+    pub fn variable_index(&self, name: &str) -> Option<u32> {
+        self.defines.value(name).map(|index| index as u32)
+    }
+
+    /// Checks escapes and references of all messages.
+    ///
+    /// Random string references are only checked if `random` strings are given.
+    pub fn check(&self, random: Option<&RandomStrings>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for chat in &self.chats {
+            let mut seen = HashMap::new();
+            for chat_type in &chat.types {
+                if let Some(previous) = seen.insert(chat_type.name.as_str(), chat_type.span) {
+                    diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "chat type '{}' already defined at {}, only the first one is used",
+                            chat_type.name,
+                            previous.start()
+                        ),
+                        chat_type.reported_span(chat_type.span),
+                    ));
+                }
+                for message in &chat_type.messages {
+                    let mut nested = Vec::new();
+                    message.check(random, &mut nested);
+                    diagnostics.extend(nested.into_iter().map(|d| chat_type.reported(d)));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// `chat "name" { … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chat {
+    name: String,
+    span: RawSpan,
+    types: Vec<ChatType>,
+}
+
+impl Chat {
+    fn from_node(
+        node: &SyntaxNode,
+        source: &str,
+        resolver: Option<&dyn FileResolver>,
+        defines: &Defines,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let mut types = Vec::new();
+        for element in node.children_with_tokens() {
+            match element {
+                cstree::util::NodeOrToken::Node(child) if child.kind() == SyntaxKind::ChatType => {
+                    types.push(ChatType::from_node(child, source, defines, None));
+                }
+                cstree::util::NodeOrToken::Token(token)
+                    if token.kind() == SyntaxKind::Preprocessor =>
+                {
+                    let span = RawSpan::from(token.text_range());
+                    if let Some(Directive::Include { path }) = Directive::parse(&source[span]) {
+                        let include = Include { path, span };
+                        types.extend(include.chat_types(resolver, defines, diagnostics));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            name: string_token(node, source).unwrap_or_default(),
+            span: node.text_range().into(),
+            types,
+        }
+    }
+
+    /// Get the chat's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the chat's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the chat's types, including `#include`d ones.
+    pub fn types(&self) -> &[ChatType] {
+        &self.types
+    }
+
+    /// Get the first chat type with the given name, like the engine does.
+    pub fn chat_type(&self, name: &str) -> Option<&ChatType> {
+        self.types.iter().find(|chat_type| chat_type.name == name)
+    }
+}
+
+/// `#include` directive within a chat.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Include {
+    path: String,
+    span: RawSpan,
+}
+
+impl Include {
+    /// Get the included path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the span of the directive.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    fn chat_types(
+        &self,
+        resolver: Option<&dyn FileResolver>,
+        defines: &Defines,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<ChatType> {
+        // Unreadable includes have already been reported while collecting defines
+        let Some(source) = resolver.and_then(|r| r.read_to_string(&self.path).ok()) else {
+            return Vec::new();
+        };
+        let script = Script::parse(&source, None, defines.clone(), parse::chat::chat_types);
+        diagnostics.extend(script.diagnostics.iter().map(|diagnostic| {
+            Diagnostic::new(
+                diagnostic.severity(),
+                format!("in '{}': {}", self.path, diagnostic.message()),
+                self.span,
+            )
+        }));
+
+        script
+            .root
+            .children()
+            .flat_map(|types| child_nodes(types, SyntaxKind::ChatType))
+            .map(|node| ChatType::from_node(node, &source, &script.defines, Some(self.clone())))
+            .collect()
+    }
+}
+
+/// `type "name" { … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatType {
+    name: String,
+    span: RawSpan,
+    messages: Vec<ChatMessage>,
+    include: Option<Include>,
+}
+
+impl ChatType {
+    fn from_node(
+        node: &SyntaxNode,
+        source: &str,
+        defines: &Defines,
+        include: Option<Include>,
+    ) -> Self {
+        Self {
+            name: string_token(node, source).unwrap_or_default(),
+            span: node.text_range().into(),
+            messages: child_nodes(node, SyntaxKind::ChatMessage)
+                .map(|node| ChatMessage::from_node(node, source, defines))
+                .collect(),
+            include,
+        }
+    }
+
+    /// Get the chat type's name, e.g. `game_enter`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the chat type's span.
+    ///
+    /// The span is relative to the included file if the type is [`include`](Self::include)d.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the chat type's messages.
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+
+    /// Get the `#include` this type was read from.
+    pub const fn include(&self) -> Option<&Include> {
+        self.include.as_ref()
+    }
+
+    fn reported_span(&self, span: RawSpan) -> RawSpan {
+        self.include.as_ref().map_or(span, Include::span)
+    }
+
+    fn reported(&self, diagnostic: Diagnostic) -> Diagnostic {
+        match &self.include {
+            None => diagnostic,
+            Some(include) => Diagnostic::new(
+                diagnostic.severity(),
+                format!("in '{}': {}", include.path, diagnostic.message()),
+                include.span,
+            ),
+        }
+    }
+}
+
+/// Chat message template, e.g. `"Hello ", NETNAME, "!";`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pieces: Vec<MessagePiece>,
+    span: RawSpan,
+}
+
+impl ChatMessage {
+    pub(crate) fn from_node(node: &SyntaxNode, source: &str, defines: &Defines) -> Self {
+        let pieces = child_tokens(node)
+            .filter_map(|token| {
+                let span = RawSpan::from(token.text_range());
+                let text = &source[span];
+                let kind = match token.kind() {
+                    SyntaxKind::QuotedString => PieceKind::Text(precomp::unquote(text).to_owned()),
+                    SyntaxKind::Number => PieceKind::Variable {
+                        name: None,
+                        index: precomp::parse_number(text)? as i64,
+                    },
+                    SyntaxKind::Name => match defines.value(text) {
+                        Some(index) => PieceKind::Variable {
+                            name: Some(text.to_owned()),
+                            index: index as i64,
+                        },
+                        None => PieceKind::Random(text.to_owned()),
+                    },
+                    _ => return None,
+                };
+                Some(MessagePiece { kind, span })
+            })
+            .collect();
+
+        Self {
+            pieces,
+            span: node.text_range().into(),
+        }
+    }
+
+    /// Get the message's pieces.
+    pub fn pieces(&self) -> &[MessagePiece] {
+        &self.pieces
+    }
+
+    /// Get the message's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    pub(crate) fn check(&self, random: Option<&RandomStrings>, diagnostics: &mut Vec<Diagnostic>) {
+        let mut text = String::new();
+        for piece in &self.pieces {
+            match &piece.kind {
+                PieceKind::Text(raw) => match precomp::unescape(raw) {
+                    Ok(unescaped) => text.push_str(&unescaped),
+                    Err(error) => {
+                        // Skip the opening quote
+                        let start = piece.span.start() + 1;
+                        let span =
+                            RawSpan::new(start + error.span().start(), start + error.span().end());
+                        diagnostics.push(Diagnostic::error(error.message(), span));
+                    }
+                },
+                PieceKind::Variable { name, index } => {
+                    if !(0..i64::from(MAX_MATCH_VARIABLES)).contains(index) {
+                        let name = name.clone().unwrap_or_else(|| index.to_string());
+                        diagnostics.push(Diagnostic::error(
+                            format!(
+                                "unknown variable '{}', there are only {} variables",
+                                name, MAX_MATCH_VARIABLES
+                            ),
+                            piece.span,
+                        ));
+                    }
+                }
+                PieceKind::Random(name) => {
+                    if random.is_some_and(|random| !random.contains(name)) {
+                        diagnostics.push(Diagnostic::error(
+                            format!("unknown variable or random string '{}'", name),
+                            piece.span,
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Escapes embedded in the text must form `\x01v0\x01` or `\x01rname\x01` sequences
+        let segments: Vec<_> = text.split(ESCAPE_CHAR).collect();
+        if segments.len() % 2 == 0 {
+            diagnostics.push(Diagnostic::error("unbalanced escape characters", self.span));
+        } else {
+            for escape in segments.iter().skip(1).step_by(2) {
+                let mut chars = escape.chars();
+                let valid = match (chars.next(), chars.as_str()) {
+                    (Some('v'), index) => index
+                        .parse::<u32>()
+                        .is_ok_and(|index| index < MAX_MATCH_VARIABLES),
+                    (Some('r'), name) => !name.is_empty(),
+                    _ => false,
+                };
+                if !valid {
+                    diagnostics.push(Diagnostic::error(
+                        format!("invalid escape sequence '{}'", escape.escape_default()),
+                        self.span,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Part of a chat message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessagePiece {
+    kind: PieceKind,
+    span: RawSpan,
+}
+
+impl MessagePiece {
+    /// Get the piece's kind.
+    pub const fn kind(&self) -> &PieceKind {
+        &self.kind
+    }
+
+    /// Get the piece's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Kind of a chat message part.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    /// Quoted text, still escaped.
+    Text(String),
+    /// Variable filled in by the game, either a number or a defined name like `NETNAME`.
+    Variable { name: Option<String>, index: i64 },
+    /// Reference to a random string, i.e. an undefined name.
+    Random(String),
+}
+
+impl PieceKind {
+    /// Unescaped text, keeping invalid escape sequences as they are.
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Self::Text(raw) => Some(precomp::unescape(raw).unwrap_or_else(|_| raw.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Random strings, e.g. `botfiles/rnd.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RandomStrings {
+    randoms: Vec<Random>,
+}
+
+impl RandomStrings {
+    /// Parses a random strings file, resolving `#include`s through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(source, resolver, Defines::new(), parse::chat::random_file);
+
+        let randoms = script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Random))
+            .map(|node| {
+                let name = child_tokens(node)
+                    .find(|token| token.kind() == SyntaxKind::Name)
+                    .map(|token| source[RawSpan::from(token.text_range())].to_owned())
+                    .unwrap_or_default();
+                Random {
+                    name,
+                    span: node.text_range().into(),
+                    messages: child_nodes(node, SyntaxKind::ChatMessage)
+                        .map(|node| ChatMessage::from_node(node, source, &script.defines))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        (Self { randoms }, script.diagnostics)
+    }
+
+    /// Get all random strings.
+    pub fn randoms(&self) -> &[Random] {
+        &self.randoms
+    }
+
+    /// Get the random string with the given name.
+    pub fn get(&self, name: &str) -> Option<&Random> {
+        self.randoms.iter().find(|random| random.name == name)
+    }
+
+    /// There is a random string with the given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+}
+
+/// `name { "alternative"; … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+    name: String,
+    span: RawSpan,
+    messages: Vec<ChatMessage>,
+}
+
+impl Random {
+    /// Get the random string's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the random string's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the alternatives the engine picks from.
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+}
+
+/// Renders chat messages with example variable values.
+///
+/// Random strings expand to their first alternative so previews are stable.
+#[derive(Debug, Clone, Default)]
+pub struct Expander<'a> {
+    variables: HashMap<i64, String>,
+    random: Option<&'a RandomStrings>,
+}
+
+impl<'a> Expander<'a> {
+    /// Creates a new expander without any variable values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the variable with the given index.
+    pub fn variable(mut self, index: u32, value: impl Into<String>) -> Self {
+        self.variables.insert(i64::from(index), value.into());
+        self
+    }
+
+    /// Sets the random strings to expand references with.
+    pub fn random_strings(mut self, random: &'a RandomStrings) -> Self {
+        self.random = Some(random);
+        self
+    }
+
+    /// Renders a message, replacing unknown references by `[name]`.
+    pub fn expand(&self, message: &ChatMessage) -> String {
+        let mut rendered = String::new();
+        self.expand_into(message, 0, &mut rendered);
+        rendered
+    }
+
+    /// Renders all messages of a chat type.
+    pub fn expand_type(&self, chat_type: &ChatType) -> Vec<String> {
+        chat_type.messages.iter().map(|m| self.expand(m)).collect()
+    }
+
+    fn expand_into(&self, message: &ChatMessage, depth: usize, rendered: &mut String) {
+        for piece in &message.pieces {
+            match &piece.kind {
+                kind @ PieceKind::Text(_) => rendered.push_str(&kind.text().unwrap_or_default()),
+                PieceKind::Variable { name, index } => match self.variables.get(index) {
+                    Some(value) => rendered.push_str(value),
+                    None => match name {
+                        Some(name) => rendered.push_str(&format!("[{}]", name)),
+                        None => rendered.push_str(&format!("[var {}]", index)),
+                    },
+                },
+                PieceKind::Random(name) => {
+                    let first = self
+                        .random
+                        .and_then(|random| random.get(name))
+                        .and_then(|random| random.messages.first());
+                    match first {
+                        Some(first) if depth < MAX_EXPAND_DEPTH => {
+                            self.expand_into(first, depth + 1, rendered)
+                        }
+                        _ => rendered.push_str(&format!("[{}]", name)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Unescaped text of the first quoted string token of `node`.
+fn string_token(node: &SyntaxNode, source: &str) -> Option<String> {
+    let token = child_tokens(node).find(|token| token.kind() == SyntaxKind::QuotedString)?;
+    let text = precomp::unquote(&source[RawSpan::from(token.text_range())]);
+    Some(precomp::unescape(text).unwrap_or_else(|_| text.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAT: &str = r#"#define NETNAME 0
+chat "sarge"
+{
+    #include "teamplay.h"
+
+    type "game_enter"
+    {
+        "Hello ", NETNAME, ", this is ", fight_insult, "!";
+        "Bad \q escape", 9;
+    }
+}
+"#;
+
+    fn files() -> HashMap<String, String> {
+        HashMap::from([(
+            "teamplay.h".to_owned(),
+            r#"type "cmd_accept" { "Yes ", NETNAME; }"#.to_owned(),
+        )])
+    }
+
+    #[test]
+    fn parse() {
+        let files = files();
+        let (file, diagnostics) = ChatFile::parse(CHAT, Some(&files));
+        assert_eq!(diagnostics, &[]);
+
+        let chat = file.chat("sarge").unwrap();
+        let names: Vec<_> = chat.types().iter().map(ChatType::name).collect();
+        assert_eq!(names, &["cmd_accept", "game_enter"]);
+        assert_eq!(chat.types()[0].include().unwrap().path(), "teamplay.h");
+
+        let message = &chat.chat_type("game_enter").unwrap().messages()[0];
+        assert_eq!(
+            message.pieces()[1].kind(),
+            &PieceKind::Variable {
+                name: Some("NETNAME".to_owned()),
+                index: 0
+            }
+        );
+        assert_eq!(
+            message.pieces()[3].kind(),
+            &PieceKind::Random("fight_insult".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let (file, diagnostics) = ChatFile::parse("chat \"a\" { type \"b\" { \"c\" } }", None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(file.chats()[0].types()[0].messages().len(), 1);
+    }
+
+    #[test]
+    fn check() {
+        let files = files();
+        let (file, _) = ChatFile::parse(CHAT, Some(&files));
+        let (random, _) = RandomStrings::parse("other { \"x\"; }", None);
+
+        let diagnostics = file.check(Some(&random));
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "unknown variable or random string 'fight_insult'",
+                "unknown escape char 'q'",
+                "unknown variable '9', there are only 8 variables",
+            ]
+        );
+        assert_eq!(&CHAT[diagnostics[1].span()], "\\q");
+    }
+
+    #[test]
+    fn check_escape_chars() {
+        let (file, _) = ChatFile::parse(
+            "chat \"a\" { type \"b\" { \"\\x01v1\\x01\"; \"\\x01v\"; \"\\x01x\\x01\"; \"\\x01é\\x01\"; } }",
+            None,
+        );
+        let diagnostics = file.check(None);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "unbalanced escape characters",
+                "invalid escape sequence 'x'",
+                "invalid escape sequence '\\u{e9}'"
+            ]
+        );
+    }
+
+    #[test]
+    fn expand() {
+        let files = files();
+        let (file, _) = ChatFile::parse(CHAT, Some(&files));
+        let (random, _) = RandomStrings::parse("fight_insult { \"loser\"; \"noob\"; }", None);
+        let expander = Expander::new()
+            .variable(file.variable_index("NETNAME").unwrap(), "Sarge")
+            .random_strings(&random);
+
+        let game_enter = file.chat("sarge").unwrap().chat_type("game_enter").unwrap();
+        assert_eq!(
+            expander.expand_type(game_enter),
+            &["Hello Sarge, this is loser!", "Bad \\q escape[var 9]"]
+        );
+    }
+}
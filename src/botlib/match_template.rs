@@ -0,0 +1,419 @@
+//! Match templates (`match.c`) recognising chat messages.
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+use super::{
+    chat::MAX_MATCH_VARIABLES,
+    evaluate,
+    precomp::{self, Defines},
+    Script,
+};
+
+/// Match templates, e.g. `botfiles/match.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchTemplates {
+    templates: Vec<MatchTemplate>,
+    defines: Defines,
+}
+
+impl MatchTemplates {
+    /// Parses match templates, resolving `#include`d headers like `match.h` through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(
+            source,
+            resolver,
+            Defines::new(),
+            parse::match_template::match_file,
+        );
+        let mut diagnostics = script.diagnostics.clone();
+
+        let mut templates = Vec::new();
+        for context in script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::MatchContext))
+        {
+            let context_value = child_nodes(context, SyntaxKind::Expression)
+                .next()
+                .and_then(|node| evaluate(source, &script.defines, node, &mut diagnostics))
+                .unwrap_or_default() as i64;
+            if context_value == 0 {
+                diagnostics.push(Diagnostic::warning(
+                    "match context is 0, its templates never match",
+                    context.text_range().into(),
+                ));
+            }
+
+            for template in child_nodes(context, SyntaxKind::MatchTemplate) {
+                let pieces = match_pieces(template, source, &script.defines, &mut diagnostics);
+                let mut values = child_nodes(template, SyntaxKind::Expression).map(|node| {
+                    evaluate(source, &script.defines, node, &mut diagnostics).unwrap_or_default()
+                        as i64
+                });
+                templates.push(MatchTemplate {
+                    context: context_value,
+                    pieces,
+                    message_type: values.next().unwrap_or_default(),
+                    subtype: values.next().unwrap_or_default(),
+                    span: template.text_range().into(),
+                });
+            }
+        }
+
+        let templates = Self {
+            templates,
+            defines: script.defines,
+        };
+        (templates, diagnostics)
+    }
+
+    /// Get the templates in file order.
+    pub fn templates(&self) -> &[MatchTemplate] {
+        &self.templates
+    }
+
+    /// Get the defines of the templates and their includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    /// Finds the first template of one of the `context` bits matching `message`, like `BotFindMatch`.
+    pub fn find(&self, message: &str, context: i64) -> Option<Match<'_>> {
+        let message = message.trim_end_matches('\n');
+        self.templates
+            .iter()
+            .filter(|template| template.context & context != 0)
+            .find_map(|template| {
+                let variables = template.matches(message)?;
+                Some(Match {
+                    template,
+                    variables,
+                })
+            })
+    }
+}
+
+/// `"help ", TEAMMATE = (MSG_HELP, ST_SOMEONE);`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchTemplate {
+    context: i64,
+    pieces: Vec<MatchPiece>,
+    message_type: i64,
+    subtype: i64,
+    span: RawSpan,
+}
+
+impl MatchTemplate {
+    /// Get the context bits of the enclosing block, `MTCONTEXT_*`.
+    pub const fn context(&self) -> i64 {
+        self.context
+    }
+
+    /// Get the template's pieces.
+    pub fn pieces(&self) -> &[MatchPiece] {
+        &self.pieces
+    }
+
+    /// Get the message type reported on a match, `MSG_*`.
+    pub const fn message_type(&self) -> i64 {
+        self.message_type
+    }
+
+    /// Get the message subtype reported on a match, `ST_*`.
+    pub const fn subtype(&self) -> i64 {
+        self.subtype
+    }
+
+    /// Get the template's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Matches `message` against the pieces, returning the captured variables.
+    pub fn matches(&self, message: &str) -> Option<Variables> {
+        strings_match(&self.pieces, message)
+    }
+}
+
+/// Part of a match template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchPiece {
+    kind: MatchPieceKind,
+    span: RawSpan,
+}
+
+impl MatchPiece {
+    /// Get the piece's kind.
+    pub const fn kind(&self) -> &MatchPieceKind {
+        &self.kind
+    }
+
+    /// Get the piece's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Kind of a match template part.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MatchPieceKind {
+    /// Alternative texts separated by `|`, unescaped.
+    Strings(Vec<String>),
+    /// Variable capturing text, either a number or a defined name like `NETNAME`.
+    Variable { name: Option<String>, index: i64 },
+}
+
+/// Variables captured by a match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variables {
+    values: Vec<Option<String>>,
+}
+
+impl Variables {
+    /// Get the text captured by the variable with the given index.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.values.get(index)?.as_deref()
+    }
+
+    /// Iterates over the captured variables and their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| Some((index, value.as_deref()?)))
+    }
+}
+
+/// Template that matched a message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    template: &'a MatchTemplate,
+    variables: Variables,
+}
+
+impl<'a> Match<'a> {
+    /// Get the template that matched.
+    pub const fn template(&self) -> &'a MatchTemplate {
+        self.template
+    }
+
+    /// Get the variables captured.
+    pub const fn variables(&self) -> &Variables {
+        &self.variables
+    }
+}
+
+/// Builds the pieces of the [`SyntaxKind::MatchPiece`] children of `node`.
+pub(crate) fn match_pieces(
+    node: &SyntaxNode,
+    source: &str,
+    defines: &Defines,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<MatchPiece> {
+    let mut pieces: Vec<MatchPiece> = Vec::new();
+    for piece in child_nodes(node, SyntaxKind::MatchPiece) {
+        let span = RawSpan::from(piece.text_range());
+        let strings: Vec<_> = child_tokens(piece)
+            .filter(|token| token.kind() == SyntaxKind::QuotedString)
+            .map(|token| {
+                let text = precomp::unquote(&source[RawSpan::from(token.text_range())]);
+                precomp::unescape(text).unwrap_or_else(|_| text.to_owned())
+            })
+            .collect();
+        let kind = if strings.is_empty() {
+            let text = source[span].trim();
+            let index = match precomp::parse_number(text).or_else(|| defines.value(text)) {
+                Some(index) => index as i64,
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("undefined name '{}'", text),
+                        span,
+                    ));
+                    continue;
+                }
+            };
+            if !(0..i64::from(MAX_MATCH_VARIABLES)).contains(&index) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "unknown variable '{}', there are only {} variables",
+                        text, MAX_MATCH_VARIABLES
+                    ),
+                    span,
+                ));
+            }
+            if pieces
+                .last()
+                .is_some_and(|last| matches!(last.kind, MatchPieceKind::Variable { .. }))
+            {
+                diagnostics.push(Diagnostic::error(
+                    "not allowed to have adjacent variables",
+                    span,
+                ));
+            }
+            let name = precomp::parse_number(text)
+                .is_none()
+                .then(|| text.to_owned());
+            MatchPieceKind::Variable { name, index }
+        } else {
+            MatchPieceKind::Strings(strings)
+        };
+        pieces.push(MatchPiece { kind, span });
+    }
+    pieces
+}
+
+/// Matches `message` against `pieces` like `StringsMatch` in `be_ai_chat.c`.
+///
+/// Texts are found case-insensitively. A text must follow the previous one directly, unless a
+/// variable in between captures everything up to it. The message must be consumed completely.
+pub(crate) fn strings_match(pieces: &[MatchPiece], message: &str) -> Option<Variables> {
+    let lower = message.to_ascii_lowercase();
+    let mut values = vec![None; MAX_MATCH_VARIABLES as usize];
+    let mut position = 0;
+    let mut variable: Option<(usize, usize)> = None;
+
+    for piece in pieces {
+        match &piece.kind {
+            MatchPieceKind::Strings(strings) => {
+                let mut found = None;
+                for string in strings {
+                    if string.is_empty() {
+                        found = Some((position, 0));
+                        break;
+                    }
+                    let Some(index) = lower[position..].find(&string.to_ascii_lowercase()) else {
+                        continue;
+                    };
+                    if let Some((variable, offset)) = variable.take() {
+                        values[variable] = Some(message[offset..position + index].to_owned());
+                        found = Some((position + index, string.len()));
+                        break;
+                    } else if index == 0 {
+                        found = Some((position, string.len()));
+                        break;
+                    }
+                }
+                let (start, len) = found?;
+                position = start + len;
+            }
+            MatchPieceKind::Variable { index, .. } => {
+                if !(0..values.len() as i64).contains(index) {
+                    return None;
+                }
+                variable = Some((*index as usize, position));
+            }
+        }
+    }
+
+    match variable {
+        Some((variable, offset)) => values[variable] = Some(message[offset..].to_owned()),
+        None if position < message.len() => return None,
+        None => {}
+    }
+    Some(Variables { values })
+}
+
+/// Characters ending a word, see `StringContainsWord` in `be_ai_chat.c`.
+const WORD_SEPARATORS: &[u8] = b" .,!";
+
+/// Finds `word` case-insensitively at the start of a word in `text`, returning its byte offset.
+pub(crate) fn contains_word(text: &str, word: &str) -> Option<usize> {
+    let text = text.to_ascii_lowercase();
+    let word = word.to_ascii_lowercase();
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    loop {
+        if text[start..].starts_with(&word) {
+            let end = start + word.len();
+            if bytes
+                .get(end)
+                .is_none_or(|byte| WORD_SEPARATORS.contains(byte))
+            {
+                return Some(start);
+            }
+        }
+        start +=
+            text[start..].find(|c: char| c.is_ascii() && WORD_SEPARATORS.contains(&(c as u8)))? + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const MATCH: &str = r#"#include "match.h"
+
+MTCONTEXT_MISC
+{
+    "help ", TEAMMATE, " near ", ITEM = (MSG_HELP, ST_NEARITEM);
+    "help ", TEAMMATE = (MSG_HELP, ST_SOMEONE);
+    "where are you" | "where r u" = (MSG_WHEREAREYOU, 0);
+}
+
+MTCONTEXT_CTF
+{
+    "get the flag" = (MSG_GETFLAG, 0);
+}
+"#;
+
+    const MATCH_H: &str = r#"#define MTCONTEXT_MISC 2
+#define MTCONTEXT_CTF 16
+#define TEAMMATE 0
+#define ITEM 1
+#define MSG_HELP 1
+#define MSG_GETFLAG 3
+#define MSG_WHEREAREYOU 7
+#define ST_NEARITEM 1
+#define ST_SOMEONE 2
+"#;
+
+    #[test]
+    fn find() {
+        let files = HashMap::from([("match.h".to_owned(), MATCH_H.to_owned())]);
+        let (templates, diagnostics) = MatchTemplates::parse(MATCH, Some(&files));
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(templates.templates().len(), 4);
+
+        let found = templates.find("Help Sarge near the RL\n", 2).unwrap();
+        assert_eq!(found.template().subtype(), 1);
+        assert_eq!(found.variables().get(0), Some("Sarge"));
+        assert_eq!(found.variables().get(1), Some("the RL"));
+
+        let found = templates.find("help Sarge", 2).unwrap();
+        assert_eq!(found.template().subtype(), 2);
+
+        let found = templates.find("where r u", 2).unwrap();
+        assert_eq!(found.template().message_type(), 7);
+
+        assert!(templates.find("get the flag", 2).is_none());
+        assert!(templates.find("get the flag", 16).is_some());
+        assert!(templates.find("please get the flag", 16).is_none());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (_, diagnostics) = MatchTemplates::parse(
+            r#"1 { "a", 0, 1 = (1, 0); "b", 9 = (UNDEFINED, 0); }
+            0 { "c" = (1, 0); }"#,
+            None,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "not allowed to have adjacent variables",
+                "unknown variable '9', there are only 8 variables",
+                "undefined name 'UNDEFINED'",
+                "match context is 0, its templates never match",
+            ]
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Subset of botlib's precompiler (`l_precomp.c`).
+//!
+//! The script lexer keeps directives as trivia, so grammars never see them.
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::{ScriptLexer, Token, TokenKind},
+    resolve::FileResolver,
+    span::RawSpan,
+};
+
+/// Maximum depth of nested `#include`s and define expansions.
+pub const MAX_DEPTH: usize = 16;
+
+/// Preprocessor directive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Directive {
+    /// `#define NAME body`
+    Define { name: String, body: String },
+    /// `#undef NAME`
+    Undef { name: String },
+    /// `#include "path"` or `#include <path>`
+    Include { path: String },
+    /// Any other directive, e.g. `#ifdef`.
+    Other { name: String },
+}
+
+impl Directive {
+    /// Parses the text of a [`TokenKind::Preprocessor`] token.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.strip_prefix('#')?.replace("\\\n", " ");
+        let text = text.trim_start();
+        let name_end = text
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(text.len());
+        let (name, rest) = text.split_at(name_end);
+        let rest = strip_comment(rest).trim();
+
+        let directive = match name {
+            "define" => {
+                let name_end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let (define, body) = rest.split_at(name_end);
+                if define.is_empty() {
+                    return None;
+                }
+                Self::Define {
+                    name: define.to_owned(),
+                    body: body.trim().to_owned(),
+                }
+            }
+            "undef" => Self::Undef {
+                name: rest.to_owned(),
+            },
+            "include" => {
+                let path = rest
+                    .strip_prefix('"')
+                    .and_then(|path| path.strip_suffix('"'))
+                    .or_else(|| rest.strip_prefix('<').and_then(|p| p.strip_suffix('>')))?;
+                Self::Include {
+                    path: path.to_owned(),
+                }
+            }
+            _ => Self::Other {
+                name: name.to_owned(),
+            },
+        };
+        Some(directive)
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let end = [text.find("//"), text.find("/*")]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Constants defined with `#define`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Defines {
+    defines: HashMap<String, String>,
+}
+
+impl Defines {
+    /// Creates an empty set of defines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines `name` as `body`, replacing any previous definition.
+    pub fn define(&mut self, name: impl Into<String>, body: impl Into<String>) {
+        self.defines.insert(name.into(), body.into());
+    }
+
+    /// Removes the definition of `name`.
+    pub fn undef(&mut self, name: &str) {
+        self.defines.remove(name);
+    }
+
+    /// Get the body `name` is defined as.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.defines.get(name).map(String::as_str)
+    }
+
+    /// `name` is defined.
+    pub fn contains(&self, name: &str) -> bool {
+        self.defines.contains_key(name)
+    }
+
+    /// Number of defines.
+    pub fn len(&self) -> usize {
+        self.defines.len()
+    }
+
+    /// There are no defines.
+    pub fn is_empty(&self) -> bool {
+        self.defines.is_empty()
+    }
+
+    /// Collects the directives of `tokens`, following `#include`s through `resolver`.
+    ///
+    /// Problems are reported with spans into the source of `tokens`; problems inside included
+    /// files are reported at the `#include` directive.
+    pub fn collect(
+        &mut self,
+        tokens: &[Token<'_>],
+        resolver: Option<&dyn FileResolver>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.collect_tokens(tokens, resolver, 0, &mut diagnostics);
+        diagnostics
+    }
+
+    /// Collects the directives of a script source, see [`collect`](Self::collect).
+    pub fn collect_source(
+        &mut self,
+        source: &str,
+        resolver: Option<&dyn FileResolver>,
+    ) -> Vec<Diagnostic> {
+        let tokens: Vec<_> = ScriptLexer::new(source).collect();
+        self.collect(&tokens, resolver)
+    }
+
+    fn collect_tokens(
+        &mut self,
+        tokens: &[Token<'_>],
+        resolver: Option<&dyn FileResolver>,
+        depth: usize,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for token in tokens
+            .iter()
+            .filter(|t| t.kind() == TokenKind::Preprocessor)
+        {
+            match Directive::parse(token.text()) {
+                Some(Directive::Define { name, body }) => self.define(name, body),
+                Some(Directive::Undef { name }) => self.undef(&name),
+                Some(Directive::Include { path }) => {
+                    self.include(&path, token.span(), resolver, depth, diagnostics)
+                }
+                Some(Directive::Other { .. }) => {}
+                None => diagnostics.push(Diagnostic::error(
+                    format!("malformed directive '{}'", token.text().trim()),
+                    token.span(),
+                )),
+            }
+        }
+    }
+
+    fn include(
+        &mut self,
+        path: &str,
+        span: RawSpan,
+        resolver: Option<&dyn FileResolver>,
+        depth: usize,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let Some(resolver) = resolver else {
+            return;
+        };
+        if depth >= MAX_DEPTH {
+            diagnostics.push(Diagnostic::error(
+                format!("#include '{}' nested too deeply", path),
+                span,
+            ));
+            return;
+        }
+        match resolver.read_to_string(path) {
+            Ok(source) => {
+                let tokens: Vec<_> = ScriptLexer::new(&source).collect();
+                let mut nested = Vec::new();
+                self.collect_tokens(&tokens, Some(resolver), depth + 1, &mut nested);
+                diagnostics.extend(nested.into_iter().map(|diagnostic| {
+                    Diagnostic::new(
+                        diagnostic.severity(),
+                        format!("in '{}': {}", path, diagnostic.message()),
+                        span,
+                    )
+                }));
+            }
+            Err(error) => diagnostics.push(Diagnostic::error(
+                format!("cannot include '{}': {}", path, error),
+                span,
+            )),
+        }
+    }
+
+    /// Evaluates the integer or floating point constant `name` is defined as.
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.evaluate(self.get(name)?)
+    }
+
+    /// Evaluates a constant expression, expanding defined names.
+    ///
+    /// Supports the arithmetic, shift and bitwise operators of `#if` expressions.
+    pub fn evaluate(&self, expression: &str) -> Option<f64> {
+        self.evaluate_depth(expression, 0)
+    }
+
+    fn evaluate_depth(&self, expression: &str, depth: usize) -> Option<f64> {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+        let tokens: Vec<_> = ScriptLexer::new(expression)
+            .filter(|token| !token.kind().is_trivia())
+            .collect();
+        let mut evaluator = Evaluator {
+            defines: self,
+            tokens: &tokens,
+            cursor: 0,
+            depth,
+        };
+        let value = evaluator.binary(0)?;
+        (evaluator.cursor == tokens.len()).then_some(value)
+    }
+}
+
+struct Evaluator<'a, 'src> {
+    defines: &'a Defines,
+    tokens: &'a [Token<'src>],
+    cursor: usize,
+    depth: usize,
+}
+
+impl Evaluator<'_, '_> {
+    fn peek(&self, n: usize) -> Option<&str> {
+        self.tokens.get(self.cursor + n).map(Token::text)
+    }
+
+    /// Binary operator at the cursor with its precedence and token length.
+    fn operator(&self) -> Option<(&'static str, u8, usize)> {
+        let first = self.peek(0)?;
+        let second = self.peek(1);
+        let operator = match (first, second) {
+            ("<", Some("<")) => ("<<", 3, 2),
+            (">", Some(">")) => (">>", 3, 2),
+            ("|", _) => ("|", 0, 1),
+            ("^", _) => ("^", 1, 1),
+            ("&", _) => ("&", 2, 1),
+            ("+", _) => ("+", 4, 1),
+            ("-", _) => ("-", 4, 1),
+            ("*", _) => ("*", 5, 1),
+            ("/", _) => ("/", 5, 1),
+            ("%", _) => ("%", 5, 1),
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Option<f64> {
+        let mut lhs = self.unary()?;
+        while let Some((operator, precedence, len)) = self.operator() {
+            if precedence < min_precedence {
+                break;
+            }
+            self.cursor += len;
+            let rhs = self.binary(precedence + 1)?;
+            lhs = match operator {
+                "|" => (lhs as i64 | rhs as i64) as f64,
+                "^" => (lhs as i64 ^ rhs as i64) as f64,
+                "&" => (lhs as i64 & rhs as i64) as f64,
+                "<<" => (lhs as i64).checked_shl(rhs as u32)? as f64,
+                ">>" => (lhs as i64).checked_shr(rhs as u32)? as f64,
+                "+" => lhs + rhs,
+                "-" => lhs - rhs,
+                "*" => lhs * rhs,
+                "/" => lhs / rhs,
+                "%" => (lhs as i64).checked_rem(rhs as i64)? as f64,
+                _ => unreachable!(),
+            };
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        let token = *self.tokens.get(self.cursor)?;
+        self.cursor += 1;
+        match (token.kind(), token.text()) {
+            (TokenKind::Punctuation, "-") => Some(-self.unary()?),
+            (TokenKind::Punctuation, "+") => self.unary(),
+            (TokenKind::Punctuation, "~") => Some(!(self.unary()? as i64) as f64),
+            (TokenKind::Punctuation, "!") => Some(f64::from(u8::from(self.unary()? == 0.0))),
+            (TokenKind::Punctuation, "$") => {
+                // Builtin `$evalint(…)` and `$evalfloat(…)`
+                let builtin = self.tokens.get(self.cursor)?.text();
+                self.cursor += 1;
+                let value = self.unary()?;
+                match builtin {
+                    "evalint" => Some(value.trunc()),
+                    "evalfloat" => Some(value),
+                    _ => None,
+                }
+            }
+            (TokenKind::LeftParen, _) => {
+                let value = self.binary(0)?;
+                (self.peek(0) == Some(")")).then(|| self.cursor += 1)?;
+                Some(value)
+            }
+            (TokenKind::Number, text) => parse_number(text),
+            (TokenKind::Name, name) => self
+                .defines
+                .evaluate_depth(self.defines.get(name)?, self.depth + 1),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the text of a [`TokenKind::Number`] token.
+pub fn parse_number(text: &str) -> Option<f64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok().map(|value| value as f64),
+        None => text.parse().ok(),
+    }
+}
+
+/// Invalid escape sequence in a string literal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EscapeError {
+    span: RawSpan,
+    message: String,
+}
+
+impl EscapeError {
+    /// Get the span of the escape sequence, relative to the unescaped text.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Strips the quotes of a [`TokenKind::QuotedString`] token text.
+pub fn unquote(text: &str) -> &str {
+    let text = text.strip_prefix('"').unwrap_or(text);
+    text.strip_suffix('"').unwrap_or(text)
+}
+
+/// Replaces the escape sequences of a string literal's contents like `PS_ReadEscapeCharacter`.
+pub fn unescape(text: &str) -> Result<String, EscapeError> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let error = |end: usize, message: &str| EscapeError {
+            span: RawSpan::new(start as u32, end as u32),
+            message: message.to_owned(),
+        };
+        let Some((index, escape)) = chars.next() else {
+            return Err(error(text.len(), "unterminated escape sequence"));
+        };
+        let value = match escape {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\x0B',
+            'b' => '\x08',
+            'f' => '\x0C',
+            'a' => '\x07',
+            '\'' => '\'',
+            '"' => '"',
+            '?' => '?',
+            'x' | '0'..='9' => {
+                let radix = if escape == 'x' { 16 } else { 10 };
+                let mut digits = String::new();
+                if escape != 'x' {
+                    digits.push(escape);
+                }
+                while let Some(&(_, digit)) = chars.peek() {
+                    if !digit.is_digit(radix) {
+                        break;
+                    }
+                    digits.push(digit);
+                    chars.next();
+                }
+                let end = chars.peek().map_or(text.len(), |&(index, _)| index);
+                match u32::from_str_radix(&digits, radix) {
+                    Ok(value) if value <= 0xFF => char::from(value as u8),
+                    _ => return Err(error(end, "escape sequence out of range")),
+                }
+            }
+            _ => {
+                let end = index + escape.len_utf8();
+                return Err(error(end, &format!("unknown escape char '{}'", escape)));
+            }
+        };
+        unescaped.push(value);
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directive() {
+        assert_eq!(
+            Directive::parse("#define INVENTORY_ARMOR\t1 // armor"),
+            Some(Directive::Define {
+                name: "INVENTORY_ARMOR".to_owned(),
+                body: "1".to_owned()
+            })
+        );
+        assert_eq!(
+            Directive::parse("# include \"inv.h\""),
+            Some(Directive::Include {
+                path: "inv.h".to_owned()
+            })
+        );
+        assert_eq!(Directive::parse("#include inv.h"), None);
+    }
+
+    #[test]
+    fn evaluate() {
+        let mut defines = Defines::new();
+        defines.define("A", "2");
+        defines.define("B", "(A << 3) | 1");
+        defines.define("LOOP", "LOOP");
+
+        assert_eq!(defines.value("B"), Some(17.0));
+        assert_eq!(defines.evaluate("-A * 0.5 + 0x10"), Some(15.0));
+        assert_eq!(defines.value("LOOP"), None);
+        assert_eq!(defines.evaluate("A A"), None);
+        assert_eq!(defines.evaluate("$evalint(B / 2)"), Some(8.0));
+    }
+
+    #[test]
+    fn collect_include() {
+        let files = HashMap::from([(
+            "inv.h".to_owned(),
+            "#define INVENTORY_ROCKETLAUNCHER 8\n".to_owned(),
+        )]);
+        let mut defines = Defines::new();
+        let diagnostics =
+            defines.collect_source("#include \"inv.h\"\n#include \"missing.h\"\n", Some(&files));
+
+        assert_eq!(defines.value("INVENTORY_ROCKETLAUNCHER"), Some(8.0));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span(), RawSpan::new(17, 37));
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r#"a\"b\n\x41\65"#).unwrap(), "a\"b\nAA");
+        assert_eq!(unescape(r"ab\q").unwrap_err().span(), RawSpan::new(2, 4));
+        assert_eq!(
+            unescape(r"ab\").unwrap_err().message(),
+            "unterminated escape sequence"
+        );
+    }
+}
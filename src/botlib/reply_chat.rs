@@ -0,0 +1,411 @@
+//! Reply chats (`rchat.c`) choosing a bot's answer to a chat message.
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind},
+};
+
+use super::{
+    chat::{ChatMessage, RandomStrings},
+    evaluate,
+    match_template::{contains_word, match_pieces, strings_match, MatchPiece, Variables},
+    precomp::{self, Defines},
+    Script,
+};
+
+/// Reply chats, e.g. `botfiles/rchat.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplyChats {
+    chats: Vec<ReplyChat>,
+    defines: Defines,
+}
+
+impl ReplyChats {
+    /// Parses reply chats, resolving `#include`d headers through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(
+            source,
+            resolver,
+            Defines::new(),
+            parse::reply_chat::reply_chat_file,
+        );
+        let mut diagnostics = script.diagnostics.clone();
+
+        let mut chats = Vec::new();
+        for chat in script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::ReplyChat))
+        {
+            let keys: Vec<_> = child_nodes(chat, SyntaxKind::ReplyKey)
+                .map(|key| {
+                    let span = RawSpan::from(key.text_range());
+                    let mut tokens = child_tokens(key)
+                        .map(|token| (token.kind(), &source[RawSpan::from(token.text_range())]))
+                        .peekable();
+                    let prefix = match tokens.peek() {
+                        Some((SyntaxKind::Punctuation, "&")) => KeyPrefix::And,
+                        Some((SyntaxKind::Punctuation, "!")) => KeyPrefix::Not,
+                        _ => KeyPrefix::Or,
+                    };
+                    if prefix != KeyPrefix::Or {
+                        tokens.next();
+                    }
+                    let strings = || {
+                        child_tokens(key)
+                            .filter(|token| token.kind() == SyntaxKind::QuotedString)
+                            .map(|token| {
+                                let text =
+                                    precomp::unquote(&source[RawSpan::from(token.text_range())]);
+                                precomp::unescape(text).unwrap_or_else(|_| text.to_owned())
+                            })
+                    };
+                    let kind = match tokens.next() {
+                        Some((SyntaxKind::LeftParen, _)) => ReplyKeyKind::Variables(match_pieces(
+                            key,
+                            source,
+                            &script.defines,
+                            &mut diagnostics,
+                        )),
+                        Some((SyntaxKind::Punctuation, "<")) => {
+                            ReplyKeyKind::BotNames(strings().collect())
+                        }
+                        Some((SyntaxKind::Name, "name")) => ReplyKeyKind::Name,
+                        Some((SyntaxKind::Name, "female")) => ReplyKeyKind::Gender(Gender::Female),
+                        Some((SyntaxKind::Name, "male")) => ReplyKeyKind::Gender(Gender::Male),
+                        Some((SyntaxKind::Name, "it")) => ReplyKeyKind::Gender(Gender::It),
+                        Some((SyntaxKind::Name, name)) => {
+                            diagnostics.push(Diagnostic::error(
+                                format!(
+                                    "unknown key '{}', expected a string, name, female, male or it",
+                                    name
+                                ),
+                                span,
+                            ));
+                            ReplyKeyKind::String(String::new())
+                        }
+                        _ => ReplyKeyKind::String(strings().next().unwrap_or_default()),
+                    };
+                    ReplyKey { prefix, kind, span }
+                })
+                .collect();
+
+            let priority = child_nodes(chat, SyntaxKind::Expression)
+                .next()
+                .and_then(|node| evaluate(source, &script.defines, node, &mut diagnostics))
+                .unwrap_or_default();
+            let messages = child_nodes(chat, SyntaxKind::ChatMessage)
+                .map(|node| ChatMessage::from_node(node, source, &script.defines))
+                .collect();
+
+            let chat = ReplyChat {
+                keys,
+                priority,
+                messages,
+                span: chat.text_range().into(),
+            };
+            chat.check_keys(&mut diagnostics);
+            chats.push(chat);
+        }
+
+        let chats = Self {
+            chats,
+            defines: script.defines,
+        };
+        (chats, diagnostics)
+    }
+
+    /// Get the reply chats in file order.
+    pub fn chats(&self) -> &[ReplyChat] {
+        &self.chats
+    }
+
+    /// Get the defines of the reply chats and their includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    /// Checks escapes and references of all messages.
+    ///
+    /// Random string references are only checked if `random` strings are given.
+    pub fn check(&self, random: Option<&RandomStrings>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for chat in &self.chats {
+            for message in &chat.messages {
+                message.check(random, &mut diagnostics);
+            }
+        }
+        diagnostics
+    }
+
+    /// Finds the reply chat with the highest priority whose keys accept `message`, like
+    /// `BotReplyChat`.
+    ///
+    /// The engine keeps its reply chats in reverse file order, so on equal priority the last one
+    /// in the file wins.
+    pub fn reply(&self, message: &str, bot_name: &str, gender: Gender) -> Option<Reply<'_>> {
+        let mut best: Option<Reply> = None;
+        for chat in self.chats.iter().rev() {
+            if best
+                .as_ref()
+                .is_some_and(|best| chat.priority <= best.chat.priority)
+            {
+                continue;
+            }
+            if let Some(variables) = chat.accepts(message, bot_name, gender) {
+                best = Some(Reply { chat, variables });
+            }
+        }
+        best
+    }
+}
+
+/// `["hello", name, !"bye"] = 5 { "hi ", 0, "!"; … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyChat {
+    keys: Vec<ReplyKey>,
+    priority: f64,
+    messages: Vec<ChatMessage>,
+    span: RawSpan,
+}
+
+impl ReplyChat {
+    /// Get the keys deciding whether this chat replies.
+    pub fn keys(&self) -> &[ReplyKey] {
+        &self.keys
+    }
+
+    /// Get the priority, the highest accepting reply chat is used.
+    pub const fn priority(&self) -> f64 {
+        self.priority
+    }
+
+    /// Get the reply messages.
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+
+    /// Get the reply chat's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Returns the variables of the first matching variables key if the keys accept `message`.
+    ///
+    /// All `&` keys must match and no `!` key may match, and at least one key without a prefix
+    /// must match.
+    pub fn accepts(&self, message: &str, bot_name: &str, gender: Gender) -> Option<Variables> {
+        let mut found = false;
+        let mut variables = None;
+        for key in &self.keys {
+            let matched = match &key.kind {
+                ReplyKeyKind::String(string) => contains_word(message, string).is_some(),
+                ReplyKeyKind::Name => contains(message, bot_name),
+                ReplyKeyKind::BotNames(names) => contains(&names.join("\\"), bot_name),
+                ReplyKeyKind::Gender(key) => *key == gender,
+                ReplyKeyKind::Variables(pieces) => match strings_match(pieces, message) {
+                    Some(matched) => {
+                        variables.get_or_insert(matched);
+                        true
+                    }
+                    None => false,
+                },
+            };
+            match key.prefix {
+                KeyPrefix::And if !matched => return None,
+                KeyPrefix::Not if matched => return None,
+                KeyPrefix::Or if matched => found = true,
+                _ => {}
+            }
+        }
+        found.then(|| variables.unwrap_or_default())
+    }
+
+    /// Warnings of `BotCheckValidReplyChatKeySet`.
+    fn check_keys(&self, diagnostics: &mut Vec<Diagnostic>) {
+        if self.keys.iter().all(|key| key.prefix != KeyPrefix::Or) {
+            diagnostics.push(Diagnostic::warning(
+                "all keys have a & or ! prefix, the reply chat never fires",
+                self.span,
+            ));
+        }
+        for not in self.keys.iter().filter(|key| key.prefix == KeyPrefix::Not) {
+            let ReplyKeyKind::String(not_string) = &not.kind else {
+                continue;
+            };
+            for key in self.keys.iter().filter(|key| key.prefix != KeyPrefix::Not) {
+                if let ReplyKeyKind::String(string) = &key.kind {
+                    if contains(string, not_string) {
+                        diagnostics.push(Diagnostic::warning(
+                            format!(
+                                "the key '{}' with prefix ! is inside the key '{}'",
+                                not_string, string
+                            ),
+                            not.span,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Key of a reply chat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyKey {
+    prefix: KeyPrefix,
+    kind: ReplyKeyKind,
+    span: RawSpan,
+}
+
+impl ReplyKey {
+    /// Get the key's prefix.
+    pub const fn prefix(&self) -> KeyPrefix {
+        self.prefix
+    }
+
+    /// Get the key's kind.
+    pub const fn kind(&self) -> &ReplyKeyKind {
+        &self.kind
+    }
+
+    /// Get the key's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// How a key's result is combined with the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyPrefix {
+    /// No prefix, one of these keys must match.
+    Or,
+    /// `&`, the key must match.
+    And,
+    /// `!`, the key must not match.
+    Not,
+}
+
+/// What a reply chat key matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplyKeyKind {
+    /// `"text"`, a word or words in the message.
+    String(String),
+    /// `name`, the bot's name in the message.
+    Name,
+    /// `female`, `male` or `it`, the bot's gender.
+    Gender(Gender),
+    /// `("i hate ", 0)`, a match template capturing variables.
+    Variables(Vec<MatchPiece>),
+    /// `<"sarge", "visor">`, the bot is one of these.
+    BotNames(Vec<String>),
+}
+
+/// Gender of a bot, `CHAT_GENDER*`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Gender {
+    Female,
+    Male,
+    #[default]
+    It,
+}
+
+/// Reply chat accepting a message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply<'a> {
+    chat: &'a ReplyChat,
+    variables: Variables,
+}
+
+impl<'a> Reply<'a> {
+    /// Get the reply chat that fired.
+    pub const fn chat(&self) -> &'a ReplyChat {
+        self.chat
+    }
+
+    /// Get the variables captured by a variables key.
+    pub const fn variables(&self) -> &Variables {
+        &self.variables
+    }
+}
+
+/// `StringContains` ignoring case.
+fn contains(text: &str, pattern: &str) -> bool {
+    text.to_ascii_lowercase()
+        .contains(&pattern.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botlib::chat::Expander;
+
+    const RCHAT: &str = r#"
+["hello", "hi"] = 5
+{
+    "hello!";
+}
+
+[("i hate ", 0), &name] = 7
+{
+    "why do you hate ", 0, "?";
+}
+
+["hi", !"bye"] = 5
+{
+    "hi there";
+}
+
+[&female, "flowers"] = 6
+{
+    "thanks!";
+}
+"#;
+
+    #[test]
+    fn reply() {
+        let (chats, diagnostics) = ReplyChats::parse(RCHAT, None);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(chats.chats().len(), 4);
+        assert_eq!(chats.check(None), &[]);
+
+        let reply = chats.reply("hi everyone", "Sarge", Gender::Male).unwrap();
+        assert_eq!(reply.chat().span(), chats.chats()[2].span());
+
+        let reply = chats.reply("hi, bye", "Sarge", Gender::Male).unwrap();
+        assert_eq!(reply.chat().span(), chats.chats()[0].span());
+
+        let reply = chats.reply("I hate Sarge", "sarge", Gender::Male).unwrap();
+        assert_eq!(reply.variables().get(0), Some("Sarge"));
+        let rendered = Expander::new()
+            .variable(0, reply.variables().get(0).unwrap())
+            .expand(&reply.chat().messages()[0]);
+        assert_eq!(rendered, "why do you hate Sarge?");
+
+        assert!(chats.reply("I hate Sarge", "Visor", Gender::Male).is_none());
+        assert!(chats.reply("nice flowers", "Visor", Gender::Male).is_none());
+        assert!(chats
+            .reply("nice flowers", "Slash", Gender::Female)
+            .is_some());
+    }
+
+    #[test]
+    fn check_keys() {
+        let (_, diagnostics) = ReplyChats::parse(
+            r#"[&name, !"x"] = 1 { "a"; } ["good game", !"game", bogus] = 1 { "b"; }"#,
+            None,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "all keys have a & or ! prefix, the reply chat never fires",
+                "unknown key 'bogus', expected a string, name, female, male or it",
+                "the key 'game' with prefix ! is inside the key 'good game'",
+            ]
+        );
+    }
+}
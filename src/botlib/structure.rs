@@ -0,0 +1,848 @@
+//! Structure definitions read with `l_struct.c`, e.g. `weapons.c` and `items.c`.
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+use super::{
+    evaluate,
+    precomp::{self, Defines},
+    Script,
+};
+
+/// Default number of weapons, the `max_weaponinfo` libvar.
+pub const MAX_WEAPON_INFO: i64 = 32;
+
+/// Type of a structure field, `FT_*` in `l_struct.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Int,
+    Float,
+    String,
+}
+
+impl ::core::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::Int => f.write_str("integer"),
+            Self::Float => f.write_str("float"),
+            Self::String => f.write_str("string"),
+        }
+    }
+}
+
+/// Field of a structure, `fielddef_t` in `l_struct.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldDef {
+    name: &'static str,
+    field_type: FieldType,
+    max_array: Option<usize>,
+}
+
+impl FieldDef {
+    /// Creates a new field definition, `max_array` is set for `FT_ARRAY` fields.
+    pub const fn new(name: &'static str, field_type: FieldType, max_array: Option<usize>) -> Self {
+        Self {
+            name,
+            field_type,
+            max_array,
+        }
+    }
+
+    /// Get the field's name.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the field's (element) type.
+    pub const fn field_type(&self) -> FieldType {
+        self.field_type
+    }
+
+    /// Get the maximum number of array elements, if the field is an array.
+    pub const fn max_array(&self) -> Option<usize> {
+        self.max_array
+    }
+}
+
+/// Parsed value of a structure field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<f64>),
+}
+
+/// Rust type of a field in a typed structure.
+trait FieldKind: Sized {
+    const TYPE: FieldType;
+    const ARRAY: Option<usize> = None;
+
+    fn from_value(value: &FieldValue) -> Option<Self>;
+}
+
+impl FieldKind for i64 {
+    const TYPE: FieldType = FieldType::Int;
+
+    fn from_value(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FieldKind for f64 {
+    const TYPE: FieldType = FieldType::Float;
+
+    fn from_value(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Int(value) => Some(*value as f64),
+            FieldValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FieldKind for String {
+    const TYPE: FieldType = FieldType::String;
+
+    fn from_value(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FieldKind for [f64; 3] {
+    const TYPE: FieldType = FieldType::Float;
+    const ARRAY: Option<usize> = Some(3);
+
+    fn from_value(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Array(values) => {
+                let mut array = [0.0; 3];
+                array.iter_mut().zip(values).for_each(|(a, v)| *a = *v);
+                Some(array)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Generic structure definition: `keyword "classname" { field value … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    keyword: String,
+    classname: Option<String>,
+    span: RawSpan,
+    fields: Vec<Field>,
+}
+
+impl Structure {
+    /// Get the structure's keyword, e.g. `weaponinfo`.
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    /// Get the class name following the keyword, e.g. `item_armor_shard`.
+    pub fn classname(&self) -> Option<&str> {
+        self.classname.as_deref()
+    }
+
+    /// Get the structure's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the structure's fields.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Get the last field with the given name, like the engine does.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().rev().find(|field| field.name == name)
+    }
+}
+
+/// `name value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    name: String,
+    span: RawSpan,
+    value: FieldValue,
+    value_span: RawSpan,
+}
+
+impl Field {
+    /// Get the field's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the field's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the field's value.
+    pub const fn value(&self) -> &FieldValue {
+        &self.value
+    }
+
+    /// Get the span of the field's value.
+    pub const fn value_span(&self) -> RawSpan {
+        self.value_span
+    }
+}
+
+/// Parses the structures of a file, validating them against the given field tables.
+///
+/// Returns the structures, the defines of the file and its includes and all problems found.
+pub fn parse_structures(
+    source: &str,
+    resolver: Option<&dyn FileResolver>,
+    tables: &[(&str, &[FieldDef])],
+) -> (Vec<Structure>, Defines, Vec<Diagnostic>) {
+    let script = Script::parse(
+        source,
+        resolver,
+        Defines::new(),
+        parse::structure::structure_file,
+    );
+    let mut diagnostics = script.diagnostics.clone();
+
+    let builder = Builder {
+        source,
+        defines: &script.defines,
+    };
+    let structures = script
+        .root
+        .children()
+        .flat_map(|file| child_nodes(file, SyntaxKind::Structure))
+        .map(|node| builder.structure(node, tables, &mut diagnostics))
+        .collect();
+
+    (structures, script.defines, diagnostics)
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    defines: &'a Defines,
+}
+
+impl Builder<'_> {
+    fn text(&self, range: cstree::text::TextRange) -> &str {
+        &self.source[RawSpan::from(range)]
+    }
+
+    fn structure(
+        &self,
+        node: &SyntaxNode,
+        tables: &[(&str, &[FieldDef])],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Structure {
+        let span = RawSpan::from(node.text_range());
+        let keyword = child_tokens(node)
+            .find(|token| token.kind() == SyntaxKind::Name)
+            .map(|token| self.text(token.text_range()).to_owned())
+            .unwrap_or_default();
+        let classname = child_tokens(node)
+            .find(|token| token.kind() == SyntaxKind::QuotedString)
+            .map(|token| precomp::unquote(self.text(token.text_range())).to_owned());
+
+        let table = tables.iter().find(|(name, _)| *name == keyword);
+        if table.is_none() {
+            let expected: Vec<_> = tables.iter().map(|(name, _)| *name).collect();
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "unknown definition '{}', expected {}",
+                    keyword,
+                    expected.join(" or ")
+                ),
+                span,
+            ));
+        }
+
+        let mut seen = HashMap::new();
+        let mut fields = Vec::new();
+        for field in child_nodes(node, SyntaxKind::Field) {
+            let field_span = RawSpan::from(field.text_range());
+            let name = child_tokens(field)
+                .find(|token| token.kind() == SyntaxKind::Name)
+                .map(|token| self.text(token.text_range()).to_owned())
+                .unwrap_or_default();
+            let Some(value_node) = field.children().next() else {
+                continue;
+            };
+            let value_span = RawSpan::from(value_node.text_range());
+            let value = self.value(value_node, diagnostics);
+
+            if let Some(previous) = seen.insert(name.clone(), field_span) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "field '{}' already set at {}, the last value is used",
+                        name,
+                        previous.start()
+                    ),
+                    field_span,
+                ));
+            }
+            if let Some((_, table)) = table {
+                match table.iter().find(|def| def.name == name) {
+                    Some(def) => check_field(def, &value, value_span, diagnostics),
+                    None => diagnostics.push(Diagnostic::error(
+                        format!("unknown structure field '{}' in {}", name, keyword),
+                        field_span,
+                    )),
+                }
+            }
+
+            fields.push(Field {
+                name,
+                span: field_span,
+                value,
+                value_span,
+            });
+        }
+
+        Structure {
+            keyword,
+            classname,
+            span,
+            fields,
+        }
+    }
+
+    fn value(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> FieldValue {
+        match node.kind() {
+            SyntaxKind::Value => {
+                FieldValue::String(precomp::unquote(self.text(node.text_range())).to_owned())
+            }
+            SyntaxKind::Array => FieldValue::Array(
+                node.children()
+                    .map(|element| match self.value(element, diagnostics) {
+                        FieldValue::Int(value) => value as f64,
+                        FieldValue::Float(value) => value,
+                        _ => {
+                            diagnostics.push(Diagnostic::error(
+                                "expected number",
+                                element.text_range().into(),
+                            ));
+                            0.0
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => {
+                let text = self.text(node.text_range());
+                match evaluate(self.source, self.defines, node, diagnostics) {
+                    // Floats are written with a decimal point, see `ReadNumber` in `l_struct.c`
+                    Some(value)
+                        if value.fract() == 0.0
+                            && !self.expanded(text).contains('.')
+                            && !text.contains("evalfloat") =>
+                    {
+                        FieldValue::Int(value as i64)
+                    }
+                    Some(value) => FieldValue::Float(value),
+                    None => FieldValue::Int(0),
+                }
+            }
+        }
+    }
+
+    /// Text of a define, or the text itself.
+    fn expanded<'a>(&'a self, text: &'a str) -> &'a str {
+        let mut text = text.trim();
+        for _ in 0..precomp::MAX_DEPTH {
+            match self.defines.get(text) {
+                Some(body) => text = body.trim(),
+                None => break,
+            }
+        }
+        text
+    }
+}
+
+fn check_field(
+    def: &FieldDef,
+    value: &FieldValue,
+    span: RawSpan,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let expected = match def.max_array {
+        Some(max) => format!("array of up to {} {}s", max, def.field_type),
+        None => def.field_type.to_string(),
+    };
+    let message = match (def.max_array, def.field_type, value) {
+        (Some(max), _, FieldValue::Array(values)) if values.len() > max => {
+            Some(format!("too many values in array, expected {}", expected))
+        }
+        (Some(_), _, FieldValue::Array(_)) => None,
+        (None, FieldType::String, FieldValue::String(_)) => None,
+        (None, FieldType::Float, FieldValue::Int(_) | FieldValue::Float(_)) => None,
+        (None, FieldType::Int, FieldValue::Int(value))
+            if !(i64::from(i32::MIN)..=i64::from(i32::MAX)).contains(value) =>
+        {
+            Some(format!("integer {} out of range", value))
+        }
+        (None, FieldType::Int, FieldValue::Int(_)) => None,
+        (None, FieldType::Int, FieldValue::Float(_)) => {
+            Some(format!("unexpected float, expected {}", expected))
+        }
+        _ => Some(format!("expected {} for field '{}'", expected, def.name)),
+    };
+    if let Some(message) = message {
+        diagnostics.push(Diagnostic::error(message, span));
+    }
+}
+
+macro_rules! structure_info {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($keyword:literal, $table:ident) {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident($key:literal): $ty:ty,
+            )*
+        }
+    ) => {
+        #[doc = concat!("Fields of `", $keyword, "`.")]
+        pub const $table: &[FieldDef] = &[
+            $(FieldDef::new($key, <$ty as FieldKind>::TYPE, <$ty as FieldKind>::ARRAY),)*
+        ];
+
+        $(#[$meta])*
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct $name {
+            classname: Option<String>,
+            span: RawSpan,
+            $($field: $ty,)*
+        }
+
+        impl $name {
+            /// Keyword starting the definition.
+            pub const KEYWORD: &'static str = $keyword;
+
+            fn from_structure(structure: &Structure) -> Self {
+                let mut info = Self {
+                    classname: structure.classname.clone(),
+                    span: structure.span,
+                    ..Self::default()
+                };
+                for field in &structure.fields {
+                    match field.name.as_str() {
+                        $($key => {
+                            if let Some(value) = FieldKind::from_value(&field.value) {
+                                info.$field = value;
+                            }
+                        })*
+                        _ => {}
+                    }
+                }
+                info
+            }
+
+            /// Get the definition's span.
+            pub const fn span(&self) -> RawSpan {
+                self.span
+            }
+
+            $(
+                $(#[$field_meta])*
+                pub const fn $field(&self) -> &$ty {
+                    &self.$field
+                }
+            )*
+        }
+    };
+}
+
+structure_info! {
+    /// `weaponinfo { … }`, `weaponinfo_t` in `be_ai_weap.h`.
+    pub struct WeaponInfo("weaponinfo", WEAPON_INFO_FIELDS) {
+        /// Get the weapon number, `WP_*`.
+        number("number"): i64,
+        /// Get the weapon's display name.
+        name("name"): String,
+        /// Get the weapon's level.
+        level("level"): i64,
+        /// Get the weapon's model.
+        model("model"): String,
+        /// Get the weapon's index into the weapon table.
+        weapon_index("weaponindex"): i64,
+        /// Get the weapon's flags.
+        flags("flags"): i64,
+        /// Get the name of the projectile fired.
+        projectile("projectile"): String,
+        /// Get the number of projectiles per shot.
+        num_projectiles("numprojectiles"): i64,
+        /// Get the horizontal spread in degrees.
+        hspread("hspread"): f64,
+        /// Get the vertical spread in degrees.
+        vspread("vspread"): f64,
+        /// Get the projectile speed.
+        speed("speed"): f64,
+        /// Get the projectile acceleration.
+        acceleration("acceleration"): f64,
+        /// Get the recoil vector.
+        recoil("recoil"): [f64; 3],
+        /// Get the projectile start offset.
+        offset("offset"): [f64; 3],
+        /// Get the projectile start angle offset.
+        angle_offset("angleoffset"): [f64; 3],
+        /// Get the extra vertical velocity.
+        extra_z_velocity("extrazvelocity"): f64,
+        /// Get the ammo used per shot.
+        ammo_amount("ammoamount"): i64,
+        /// Get the inventory index of the ammo, `INVENTORY_*`.
+        ammo_index("ammoindex"): i64,
+        /// Get the time it takes to activate the weapon.
+        activate("activate"): f64,
+        /// Get the reload time.
+        reload("reload"): f64,
+        /// Get the spin up time.
+        spin_up("spinup"): f64,
+        /// Get the spin down time.
+        spin_down("spindown"): f64,
+    }
+}
+
+structure_info! {
+    /// `projectileinfo { … }`, `projectileinfo_t` in `be_ai_weap.h`.
+    pub struct ProjectileInfo("projectileinfo", PROJECTILE_INFO_FIELDS) {
+        /// Get the projectile's name.
+        name("name"): String,
+        /// Get the projectile's model.
+        model("model"): String,
+        /// Get the projectile's flags, `PFL_*`.
+        flags("flags"): i64,
+        /// Get the gravity factor.
+        gravity("gravity"): f64,
+        /// Get the damage.
+        damage("damage"): i64,
+        /// Get the splash damage radius.
+        radius("radius"): f64,
+        /// Get the visible damage.
+        vis_damage("visdamage"): i64,
+        /// Get the damage type, `DAMAGETYPE_*`.
+        damage_type("damagetype"): i64,
+        /// Get the health increase.
+        health_inc("healthinc"): i64,
+        /// Get the push factor.
+        push("push"): f64,
+        /// Get the detonation time.
+        detonation("detonation"): f64,
+        /// Get the bounce factor.
+        bounce("bounce"): f64,
+        /// Get the bounce friction.
+        bounce_fric("bouncefric"): f64,
+        /// Get the bounce stop speed.
+        bounce_stop("bouncestop"): f64,
+    }
+}
+
+structure_info! {
+    /// `iteminfo "classname" { … }`, `iteminfo_t` in `be_ai_goal.c`.
+    pub struct ItemInfo("iteminfo", ITEM_INFO_FIELDS) {
+        /// Get the item's display name.
+        name("name"): String,
+        /// Get the item's model.
+        model("model"): String,
+        /// Get the item's model index, `MODELINDEX_*`.
+        model_index("modelindex"): i64,
+        /// Get the item's type, `ITEM_*`.
+        item_type("type"): i64,
+        /// Get the inventory index, `INVENTORY_*`.
+        index("index"): i64,
+        /// Get the respawn time in seconds.
+        respawn_time("respawntime"): f64,
+        /// Get the bounding box minimum.
+        mins("mins"): [f64; 3],
+        /// Get the bounding box maximum.
+        maxs("maxs"): [f64; 3],
+    }
+}
+
+impl WeaponInfo {
+    /// Get the class name, never set for weapons.
+    pub fn classname(&self) -> Option<&str> {
+        self.classname.as_deref()
+    }
+}
+
+impl ItemInfo {
+    /// Get the item's class name, e.g. `item_armor_shard`.
+    pub fn classname(&self) -> &str {
+        self.classname.as_deref().unwrap_or_default()
+    }
+}
+
+/// Weapon config, e.g. `botfiles/weapons.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeaponConfig {
+    weapons: Vec<WeaponInfo>,
+    projectiles: Vec<ProjectileInfo>,
+    defines: Defines,
+}
+
+impl WeaponConfig {
+    /// Parses a weapon config, resolving `#include`s through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let tables: &[(&str, &[FieldDef])] = &[
+            (WeaponInfo::KEYWORD, WEAPON_INFO_FIELDS),
+            (ProjectileInfo::KEYWORD, PROJECTILE_INFO_FIELDS),
+        ];
+        let (structures, defines, mut diagnostics) = parse_structures(source, resolver, tables);
+
+        let mut config = Self {
+            defines,
+            ..Self::default()
+        };
+        for structure in &structures {
+            match structure.keyword.as_str() {
+                WeaponInfo::KEYWORD => config.weapons.push(WeaponInfo::from_structure(structure)),
+                ProjectileInfo::KEYWORD => config
+                    .projectiles
+                    .push(ProjectileInfo::from_structure(structure)),
+                _ => {}
+            }
+        }
+
+        let mut numbers = HashMap::new();
+        for (weapon, structure) in config.weapons.iter().zip(
+            structures
+                .iter()
+                .filter(|s| s.keyword == WeaponInfo::KEYWORD),
+        ) {
+            let span = |name| {
+                structure
+                    .field(name)
+                    .map_or(weapon.span(), Field::value_span)
+            };
+            if !(0..MAX_WEAPON_INFO).contains(&weapon.number) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "weapon number {} out of range 0..{}",
+                        weapon.number, MAX_WEAPON_INFO
+                    ),
+                    span("number"),
+                ));
+            } else if let Some(previous) = numbers.insert(weapon.number, weapon.span()) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "weapon number {} already defined at {}",
+                        weapon.number,
+                        previous.start()
+                    ),
+                    span("number"),
+                ));
+            }
+            if weapon.name.is_empty() {
+                diagnostics.push(Diagnostic::error("weapon without name", weapon.span()));
+            }
+            if config.projectile(&weapon.projectile).is_none() {
+                diagnostics.push(Diagnostic::error(
+                    format!("couldn't find projectile '{}'", weapon.projectile),
+                    span("projectile"),
+                ));
+            }
+        }
+
+        (config, diagnostics)
+    }
+
+    /// Get the weapons.
+    pub fn weapons(&self) -> &[WeaponInfo] {
+        &self.weapons
+    }
+
+    /// Get the projectiles.
+    pub fn projectiles(&self) -> &[ProjectileInfo] {
+        &self.projectiles
+    }
+
+    /// Get the weapon with the given number.
+    pub fn weapon(&self, number: i64) -> Option<&WeaponInfo> {
+        self.weapons.iter().find(|weapon| weapon.number == number)
+    }
+
+    /// Get the projectile with the given name.
+    pub fn projectile(&self, name: &str) -> Option<&ProjectileInfo> {
+        self.projectiles
+            .iter()
+            .find(|projectile| projectile.name == name)
+    }
+
+    /// Get the defines of this config and its includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+}
+
+/// Item config, e.g. `botfiles/items.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemConfig {
+    items: Vec<ItemInfo>,
+    defines: Defines,
+}
+
+impl ItemConfig {
+    /// Parses an item config, resolving `#include`s through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let tables: &[(&str, &[FieldDef])] = &[(ItemInfo::KEYWORD, ITEM_INFO_FIELDS)];
+        let (structures, defines, mut diagnostics) = parse_structures(source, resolver, tables);
+
+        let mut classnames = HashMap::new();
+        let mut items = Vec::new();
+        for structure in structures.iter().filter(|s| s.keyword == ItemInfo::KEYWORD) {
+            let item = ItemInfo::from_structure(structure);
+            if item.classname.is_none() {
+                diagnostics.push(Diagnostic::error(
+                    "item info without class name",
+                    item.span(),
+                ));
+            } else if let Some(previous) =
+                classnames.insert(item.classname().to_owned(), item.span())
+            {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "item '{}' already defined at {}",
+                        item.classname(),
+                        previous.start()
+                    ),
+                    item.span(),
+                ));
+            }
+            items.push(item);
+        }
+
+        (Self { items, defines }, diagnostics)
+    }
+
+    /// Get the items.
+    pub fn items(&self) -> &[ItemInfo] {
+        &self.items
+    }
+
+    /// Get the item with the given class name.
+    pub fn item(&self, classname: &str) -> Option<&ItemInfo> {
+        self.items.iter().find(|item| item.classname() == classname)
+    }
+
+    /// Get the defines of this config and its includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEAPONS: &str = r#"#include "inv.h"
+#define WEAPONINDEX_ROCKET_LAUNCHER 5
+#define DAMAGETYPE_IMPACT 1
+#define DAMAGETYPE_RADIAL 2
+
+projectileinfo //for Rocket Launcher
+{
+    name            "rocket"
+    damage          100
+    radius          120
+    damagetype      $evalint(DAMAGETYPE_IMPACT|DAMAGETYPE_RADIAL)
+}
+
+weaponinfo
+{
+    name            "Rocket Launcher"
+    number          WEAPONINDEX_ROCKET_LAUNCHER
+    projectile      "rocket"
+    numprojectiles  1
+    speed           900
+    offset          {0, 0, -4.5}
+    ammoindex       INVENTORY_ROCKETS
+    reload          0.8
+}
+"#;
+
+    #[test]
+    fn weapons() {
+        let files = HashMap::from([(
+            "inv.h".to_owned(),
+            "#define INVENTORY_ROCKETS 20\n".to_owned(),
+        )]);
+        let (config, diagnostics) = WeaponConfig::parse(WEAPONS, Some(&files));
+        assert_eq!(diagnostics, &[]);
+
+        let weapon = config.weapon(5).unwrap();
+        assert_eq!(weapon.name(), "Rocket Launcher");
+        assert_eq!(*weapon.speed(), 900.0);
+        assert_eq!(*weapon.offset(), [0.0, 0.0, -4.5]);
+        assert_eq!(*weapon.ammo_index(), 20);
+        assert_eq!(*weapon.reload(), 0.8);
+
+        let projectile = config.projectile(weapon.projectile()).unwrap();
+        assert_eq!(*projectile.damage_type(), 3);
+    }
+
+    #[test]
+    fn weapons_invalid() {
+        let (_, diagnostics) = WeaponConfig::parse(
+            r#"weaponinfo {
+                name "Broken" number 40 projectile "missing"
+                speed "fast" ammoamount 1.5 recoil {1, 2, 3, 4} sped 900
+            }
+            iteminfo "x" { }"#,
+            None,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "expected float for field 'speed'",
+                "unexpected float, expected integer",
+                "too many values in array, expected array of up to 3 floats",
+                "unknown structure field 'sped' in weaponinfo",
+                "unknown definition 'iteminfo', expected weaponinfo or projectileinfo",
+                "weapon number 40 out of range 0..32",
+                "couldn't find projectile 'missing'",
+            ]
+        );
+    }
+
+    #[test]
+    fn items() {
+        let (config, diagnostics) = ItemConfig::parse(
+            r#"iteminfo "item_armor_shard" {
+                name "Armor Shard" type 1 respawntime 25 mins {-15, -15, -15}
+            }
+            iteminfo "item_armor_shard" { }
+            iteminfo { }"#,
+            None,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "item 'item_armor_shard' already defined at 0",
+                "item info without class name"
+            ]
+        );
+
+        let item = config.item("item_armor_shard").unwrap();
+        assert_eq!(item.name(), "Armor Shard");
+        assert_eq!(*item.item_type(), 1);
+        assert_eq!(*item.mins(), [-15.0; 3]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Synonyms (`syn.c`) normalising chat messages before matching.
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind},
+};
+
+use super::{
+    evaluate,
+    match_template::contains_word,
+    precomp::{self, Defines},
+    Script,
+};
+
+/// Synonyms, e.g. `botfiles/syn.c`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Synonyms {
+    groups: Vec<SynonymGroup>,
+    defines: Defines,
+}
+
+impl Synonyms {
+    /// Parses synonyms, resolving `#include`d headers like `syn.h` through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(
+            source,
+            resolver,
+            Defines::new(),
+            parse::synonym::synonym_file,
+        );
+        let mut diagnostics = script.diagnostics.clone();
+
+        let mut groups = Vec::new();
+        for context in script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::SynonymContext))
+        {
+            let context_value = child_nodes(context, SyntaxKind::Expression)
+                .next()
+                .and_then(|node| evaluate(source, &script.defines, node, &mut diagnostics))
+                .unwrap_or_default() as i64;
+
+            let mut seen = HashMap::new();
+            for group in child_nodes(context, SyntaxKind::SynonymGroup) {
+                let span = RawSpan::from(group.text_range());
+                let synonyms: Vec<_> = child_nodes(group, SyntaxKind::Synonym)
+                    .map(|synonym| {
+                        let text = child_tokens(synonym)
+                            .find(|token| token.kind() == SyntaxKind::QuotedString)
+                            .map(|token| {
+                                let text =
+                                    precomp::unquote(&source[RawSpan::from(token.text_range())]);
+                                precomp::unescape(text).unwrap_or_else(|_| text.to_owned())
+                            })
+                            .unwrap_or_default();
+                        let weight = child_nodes(synonym, SyntaxKind::Expression)
+                            .next()
+                            .and_then(|node| {
+                                evaluate(source, &script.defines, node, &mut diagnostics)
+                            })
+                            .unwrap_or_default();
+                        Synonym {
+                            text,
+                            weight,
+                            span: synonym.text_range().into(),
+                        }
+                    })
+                    .collect();
+
+                if synonyms.len() < 2 {
+                    diagnostics.push(Diagnostic::error(
+                        "synonym must have at least two entries",
+                        span,
+                    ));
+                }
+                for synonym in &synonyms {
+                    let key = synonym.text.to_ascii_lowercase();
+                    if let Some(previous) = seen.insert(key, synonym.span) {
+                        diagnostics.push(Diagnostic::warning(
+                            format!(
+                                "synonym '{}' already listed in this context at {}",
+                                synonym.text,
+                                previous.start()
+                            ),
+                            synonym.span,
+                        ));
+                    }
+                }
+
+                groups.push(SynonymGroup {
+                    context: context_value,
+                    synonyms,
+                    span,
+                });
+            }
+        }
+
+        let synonyms = Self {
+            groups,
+            defines: script.defines,
+        };
+        (synonyms, diagnostics)
+    }
+
+    /// Get the synonym groups in file order.
+    pub fn groups(&self) -> &[SynonymGroup] {
+        &self.groups
+    }
+
+    /// Get the defines of the synonyms and their includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    /// Replaces synonyms of the `context` bits by the first synonym of their group, like
+    /// `BotReplaceSynonyms`.
+    pub fn replace(&self, message: &str, context: i64) -> String {
+        let mut message = message.to_owned();
+        for group in self
+            .groups
+            .iter()
+            .filter(|group| group.context & context != 0)
+        {
+            let Some((first, rest)) = group.synonyms.split_first() else {
+                continue;
+            };
+            for synonym in rest {
+                replace_words(&mut message, &synonym.text, &first.text);
+            }
+        }
+        message
+    }
+}
+
+/// `[("Rocket Launcher", 1), ("RL", 0.5)]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynonymGroup {
+    context: i64,
+    synonyms: Vec<Synonym>,
+    span: RawSpan,
+}
+
+impl SynonymGroup {
+    /// Get the context bits of the enclosing block, `CONTEXT_*`.
+    pub const fn context(&self) -> i64 {
+        self.context
+    }
+
+    /// Get the synonyms, the first one replaces all others.
+    pub fn synonyms(&self) -> &[Synonym] {
+        &self.synonyms
+    }
+
+    /// Get the group's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `("Rocket Launcher", 1)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synonym {
+    text: String,
+    weight: f64,
+    span: RawSpan,
+}
+
+impl Synonym {
+    /// Get the synonym's unescaped text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the weight used when picking a random synonym.
+    pub const fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Get the synonym's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Replaces the words `synonym` in `text`, unless they are part of a `replacement` already there.
+fn replace_words(text: &mut String, synonym: &str, replacement: &str) {
+    let mut start = 0;
+    while let Some(offset) = contains_word(&text[start..], synonym) {
+        let position = start + offset;
+        let mut inside = false;
+        let mut search = 0;
+        while let Some(offset) = contains_word(&text[search..], replacement) {
+            let existing = search + offset;
+            if existing <= position && position < existing + replacement.len() {
+                inside = true;
+                break;
+            }
+            search = existing + text[existing..].chars().next().map_or(1, char::len_utf8);
+            if search >= text.len() {
+                break;
+            }
+        }
+        if inside {
+            start = position + synonym.len();
+        } else {
+            text.replace_range(position..position + synonym.len(), replacement);
+            start = position + replacement.len();
+        }
+        if start >= text.len() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace() {
+        let (synonyms, diagnostics) = Synonyms::parse(
+            r#"#define CONTEXT_NEARBYITEM 2
+CONTEXT_NEARBYITEM
+{
+    [("Rocket Launcher", 1), ("RL", 0.5), ("rocket", 0)]
+    [("Quad Damage", 1), ("quad", 1)]
+}
+"#,
+            None,
+        );
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(synonyms.groups().len(), 2);
+        assert_eq!(synonyms.groups()[0].synonyms()[1].weight(), 0.5);
+
+        assert_eq!(
+            synonyms.replace("get the rl, then the Quad!", 2),
+            "get the Rocket Launcher, then the Quad Damage!"
+        );
+        assert_eq!(
+            synonyms.replace("at the Rocket Launcher", 2),
+            "at the Rocket Launcher"
+        );
+        assert_eq!(synonyms.replace("rocketeer", 2), "rocketeer");
+        assert_eq!(synonyms.replace("get the rl", 1), "get the rl");
+
+        let (synonyms, _) = Synonyms::parse(r#"1 { [("Ärger", 1), ("x", 1)] }"#, None);
+        assert_eq!(synonyms.replace("Ärger x", 1), "Ärger Ärger");
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (_, diagnostics) =
+            Synonyms::parse(r#"1 { [("a", 1)] [("b", 1), ("B", 1), ("c", X)] }"#, None);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "synonym must have at least two entries",
+                "undefined name 'X'",
+                "synonym 'B' already listed in this context at 16",
+            ]
+        );
+    }
+}
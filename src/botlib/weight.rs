@@ -0,0 +1,503 @@
+//! Fuzzy weight configs (`*_w.c`, `fw_*.c`).
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parse,
+    resolve::FileResolver,
+    span::RawSpan,
+    syntax::{child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+use super::{
+    evaluate,
+    precomp::{self, Defines},
+    Script,
+};
+
+/// Value of `default` cases, `MAX_INVENTORYVALUE` in `be_ai_weight.c`.
+pub const MAX_INVENTORY_VALUE: i64 = 999999;
+
+/// Fuzzy weight config, e.g. `botfiles/bots/sarge_w.c`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightConfig {
+    weights: Vec<Weight>,
+    defines: Defines,
+}
+
+impl WeightConfig {
+    /// Parses a weight config, resolving `#include`s like `inv.h` through `resolver`.
+    pub fn parse(source: &str, resolver: Option<&dyn FileResolver>) -> (Self, Vec<Diagnostic>) {
+        let script = Script::parse(source, resolver, Defines::new(), parse::weight::weight_file);
+        let mut diagnostics = script.diagnostics.clone();
+
+        let builder = Builder {
+            source,
+            defines: &script.defines,
+        };
+        let weights: Vec<_> = script
+            .root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Weight))
+            .map(|node| builder.weight(node, &mut diagnostics))
+            .collect();
+
+        let mut seen = HashMap::new();
+        for weight in &weights {
+            if let Some(previous) = seen.insert(weight.name.as_str(), weight.span) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "weight '{}' already defined at {}, only the first one is used",
+                        weight.name,
+                        previous.start()
+                    ),
+                    weight.span,
+                ));
+            }
+        }
+
+        let config = Self {
+            weights,
+            defines: script.defines,
+        };
+        (config, diagnostics)
+    }
+
+    /// Get the weights of this config.
+    pub fn weights(&self) -> &[Weight] {
+        &self.weights
+    }
+
+    /// Get the first weight with the given name, like the engine does.
+    pub fn weight(&self, name: &str) -> Option<&Weight> {
+        self.weights.iter().find(|weight| weight.name == name)
+    }
+
+    /// Get the defines of this config and its includes.
+    pub const fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    /// Inventory index defined as `name`, e.g. `INVENTORY_ROCKETLAUNCHER`.
+    pub fn index(&self, name: &str) -> Option<i64> {
+        self.defines.value(name).map(|index| index as i64)
+    }
+
+    /// Evaluates the weight with the given name for `inventory`.
+    pub fn evaluate(&self, name: &str, inventory: &Inventory) -> Option<f64> {
+        self.weight(name).map(|weight| weight.evaluate(inventory))
+    }
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    defines: &'a Defines,
+}
+
+impl Builder<'_> {
+    fn weight(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> Weight {
+        let name = child_tokens(node)
+            .find(|token| token.kind() == SyntaxKind::QuotedString)
+            .map(|token| precomp::unquote(&self.source[RawSpan::from(token.text_range())]))
+            .unwrap_or_default()
+            .to_owned();
+
+        Weight {
+            name,
+            span: node.text_range().into(),
+            body: self.body(node, diagnostics),
+        }
+    }
+
+    fn body(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> WeightNode {
+        let span = RawSpan::from(node.text_range());
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::Switch => return WeightNode::Switch(self.switch(child, diagnostics)),
+                SyntaxKind::Return => return WeightNode::Return(self.value(child, diagnostics)),
+                _ => {}
+            }
+        }
+        WeightNode::Return(WeightValue::constant(0.0, span))
+    }
+
+    fn switch(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> Switch {
+        let span = RawSpan::from(node.text_range());
+        let index = child_nodes(node, SyntaxKind::Expression)
+            .next()
+            .map(|expression| self.expression(expression, diagnostics))
+            .unwrap_or_default();
+        let index_name = child_nodes(node, SyntaxKind::Expression)
+            .next()
+            .map(|expression| self.text(expression).trim().to_owned())
+            .unwrap_or_default();
+
+        let mut cases = Vec::new();
+        let mut has_default = false;
+        let mut previous = i64::MIN;
+        for case in child_nodes(node, SyntaxKind::Case) {
+            let case_span = RawSpan::from(case.text_range());
+            let value = child_nodes(case, SyntaxKind::Expression)
+                .next()
+                .map(|expression| self.expression(expression, diagnostics) as i64);
+            if has_default || value.is_some_and(|value| value <= previous) {
+                diagnostics.push(Diagnostic::warning(
+                    "unreachable case, cases have to be in ascending order before default",
+                    case_span,
+                ));
+            }
+            has_default |= value.is_none();
+            previous = value.unwrap_or(MAX_INVENTORY_VALUE).max(previous);
+
+            cases.push(Case {
+                value,
+                span: case_span,
+                body: self.body(case, diagnostics),
+            });
+        }
+        if !has_default {
+            diagnostics.push(Diagnostic::warning("switch without default", span));
+        }
+
+        Switch {
+            index: index as i64,
+            index_name,
+            span,
+            cases,
+        }
+    }
+
+    fn value(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> WeightValue {
+        let span = RawSpan::from(node.text_range());
+        if let Some(balance) = child_nodes(node, SyntaxKind::Balance).next() {
+            let mut arguments = child_nodes(balance, SyntaxKind::Expression)
+                .map(|expression| self.expression(expression, diagnostics));
+            let weight = arguments.next().unwrap_or_default();
+            let min = arguments.next().unwrap_or(weight);
+            let max = arguments.next().unwrap_or(weight);
+            if min > max {
+                diagnostics.push(Diagnostic::warning(
+                    format!("balance minimum {} is greater than maximum {}", min, max),
+                    balance.text_range().into(),
+                ));
+            }
+            return WeightValue {
+                weight,
+                min,
+                max,
+                span,
+            };
+        }
+        let weight = child_nodes(node, SyntaxKind::Expression)
+            .next()
+            .map(|expression| self.expression(expression, diagnostics))
+            .unwrap_or_default();
+        WeightValue::constant(weight, span)
+    }
+
+    fn expression(&self, node: &SyntaxNode, diagnostics: &mut Vec<Diagnostic>) -> f64 {
+        evaluate(self.source, self.defines, node, diagnostics).unwrap_or_default()
+    }
+
+    fn text(&self, node: &SyntaxNode) -> &str {
+        &self.source[RawSpan::from(node.text_range())]
+    }
+}
+
+/// `weight "name" { … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weight {
+    name: String,
+    span: RawSpan,
+    body: WeightNode,
+}
+
+impl Weight {
+    /// Get the weight's name, e.g. `weapon_rocketlauncher`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the weight's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the weight's switch or return.
+    pub const fn body(&self) -> &WeightNode {
+        &self.body
+    }
+
+    /// Evaluates the weight for `inventory` like `FuzzyWeight`, interpolating between the cases around
+    /// each inventory count.
+    pub fn evaluate(&self, inventory: &Inventory) -> f64 {
+        self.body.evaluate(inventory).weight
+    }
+
+    /// Evaluates the range a weight balanced by the bot's characteristics lies in.
+    pub fn evaluate_range(&self, inventory: &Inventory) -> (f64, f64) {
+        let value = self.body.evaluate(inventory);
+        (value.min, value.max)
+    }
+}
+
+/// Body of a weight or case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WeightNode {
+    Switch(Switch),
+    Return(WeightValue),
+}
+
+impl WeightNode {
+    fn evaluate(&self, inventory: &Inventory) -> WeightValue {
+        match self {
+            Self::Return(value) => *value,
+            Self::Switch(switch) => switch.evaluate(inventory),
+        }
+    }
+}
+
+/// `switch (INVENTORY_X) { … }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Switch {
+    index: i64,
+    index_name: String,
+    span: RawSpan,
+    cases: Vec<Case>,
+}
+
+impl Switch {
+    /// Get the inventory index switched on.
+    pub const fn index(&self) -> i64 {
+        self.index
+    }
+
+    /// Get the expression of the index, e.g. `INVENTORY_ROCKETLAUNCHER`.
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    /// Get the switch's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the switch's cases.
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    }
+
+    fn evaluate(&self, inventory: &Inventory) -> WeightValue {
+        let count = inventory.get(self.index);
+        // First case whose value is greater than the inventory count, see `FuzzyWeight_r`
+        let next = match self.cases.iter().position(|case| count < case.value()) {
+            Some(next) => next,
+            None => {
+                return match self.cases.last() {
+                    Some(case) => case.body.evaluate(inventory),
+                    None => WeightValue::constant(0.0, self.span),
+                }
+            }
+        };
+        let case = &self.cases[next];
+        let value = case.body.evaluate(inventory);
+        // Below the first case or before the default there is nothing to interpolate
+        if next == 0 || case.value() == MAX_INVENTORY_VALUE {
+            return value;
+        }
+        let previous = &self.cases[next - 1];
+        let scale = (count - previous.value()) as f64 / (case.value() - previous.value()) as f64;
+        previous.body.evaluate(inventory).interpolate(&value, scale)
+    }
+}
+
+/// `case 1: …` or `default: …`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    value: Option<i64>,
+    span: RawSpan,
+    body: WeightNode,
+}
+
+impl Case {
+    /// Get the case's value, [`MAX_INVENTORY_VALUE`] for `default`.
+    pub fn value(&self) -> i64 {
+        self.value.unwrap_or(MAX_INVENTORY_VALUE)
+    }
+
+    /// Case is `default`.
+    pub const fn is_default(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Get the case's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the case's nested switch or return.
+    pub const fn body(&self) -> &WeightNode {
+        &self.body
+    }
+}
+
+/// `return weight;` or `return balance(weight, min, max);`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightValue {
+    weight: f64,
+    min: f64,
+    max: f64,
+    span: RawSpan,
+}
+
+impl WeightValue {
+    const fn constant(weight: f64, span: RawSpan) -> Self {
+        Self {
+            weight,
+            min: weight,
+            max: weight,
+            span,
+        }
+    }
+
+    /// Scales linearly from this value at 0 to `other` at 1, keeping this span.
+    fn interpolate(&self, other: &Self, scale: f64) -> Self {
+        let lerp = |from: f64, to: f64| (1.0 - scale) * from + scale * to;
+        Self {
+            weight: lerp(self.weight, other.weight),
+            min: lerp(self.min, other.min),
+            max: lerp(self.max, other.max),
+            span: self.span,
+        }
+    }
+
+    /// Get the weight.
+    pub const fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Get the minimum balanced weight.
+    pub const fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Get the maximum balanced weight.
+    pub const fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Get the return's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Item counts of a bot, indexed by `INVENTORY_*` constants.
+///
+/// Missing items count as zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    counts: HashMap<i64, i64>,
+}
+
+impl Inventory {
+    /// Creates an empty inventory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the count of the item with the given index.
+    pub fn with(mut self, index: i64, count: i64) -> Self {
+        self.set(index, count);
+        self
+    }
+
+    /// Sets the count of the item with the given index.
+    pub fn set(&mut self, index: i64, count: i64) {
+        self.counts.insert(index, count);
+    }
+
+    /// Get the count of the item with the given index.
+    pub fn get(&self, index: i64) -> i64 {
+        self.counts.get(&index).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEIGHTS: &str = r#"#include "inv.h"
+#define W_ROCKETLAUNCHER 300
+
+weight "weapon_rocketlauncher"
+{
+    switch(INVENTORY_ROCKETLAUNCHER)
+    {
+        case 1: return 0;
+        default:
+        {
+            switch(INVENTORY_ROCKETS)
+            {
+                case 1: return 0;
+                case 10: return $evalint(W_ROCKETLAUNCHER / 2);
+                default: return balance(W_ROCKETLAUNCHER, 250, 350);
+            } //end switch
+        } //end default
+    } //end switch
+} //end weight
+
+weight "item_armor" { return 50; }
+"#;
+
+    fn files() -> HashMap<String, String> {
+        HashMap::from([(
+            "inv.h".to_owned(),
+            "#define INVENTORY_ROCKETLAUNCHER 6\n#define INVENTORY_ROCKETS 20\n".to_owned(),
+        )])
+    }
+
+    #[test]
+    fn evaluate() {
+        let files = files();
+        let (config, diagnostics) = WeightConfig::parse(WEIGHTS, Some(&files));
+        assert_eq!(diagnostics, &[]);
+
+        let launcher = config.index("INVENTORY_ROCKETLAUNCHER").unwrap();
+        let rockets = config.index("INVENTORY_ROCKETS").unwrap();
+        let weight = config.weight("weapon_rocketlauncher").unwrap();
+
+        assert_eq!(weight.evaluate(&Inventory::new()), 0.0);
+        let inventory = Inventory::new().with(launcher, 1);
+        assert_eq!(weight.evaluate(&inventory), 0.0);
+        // Between `case 1` and `case 10`, 4/9 of the way from 0 to 150
+        let inventory = inventory.with(rockets, 5);
+        assert!((weight.evaluate(&inventory) - 200.0 / 3.0).abs() < 1e-9);
+        let inventory = inventory.with(rockets, 10);
+        assert_eq!(weight.evaluate(&inventory), 300.0);
+        let inventory = inventory.with(rockets, 50);
+        assert_eq!(weight.evaluate(&inventory), 300.0);
+        assert_eq!(weight.evaluate_range(&inventory), (250.0, 350.0));
+
+        assert_eq!(config.evaluate("item_armor", &Inventory::new()), Some(50.0));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (_, diagnostics) = WeightConfig::parse(
+            "weight \"a\" { switch(INVENTORY_X) { case 5: return 1; case 2: return UNKNOWN; } }",
+            None,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "undefined name 'INVENTORY_X'",
+                "unreachable case, cases have to be in ascending order before default",
+                "undefined name 'UNKNOWN'",
+                "switch without default",
+            ]
+        );
+    }
+}
@@ -0,0 +1,721 @@
+//! Compiled maps (`maps/*.bsp`) in the IBSP version 46 format of q3map.
+//!
+//! Spans of diagnostics and records are byte ranges in the file, except for the entities whose
+//! spans are in [`Bsp::entity_source`], which has the same offsets as the entity lump.
+
+use std::fmt;
+
+use crate::{
+    binary::{self, block_checksum, float, index, int, span, vector, MAX_QPATH},
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    map::Entity,
+    parse, resolve,
+    span::RawSpan,
+    syntax::{self, child_nodes, SyntaxKind},
+};
+
+/// Magic at the start of the file.
+pub const BSP_IDENT: &[u8; 4] = b"IBSP";
+/// Version written by q3map and read by the engine.
+pub const BSP_VERSION: i32 = 46;
+/// Number of lumps in the directory.
+pub const HEADER_LUMPS: usize = 17;
+/// Width and height of a lightmap page.
+pub const LIGHTMAP_SIZE: usize = 128;
+
+/// Size of the ident, version and lump directory.
+const HEADER_SIZE: usize = 8 + HEADER_LUMPS * 8;
+
+/// Lumps in directory order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LumpKind {
+    Entities,
+    Shaders,
+    Planes,
+    Nodes,
+    Leafs,
+    LeafSurfaces,
+    LeafBrushes,
+    Models,
+    Brushes,
+    BrushSides,
+    DrawVerts,
+    DrawIndexes,
+    Fogs,
+    Surfaces,
+    Lightmaps,
+    LightGrid,
+    Visibility,
+}
+
+impl LumpKind {
+    /// Lumps in directory order.
+    pub const ALL: [Self; HEADER_LUMPS] = [
+        Self::Entities,
+        Self::Shaders,
+        Self::Planes,
+        Self::Nodes,
+        Self::Leafs,
+        Self::LeafSurfaces,
+        Self::LeafBrushes,
+        Self::Models,
+        Self::Brushes,
+        Self::BrushSides,
+        Self::DrawVerts,
+        Self::DrawIndexes,
+        Self::Fogs,
+        Self::Surfaces,
+        Self::Lightmaps,
+        Self::LightGrid,
+        Self::Visibility,
+    ];
+
+    /// Get the size of a record, `None` for lumps without fixed size records.
+    pub const fn record_size(self) -> Option<usize> {
+        match self {
+            Self::Entities | Self::Visibility => None,
+            Self::Shaders => Some(MAX_QPATH + 8),
+            Self::Planes => Some(16),
+            Self::Nodes => Some(36),
+            Self::Leafs => Some(48),
+            Self::LeafSurfaces | Self::LeafBrushes | Self::DrawIndexes => Some(4),
+            Self::Models => Some(40),
+            Self::Brushes => Some(12),
+            Self::BrushSides | Self::LightGrid => Some(8),
+            Self::DrawVerts => Some(44),
+            Self::Fogs => Some(MAX_QPATH + 8),
+            Self::Surfaces => Some(104),
+            Self::Lightmaps => Some(LIGHTMAP_SIZE * LIGHTMAP_SIZE * 3),
+        }
+    }
+}
+
+impl fmt::Display for LumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Lump directory entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Lump {
+    offset: u32,
+    length: u32,
+    span: RawSpan,
+}
+
+impl Lump {
+    pub(crate) const fn new(offset: u32, length: u32, span: RawSpan) -> Self {
+        Self {
+            offset,
+            length,
+            span,
+        }
+    }
+
+    /// Get the offset of the lump's data.
+    pub const fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Get the length of the lump's data in bytes.
+    pub const fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Get the span of the lump's data.
+    pub const fn data_span(&self) -> RawSpan {
+        RawSpan::new(self.offset, self.offset + self.length)
+    }
+
+    /// Get the span of the directory entry.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Read and validated compiled map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bsp {
+    checksum: i32,
+    lumps: Vec<Lump>,
+    entity_source: String,
+    entities: Vec<Entity>,
+    shaders: Vec<Shader>,
+    planes: Vec<Plane>,
+    models: Vec<Model>,
+    brushes: Vec<Brush>,
+    brush_sides: Vec<BrushSide>,
+    surface_count: usize,
+    lightmap_count: usize,
+}
+
+impl Bsp {
+    /// Reads and validates a compiled map, nothing but the diagnostics if the header is invalid.
+    pub fn parse(data: &[u8]) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut bsp = Self {
+            checksum: block_checksum(data),
+            ..Self::default()
+        };
+
+        if data.len() < HEADER_SIZE {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "file of {} bytes is too short for the {} bytes header",
+                    data.len(),
+                    HEADER_SIZE
+                ),
+                span(0, data.len()),
+            ));
+            return (bsp, diagnostics);
+        }
+        if &data[..4] != BSP_IDENT {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "expect ident 'IBSP', found '{}'",
+                    String::from_utf8_lossy(&data[..4])
+                ),
+                span(0, 4),
+            ));
+            return (bsp, diagnostics);
+        }
+        let version = int(data, 4);
+        if version != BSP_VERSION {
+            diagnostics.push(Diagnostic::error(
+                format!("unsupported version {}, expect {}", version, BSP_VERSION),
+                span(4, 8),
+            ));
+            return (bsp, diagnostics);
+        }
+
+        for (number, kind) in LumpKind::ALL.iter().enumerate() {
+            let entry = 8 + number * 8;
+            let mut lump = Lump {
+                offset: index(data, entry) as u32,
+                length: index(data, entry + 4) as u32,
+                span: span(entry, entry + 8),
+            };
+            let end = lump.offset as usize + lump.length as usize;
+            if end > data.len() {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "{} lump {}..{} is outside of the file's {} bytes",
+                        kind,
+                        lump.offset,
+                        end,
+                        data.len()
+                    ),
+                    lump.span,
+                ));
+                lump.offset = 0;
+                lump.length = 0;
+            } else if let Some(size) = kind.record_size() {
+                if !(lump.length as usize).is_multiple_of(size) {
+                    diagnostics.push(Diagnostic::error(
+                        format!(
+                            "{} lump length {} isn't a multiple of {}",
+                            kind, lump.length, size
+                        ),
+                        lump.span,
+                    ));
+                }
+            }
+            bsp.lumps.push(lump);
+        }
+
+        bsp.read_entities(data, &mut diagnostics);
+        bsp.shaders = bsp
+            .records(data, LumpKind::Shaders)
+            .map(|(span, record)| Shader::read(span, record, &mut diagnostics))
+            .collect();
+        bsp.planes = bsp
+            .records(data, LumpKind::Planes)
+            .map(|(span, record)| Plane {
+                normal: vector(record, 0),
+                distance: float(record, 12),
+                span,
+            })
+            .collect();
+        bsp.models = bsp
+            .records(data, LumpKind::Models)
+            .map(|(span, record)| Model {
+                mins: vector(record, 0),
+                maxs: vector(record, 12),
+                first_surface: index(record, 24),
+                surface_count: index(record, 28),
+                first_brush: index(record, 32),
+                brush_count: index(record, 36),
+                span,
+            })
+            .collect();
+        bsp.brushes = bsp
+            .records(data, LumpKind::Brushes)
+            .map(|(span, record)| Brush {
+                first_side: index(record, 0),
+                side_count: index(record, 4),
+                shader: index(record, 8),
+                span,
+            })
+            .collect();
+        bsp.brush_sides = bsp
+            .records(data, LumpKind::BrushSides)
+            .map(|(span, record)| BrushSide {
+                plane: index(record, 0),
+                shader: index(record, 4),
+                span,
+            })
+            .collect();
+        bsp.surface_count = bsp.records(data, LumpKind::Surfaces).count();
+        bsp.lightmap_count = bsp.records(data, LumpKind::Lightmaps).count();
+
+        bsp.check_indexes(&mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (bsp, diagnostics)
+    }
+
+    fn read_entities(&mut self, data: &[u8], diagnostics: &mut Vec<Diagnostic>) {
+        let lump = self.lump(LumpKind::Entities);
+        let text = &data[std::ops::Range::from(lump.data_span())];
+        let text = &text[..text
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(text.len())];
+        // Invalid bytes are replaced one for one, so offsets in the text are offsets in the lump
+        self.entity_source = resolve::decode(text);
+
+        let source = &self.entity_source;
+        let parse = syntax::parse_tokens(
+            source,
+            Lexer::new(source).collect(),
+            parse::bsp::entity_lump,
+        );
+        let mut errors = parse.errors().to_vec();
+        self.entities = parse
+            .syntax()
+            .children()
+            .flat_map(|lump| child_nodes(lump, SyntaxKind::Entity))
+            .map(|node| Entity::from_node(node, source, &mut errors))
+            .collect();
+        diagnostics.extend(errors.into_iter().map(|error| {
+            let span = RawSpan::new(
+                lump.offset + error.span().start(),
+                lump.offset + error.span().end(),
+            );
+            Diagnostic::new(error.severity(), error.message(), span)
+        }));
+    }
+
+    fn records<'a>(
+        &self,
+        data: &'a [u8],
+        kind: LumpKind,
+    ) -> impl Iterator<Item = (RawSpan, &'a [u8])> {
+        let lump = self.lump(kind);
+        let size = kind.record_size().unwrap_or(1);
+        data[std::ops::Range::from(lump.data_span())]
+            .chunks_exact(size)
+            .enumerate()
+            .map(move |(index, record)| {
+                let start = lump.offset as usize + index * size;
+                (span(start, start + size), record)
+            })
+    }
+
+    fn check_indexes(&self, diagnostics: &mut Vec<Diagnostic>) {
+        // `count` is `None` for a single index
+        let mut check = |what: &str, index: usize, count: Option<usize>, len, span| {
+            let end = index.checked_add(count.unwrap_or(1));
+            if end.is_none_or(|end| end > len) {
+                let range = match count {
+                    Some(count) => format!("{}..{}", index, index.saturating_add(count)),
+                    None => index.to_string(),
+                };
+                diagnostics.push(Diagnostic::error(
+                    format!("{} {} out of {}", what, range, len),
+                    span,
+                ));
+            }
+        };
+        for model in &self.models {
+            check(
+                "surfaces",
+                model.first_surface,
+                Some(model.surface_count),
+                self.surface_count,
+                model.span,
+            );
+            check(
+                "brushes",
+                model.first_brush,
+                Some(model.brush_count),
+                self.brushes.len(),
+                model.span,
+            );
+        }
+        for brush in &self.brushes {
+            check(
+                "brush sides",
+                brush.first_side,
+                Some(brush.side_count),
+                self.brush_sides.len(),
+                brush.span,
+            );
+            check("shader", brush.shader, None, self.shaders.len(), brush.span);
+        }
+        for side in &self.brush_sides {
+            check("plane", side.plane, None, self.planes.len(), side.span);
+            check("shader", side.shader, None, self.shaders.len(), side.span);
+        }
+    }
+
+    /// Get the checksum of the file, the `sv_mapChecksum` that bot navigation files must match.
+    pub const fn checksum(&self) -> i32 {
+        self.checksum
+    }
+
+    /// Get the directory entry of the lump.
+    pub fn lump(&self, kind: LumpKind) -> Lump {
+        self.lumps.get(kind as usize).copied().unwrap_or_default()
+    }
+
+    /// Get the text of the entity lump, without the terminating NUL and with `?` for each byte
+    /// that isn't UTF-8.
+    pub fn entity_source(&self) -> &str {
+        &self.entity_source
+    }
+
+    /// Get the entities in lump order, the first being `worldspawn`.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Get the entities with the given `classname`, e.g. `info_player_deathmatch`.
+    pub fn entities_of_class<'a>(&'a self, classname: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |entity| entity.classname() == Some(classname))
+    }
+
+    /// Get the shaders referenced by brush sides, surfaces and fogs.
+    pub fn shaders(&self) -> &[Shader] {
+        &self.shaders
+    }
+
+    /// Get the planes.
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes
+    }
+
+    /// Get the models, the first being the world and the others brush entities like doors.
+    pub fn models(&self) -> &[Model] {
+        &self.models
+    }
+
+    /// Get the brushes.
+    pub fn brushes(&self) -> &[Brush] {
+        &self.brushes
+    }
+
+    /// Get the sides of the brushes.
+    pub fn brush_sides(&self) -> &[BrushSide] {
+        &self.brush_sides
+    }
+
+    /// Get the number of draw surfaces.
+    pub const fn surface_count(&self) -> usize {
+        self.surface_count
+    }
+
+    /// Get the number of internal lightmap pages of [`LIGHTMAP_SIZE`] squared RGB texels.
+    pub const fn lightmap_count(&self) -> usize {
+        self.lightmap_count
+    }
+}
+
+/// `dshader_t`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shader {
+    name: String,
+    surface_flags: i32,
+    contents: i32,
+    span: RawSpan,
+}
+
+impl Shader {
+    fn read(span: RawSpan, record: &[u8], diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let name = binary::name(&record[..MAX_QPATH]).unwrap_or_else(|| {
+            diagnostics.push(Diagnostic::error("shader name isn't terminated", span));
+            String::from_utf8_lossy(&record[..MAX_QPATH])
+        });
+        Self {
+            name: name.into_owned(),
+            surface_flags: int(record, MAX_QPATH),
+            contents: int(record, MAX_QPATH + 4),
+            span,
+        }
+    }
+
+    /// Get the shader's name, e.g. `textures/base_wall/concrete`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the `SURF_*` flags.
+    pub const fn surface_flags(&self) -> i32 {
+        self.surface_flags
+    }
+
+    /// Get the `CONTENTS_*` flags.
+    pub const fn contents(&self) -> i32 {
+        self.contents
+    }
+
+    /// Get the record's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `dplane_t`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Plane {
+    normal: [f32; 3],
+    distance: f32,
+    span: RawSpan,
+}
+
+impl Plane {
+    /// Get the plane's normal.
+    pub const fn normal(&self) -> [f32; 3] {
+        self.normal
+    }
+
+    /// Get the distance from the origin along the normal.
+    pub const fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Get the record's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `dmodel_t`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Model {
+    mins: [f32; 3],
+    maxs: [f32; 3],
+    first_surface: usize,
+    surface_count: usize,
+    first_brush: usize,
+    brush_count: usize,
+    span: RawSpan,
+}
+
+impl Model {
+    /// Get the minimum corner of the bounds.
+    pub const fn mins(&self) -> [f32; 3] {
+        self.mins
+    }
+
+    /// Get the maximum corner of the bounds.
+    pub const fn maxs(&self) -> [f32; 3] {
+        self.maxs
+    }
+
+    /// Get the indexes of the model's draw surfaces.
+    pub const fn surfaces(&self) -> std::ops::Range<usize> {
+        self.first_surface..self.first_surface.saturating_add(self.surface_count)
+    }
+
+    /// Get the indexes of the model's brushes in [`Bsp::brushes`].
+    pub const fn brushes(&self) -> std::ops::Range<usize> {
+        self.first_brush..self.first_brush.saturating_add(self.brush_count)
+    }
+
+    /// Get the record's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `dbrush_t`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Brush {
+    first_side: usize,
+    side_count: usize,
+    shader: usize,
+    span: RawSpan,
+}
+
+impl Brush {
+    /// Get the indexes of the brush's sides in [`Bsp::brush_sides`].
+    pub const fn sides(&self) -> std::ops::Range<usize> {
+        self.first_side..self.first_side.saturating_add(self.side_count)
+    }
+
+    /// Get the index of the shader providing the contents in [`Bsp::shaders`].
+    pub const fn shader(&self) -> usize {
+        self.shader
+    }
+
+    /// Get the record's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `dbrushside_t`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BrushSide {
+    plane: usize,
+    shader: usize,
+    span: RawSpan,
+}
+
+impl BrushSide {
+    /// Get the index of the side's plane in [`Bsp::planes`].
+    pub const fn plane(&self) -> usize {
+        self.plane
+    }
+
+    /// Get the index of the side's shader in [`Bsp::shaders`].
+    pub const fn shader(&self) -> usize {
+        self.shader
+    }
+
+    /// Get the record's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary::{floats, ints};
+
+    use super::*;
+
+    /// Writes a BSP with the lumps in directory order.
+    fn write(lumps: &[(LumpKind, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(BSP_IDENT);
+        data.extend_from_slice(&BSP_VERSION.to_le_bytes());
+        data.resize(HEADER_SIZE, 0);
+        for (kind, lump) in lumps {
+            let entry = 8 + *kind as usize * 8;
+            let offset = data.len() as u32;
+            data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+            data[entry + 4..entry + 8].copy_from_slice(&(lump.len() as u32).to_le_bytes());
+            data.extend_from_slice(lump);
+        }
+        data
+    }
+
+    fn shader(name: &str, contents: i32) -> Vec<u8> {
+        let mut record = name.as_bytes().to_vec();
+        record.resize(MAX_QPATH, 0);
+        record.extend(ints(&[0, contents]));
+        record
+    }
+
+    #[test]
+    fn read() {
+        let entities =
+            b"{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"info_player_deathmatch\"\n\"origin\" \"16 32 24\"\n}\n\0";
+        let mut shaders = shader("textures/base_wall/concrete", 1);
+        shaders.extend(shader("textures/common/caulk", 1));
+        let planes = floats(&[1.0, 0.0, 0.0, 64.0]);
+        let mut model = floats(&[-64.0, -64.0, -64.0, 64.0, 64.0, 64.0]);
+        model.extend(ints(&[0, 0, 0, 1]));
+        let data = write(&[
+            (LumpKind::Entities, entities.to_vec()),
+            (LumpKind::Shaders, shaders),
+            (LumpKind::Planes, planes),
+            (LumpKind::Models, model),
+            (LumpKind::Brushes, ints(&[0, 2, 1])),
+            (LumpKind::BrushSides, ints(&[0, 0, 0, 1])),
+            (
+                LumpKind::Lightmaps,
+                vec![0; 2 * LIGHTMAP_SIZE * LIGHTMAP_SIZE * 3],
+            ),
+        ]);
+
+        let (bsp, diagnostics) = Bsp::parse(&data);
+        assert_eq!(diagnostics, &[]);
+        let spawns: Vec<_> = bsp
+            .entities_of_class("info_player_deathmatch")
+            .map(Entity::origin)
+            .collect();
+        assert_eq!(spawns, &[Some([16.0, 32.0, 24.0])]);
+        let origin = bsp.entities()[1].pair("origin").unwrap();
+        assert_eq!(&bsp.entity_source()[origin.value_span()], "\"16 32 24\"");
+
+        assert_eq!(bsp.shaders()[1].name(), "textures/common/caulk");
+        assert_eq!(bsp.planes()[0].distance(), 64.0);
+        assert_eq!(bsp.models()[0].brushes(), 0..1);
+        assert_eq!(bsp.brushes()[0].sides(), 0..2);
+        assert_eq!(bsp.brush_sides()[1].shader(), 1);
+        assert_eq!(bsp.lightmap_count(), 2);
+    }
+
+    #[test]
+    fn validation() {
+        let (_, diagnostics) = Bsp::parse(b"IBSP\x2f\0\0\0");
+        assert_eq!(
+            diagnostics[0].message(),
+            "file of 8 bytes is too short for the 144 bytes header"
+        );
+        let mut data = write(&[]);
+        data[4] = 47;
+        let (_, diagnostics) = Bsp::parse(&data);
+        assert_eq!(
+            diagnostics[0].message(),
+            "unsupported version 47, expect 46"
+        );
+
+        let mut data = write(&[
+            (LumpKind::Entities, b"{\n\"classname\"\n}\0".to_vec()),
+            (LumpKind::Shaders, shader("textures/common/caulk", 1)),
+            (LumpKind::Brushes, ints(&[0, 1, 3, 0])),
+        ]);
+        data[8 + LumpKind::Planes as usize * 8] = 0xff;
+        data[8 + LumpKind::Planes as usize * 8 + 4] = 16;
+        let (_, diagnostics) = Bsp::parse(&data);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "Planes lump 255..271 is outside of the file's 248 bytes",
+                "Brushes lump length 16 isn't a multiple of 12",
+                "entity without classname",
+                "expect_any BitFlags<TokenKind>(0b110000, String | QuotedString)",
+                "expect RightBrace",
+                "brush sides 0..1 out of 0",
+                "shader 3 out of 1",
+            ]
+        );
+
+        // The invalid byte keeps its single byte in the entity text
+        let entities = b"{\n\"message\" \"caf\xe9\"\n\"classname\"\n}\0";
+        let data = write(&[(LumpKind::Entities, entities.to_vec())]);
+        let (bsp, diagnostics) = Bsp::parse(&data);
+        let offset = bsp.lump(LumpKind::Entities).data_span().start() as usize;
+        let message = bsp.entities()[0].pair("message").unwrap();
+        assert_eq!(&bsp.entity_source()[message.value_span()], "\"caf?\"");
+        let error = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.message().starts_with("expect_any"))
+            .unwrap();
+        assert_eq!(&data[std::ops::Range::from(error.span())], b"}");
+        let entity = bsp.entities()[0].span();
+        assert_eq!(
+            diagnostics[0].span(),
+            RawSpan::new(offset as u32 + entity.start(), offset as u32 + entity.end())
+        );
+    }
+}
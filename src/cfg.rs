@@ -0,0 +1,331 @@
+//! Console configs (`*.cfg`), e.g. `autoexec.cfg`, `q3config.cfg` and server configs.
+//!
+//! Commands are split like `Cbuf_Execute` and their arguments like `Cmd_TokenizeString`.
+
+use std::fmt;
+
+use crate::{
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::CfgLexer,
+    parse,
+    span::RawSpan,
+    syntax::{self, child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+pub mod eval;
+pub mod rotation;
+
+/// Parsed console config.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    commands: Vec<Command>,
+}
+
+impl Config {
+    /// Parses a console config or a command line like a cvar value run by `vstr`.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(
+            source,
+            CfgLexer::new(source).collect(),
+            parse::cfg::cfg_file,
+        );
+        let mut diagnostics = parse.errors().to_vec();
+
+        let commands = parse
+            .syntax()
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Command))
+            .map(|node| Command::from_node(node, source, &mut diagnostics))
+            .collect();
+
+        (Self { commands }, diagnostics)
+    }
+
+    /// Get the commands in execution order.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+}
+
+/// `seta r_mode "3"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    name: Argument,
+    arguments: Vec<Argument>,
+    kind: CommandKind,
+    span: RawSpan,
+}
+
+impl Command {
+    fn from_node(node: &SyntaxNode, source: &str, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        // A `/` ending a word is lexed on its own, so adjacent words form one argument
+        let mut words: Vec<Argument> = Vec::new();
+        let mut previous = None;
+        for token in child_tokens(node).filter(|token| !token.kind().is_trivia()) {
+            let span = RawSpan::from(token.text_range());
+            match (words.last_mut(), previous) {
+                (Some(word), Some(SyntaxKind::String))
+                    if token.kind() == SyntaxKind::String && word.span.end() == span.start() =>
+                {
+                    word.span = RawSpan::new(word.span.start(), span.end());
+                    word.text.push_str(&source[span]);
+                }
+                _ => words.push(Argument {
+                    text: unquote(&source[span]).to_owned(),
+                    span,
+                }),
+            }
+            previous = Some(token.kind());
+        }
+        let span = RawSpan::new(
+            words.first().map_or(0, |word| word.span.start()),
+            words.last().map_or(0, |word| word.span.end()),
+        );
+        let mut words = words.into_iter();
+        let name = words.next().unwrap_or_default();
+        let arguments: Vec<_> = words.collect();
+
+        let mut command = Self {
+            name,
+            arguments,
+            kind: CommandKind::Other,
+            span,
+        };
+        command.kind = command.typed(diagnostics);
+        command
+    }
+
+    fn typed(&self, diagnostics: &mut Vec<Diagnostic>) -> CommandKind {
+        let keyword = self.name.text.to_ascii_lowercase();
+        let (required, kind) = match keyword.as_str() {
+            "set" | "seta" | "sets" | "setu" => {
+                let flag = match keyword.as_str() {
+                    "seta" => SetFlag::Archive,
+                    "sets" => SetFlag::ServerInfo,
+                    "setu" => SetFlag::UserInfo,
+                    _ => SetFlag::None,
+                };
+                let variable = self.argument(0).unwrap_or_default();
+                if variable.contains(['\\', '"', ';']) {
+                    diagnostics.push(Diagnostic::error(
+                        format!("invalid cvar name '{}'", variable),
+                        self.arguments[0].span,
+                    ));
+                }
+                let kind = CommandKind::Set {
+                    variable: variable.to_owned(),
+                    value: self.argument(1).map(|_| self.arguments_from(1)),
+                    flag,
+                };
+                (1, kind)
+            }
+            "bind" => {
+                let kind = CommandKind::Bind {
+                    key: self.argument(0).unwrap_or_default().to_owned(),
+                    command: self.argument(1).map(|_| self.arguments_from(1)),
+                };
+                (1, kind)
+            }
+            "vstr" => {
+                let kind = CommandKind::Vstr {
+                    variable: self.argument(0).unwrap_or_default().to_owned(),
+                };
+                (1, kind)
+            }
+            "exec" => {
+                let mut path = self.argument(0).unwrap_or_default().to_owned();
+                if !path.rsplit('/').next().unwrap_or_default().contains('.') {
+                    path.push_str(".cfg");
+                }
+                (1, CommandKind::Exec { path })
+            }
+            "map" | "devmap" | "spmap" | "spdevmap" => {
+                let kind = CommandKind::Map {
+                    name: self.argument(0).unwrap_or_default().to_owned(),
+                    cheats: keyword.contains("dev"),
+                };
+                (1, kind)
+            }
+            _ => (0, CommandKind::Other),
+        };
+        if self.arguments.len() < required {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "missing parameter for '{}', the engine only prints its usage",
+                    self.name.text
+                ),
+                self.span,
+            ));
+            return CommandKind::Other;
+        }
+        kind
+    }
+
+    /// Get the command's name as written.
+    pub fn name(&self) -> &str {
+        &self.name.text
+    }
+
+    /// Get the arguments after the name, without quotes.
+    pub fn arguments(&self) -> &[Argument] {
+        &self.arguments
+    }
+
+    /// Get the text of the `index`th argument after the name.
+    pub fn argument(&self, index: usize) -> Option<&str> {
+        self.arguments.get(index).map(Argument::text)
+    }
+
+    /// Get the arguments from the `index`th on joined by spaces, like `Cmd_ArgsFrom`.
+    pub fn arguments_from(&self, index: usize) -> String {
+        self.arguments
+            .iter()
+            .skip(index)
+            .map(Argument::text)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Get what the command does.
+    pub const fn kind(&self) -> &CommandKind {
+        &self.kind
+    }
+
+    /// Get the command's span, without the `;` or newline ending it.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.text)?;
+        for argument in &self.arguments {
+            if argument.text.is_empty() || argument.text.contains([' ', ';']) {
+                write!(f, " \"{}\"", argument.text)?;
+            } else {
+                write!(f, " {}", argument.text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Command's name or argument.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Argument {
+    text: String,
+    span: RawSpan,
+}
+
+impl Argument {
+    /// Get the text without quotes.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the argument's span, including quotes.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Commands the tooling understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandKind {
+    /// `set`, `seta`, `sets` or `setu` with the value joined from the remaining arguments,
+    /// without a value it prints the cvar.
+    Set {
+        variable: String,
+        value: Option<String>,
+        flag: SetFlag,
+    },
+    /// `bind <key> [command]`, without a command it prints the binding.
+    Bind {
+        key: String,
+        command: Option<String>,
+    },
+    /// `vstr <variable>`, executes a cvar's value.
+    Vstr { variable: String },
+    /// `exec <file>`, with `.cfg` added to a path without extension.
+    Exec { path: String },
+    /// `map`, `devmap`, `spmap` or `spdevmap`.
+    Map { name: String, cheats: bool },
+    /// Any other command, and commands missing parameters.
+    Other,
+}
+
+/// Flag set besides the value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SetFlag {
+    /// `set`
+    #[default]
+    None,
+    /// `seta`, saved to `q3config.cfg`.
+    Archive,
+    /// `sets`, sent in the server info.
+    ServerInfo,
+    /// `setu`, sent in the user info.
+    UserInfo,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let (config, diagnostics) = Config::parse(
+            "seta sv_hostname \"My Server\"\nset d1 \"map q3dm1; set nextmap vstr d2\"; vstr d1\nbind MOUSE1 +attack\nexec bots/setup\nexec\nset bad;name 1\n",
+        );
+        let kinds: Vec<_> = config
+            .commands()
+            .iter()
+            .map(Command::kind)
+            .cloned()
+            .collect();
+        assert_eq!(
+            kinds,
+            &[
+                CommandKind::Set {
+                    variable: "sv_hostname".to_owned(),
+                    value: Some("My Server".to_owned()),
+                    flag: SetFlag::Archive,
+                },
+                CommandKind::Set {
+                    variable: "d1".to_owned(),
+                    value: Some("map q3dm1; set nextmap vstr d2".to_owned()),
+                    flag: SetFlag::None,
+                },
+                CommandKind::Vstr {
+                    variable: "d1".to_owned()
+                },
+                CommandKind::Bind {
+                    key: "MOUSE1".to_owned(),
+                    command: Some("+attack".to_owned())
+                },
+                CommandKind::Exec {
+                    path: "bots/setup.cfg".to_owned()
+                },
+                CommandKind::Other,
+                CommandKind::Set {
+                    variable: "bad".to_owned(),
+                    value: None,
+                    flag: SetFlag::None,
+                },
+                CommandKind::Other,
+            ]
+        );
+        assert_eq!(
+            config.commands()[1].to_string(),
+            "set d1 \"map q3dm1; set nextmap vstr d2\""
+        );
+
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &["missing parameter for 'exec', the engine only prints its usage",]
+        );
+    }
+}
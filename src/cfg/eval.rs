@@ -0,0 +1,417 @@
+//! Simulates executing console configs: `exec` includes, cvars, `vstr` and map rotations.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    resolve::FileResolver,
+    span::RawSpan,
+};
+
+use super::{CommandKind, Config, SetFlag};
+
+/// Maximum depth of nested `exec`s and `vstr`s.
+const MAX_DEPTH: usize = 64;
+
+/// Maximum number of map changes followed by [`Evaluator::rotation`].
+pub const MAX_ROTATION: usize = 256;
+
+/// Cvar state after executing configs, reading `exec`ed files through a resolver.
+#[derive(Clone)]
+pub struct Evaluator<'r> {
+    resolver: &'r dyn FileResolver,
+    cvars: BTreeMap<String, Cvar>,
+    maps: Vec<String>,
+}
+
+impl fmt::Debug for Evaluator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Evaluator")
+            .field("cvars", &self.cvars)
+            .field("maps", &self.maps)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'r> Evaluator<'r> {
+    /// Creates a new evaluator without cvars.
+    pub fn new(resolver: &'r dyn FileResolver) -> Self {
+        Self {
+            resolver,
+            cvars: BTreeMap::new(),
+            maps: Vec::new(),
+        }
+    }
+
+    /// Sets a cvar before executing configs, e.g. from the command line.
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.set_cvar(name, value, SetFlag::None);
+    }
+
+    /// Executes the config at `path`, reported with spans into that file.
+    pub fn exec(&mut self, path: &str) -> Vec<Diagnostic> {
+        match self.resolver.read_to_string(path) {
+            Ok(source) => {
+                let mut stack = vec![Frame::Exec(path.to_ascii_lowercase())];
+                self.run(&source, &mut stack)
+            }
+            Err(error) => vec![Diagnostic::error(
+                format!("cannot exec '{}': {}", path, error),
+                RawSpan::default(),
+            )],
+        }
+    }
+
+    /// Executes command lines, like typed into the console.
+    pub fn execute(&mut self, source: &str) -> Vec<Diagnostic> {
+        self.run(source, &mut Vec::new())
+    }
+
+    /// Get the cvar with the given name, ignoring case.
+    pub fn cvar(&self, name: &str) -> Option<&Cvar> {
+        self.cvars.get(&name.to_ascii_lowercase())
+    }
+
+    /// Get the cvars sorted by name.
+    pub fn cvars(&self) -> impl Iterator<Item = &Cvar> {
+        self.cvars.values()
+    }
+
+    /// Get the maps started so far, in order.
+    pub fn maps(&self) -> &[String] {
+        &self.maps
+    }
+
+    /// Follows `vstr nextmap` from the last map started, like the game at the end of each level.
+    pub fn rotation(&self) -> Rotation {
+        let mut evaluator = self.clone();
+        let Some(first) = evaluator.maps.last().cloned() else {
+            return Rotation {
+                maps: Vec::new(),
+                end: RotationEnd::DeadEnd("no map is started".to_owned()),
+            };
+        };
+        let mut maps = vec![first];
+        let mut states = Vec::new();
+
+        let end = loop {
+            let current = &maps[maps.len() - 1];
+            if let Some(start) = states.iter().position(|state| *state == evaluator.cvars) {
+                maps.pop();
+                break RotationEnd::Loop(start);
+            }
+            if maps.len() > MAX_ROTATION {
+                break RotationEnd::Limit;
+            }
+            states.push(evaluator.cvars.clone());
+
+            if evaluator
+                .cvar("nextmap")
+                .is_none_or(|cvar| cvar.value.is_empty())
+            {
+                break RotationEnd::DeadEnd(format!("nextmap is empty after '{}'", current));
+            }
+            evaluator.maps.clear();
+            let diagnostics = evaluator.execute("vstr nextmap");
+            if let Some(error) = diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic.severity() >= Severity::Warning)
+            {
+                break RotationEnd::DeadEnd(format!("after '{}': {}", current, error.message()));
+            }
+            match evaluator.maps.last() {
+                Some(map) => maps.push(map.clone()),
+                None => {
+                    break RotationEnd::DeadEnd(format!(
+                        "nextmap doesn't start a map after '{}'",
+                        current
+                    ))
+                }
+            }
+        };
+
+        Rotation { maps, end }
+    }
+
+    fn run(&mut self, source: &str, stack: &mut Vec<Frame>) -> Vec<Diagnostic> {
+        let (config, mut diagnostics) = Config::parse(source);
+        for command in config.commands() {
+            let span = command.span();
+            match command.kind() {
+                CommandKind::Set {
+                    variable,
+                    value: Some(value),
+                    flag,
+                } => self.set_cvar(variable, value, *flag),
+                CommandKind::Map { name, .. } => {
+                    self.maps.push(name.clone());
+                    self.set_cvar("mapname", name, SetFlag::ServerInfo);
+                }
+                CommandKind::Exec { path } => {
+                    let frame = Frame::Exec(path.to_ascii_lowercase());
+                    if self.enter(&frame, stack, span, &mut diagnostics) {
+                        match self.resolver.read_to_string(path) {
+                            Ok(source) => {
+                                let nested = self.run(&source, stack);
+                                diagnostics.extend(nested.into_iter().map(|diagnostic| {
+                                    Diagnostic::new(
+                                        diagnostic.severity(),
+                                        format!("in '{}': {}", path, diagnostic.message()),
+                                        span,
+                                    )
+                                }));
+                            }
+                            Err(_) => diagnostics.push(Diagnostic::warning(
+                                format!("couldn't exec '{}'", path),
+                                span,
+                            )),
+                        }
+                        stack.pop();
+                    }
+                }
+                CommandKind::Vstr { variable } => {
+                    let value = match self.cvar(variable) {
+                        None => {
+                            diagnostics.push(Diagnostic::warning(
+                                format!("vstr of undefined cvar '{}'", variable),
+                                span,
+                            ));
+                            continue;
+                        }
+                        Some(cvar) if cvar.value.trim().is_empty() => {
+                            diagnostics.push(Diagnostic::warning(
+                                format!("vstr of empty cvar '{}' is a dead end", variable),
+                                span,
+                            ));
+                            continue;
+                        }
+                        Some(cvar) => cvar.value.clone(),
+                    };
+                    let frame = Frame::Vstr(variable.to_ascii_lowercase(), value.clone());
+                    if self.enter(&frame, stack, span, &mut diagnostics) {
+                        let nested = self.run(&value, stack);
+                        diagnostics.extend(nested.into_iter().map(|diagnostic| {
+                            Diagnostic::new(
+                                diagnostic.severity(),
+                                format!("in vstr '{}': {}", variable, diagnostic.message()),
+                                span,
+                            )
+                        }));
+                        stack.pop();
+                    }
+                }
+                CommandKind::Set { value: None, .. }
+                | CommandKind::Bind { .. }
+                | CommandKind::Other => {}
+            }
+        }
+        diagnostics
+    }
+
+    /// Pushes `frame` unless it is already being executed or the stack is too deep.
+    fn enter(
+        &self,
+        frame: &Frame,
+        stack: &mut Vec<Frame>,
+        span: RawSpan,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> bool {
+        if let Some(start) = stack.iter().position(|entered| entered == frame) {
+            let chain: Vec<_> = stack[start..]
+                .iter()
+                .chain([frame])
+                .map(Frame::name)
+                .collect();
+            diagnostics.push(Diagnostic::error(
+                format!("{} cycle {}", frame.command(), chain.join(" -> ")),
+                span,
+            ));
+            return false;
+        }
+        if stack.len() >= MAX_DEPTH {
+            diagnostics.push(Diagnostic::error(
+                format!("{} '{}' nested too deeply", frame.command(), frame.name()),
+                span,
+            ));
+            return false;
+        }
+        stack.push(frame.clone());
+        true
+    }
+
+    fn set_cvar(&mut self, name: &str, value: &str, flag: SetFlag) {
+        let cvar = self
+            .cvars
+            .entry(name.to_ascii_lowercase())
+            .or_insert_with(|| Cvar {
+                name: name.to_owned(),
+                value: String::new(),
+                flag,
+            });
+        cvar.value = value.to_owned();
+        if flag != SetFlag::None {
+            cvar.flag = flag;
+        }
+    }
+}
+
+/// `exec` or `vstr` being executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Frame {
+    Exec(String),
+    /// A cvar and the value executed, which may legitimately change between nested `vstr`s.
+    Vstr(String, String),
+}
+
+impl Frame {
+    fn name(&self) -> &str {
+        match self {
+            Self::Exec(path) => path,
+            Self::Vstr(name, _) => name,
+        }
+    }
+
+    const fn command(&self) -> &'static str {
+        match self {
+            Self::Exec(_) => "exec",
+            Self::Vstr(..) => "vstr",
+        }
+    }
+}
+
+/// Console variable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cvar {
+    name: String,
+    value: String,
+    flag: SetFlag,
+}
+
+impl Cvar {
+    /// Get the name as first set.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the current value.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get the flag of the last `seta`, `sets` or `setu`.
+    pub const fn flag(&self) -> SetFlag {
+        self.flag
+    }
+}
+
+/// Maps a server cycles through.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rotation {
+    maps: Vec<String>,
+    end: RotationEnd,
+}
+
+impl Rotation {
+    /// Get the maps in order, starting with the map the config started.
+    pub fn maps(&self) -> &[String] {
+        &self.maps
+    }
+
+    /// Get how the rotation continues after the last map.
+    pub const fn end(&self) -> &RotationEnd {
+        &self.end
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.maps.join(" -> "))?;
+        match &self.end {
+            RotationEnd::Loop(start) => write!(f, " -> {} (loop)", self.maps[*start]),
+            RotationEnd::DeadEnd(reason) => write!(f, " (stops: {})", reason),
+            RotationEnd::Limit => write!(f, " -> …"),
+        }
+    }
+}
+
+/// How a rotation continues after its last map.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RotationEnd {
+    /// Continues with the map at the index.
+    Loop(usize),
+    /// Stops changing maps, with the reason.
+    DeadEnd(String),
+    /// Didn't repeat within [`MAX_ROTATION`] maps.
+    Limit,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        let files = HashMap::from([
+            (
+                "server.cfg".to_owned(),
+                "seta sv_hostname \"Test\"\nexec rotation\nvstr d1\n".to_owned(),
+            ),
+            (
+                "rotation.cfg".to_owned(),
+                "set d1 \"map q3dm1; set nextmap vstr d2\"\nset d2 \"map q3dm7; set nextmap vstr d3\"\nset d3 \"map q3dm17; set nextmap vstr d1\"\n"
+                    .to_owned(),
+            ),
+        ]);
+        let mut evaluator = Evaluator::new(&files);
+        assert_eq!(evaluator.exec("server.cfg"), &[]);
+        assert_eq!(evaluator.maps(), &["q3dm1"]);
+        assert_eq!(evaluator.cvar("NEXTMAP").unwrap().value(), "vstr d2");
+        assert_eq!(
+            evaluator.rotation().to_string(),
+            "q3dm1 -> q3dm7 -> q3dm17 -> q3dm1 (loop)"
+        );
+
+        evaluator.execute("set d3 \"map q3dm17; set nextmap vstr d4\"; vstr d1");
+        assert_eq!(
+            evaluator.rotation().to_string(),
+            "q3dm1 -> q3dm7 -> q3dm17 (stops: after 'q3dm17': in vstr 'nextmap': vstr of undefined cvar 'd4')"
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let files = HashMap::from([
+            ("a.cfg".to_owned(), "exec b.cfg\n".to_owned()),
+            ("b.cfg".to_owned(), "exec a\nexec missing\n".to_owned()),
+        ]);
+        let mut evaluator = Evaluator::new(&files);
+        let messages: Vec<_> = evaluator
+            .exec("a.cfg")
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                "in 'b.cfg': exec cycle a.cfg -> b.cfg -> a.cfg",
+                "in 'b.cfg': couldn't exec 'missing.cfg'",
+            ]
+        );
+
+        let messages: Vec<_> = evaluator
+            .execute("set a \"vstr b\"; set b \"vstr a\"; vstr a; set c \"set c echo; vstr c\"; vstr c; set e \"\"; vstr e")
+            .iter()
+            .map(|diagnostic| diagnostic.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            &[
+                "in vstr 'a': in vstr 'b': vstr cycle a -> b -> a",
+                "vstr of empty cvar 'e' is a dead end",
+            ]
+        );
+    }
+}
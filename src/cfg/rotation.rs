@@ -0,0 +1,216 @@
+//! Server map rotations generated from arenas.
+
+use std::fmt::Write as _;
+
+use crate::arena::Arena;
+
+/// Generates a `vstr` map rotation cfg for the arenas of a game type.
+///
+/// Each map is a cvar like `set d1 "set fraglimit 20; set timelimit 0; map q3dm1; …; set nextmap
+/// vstr d2"`, the last one continuing with the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationGenerator {
+    game_type: String,
+    cvar_prefix: String,
+    fraglimit: Option<u32>,
+    timelimit: Option<u32>,
+    bot_skill: Option<u32>,
+    seed: Option<u64>,
+}
+
+impl RotationGenerator {
+    /// Creates a new generator for arenas listing `game_type` in their `type`, e.g. `ffa`.
+    pub fn new(game_type: impl Into<String>) -> Self {
+        Self {
+            game_type: game_type.into(),
+            cvar_prefix: "d".to_owned(),
+            fraglimit: None,
+            timelimit: None,
+            bot_skill: None,
+            seed: None,
+        }
+    }
+
+    /// Names the rotation cvars `<prefix>1`, `<prefix>2`, …, `d` by default.
+    pub fn cvar_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.cvar_prefix = prefix.into();
+        self
+    }
+
+    /// Uses `fraglimit` for arenas without one, instead of no limit.
+    pub fn fraglimit(mut self, fraglimit: u32) -> Self {
+        self.fraglimit = Some(fraglimit);
+        self
+    }
+
+    /// Uses `timelimit` for arenas without one, instead of no limit.
+    pub fn timelimit(mut self, timelimit: u32) -> Self {
+        self.timelimit = Some(timelimit);
+        self
+    }
+
+    /// Adds each arena's `bots` with the skill from 1 to 5 when changing to its map.
+    pub fn bots(mut self, skill: u32) -> Self {
+        self.bot_skill = Some(skill);
+        self
+    }
+
+    /// Shuffles the maps, always the same way for the same seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Get the arenas in rotation order: those with a map and the game type, shuffled if seeded.
+    pub fn select<'a>(&self, arenas: &'a [Arena]) -> Vec<&'a Arena> {
+        let mut selected: Vec<_> = arenas
+            .iter()
+            .filter(|arena| arena.map().is_some() && arena.has_type(&self.game_type))
+            .collect();
+        if let Some(seed) = self.seed {
+            let mut random = SplitMix64(seed);
+            for index in (1..selected.len()).rev() {
+                let other = (random.next() % (index as u64 + 1)) as usize;
+                selected.swap(index, other);
+            }
+        }
+        selected
+    }
+
+    /// Generates the rotation cfg, starting it with `vstr <prefix>1`.
+    pub fn generate(&self, arenas: &[Arena]) -> String {
+        let selected = self.select(arenas);
+        let mut cfg = String::new();
+        let _ = writeln!(
+            cfg,
+            "// {} rotation of {} maps",
+            self.game_type,
+            selected.len()
+        );
+        if let Some(number) = game_type_number(&self.game_type) {
+            let _ = writeln!(cfg, "set g_gametype {}", number);
+        }
+        if selected.is_empty() {
+            return cfg;
+        }
+
+        for (index, arena) in selected.iter().enumerate() {
+            let mut commands = Vec::new();
+            // Both limits are set for every map, or the previous map's would stay in effect
+            commands.push(format!(
+                "set fraglimit {}",
+                arena.fraglimit().or(self.fraglimit).unwrap_or(0)
+            ));
+            commands.push(format!(
+                "set timelimit {}",
+                arena.timelimit().or(self.timelimit).unwrap_or(0)
+            ));
+            commands.push(format!("map {}", arena.map().unwrap_or_default()));
+            if let Some(skill) = self.bot_skill {
+                commands.push("kick allbots".to_owned());
+                commands.extend(arena.bots().map(|bot| format!("addbot {} {}", bot, skill)));
+            }
+            commands.push(format!(
+                "set nextmap vstr {}{}",
+                self.cvar_prefix,
+                (index + 1) % selected.len() + 1
+            ));
+            let _ = writeln!(
+                cfg,
+                "set {}{} \"{}\"",
+                self.cvar_prefix,
+                index + 1,
+                commands.join("; ")
+            );
+        }
+        let _ = writeln!(cfg, "vstr {}1", self.cvar_prefix);
+        cfg
+    }
+}
+
+/// `g_gametype` of the base game's types.
+fn game_type_number(game_type: &str) -> Option<u32> {
+    match game_type.to_ascii_lowercase().as_str() {
+        "ffa" => Some(0),
+        "tourney" => Some(1),
+        "single" => Some(2),
+        "team" => Some(3),
+        "ctf" => Some(4),
+        _ => None,
+    }
+}
+
+/// SplitMix64, so shuffles don't depend on a platform or crate's random number generator.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{arena::ArenaFile, cfg::eval::Evaluator};
+
+    use super::*;
+
+    const ARENAS: &str = r#"
+{ map "q3dm1" bots "sarge" fraglimit 10 type "single ffa" }
+{ map "q3ctf1" type "ctf" }
+{ map "q3dm7" bots "grunt major" timelimit 15 type "ffa team" }
+{ map "q3dm17" type "single ffa tourney" }
+{ longname "no map" type "ffa" }
+"#;
+
+    #[test]
+    fn generate() {
+        let (file, _) = ArenaFile::parse(ARENAS);
+        let cfg = RotationGenerator::new("ffa")
+            .timelimit(20)
+            .bots(3)
+            .generate(file.arenas());
+        assert_eq!(
+            cfg,
+            r#"// ffa rotation of 3 maps
+set g_gametype 0
+set d1 "set fraglimit 10; set timelimit 20; map q3dm1; kick allbots; addbot sarge 3; set nextmap vstr d2"
+set d2 "set fraglimit 0; set timelimit 15; map q3dm7; kick allbots; addbot grunt 3; addbot major 3; set nextmap vstr d3"
+set d3 "set fraglimit 0; set timelimit 20; map q3dm17; kick allbots; set nextmap vstr d1"
+vstr d1
+"#
+        );
+
+        let files = HashMap::new();
+        let mut evaluator = Evaluator::new(&files);
+        assert_eq!(evaluator.execute(&cfg), &[]);
+        assert_eq!(
+            evaluator.rotation().to_string(),
+            "q3dm1 -> q3dm7 -> q3dm17 -> q3dm1 (loop)"
+        );
+    }
+
+    #[test]
+    fn shuffle() {
+        let (file, _) = ArenaFile::parse(ARENAS);
+        let maps = |seed| {
+            RotationGenerator::new("ffa")
+                .seed(seed)
+                .select(file.arenas())
+                .iter()
+                .map(|arena| arena.map().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(maps(1), maps(1));
+        let mut sorted = maps(7);
+        sorted.sort_unstable();
+        assert_eq!(sorted, &["q3dm1", "q3dm17", "q3dm7"]);
+        assert!((0..16).any(|seed| maps(seed) != maps(0)));
+    }
+}
@@ -0,0 +1,803 @@
+//! Recorded demos (`demos/*.dm_68`) of protocol 68.
+//!
+//! A demo is a sequence of server messages as received by the client recording it, each framed
+//! by its sequence number and length. Spans are byte ranges in the file.
+
+use std::collections::VecDeque;
+
+use crate::{
+    binary::{int, span},
+    diagnostic::Diagnostic,
+    info::InfoString,
+    span::RawSpan,
+};
+
+use self::{
+    message::{Message, Overflow},
+    state::{DeltaError, EntityState, PlayerState, GENTITYNUM_BITS, MAX_GENTITIES},
+};
+
+mod huffman;
+mod message;
+pub mod state;
+
+/// `MAX_MSGLEN`
+pub const MAX_MSGLEN: usize = 16384;
+/// `MAX_CONFIGSTRINGS`
+pub const MAX_CONFIGSTRINGS: usize = 1024;
+/// `CS_SERVERINFO`, the `serverinfo` cvars like `mapname`.
+pub const CS_SERVERINFO: usize = 0;
+/// `CS_SYSTEMINFO`, the `systeminfo` cvars like `sv_paks`.
+pub const CS_SYSTEMINFO: usize = 1;
+/// `CS_PLAYERS`, the first of the players' info strings.
+pub const CS_PLAYERS: usize = 544;
+/// `MAX_CLIENTS`
+pub const MAX_CLIENTS: usize = 64;
+
+/// `PACKET_BACKUP`, snapshots kept to delta from.
+const PACKET_BACKUP: usize = 32;
+
+/// `svc_ops_e`
+mod svc {
+    pub const NOP: u8 = 1;
+    pub const GAMESTATE: u8 = 2;
+    pub const CONFIGSTRING: u8 = 3;
+    pub const BASELINE: u8 = 4;
+    pub const SERVER_COMMAND: u8 = 5;
+    pub const DOWNLOAD: u8 = 6;
+    pub const SNAPSHOT: u8 = 7;
+    pub const EOF: u8 = 8;
+}
+
+/// What a server message contained.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Configstrings and entity baselines sent when a map is loaded.
+    Gamestate(Gamestate),
+    /// Reliable command like `print` or `cs`.
+    ServerCommand(ServerCommand),
+    /// State of the game at a server frame.
+    Snapshot(Box<Snapshot>),
+}
+
+/// `svc_gamestate`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Gamestate {
+    command_sequence: i32,
+    configstrings: Vec<(usize, String)>,
+    client_num: i32,
+    checksum_feed: i32,
+}
+
+impl Gamestate {
+    /// Get the sequence of the last server command sent before.
+    pub const fn command_sequence(&self) -> i32 {
+        self.command_sequence
+    }
+
+    /// Get the configstrings that aren't empty with their index.
+    pub fn configstrings(&self) -> &[(usize, String)] {
+        &self.configstrings
+    }
+
+    /// Get the number of the recording client.
+    pub const fn client_num(&self) -> i32 {
+        self.client_num
+    }
+
+    /// Get the feed of the pak checksums.
+    pub const fn checksum_feed(&self) -> i32 {
+        self.checksum_feed
+    }
+}
+
+/// `svc_serverCommand`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerCommand {
+    sequence: i32,
+    text: String,
+    kind: ServerCommandKind,
+}
+
+impl ServerCommand {
+    /// Get the command's sequence number.
+    pub const fn sequence(&self) -> i32 {
+        self.sequence
+    }
+
+    /// Get the command as sent, e.g. `print "Sarge entered the game\n"`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get what the command does.
+    pub const fn kind(&self) -> &ServerCommandKind {
+        &self.kind
+    }
+}
+
+/// Server commands the tooling understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerCommandKind {
+    /// `print`, console text.
+    Print(String),
+    /// `cp`, center printed text.
+    CenterPrint(String),
+    /// `chat`
+    Chat(String),
+    /// `tchat`, team chat.
+    TeamChat(String),
+    /// `cs`, or `bcs2` completing a big configstring started by `bcs0`.
+    ConfigString { index: usize, value: String },
+    /// `scores` with the numbers of the scoreboard.
+    Scores(Vec<i32>),
+    /// Any other command, including the first parts of big configstrings.
+    Other,
+}
+
+/// `svc_snapshot`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    server_time: i32,
+    message_num: i32,
+    delta_num: Option<i32>,
+    flags: u8,
+    area_mask: Vec<u8>,
+    player_state: PlayerState,
+    entities: Vec<EntityState>,
+}
+
+impl Snapshot {
+    /// Get the server time in milliseconds.
+    pub const fn server_time(&self) -> i32 {
+        self.server_time
+    }
+
+    /// Get the sequence number of the message it was sent in.
+    pub const fn message_num(&self) -> i32 {
+        self.message_num
+    }
+
+    /// Get the message number of the snapshot it is a delta from, `None` if it is complete.
+    pub const fn delta_num(&self) -> Option<i32> {
+        self.delta_num
+    }
+
+    /// Get the `SNAPFLAG_*` flags.
+    pub const fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Get the bits of the areas visible from the player's position.
+    pub fn area_mask(&self) -> &[u8] {
+        &self.area_mask
+    }
+
+    /// Get the recording player's state.
+    pub const fn player_state(&self) -> &PlayerState {
+        &self.player_state
+    }
+
+    /// Get the visible entities by number.
+    pub fn entities(&self) -> &[EntityState] {
+        &self.entities
+    }
+}
+
+/// Reads the events of a demo, one message at a time.
+///
+/// Decoding stops at the first invalid message, with a diagnostic in
+/// [`diagnostics`](Self::diagnostics).
+pub struct DemoReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    events: VecDeque<Event>,
+    baselines: Vec<EntityState>,
+    snapshots: Vec<Option<Snapshot>>,
+    command_sequence: i32,
+    big_configstring: Option<(usize, String)>,
+    diagnostics: Vec<Diagnostic>,
+    done: bool,
+}
+
+impl<'a> DemoReader<'a> {
+    /// Creates a new reader of the demo's data.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            events: VecDeque::new(),
+            baselines: (0..MAX_GENTITIES).map(EntityState::null).collect(),
+            snapshots: vec![None; PACKET_BACKUP],
+            command_sequence: 0,
+            big_configstring: None,
+            diagnostics: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Get the problems found so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Reads the next message like `CL_ReadDemoMessage`, `false` at the end.
+    fn read_message(&mut self) -> bool {
+        let start = self.offset;
+        let header = match self.data.get(start..start + 8) {
+            Some(header) => header,
+            None => {
+                if start < self.data.len() {
+                    self.error("demo is truncated", span(start, self.data.len()));
+                }
+                return false;
+            }
+        };
+        let sequence = int(header, 0);
+        let length = int(header, 4);
+        if length == -1 {
+            return false;
+        }
+        let length = length as u32 as usize;
+        if length > MAX_MSGLEN {
+            self.error(
+                format!("message length {} exceeds {}", length, MAX_MSGLEN),
+                span(start + 4, start + 8),
+            );
+            return false;
+        }
+        let data = self.data;
+        let body = match data.get(start + 8..start + 8 + length) {
+            Some(body) => body,
+            None => {
+                self.error("demo is truncated", span(start, data.len()));
+                return false;
+            }
+        };
+        self.offset = start + 8 + length;
+
+        let span = span(start, self.offset);
+        let mut message = Message::new(body);
+        match self.parse_message(&mut message, sequence) {
+            Ok(()) => true,
+            Err(MessageError::Overflow) => {
+                self.error("read past the end of the message", span);
+                false
+            }
+            Err(MessageError::Invalid(text)) => {
+                self.error(text, span);
+                false
+            }
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: RawSpan) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    /// `CL_ParseServerMessage`
+    fn parse_message(&mut self, message: &mut Message, sequence: i32) -> Result<(), MessageError> {
+        let _reliable_acknowledge = message.long()?;
+        loop {
+            match message.byte()? {
+                svc::EOF => return Ok(()),
+                svc::NOP => {}
+                svc::SERVER_COMMAND => self.parse_command(message)?,
+                svc::GAMESTATE => self.parse_gamestate(message)?,
+                svc::SNAPSHOT => self.parse_snapshot(message, sequence)?,
+                svc::DOWNLOAD => {
+                    return Err(MessageError::Invalid(
+                        "download in a demo isn't supported".to_owned(),
+                    ))
+                }
+                command => {
+                    return Err(MessageError::Invalid(format!(
+                        "illegal server message command {}",
+                        command
+                    )))
+                }
+            }
+        }
+    }
+
+    /// `CL_ParseCommandString`
+    fn parse_command(&mut self, message: &mut Message) -> Result<(), MessageError> {
+        let sequence = message.long()?;
+        let text = message.string()?;
+        if sequence <= self.command_sequence {
+            // Sent again until acknowledged
+            return Ok(());
+        }
+        self.command_sequence = sequence;
+        let kind = self.command_kind(&text);
+        self.events.push_back(Event::ServerCommand(ServerCommand {
+            sequence,
+            text,
+            kind,
+        }));
+        Ok(())
+    }
+
+    fn command_kind(&mut self, text: &str) -> ServerCommandKind {
+        let arguments = tokenize(text);
+        let (name, arguments) = match arguments.split_first() {
+            Some((name, arguments)) => (name.as_str(), arguments),
+            None => return ServerCommandKind::Other,
+        };
+        let argument = |index: usize| arguments.get(index).cloned().unwrap_or_default();
+        let index = || {
+            arguments
+                .first()
+                .and_then(|index| index.parse().ok())
+                .filter(|index| *index < MAX_CONFIGSTRINGS)
+        };
+        match name {
+            "print" => ServerCommandKind::Print(argument(0)),
+            "cp" => ServerCommandKind::CenterPrint(argument(0)),
+            "chat" => ServerCommandKind::Chat(argument(0)),
+            "tchat" => ServerCommandKind::TeamChat(argument(0)),
+            "cs" => match index() {
+                Some(index) => ServerCommandKind::ConfigString {
+                    index,
+                    value: argument(1),
+                },
+                None => ServerCommandKind::Other,
+            },
+            "bcs0" => {
+                self.big_configstring = index().map(|index| (index, argument(1)));
+                ServerCommandKind::Other
+            }
+            "bcs1" | "bcs2" => {
+                let mut big = match self.big_configstring.take() {
+                    Some(big) if Some(big.0) == index() => big,
+                    _ => return ServerCommandKind::Other,
+                };
+                big.1.push_str(&argument(1));
+                if name == "bcs1" {
+                    self.big_configstring = Some(big);
+                    return ServerCommandKind::Other;
+                }
+                ServerCommandKind::ConfigString {
+                    index: big.0,
+                    value: big.1,
+                }
+            }
+            "scores" => ServerCommandKind::Scores(
+                arguments
+                    .iter()
+                    .map(|argument| argument.parse().unwrap_or_default())
+                    .collect(),
+            ),
+            _ => ServerCommandKind::Other,
+        }
+    }
+
+    /// `CL_ParseGamestate`
+    fn parse_gamestate(&mut self, message: &mut Message) -> Result<(), MessageError> {
+        let mut gamestate = Gamestate {
+            command_sequence: message.long()?,
+            ..Gamestate::default()
+        };
+        self.command_sequence = gamestate.command_sequence;
+        self.baselines = (0..MAX_GENTITIES).map(EntityState::null).collect();
+        self.snapshots = vec![None; PACKET_BACKUP];
+        loop {
+            match message.byte()? {
+                svc::EOF => break,
+                svc::CONFIGSTRING => {
+                    let index = message.short()? as u16 as usize;
+                    if index >= MAX_CONFIGSTRINGS {
+                        return Err(MessageError::Invalid(format!(
+                            "configstring {} exceeds {}",
+                            index, MAX_CONFIGSTRINGS
+                        )));
+                    }
+                    gamestate.configstrings.push((index, message.big_string()?));
+                }
+                svc::BASELINE => {
+                    let number = message.bits(GENTITYNUM_BITS)? as usize;
+                    let null = EntityState::null(number);
+                    self.baselines[number] =
+                        EntityState::read_delta(message, &null, number)?.unwrap_or(null);
+                }
+                command => {
+                    return Err(MessageError::Invalid(format!(
+                        "bad gamestate command {}",
+                        command
+                    )))
+                }
+            }
+        }
+        gamestate.client_num = message.long()?;
+        gamestate.checksum_feed = message.long()?;
+        self.events.push_back(Event::Gamestate(gamestate));
+        Ok(())
+    }
+
+    /// `CL_ParseSnapshot`
+    fn parse_snapshot(&mut self, message: &mut Message, sequence: i32) -> Result<(), MessageError> {
+        let server_time = message.long()?;
+        let delta = message.byte()?;
+        // Numbers of 0 or less, e.g. early in a demo or wrapped around, aren't deltas either
+        let delta_num = (delta != 0)
+            .then(|| sequence.wrapping_sub(i32::from(delta)))
+            .filter(|delta_num| *delta_num > 0);
+        let flags = message.byte()?;
+
+        // Entities are read the same way without the old snapshot, so a missing one is skipped
+        let old = delta_num.and_then(|delta_num| {
+            self.snapshots[delta_num as usize % PACKET_BACKUP]
+                .as_ref()
+                .filter(|old| old.message_num == delta_num)
+        });
+        let valid = delta_num.is_none() || old.is_some();
+
+        let length = usize::from(message.byte()?);
+        let area_mask = message.data(length)?;
+        let player_state = PlayerState::read_delta(message, old.map(|old| &old.player_state))?;
+        let entities = self.parse_entities(message, old.map(|old| old.entities.as_slice()))?;
+
+        let snapshot = Snapshot {
+            server_time,
+            message_num: sequence,
+            delta_num,
+            flags,
+            area_mask,
+            player_state,
+            entities,
+        };
+        if valid {
+            self.snapshots[sequence as usize % PACKET_BACKUP] = Some(snapshot.clone());
+            self.events.push_back(Event::Snapshot(Box::new(snapshot)));
+        }
+        Ok(())
+    }
+
+    /// `CL_ParsePacketEntities`, merging the changes with the old snapshot's entities.
+    fn parse_entities(
+        &self,
+        message: &mut Message,
+        old: Option<&[EntityState]>,
+    ) -> Result<Vec<EntityState>, MessageError> {
+        let mut entities = Vec::new();
+        let mut old = old.unwrap_or_default().iter().peekable();
+        loop {
+            let number = message.bits(GENTITYNUM_BITS)? as usize;
+            if number == MAX_GENTITIES - 1 {
+                break;
+            }
+            while let Some(unchanged) = old.next_if(|entity| entity.number() < number) {
+                entities.push(unchanged.clone());
+            }
+            let from = match old.next_if(|entity| entity.number() == number) {
+                Some(entity) => entity,
+                None => &self.baselines[number],
+            };
+            entities.extend(EntityState::read_delta(message, from, number)?);
+        }
+        entities.extend(old.cloned());
+        Ok(entities)
+    }
+}
+
+impl Iterator for DemoReader<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.events.is_empty() && !self.done {
+            self.done = !self.read_message();
+        }
+        self.events.pop_front()
+    }
+}
+
+/// Splits a server command into arguments like `Cmd_TokenizeString`.
+///
+/// Unlike in config files, quoted arguments go on across lines and there are no `;` separators.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c <= ' ');
+        if rest.is_empty() || rest.starts_with("//") {
+            return arguments;
+        }
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            arguments.push(quoted[..end].to_owned());
+            rest = quoted.get(end + 1..).unwrap_or_default();
+        } else {
+            let end = rest.find(|c: char| c <= ' ').unwrap_or(rest.len());
+            arguments.push(rest[..end].to_owned());
+            rest = &rest[end..];
+        }
+    }
+}
+
+/// Message that can't be decoded.
+enum MessageError {
+    Overflow,
+    Invalid(String),
+}
+
+impl From<Overflow> for MessageError {
+    fn from(_: Overflow) -> Self {
+        Self::Overflow
+    }
+}
+
+impl From<DeltaError> for MessageError {
+    fn from(error: DeltaError) -> Self {
+        match error {
+            DeltaError::Overflow => Self::Overflow,
+            DeltaError::FieldCount(count) => {
+                Self::Invalid(format!("invalid field count {}", count))
+            }
+        }
+    }
+}
+
+/// Match metadata of a demo, without the snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Demo {
+    configstrings: Vec<String>,
+    client_num: Option<i32>,
+    server_commands: Vec<ServerCommand>,
+    snapshot_count: usize,
+    server_times: Option<(i32, i32)>,
+}
+
+impl Demo {
+    /// Reads a demo, applying configstring changes to the gamestate.
+    pub fn parse(data: &[u8]) -> (Self, Vec<Diagnostic>) {
+        let mut demo = Self {
+            configstrings: vec![String::new(); MAX_CONFIGSTRINGS],
+            client_num: None,
+            server_commands: Vec::new(),
+            snapshot_count: 0,
+            server_times: None,
+        };
+        let mut reader = DemoReader::new(data);
+        for event in &mut reader {
+            match event {
+                Event::Gamestate(gamestate) => {
+                    demo.configstrings = vec![String::new(); MAX_CONFIGSTRINGS];
+                    for (index, value) in gamestate.configstrings {
+                        demo.configstrings[index] = value;
+                    }
+                    demo.client_num = Some(gamestate.client_num);
+                }
+                Event::ServerCommand(command) => {
+                    if let ServerCommandKind::ConfigString { index, value } = &command.kind {
+                        demo.configstrings[*index] = value.clone();
+                    }
+                    demo.server_commands.push(command);
+                }
+                Event::Snapshot(snapshot) => {
+                    demo.snapshot_count += 1;
+                    let time = snapshot.server_time;
+                    demo.server_times = Some(match demo.server_times {
+                        Some((first, _)) => (first, time),
+                        None => (time, time),
+                    });
+                }
+            }
+        }
+        (demo, reader.diagnostics)
+    }
+
+    /// Get the configstring at `index` as of the end of the demo, `None` if it is empty.
+    pub fn configstring(&self, index: usize) -> Option<&str> {
+        self.configstrings
+            .get(index)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Get the configstring at `index` as an info string.
+    pub fn info(&self, index: usize) -> InfoString {
+        InfoString::parse(self.configstring(index).unwrap_or_default())
+    }
+
+    /// Get the `serverinfo` cvars.
+    pub fn server_info(&self) -> InfoString {
+        self.info(CS_SERVERINFO)
+    }
+
+    /// Get the map's name from the `serverinfo`.
+    pub fn map(&self) -> Option<String> {
+        self.server_info().get("mapname").map(str::to_owned)
+    }
+
+    /// Get the connected players' info strings with their client numbers, e.g. `n` for the name.
+    pub fn players(&self) -> impl Iterator<Item = (usize, InfoString)> + '_ {
+        (0..MAX_CLIENTS)
+            .filter(move |client| self.configstring(CS_PLAYERS + client).is_some())
+            .map(move |client| (client, self.info(CS_PLAYERS + client)))
+    }
+
+    /// Get the number of the recording client.
+    pub const fn client_num(&self) -> Option<i32> {
+        self.client_num
+    }
+
+    /// Get the server commands in order.
+    pub fn server_commands(&self) -> &[ServerCommand] {
+        &self.server_commands
+    }
+
+    /// Get the number of snapshots.
+    pub const fn snapshot_count(&self) -> usize {
+        self.snapshot_count
+    }
+
+    /// Get the server times of the first and last snapshot.
+    pub const fn server_times(&self) -> Option<(i32, i32)> {
+        self.server_times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use self::message::Writer;
+
+    use super::*;
+
+    fn frame(data: &mut Vec<u8>, sequence: i32, message: &[u8]) {
+        data.extend(sequence.to_le_bytes());
+        data.extend((message.len() as i32).to_le_bytes());
+        data.extend(message);
+    }
+
+    /// Writes a gamestate, two snapshots and server commands.
+    fn write() -> Vec<u8> {
+        let mut data = Vec::new();
+
+        let mut writer = Writer::new();
+        writer
+            .bits(0, 32)
+            .bits(svc::GAMESTATE.into(), 8)
+            .bits(2, 32);
+        writer
+            .bits(svc::CONFIGSTRING.into(), 8)
+            .bits(CS_SERVERINFO as i32, 16)
+            .string("\\mapname\\q3dm17\\g_gametype\\0");
+        writer
+            .bits(svc::CONFIGSTRING.into(), 8)
+            .bits(CS_PLAYERS as i32, 16)
+            .string("n\\Sarge\\t\\0");
+        // Baseline of entity 5 with eType 2, the 12th field
+        writer.bits(svc::BASELINE.into(), 8).bits(5, 10);
+        writer.bits(0, 1).bits(1, 1).bits(12, 8);
+        for _ in 0..11 {
+            writer.bits(0, 1);
+        }
+        writer.bits(1, 1).bits(1, 1).bits(2, 8);
+        writer.bits(svc::EOF.into(), 8).bits(0, 32).bits(1234, 32);
+        writer.bits(svc::EOF.into(), 8);
+        frame(&mut data, 1, &writer.finish());
+
+        // Complete snapshot with commandTime and origin[0] of the player and entity 5
+        writer
+            .bits(0, 32)
+            .bits(svc::SERVER_COMMAND.into(), 8)
+            .bits(3, 32);
+        writer.string("print \"Sarge entered the game\n\"");
+        writer
+            .bits(svc::SNAPSHOT.into(), 8)
+            .bits(1000, 32)
+            .bits(0, 8)
+            .bits(0, 8);
+        writer.bits(1, 8).bits(0xff, 8);
+        // Two fields, commandTime and origin[0] as an integral float
+        writer.bits(2, 8).bits(1, 1).bits(1000, 32);
+        writer.bits(1, 1).bits(0, 1).bits(64 + 4096, 13);
+        // Stats changed, 100 health, no persistant, ammo or powerups
+        writer.bits(1, 1).bits(1, 1).bits(1, 16).bits(100, 16);
+        writer.bits(0, 1).bits(0, 1).bits(0, 1);
+        writer.bits(5, 10).bits(0, 1).bits(0, 1);
+        writer.bits(1023, 10).bits(svc::EOF.into(), 8);
+        frame(&mut data, 2, &writer.finish());
+
+        // Delta snapshot removing entity 5 and a player joining
+        writer
+            .bits(0, 32)
+            .bits(svc::SERVER_COMMAND.into(), 8)
+            .bits(4, 32);
+        writer.string("cs 545 \"n\\Grunt\\t\\0\"");
+        writer
+            .bits(svc::SNAPSHOT.into(), 8)
+            .bits(1050, 32)
+            .bits(1, 8)
+            .bits(0, 8);
+        writer.bits(0, 8).bits(0, 8).bits(0, 1);
+        writer.bits(5, 10).bits(1, 1);
+        writer.bits(1023, 10).bits(svc::EOF.into(), 8);
+        frame(&mut data, 3, &writer.finish());
+
+        data.extend((-1i32).to_le_bytes());
+        data.extend((-1i32).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn reader() {
+        let data = write();
+        let mut reader = DemoReader::new(&data);
+        let events: Vec<_> = (&mut reader).collect();
+        assert_eq!(reader.diagnostics(), &[]);
+        assert_eq!(events.len(), 5);
+
+        let snapshot = match &events[2] {
+            Event::Snapshot(snapshot) => snapshot,
+            event => panic!("expect snapshot, found {:?}", event),
+        };
+        assert_eq!(snapshot.server_time(), 1000);
+        assert_eq!(snapshot.area_mask(), &[0xff]);
+        assert_eq!(
+            snapshot.player_state().get("commandTime"),
+            Some(state::FieldValue::Int(1000))
+        );
+        assert_eq!(snapshot.player_state().origin(), [64.0, 0.0, 0.0]);
+        assert_eq!(snapshot.player_state().stats()[0], 100);
+        assert_eq!(snapshot.entities()[0].number(), 5);
+        assert_eq!(snapshot.entities()[0].entity_type(), 2);
+
+        let delta = match &events[4] {
+            Event::Snapshot(snapshot) => snapshot,
+            event => panic!("expect snapshot, found {:?}", event),
+        };
+        assert_eq!(delta.delta_num(), Some(2));
+        assert_eq!(delta.player_state().origin(), [64.0, 0.0, 0.0]);
+        assert_eq!(delta.entities(), &[]);
+    }
+
+    #[test]
+    fn delta_num() {
+        let mut data = Vec::new();
+        let mut writer = Writer::new();
+        for (sequence, delta) in [(i32::MIN, 1), (1, 1)] {
+            writer
+                .bits(0, 32)
+                .bits(svc::SNAPSHOT.into(), 8)
+                .bits(1000, 32)
+                .bits(delta, 8)
+                .bits(0, 8);
+            writer.bits(0, 8).bits(0, 8).bits(0, 1);
+            writer.bits(1023, 10).bits(svc::EOF.into(), 8);
+            frame(&mut data, sequence, &writer.finish());
+        }
+        // The first is a delta from a snapshot that was never received, so it's dropped
+        let mut reader = DemoReader::new(&data);
+        let delta_nums: Vec<_> = (&mut reader)
+            .filter_map(|event| match event {
+                Event::Snapshot(snapshot) => Some(snapshot.delta_num()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reader.diagnostics(), &[]);
+        assert_eq!(delta_nums, &[None]);
+    }
+
+    #[test]
+    fn metadata() {
+        let data = write();
+        let (demo, diagnostics) = Demo::parse(&data);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(demo.map().as_deref(), Some("q3dm17"));
+        assert_eq!(demo.client_num(), Some(0));
+        let names: Vec<_> = demo
+            .players()
+            .map(|(client, info)| (client, info.get("n").unwrap().to_owned()))
+            .collect();
+        assert_eq!(names, &[(0, "Sarge".to_owned()), (1, "Grunt".to_owned())]);
+        assert_eq!(
+            demo.server_commands()[0].kind(),
+            &ServerCommandKind::Print("Sarge entered the game\n".to_owned())
+        );
+        assert_eq!(demo.server_times(), Some((1000, 1050)));
+
+        let (_, diagnostics) = Demo::parse(&data[..data.len() - 20]);
+        assert_eq!(diagnostics[0].message(), "demo is truncated");
+    }
+}
@@ -0,0 +1,407 @@
+//! Huffman coding of network messages.
+//!
+//! The engine's coder is adaptive but seeded once with [`FREQUENCIES`] and never updated for
+//! messages, so the tree is built by replaying those updates like `MSG_initHuffman`.
+
+use std::{convert::TryFrom, sync::OnceLock};
+
+/// Symbol of the not yet transmitted node.
+const NYT: u16 = 256;
+/// Symbol of internal nodes.
+const INTERNAL: u16 = 257;
+
+/// `msg_hData`, how often each byte was seen in recorded network traffic.
+#[rustfmt::skip]
+const FREQUENCIES: [u32; 256] = [
+    250315, 41193, 6292, 7106, 3730, 3750, 6110, 23283, 33317, 6950, 7838, 9714, 9257, 17259, 3949, 1778,
+    8288, 1604, 1590, 1663, 1100, 1213, 1238, 1134, 1749, 1059, 1246, 1149, 1273, 4486, 2805, 3472,
+    21819, 1159, 1670, 1066, 1043, 1012, 1053, 1070, 1726, 888, 1180, 850, 960, 780, 1752, 3296,
+    10630, 4514, 5881, 2685, 4650, 3837, 2093, 1867, 2584, 1949, 1972, 940, 1134, 1788, 1670, 1206,
+    5719, 6128, 7222, 6654, 3710, 3795, 1492, 1524, 2215, 1140, 1355, 971, 2180, 1248, 1328, 1195,
+    1770, 1078, 1264, 1266, 1168, 965, 1155, 1186, 1347, 1228, 1529, 1600, 2617, 2048, 2546, 3275,
+    2410, 3585, 2504, 2800, 2675, 6146, 3663, 2840, 14253, 3164, 2221, 1687, 3208, 2739, 3512, 4796,
+    4091, 3515, 5288, 4016, 7937, 6031, 5360, 3924, 4892, 3743, 4566, 4807, 5852, 6400, 6225, 8291,
+    23243, 7838, 7073, 8935, 5437, 4483, 3641, 5256, 5312, 5328, 5370, 3492, 2458, 1694, 1821, 2121,
+    1916, 1149, 1516, 1367, 1236, 1029, 1258, 1104, 1245, 1006, 1149, 1025, 1241, 952, 1287, 997,
+    1713, 1009, 1187, 879, 1099, 929, 1078, 951, 1656, 930, 1153, 1030, 1262, 1062, 1214, 1060,
+    1621, 930, 1106, 912, 1034, 892, 1158, 990, 1175, 850, 1121, 903, 1087, 920, 1144, 1056,
+    3462, 2240, 4397, 12136, 7758, 1345, 1307, 3278, 1950, 886, 1023, 1112, 1077, 1042, 1061, 1071,
+    1484, 1001, 1096, 915, 1052, 995, 1070, 876, 1111, 851, 1059, 805, 1112, 923, 1103, 817,
+    1899, 1872, 976, 841, 1127, 956, 1159, 950, 7791, 954, 1289, 933, 1127, 3207, 1020, 927,
+    1355, 768, 1040, 745, 952, 805, 1073, 740, 1013, 805, 1008, 796, 996, 1057, 11457, 13504,
+];
+
+/// Decoding tree, each node being the children for a 0 and a 1 bit or a leaf symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Huffman {
+    nodes: Vec<Decode>,
+    root: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decode {
+    Internal([usize; 2]),
+    Leaf(u16),
+}
+
+impl Huffman {
+    /// Get the tree built from [`FREQUENCIES`].
+    pub(crate) fn get() -> &'static Self {
+        static HUFFMAN: OnceLock<Huffman> = OnceLock::new();
+        HUFFMAN.get_or_init(|| {
+            let mut builder = Builder::new();
+            for (byte, frequency) in FREQUENCIES.iter().enumerate() {
+                for _ in 0..*frequency {
+                    builder.add_ref(byte as u16);
+                }
+            }
+            builder.finish()
+        })
+    }
+
+    /// Decodes a byte starting at `bit` in LSB first order, advancing `bit`.
+    pub(crate) fn decode(&self, data: &[u8], bit: &mut usize) -> Option<u8> {
+        let mut node = self.root;
+        loop {
+            match self.nodes[node] {
+                Decode::Internal(children) => {
+                    node = children[usize::from(read_bit(data, bit)?)];
+                }
+                Decode::Leaf(symbol) => return u8::try_from(symbol).ok(),
+            }
+        }
+    }
+
+    /// Get the code of each byte as bits from the root, for writing messages.
+    #[cfg(test)]
+    pub(crate) fn codes(&self) -> Vec<Vec<u8>> {
+        let mut codes = vec![Vec::new(); 256];
+        let mut stack = vec![(self.root, Vec::new())];
+        while let Some((node, code)) = stack.pop() {
+            match self.nodes[node] {
+                Decode::Internal(children) => {
+                    for (bit, child) in children.iter().enumerate() {
+                        let mut code = code.clone();
+                        code.push(bit as u8);
+                        stack.push((*child, code));
+                    }
+                }
+                Decode::Leaf(symbol) if symbol < NYT => codes[usize::from(symbol)] = code,
+                Decode::Leaf(_) => {}
+            }
+        }
+        codes
+    }
+}
+
+/// Reads a bit in LSB first order like `Huff_getBit`, advancing `bit`.
+pub(crate) fn read_bit(data: &[u8], bit: &mut usize) -> Option<u8> {
+    let byte = data.get(*bit >> 3)?;
+    let value = (byte >> (*bit & 7)) & 1;
+    *bit += 1;
+    Some(value)
+}
+
+/// `node_t`, linked by indexes.
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
+    next: Option<usize>,
+    prev: Option<usize>,
+    /// Slot holding the highest ranked node of the node's weight.
+    head: Option<usize>,
+    weight: u32,
+    symbol: u16,
+}
+
+/// `huff_t`, the adaptive tree with its ranked node list.
+///
+/// The updates mirror `huffman.c` step by step, as ties between equal weights decide the codes.
+struct Builder {
+    nodes: Vec<Node>,
+    /// Head slots, free ones linking to the next free one.
+    slots: Vec<Option<usize>>,
+    free: Option<usize>,
+    tree: usize,
+    lhead: usize,
+    loc: Vec<Option<usize>>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        let nyt = Node {
+            symbol: NYT,
+            ..Node::default()
+        };
+        let mut loc = vec![None; usize::from(NYT) + 1];
+        loc[usize::from(NYT)] = Some(0);
+        Self {
+            nodes: vec![nyt],
+            slots: Vec::new(),
+            free: None,
+            tree: 0,
+            lhead: 0,
+            loc,
+        }
+    }
+
+    fn get_slot(&mut self) -> usize {
+        match self.free {
+            Some(slot) => {
+                self.free = self.slots[slot];
+                slot
+            }
+            None => {
+                self.slots.push(None);
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn free_slot(&mut self, slot: usize) {
+        self.slots[slot] = self.free;
+        self.free = Some(slot);
+    }
+
+    fn head(&self, node: usize) -> usize {
+        self.nodes[node].head.expect("ranked node has a head")
+    }
+
+    /// Swaps the nodes' positions in the tree.
+    fn swap(&mut self, node1: usize, node2: usize) {
+        let parent1 = self.nodes[node1].parent;
+        let parent2 = self.nodes[node2].parent;
+        match parent1 {
+            Some(parent) if self.nodes[parent].left == Some(node1) => {
+                self.nodes[parent].left = Some(node2)
+            }
+            Some(parent) => self.nodes[parent].right = Some(node2),
+            None => self.tree = node2,
+        }
+        match parent2 {
+            Some(parent) if self.nodes[parent].left == Some(node2) => {
+                self.nodes[parent].left = Some(node1)
+            }
+            Some(parent) => self.nodes[parent].right = Some(node1),
+            None => self.tree = node1,
+        }
+        self.nodes[node1].parent = parent2;
+        self.nodes[node2].parent = parent1;
+    }
+
+    /// Swaps the nodes' ranks in the list.
+    fn swap_list(&mut self, node1: usize, node2: usize) {
+        let next = self.nodes[node1].next;
+        self.nodes[node1].next = self.nodes[node2].next;
+        self.nodes[node2].next = next;
+        let prev = self.nodes[node1].prev;
+        self.nodes[node1].prev = self.nodes[node2].prev;
+        self.nodes[node2].prev = prev;
+
+        if self.nodes[node1].next == Some(node1) {
+            self.nodes[node1].next = Some(node2);
+        }
+        if self.nodes[node2].next == Some(node2) {
+            self.nodes[node2].next = Some(node1);
+        }
+        if let Some(next) = self.nodes[node1].next {
+            self.nodes[next].prev = Some(node1);
+        }
+        if let Some(next) = self.nodes[node2].next {
+            self.nodes[next].prev = Some(node2);
+        }
+        if let Some(prev) = self.nodes[node1].prev {
+            self.nodes[prev].next = Some(node1);
+        }
+        if let Some(prev) = self.nodes[node2].prev {
+            self.nodes[prev].next = Some(node2);
+        }
+    }
+
+    fn increment(&mut self, node: Option<usize>) {
+        let node = match node {
+            Some(node) => node,
+            None => return,
+        };
+        let weight = self.nodes[node].weight;
+
+        if let Some(next) = self.nodes[node].next {
+            if self.nodes[next].weight == weight {
+                let lnode = self.slots[self.head(node)].expect("head slot is taken");
+                if Some(lnode) != self.nodes[node].parent {
+                    self.swap(lnode, node);
+                }
+                self.swap_list(lnode, node);
+            }
+        }
+        let head = self.head(node);
+        match self.nodes[node].prev {
+            Some(prev) if self.nodes[prev].weight == weight => self.slots[head] = Some(prev),
+            _ => {
+                self.slots[head] = None;
+                self.free_slot(head);
+            }
+        }
+        self.nodes[node].weight += 1;
+        match self.nodes[node].next {
+            Some(next) if self.nodes[next].weight == weight + 1 => {
+                self.nodes[node].head = self.nodes[next].head;
+            }
+            _ => {
+                let slot = self.get_slot();
+                self.nodes[node].head = Some(slot);
+                self.slots[slot] = Some(node);
+            }
+        }
+
+        if let Some(parent) = self.nodes[node].parent {
+            self.increment(Some(parent));
+            if self.nodes[node].prev == Some(parent) {
+                self.swap_list(node, parent);
+                let head = self.head(node);
+                if self.slots[head] == Some(node) {
+                    self.slots[head] = Some(parent);
+                }
+            }
+        }
+    }
+
+    /// Inserts a weight 1 node at the front of the list, after the NYT node.
+    fn insert_front(&mut self, node: usize, head_of_new: usize) {
+        let lhead = self.lhead;
+        self.nodes[node].next = self.nodes[lhead].next;
+        match self.nodes[lhead].next {
+            Some(next) => {
+                self.nodes[next].prev = Some(node);
+                if self.nodes[next].weight == 1 {
+                    self.nodes[node].head = self.nodes[next].head;
+                } else {
+                    let slot = self.get_slot();
+                    self.nodes[node].head = Some(slot);
+                    self.slots[slot] = Some(head_of_new);
+                }
+            }
+            None => {
+                let slot = self.get_slot();
+                self.nodes[node].head = Some(slot);
+                self.slots[slot] = Some(node);
+            }
+        }
+        self.nodes[lhead].next = Some(node);
+        self.nodes[node].prev = Some(lhead);
+    }
+
+    /// `Huff_addRef`
+    fn add_ref(&mut self, symbol: u16) {
+        if let Some(node) = self.loc[usize::from(symbol)] {
+            self.increment(Some(node));
+            return;
+        }
+
+        let leaf = self.nodes.len();
+        let internal = leaf + 1;
+        self.nodes.push(Node {
+            symbol,
+            weight: 1,
+            ..Node::default()
+        });
+        self.nodes.push(Node {
+            symbol: INTERNAL,
+            weight: 1,
+            ..Node::default()
+        });
+        self.insert_front(internal, internal);
+        // The engine's fallback points the leaf's new head at the internal node
+        self.insert_front(leaf, internal);
+
+        let lhead = self.lhead;
+        match self.nodes[lhead].parent {
+            Some(parent) if self.nodes[parent].left == Some(lhead) => {
+                self.nodes[parent].left = Some(internal)
+            }
+            Some(parent) => self.nodes[parent].right = Some(internal),
+            None => self.tree = internal,
+        }
+        self.nodes[internal].right = Some(leaf);
+        self.nodes[internal].left = Some(lhead);
+        self.nodes[internal].parent = self.nodes[lhead].parent;
+        self.nodes[lhead].parent = Some(internal);
+        self.nodes[leaf].parent = Some(internal);
+        self.loc[usize::from(symbol)] = Some(leaf);
+
+        self.increment(self.nodes[internal].parent);
+    }
+
+    fn finish(self) -> Huffman {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| match (node.left, node.right) {
+                (Some(left), Some(right)) => Decode::Internal([left, right]),
+                _ => Decode::Leaf(node.symbol),
+            })
+            .collect();
+        Huffman {
+            nodes,
+            root: self.tree,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        let huffman = Huffman::get();
+        let codes = huffman.codes();
+        assert!(codes.iter().all(|code| !code.is_empty()));
+        let shortest = codes.iter().map(Vec::len).min().unwrap();
+        assert_eq!(codes[0].len(), shortest);
+
+        let mut data = vec![0u8; 16];
+        let mut bit = 0;
+        for byte in [0u8, 255, b'q', 3] {
+            for value in &codes[usize::from(byte)] {
+                data[bit >> 3] |= value << (bit & 7);
+                bit += 1;
+            }
+        }
+        let mut read = 0;
+        let decoded: Vec<_> = (0..4)
+            .map(|_| huffman.decode(&data, &mut read).unwrap())
+            .collect();
+        assert_eq!(decoded, &[0, 255, b'q', 3]);
+        assert_eq!(read, bit);
+    }
+
+    #[test]
+    fn optimal() {
+        use std::{cmp::Reverse, collections::BinaryHeap};
+
+        // Adaptive updates keep the sibling property, so seeded with `msg_hData` the engine's tree
+        // is a Huffman tree for it, the not yet transmitted node being a leaf of weight 0
+        let mut heap: BinaryHeap<_> = FREQUENCIES
+            .iter()
+            .map(|frequency| Reverse(u64::from(*frequency)))
+            .chain([Reverse(0)])
+            .collect();
+        let mut cost = 0;
+        while heap.len() > 1 {
+            let (Reverse(first), Reverse(second)) = (heap.pop().unwrap(), heap.pop().unwrap());
+            cost += first + second;
+            heap.push(Reverse(first + second));
+        }
+
+        let codes = Huffman::get().codes();
+        let length: u64 = FREQUENCIES
+            .iter()
+            .zip(&codes)
+            .map(|(frequency, code)| u64::from(*frequency) * code.len() as u64)
+            .sum();
+        assert_eq!(length, cost);
+    }
+}
@@ -0,0 +1,152 @@
+//! Bit reading of Huffman compressed messages like `MSG_ReadBits`.
+
+use super::huffman::{read_bit, Huffman};
+
+/// `MAX_STRING_CHARS`
+const MAX_STRING_CHARS: usize = 1024;
+/// `BIG_INFO_STRING`
+const BIG_INFO_STRING: usize = 8192;
+
+/// Message read past its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Overflow;
+
+/// Message being read.
+pub(crate) struct Message<'a> {
+    data: &'a [u8],
+    bit: usize,
+    huffman: &'static Huffman,
+}
+
+impl<'a> Message<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            bit: 0,
+            huffman: Huffman::get(),
+        }
+    }
+
+    /// Reads `bits` bits, sign extended for negative `bits`.
+    ///
+    /// Bits beyond whole bytes come first uncompressed, then each byte is Huffman coded.
+    pub(crate) fn bits(&mut self, bits: i32) -> Result<i32, Overflow> {
+        let signed = bits < 0;
+        let bits = bits.unsigned_abs();
+        let raw = bits & 7;
+        let mut value = 0u32;
+        for i in 0..raw {
+            value |= u32::from(read_bit(self.data, &mut self.bit).ok_or(Overflow)?) << i;
+        }
+        for i in (raw..bits).step_by(8) {
+            let byte = self
+                .huffman
+                .decode(self.data, &mut self.bit)
+                .ok_or(Overflow)?;
+            value |= u32::from(byte) << i;
+        }
+        if signed && bits > 0 && bits < 32 && value & (1 << (bits - 1)) != 0 {
+            value |= !((1 << bits) - 1);
+        }
+        Ok(value as i32)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, Overflow> {
+        Ok(self.bits(8)? as u8)
+    }
+
+    pub(crate) fn short(&mut self) -> Result<i16, Overflow> {
+        Ok(self.bits(16)? as i16)
+    }
+
+    pub(crate) fn long(&mut self) -> Result<i32, Overflow> {
+        self.bits(32)
+    }
+
+    /// `MSG_ReadString`
+    pub(crate) fn string(&mut self) -> Result<String, Overflow> {
+        self.string_of(MAX_STRING_CHARS)
+    }
+
+    /// `MSG_ReadBigString`
+    pub(crate) fn big_string(&mut self) -> Result<String, Overflow> {
+        self.string_of(BIG_INFO_STRING)
+    }
+
+    /// Reads a NUL terminated string, replacing `%` and non ASCII like the engine.
+    ///
+    /// A string of `size - 1` characters ends without reading its terminator.
+    fn string_of(&mut self, size: usize) -> Result<String, Overflow> {
+        let mut string = String::new();
+        while string.len() < size - 1 {
+            let byte = self.byte()?;
+            if byte == 0 {
+                break;
+            }
+            string.push(if byte == b'%' || byte > 127 {
+                '.'
+            } else {
+                char::from(byte)
+            });
+        }
+        Ok(string)
+    }
+
+    pub(crate) fn data(&mut self, length: usize) -> Result<Vec<u8>, Overflow> {
+        (0..length).map(|_| self.byte()).collect()
+    }
+}
+
+/// Writes messages the way `MSG_WriteBits` does, for tests.
+#[cfg(test)]
+pub(crate) struct Writer {
+    data: Vec<u8>,
+    bit: usize,
+    codes: Vec<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl Writer {
+    pub(crate) fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            bit: 0,
+            codes: Huffman::get().codes(),
+        }
+    }
+
+    fn put_bit(&mut self, bit: u8) {
+        if self.bit & 7 == 0 {
+            self.data.push(0);
+        }
+        self.data[self.bit >> 3] |= bit << (self.bit & 7);
+        self.bit += 1;
+    }
+
+    pub(crate) fn bits(&mut self, value: i32, bits: u32) -> &mut Self {
+        let mut value = value as u32;
+        for _ in 0..bits & 7 {
+            self.put_bit((value & 1) as u8);
+            value >>= 1;
+        }
+        for _ in (bits & 7..bits).step_by(8) {
+            for bit in self.codes[(value & 0xff) as usize].clone() {
+                self.put_bit(bit);
+            }
+            value >>= 8;
+        }
+        self
+    }
+
+    pub(crate) fn string(&mut self, string: &str) -> &mut Self {
+        for byte in string.bytes() {
+            self.bits(i32::from(byte), 8);
+        }
+        self.bits(0, 8)
+    }
+
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        self.bit = 0;
+        std::mem::take(&mut self.data)
+    }
+}
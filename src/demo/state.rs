@@ -0,0 +1,404 @@
+//! Entity and player states with their delta compression like `MSG_ReadDeltaEntity`.
+
+use super::message::{Message, Overflow};
+
+/// `GENTITYNUM_BITS`
+pub const GENTITYNUM_BITS: i32 = 10;
+/// `MAX_GENTITIES`
+pub const MAX_GENTITIES: usize = 1 << GENTITYNUM_BITS;
+/// `MAX_STATS`, `MAX_PERSISTANT`, `MAX_WEAPONS` and `MAX_POWERUPS`.
+pub const MAX_STATS: usize = 16;
+
+/// Bits of an integral float.
+const FLOAT_INT_BITS: i32 = 13;
+/// Bias of an integral float, so it can be negative.
+const FLOAT_INT_BIAS: i32 = 1 << (FLOAT_INT_BITS - 1);
+
+/// `netField_t`, a field's name and bits, 0 for floats and negative for signed integers.
+type Field = (&'static str, i32);
+
+/// `entityStateFields`, in the order they're sent.
+pub const ENTITY_FIELDS: [Field; 51] = [
+    ("pos.trTime", 32),
+    ("pos.trBase[0]", 0),
+    ("pos.trBase[1]", 0),
+    ("pos.trDelta[0]", 0),
+    ("pos.trDelta[1]", 0),
+    ("pos.trBase[2]", 0),
+    ("apos.trBase[1]", 0),
+    ("pos.trDelta[2]", 0),
+    ("apos.trBase[0]", 0),
+    ("event", 10),
+    ("angles2[1]", 0),
+    ("eType", 8),
+    ("torsoAnim", 8),
+    ("eventParm", 8),
+    ("legsAnim", 8),
+    ("groundEntityNum", GENTITYNUM_BITS),
+    ("pos.trType", 8),
+    ("eFlags", 19),
+    ("otherEntityNum", GENTITYNUM_BITS),
+    ("weapon", 8),
+    ("clientNum", 8),
+    ("angles[1]", 0),
+    ("pos.trDuration", 32),
+    ("apos.trType", 8),
+    ("origin[0]", 0),
+    ("origin[1]", 0),
+    ("origin[2]", 0),
+    ("solid", 24),
+    ("powerups", MAX_STATS as i32),
+    ("modelindex", 8),
+    ("otherEntityNum2", GENTITYNUM_BITS),
+    ("loopSound", 8),
+    ("generic1", 8),
+    ("origin2[2]", 0),
+    ("origin2[0]", 0),
+    ("origin2[1]", 0),
+    ("modelindex2", 8),
+    ("angles[0]", 0),
+    ("time", 32),
+    ("apos.trTime", 32),
+    ("apos.trDuration", 32),
+    ("apos.trBase[2]", 0),
+    ("apos.trDelta[0]", 0),
+    ("apos.trDelta[1]", 0),
+    ("apos.trDelta[2]", 0),
+    ("time2", 32),
+    ("angles[2]", 0),
+    ("angles2[0]", 0),
+    ("angles2[2]", 0),
+    ("constantLight", 32),
+    ("frame", 16),
+];
+
+/// `playerStateFields`, in the order they're sent.
+pub const PLAYER_FIELDS: [Field; 48] = [
+    ("commandTime", 32),
+    ("origin[0]", 0),
+    ("origin[1]", 0),
+    ("bobCycle", 8),
+    ("velocity[0]", 0),
+    ("velocity[1]", 0),
+    ("viewangles[1]", 0),
+    ("viewangles[0]", 0),
+    ("weaponTime", -16),
+    ("origin[2]", 0),
+    ("velocity[2]", 0),
+    ("legsTimer", 8),
+    ("pm_time", -16),
+    ("eventSequence", 16),
+    ("torsoAnim", 8),
+    ("movementDir", 4),
+    ("events[0]", 8),
+    ("legsAnim", 8),
+    ("events[1]", 8),
+    ("pm_flags", 16),
+    ("groundEntityNum", GENTITYNUM_BITS),
+    ("weaponstate", 4),
+    ("eFlags", 16),
+    ("externalEvent", 10),
+    ("gravity", 16),
+    ("speed", 16),
+    ("delta_angles[1]", 16),
+    ("externalEventParm", 8),
+    ("viewheight", -8),
+    ("damageEvent", 8),
+    ("damageYaw", 8),
+    ("damagePitch", 8),
+    ("damageCount", 8),
+    ("generic1", 8),
+    ("pm_type", 8),
+    ("delta_angles[0]", 16),
+    ("delta_angles[2]", 16),
+    ("torsoTimer", 12),
+    ("eventParms[0]", 8),
+    ("eventParms[1]", 8),
+    ("clientNum", 8),
+    ("weapon", 5),
+    ("viewangles[2]", 0),
+    ("grapplePoint[0]", 0),
+    ("grapplePoint[1]", 0),
+    ("grapplePoint[2]", 0),
+    ("jumppad_ent", 10),
+    ("loopSound", 16),
+];
+
+/// Value of a field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue {
+    Int(i32),
+    Float(f32),
+}
+
+/// Delta read beyond the field table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DeltaError {
+    Overflow,
+    FieldCount(u8),
+}
+
+impl From<Overflow> for DeltaError {
+    fn from(_: Overflow) -> Self {
+        Self::Overflow
+    }
+}
+
+/// `entityState_t`, an entity of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityState {
+    number: usize,
+    /// Raw field bits in [`ENTITY_FIELDS`] order, floats as their bit patterns.
+    fields: [u32; 51],
+}
+
+impl EntityState {
+    /// State all fields of an entity's baseline start from.
+    pub(crate) const fn null(number: usize) -> Self {
+        Self {
+            number,
+            fields: [0; 51],
+        }
+    }
+
+    /// Reads the delta from `from`, `None` if the entity was removed.
+    pub(crate) fn read_delta(
+        message: &mut Message,
+        from: &Self,
+        number: usize,
+    ) -> Result<Option<Self>, DeltaError> {
+        if message.bits(1)? == 1 {
+            return Ok(None);
+        }
+        let mut to = Self {
+            number,
+            fields: from.fields,
+        };
+        if message.bits(1)? == 0 {
+            return Ok(Some(to));
+        }
+        let count = message.byte()?;
+        if usize::from(count) > ENTITY_FIELDS.len() {
+            return Err(DeltaError::FieldCount(count));
+        }
+        for (value, (_, bits)) in to.fields.iter_mut().zip(&ENTITY_FIELDS[..count.into()]) {
+            if message.bits(1)? == 0 {
+                continue;
+            }
+            *value = if message.bits(1)? == 0 {
+                0
+            } else if *bits == 0 {
+                read_float(message)?
+            } else {
+                message.bits(*bits)? as u32
+            };
+        }
+        Ok(Some(to))
+    }
+
+    /// Get the entity's number, its index in the game's entities.
+    pub const fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Get the field's value by its name in [`ENTITY_FIELDS`], e.g. `eType`.
+    pub fn get(&self, name: &str) -> Option<FieldValue> {
+        field(&ENTITY_FIELDS, &self.fields, name)
+    }
+
+    /// Get the fields' names and values.
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, FieldValue)> + '_ {
+        fields(&ENTITY_FIELDS, &self.fields)
+    }
+
+    /// Get the entity type, e.g. 1 for a player.
+    pub fn entity_type(&self) -> i32 {
+        self.int("eType")
+    }
+
+    /// Get the position's base, where the entity is without movement.
+    pub fn position(&self) -> [f32; 3] {
+        self.vector("pos.trBase")
+    }
+
+    /// Get the model's index in the model configstrings.
+    pub fn model_index(&self) -> i32 {
+        self.int("modelindex")
+    }
+
+    fn int(&self, name: &str) -> i32 {
+        match self.get(name) {
+            Some(FieldValue::Int(value)) => value,
+            _ => 0,
+        }
+    }
+
+    fn vector(&self, name: &str) -> [f32; 3] {
+        vector(&ENTITY_FIELDS, &self.fields, name)
+    }
+}
+
+/// `playerState_t`, the state of the player the demo was recorded by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerState {
+    /// Raw field bits in [`PLAYER_FIELDS`] order, floats as their bit patterns.
+    fields: [u32; 48],
+    stats: [i16; MAX_STATS],
+    persistant: [i16; MAX_STATS],
+    ammo: [i16; MAX_STATS],
+    powerups: [i32; MAX_STATS],
+}
+
+impl Default for PlayerState {
+    fn default() -> Self {
+        Self {
+            fields: [0; 48],
+            stats: [0; MAX_STATS],
+            persistant: [0; MAX_STATS],
+            ammo: [0; MAX_STATS],
+            powerups: [0; MAX_STATS],
+        }
+    }
+}
+
+impl PlayerState {
+    /// Reads the delta from `from`, or from a zeroed state.
+    pub(crate) fn read_delta(
+        message: &mut Message,
+        from: Option<&Self>,
+    ) -> Result<Self, DeltaError> {
+        let mut to = from.cloned().unwrap_or_default();
+        let count = message.byte()?;
+        if usize::from(count) > PLAYER_FIELDS.len() {
+            return Err(DeltaError::FieldCount(count));
+        }
+        for (value, (_, bits)) in to.fields.iter_mut().zip(&PLAYER_FIELDS[..count.into()]) {
+            if message.bits(1)? == 0 {
+                continue;
+            }
+            *value = if *bits == 0 {
+                read_float(message)?
+            } else {
+                message.bits(*bits)? as u32
+            };
+        }
+
+        if message.bits(1)? == 1 {
+            for array in [&mut to.stats, &mut to.persistant, &mut to.ammo] {
+                if message.bits(1)? == 1 {
+                    let changed = message.bits(MAX_STATS as i32)?;
+                    for (index, value) in array.iter_mut().enumerate() {
+                        if changed & (1 << index) != 0 {
+                            *value = message.short()?;
+                        }
+                    }
+                }
+            }
+            if message.bits(1)? == 1 {
+                let changed = message.bits(MAX_STATS as i32)?;
+                for (index, value) in to.powerups.iter_mut().enumerate() {
+                    if changed & (1 << index) != 0 {
+                        *value = message.long()?;
+                    }
+                }
+            }
+        }
+        Ok(to)
+    }
+
+    /// Get the field's value by its name in [`PLAYER_FIELDS`], e.g. `commandTime`.
+    pub fn get(&self, name: &str) -> Option<FieldValue> {
+        field(&PLAYER_FIELDS, &self.fields, name)
+    }
+
+    /// Get the fields' names and values.
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, FieldValue)> + '_ {
+        fields(&PLAYER_FIELDS, &self.fields)
+    }
+
+    /// Get the number of the client the state is of.
+    pub fn client_num(&self) -> i32 {
+        match self.get("clientNum") {
+            Some(FieldValue::Int(value)) => value,
+            _ => 0,
+        }
+    }
+
+    /// Get the origin.
+    pub fn origin(&self) -> [f32; 3] {
+        vector(&PLAYER_FIELDS, &self.fields, "origin")
+    }
+
+    /// Get the velocity.
+    pub fn velocity(&self) -> [f32; 3] {
+        vector(&PLAYER_FIELDS, &self.fields, "velocity")
+    }
+
+    /// Get the view angles.
+    pub fn view_angles(&self) -> [f32; 3] {
+        vector(&PLAYER_FIELDS, &self.fields, "viewangles")
+    }
+
+    /// Get the `STAT_*` values, e.g. health.
+    pub const fn stats(&self) -> &[i16; MAX_STATS] {
+        &self.stats
+    }
+
+    /// Get the `PERS_*` values kept across respawns, e.g. the score.
+    pub const fn persistant(&self) -> &[i16; MAX_STATS] {
+        &self.persistant
+    }
+
+    /// Get the ammo per weapon.
+    pub const fn ammo(&self) -> &[i16; MAX_STATS] {
+        &self.ammo
+    }
+
+    /// Get the times the powerups run out at.
+    pub const fn powerups(&self) -> &[i32; MAX_STATS] {
+        &self.powerups
+    }
+}
+
+/// Reads a float sent as a biased integer or as its bits.
+fn read_float(message: &mut Message) -> Result<u32, Overflow> {
+    if message.bits(1)? == 0 {
+        let integral = message.bits(FLOAT_INT_BITS)? - FLOAT_INT_BIAS;
+        Ok((integral as f32).to_bits())
+    } else {
+        Ok(message.bits(32)? as u32)
+    }
+}
+
+fn value((_, bits): Field, raw: u32) -> FieldValue {
+    if bits == 0 {
+        FieldValue::Float(f32::from_bits(raw))
+    } else {
+        FieldValue::Int(raw as i32)
+    }
+}
+
+fn field(table: &[Field], raw: &[u32], name: &str) -> Option<FieldValue> {
+    let index = table.iter().position(|(field, _)| *field == name)?;
+    Some(value(table[index], raw[index]))
+}
+
+fn fields<'a>(
+    table: &'static [Field],
+    raw: &'a [u32],
+) -> impl Iterator<Item = (&'static str, FieldValue)> + 'a {
+    table
+        .iter()
+        .zip(raw)
+        .map(|(field, raw)| (field.0, value(*field, *raw)))
+}
+
+/// `name[0]`, `name[1]` and `name[2]` floats.
+fn vector(table: &[Field], raw: &[u32], name: &str) -> [f32; 3] {
+    [0, 1, 2].map(
+        |index| match field(table, raw, &format!("{}[{}]", name, index)) {
+            Some(FieldValue::Float(value)) => value,
+            _ => 0.0,
+        },
+    )
+}
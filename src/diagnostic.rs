@@ -0,0 +1,83 @@
+use crate::span::RawSpan;
+
+/// Severity of a diagnostic.
+///
+/// Mirrors the SARIF result levels, see `fixtures/arenas.sarif`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl ::core::fmt::Display for Severity {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::Note => f.write_str("note"),
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// Problem found in a source, pointing at the offending text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    span: RawSpan,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic.
+    pub fn new(severity: Severity, message: impl Into<String>, span: RawSpan) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Creates a new error diagnostic.
+    pub fn error(message: impl Into<String>, span: RawSpan) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    /// Creates a new warning diagnostic.
+    pub fn warning(message: impl Into<String>, span: RawSpan) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    /// Creates a new note diagnostic.
+    pub fn note(message: impl Into<String>, span: RawSpan) -> Self {
+        Self::new(Severity::Note, message, span)
+    }
+
+    /// Get the diagnostic's severity.
+    pub const fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Get the diagnostic's message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the diagnostic's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+impl ::core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(
+            f,
+            "{}: {} at {}..{}",
+            self.severity,
+            self.message,
+            self.span.start(),
+            self.span.end()
+        )
+    }
+}
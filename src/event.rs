@@ -2,7 +2,7 @@ use std::num::NonZeroUsize;
 
 use crate::{span::RawSpan, syntax::SyntaxKind};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Event {
     Enter {
         kind: SyntaxKind,
@@ -13,6 +13,7 @@ pub enum Event {
         span: RawSpan,
     },
     Exit,
+    #[default]
     Abandoned,
 }
 
@@ -21,9 +22,3 @@ impl Event {
         matches!(self, Self::Abandoned)
     }
 }
-
-impl Default for Event {
-    fn default() -> Self {
-        Self::Abandoned
-    }
-}
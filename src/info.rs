@@ -0,0 +1,79 @@
+//! Info strings (`\key\value\key2\value2`), e.g. `serverinfo` and player configstrings.
+
+use crate::{arena::KeyValue, span::RawSpan};
+
+/// Parsed info string with spans in its text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InfoString {
+    pairs: Vec<KeyValue>,
+}
+
+impl InfoString {
+    /// Parses an info string like `Info_NextPair`, a key without a value gets an empty one.
+    pub fn parse(text: &str) -> Self {
+        let mut pairs = Vec::new();
+        let mut parts = text.split('\\').scan(0, |start, part| {
+            let span = RawSpan::new(*start as u32, (*start + part.len()) as u32);
+            *start += part.len() + 1;
+            Some((part, span))
+        });
+        if text.starts_with('\\') {
+            parts.next();
+        }
+        while let Some((key, key_span)) = parts.next() {
+            if key.is_empty() && key_span.end() as usize == text.len() {
+                break;
+            }
+            let (value, value_span) = parts
+                .next()
+                .unwrap_or(("", RawSpan::new(key_span.end(), key_span.end())));
+            pairs.push(KeyValue::new(
+                key.to_owned(),
+                key_span,
+                value.to_owned(),
+                value_span,
+            ));
+        }
+        Self { pairs }
+    }
+
+    /// Get the key/value pairs in text order.
+    pub fn pairs(&self) -> &[KeyValue] {
+        &self.pairs
+    }
+
+    /// Get the pair for `key`, ignoring case; the first one wins like in `Info_ValueForKey`.
+    pub fn pair(&self, key: &str) -> Option<&KeyValue> {
+        self.pairs
+            .iter()
+            .find(|pair| pair.key().eq_ignore_ascii_case(key))
+    }
+
+    /// Get the value for `key`, see [`pair`](Self::pair).
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pair(key).map(|pair| pair.value().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info() {
+        let text = "\\mapname\\q3dm17\\sv_hostname\\My Server\\MapName\\other\\g_gametype";
+        let info = InfoString::parse(text);
+        assert_eq!(info.get("MAPNAME"), Some("q3dm17"));
+        assert_eq!(info.get("sv_hostname"), Some("My Server"));
+        assert_eq!(info.get("g_gametype"), Some(""));
+        assert_eq!(
+            &text[info.pair("sv_hostname").unwrap().value_span()],
+            "My Server"
+        );
+
+        let player = InfoString::parse("n\\Sarge\\t\\1");
+        let keys: Vec<_> = player.pairs().iter().map(KeyValue::key).collect();
+        assert_eq!(keys, &["n", "t"]);
+        assert_eq!(InfoString::parse("").pairs(), &[]);
+    }
+}
@@ -3,68 +3,154 @@ use logos::Logos;
 
 use crate::span::RawSpan;
 
-/// Kind of lexed token.
-#[bitflags]
-#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u32)]
-pub enum TokenKind {
-    /// Whitespace.
-    ///
-    /// `\x01-\x20` (ASCII control characters except NUL `\0` and newline `\n` but including space ` `)
-    #[regex(r"[\x01-\x09\x0B-\x20]+")]
-    Whitespace,
-
-    /// Newline.
-    ///
-    /// `\n` (newline)
-    #[token("\n")]
-    Newline,
-
-    /// Line comment.
-    ///
-    /// `//…\n` (a line started by `//` comment prefix)
-    #[regex(r"//[^\n]*\n?", priority = 69)]
-    LineComment,
-    /// Block comment.
-    ///
-    /// `/*…*/` (a block surrounded by comment delimiters `/*`, `*/`)
-    #[regex(r"/\*([^*]|\*[^/])*\*/")]
-    BlockComment,
-
-    /// String.
-    ///
-    /// `[\x21-\x7F]+` (ASCII non-control characters except space ` `)
-    #[regex(r"[\x21-\x7F]+")]
-    String,
-    /// Quoted string.
-    ///
-    /// `"[^"]*"` (a string that can also include whitespace and newlines)
-    #[regex(r#""[^"]*""#)]
-    QuotedString,
-
-    /// Left brace.
-    ///
-    /// `{`
-    #[token("{")]
-    LeftBrace,
-    /// Right brace.
-    ///
-    /// `}`
-    #[token("}")]
-    RightBrace,
+/// Declares a token kind starting with the whitespace, comment, string and quoted string tokens
+/// of [`TokenKind`], so dialects share their patterns, followed by the dialect's own tokens.
+///
+/// Only the pattern of the unquoted string is the dialect's. With `lines;` first, line comments
+/// leave the newline to end a command and quoted strings end at the line, like in console
+/// configs.
+macro_rules! token_kind {
+    (lines; $($body:tt)*) => {
+        token_kind!(@kind r"//[^\n]*", r#""[^"\n]*"?"#, $($body)*);
+    };
+    (
+        @kind $line_comment:tt, $quoted_string:tt,
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $(#[$string_attr:meta])*
+            String = $string:tt;
+            $($rest:tt)*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            /// Whitespace.
+            ///
+            /// `\x01-\x20` (ASCII control characters except NUL `\0` and newline `\n` but including space ` `)
+            #[regex(r"[\x01-\x09\x0B-\x20]+")]
+            Whitespace,
+
+            /// Newline.
+            ///
+            /// `\n` (newline)
+            #[token("\n")]
+            Newline,
+
+            /// Line comment.
+            ///
+            /// `//…\n` (a line started by `//` comment prefix)
+            #[regex($line_comment, priority = 69)]
+            LineComment,
+            /// Block comment.
+            ///
+            /// `/*…*/` (a block surrounded by comment delimiters `/*`, `*/`)
+            #[regex(r"/\*([^*]|\*[^/])*\*/")]
+            BlockComment,
+
+            $(#[$string_attr])*
+            #[regex($string)]
+            String,
+            /// Quoted string.
+            ///
+            /// `"[^"]*"` (a string that can also include whitespace and, unless line based, newlines)
+            #[regex($quoted_string)]
+            QuotedString,
+
+            $($rest)*
+        }
+    };
+    ($($body:tt)*) => {
+        token_kind!(@kind r"//[^\n]*\n?", r#""[^"]*""#, $($body)*);
+    };
+}
 
-    /// Unknown token.
-    ///
-    /// Kept for lossless parsing.
-    Error,
+token_kind! {
+    /// Kind of lexed token.
+    #[bitflags]
+    #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[repr(u32)]
+    pub enum TokenKind {
+        /// String.
+        ///
+        /// `[\x21-\x7F]+` (ASCII non-control characters except space ` `)
+        String = r"[\x21-\x7F]+";
+
+        /// Left brace.
+        ///
+        /// `{`
+        #[token("{")]
+        LeftBrace,
+        /// Right brace.
+        ///
+        /// `}`
+        #[token("}")]
+        RightBrace,
+
+        /// Preprocessor directive.
+        ///
+        /// `#…\n` (a line started by `#`, only produced by the script dialect)
+        Preprocessor,
+        /// Number.
+        ///
+        /// Decimal, floating point or hexadecimal number (only produced by the script dialect)
+        Number,
+        /// Name.
+        ///
+        /// `[A-Za-z_][A-Za-z0-9_]*` (an identifier, only produced by the script dialect)
+        Name,
+
+        /// Left parenthesis.
+        ///
+        /// `(`
+        LeftParen,
+        /// Right parenthesis.
+        ///
+        /// `)`
+        RightParen,
+        /// Left bracket.
+        ///
+        /// `[`
+        LeftBracket,
+        /// Right bracket.
+        ///
+        /// `]`
+        RightBracket,
+        /// Comma.
+        ///
+        /// `,`
+        Comma,
+        /// Semicolon.
+        ///
+        /// `;`
+        Semicolon,
+        /// Colon.
+        ///
+        /// `:`
+        Colon,
+        /// Equals sign.
+        ///
+        /// `=`
+        Equals,
+        /// Any other punctuation.
+        Punctuation,
+
+        /// Unknown token.
+        ///
+        /// Kept for lossless parsing.
+        Error,
+    }
 }
 
 impl TokenKind {
-    /// Token is whitespace, newline or a comment.
+    /// Token is whitespace, newline, a comment or a preprocessor directive.
     pub const fn is_trivia(self) -> bool {
         matches!(
             self,
-            Self::Whitespace | Self::Newline | Self::LineComment | Self::BlockComment
+            Self::Whitespace
+                | Self::Newline
+                | Self::LineComment
+                | Self::BlockComment
+                | Self::Preprocessor
         )
     }
 
@@ -90,6 +176,18 @@ impl ::core::fmt::Display for TokenKind {
             Self::QuotedString => f.write_str("quoted string"),
             Self::LeftBrace => f.write_str("left brace"),
             Self::RightBrace => f.write_str("right brace"),
+            Self::Preprocessor => f.write_str("preprocessor directive"),
+            Self::Number => f.write_str("number"),
+            Self::Name => f.write_str("name"),
+            Self::LeftParen => f.write_str("left parenthesis"),
+            Self::RightParen => f.write_str("right parenthesis"),
+            Self::LeftBracket => f.write_str("left bracket"),
+            Self::RightBracket => f.write_str("right bracket"),
+            Self::Comma => f.write_str("comma"),
+            Self::Semicolon => f.write_str("semicolon"),
+            Self::Colon => f.write_str("colon"),
+            Self::Equals => f.write_str("equals sign"),
+            Self::Punctuation => f.write_str("punctuation"),
             Self::Error => f.write_str("error"),
         }
     }
@@ -169,6 +267,244 @@ impl<'src> Iterator for Lexer<'src> {
     }
 }
 
+/// Kind of token lexed from botlib scripts.
+///
+/// Bot chats, fuzzy weights and item configs are read by botlib's C-like script
+/// lexer (`l_script.c`) rather than the engine's `COM_Parse`.
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptTokenKind {
+    #[regex(r"[\x01-\x09\x0B-\x20]+")]
+    Whitespace,
+    #[token("\n")]
+    Newline,
+    #[regex(r"//[^\n]*\n?")]
+    LineComment,
+    #[regex(r"/\*([^*]|\*[^/])*\*/")]
+    BlockComment,
+    #[regex(r"#([^\n\\]|\\[^\n]|\\\n)*")]
+    Preprocessor,
+
+    #[regex(r#""([^"\\\n]|\\[^\n])*""#)]
+    QuotedString,
+    #[regex(r"[0-9]+(\.[0-9]*)?([eE][+-]?[0-9]+)?|\.[0-9]+|0[xX][0-9a-fA-F]+")]
+    Number,
+    #[regex(r"[A-Za-z_][A-Za-z0-9_]*")]
+    Name,
+
+    #[token("{")]
+    LeftBrace,
+    #[token("}")]
+    RightBrace,
+    #[token("(")]
+    LeftParen,
+    #[token(")")]
+    RightParen,
+    #[token("[")]
+    LeftBracket,
+    #[token("]")]
+    RightBracket,
+    #[token(",")]
+    Comma,
+    #[token(";")]
+    Semicolon,
+    #[token(":")]
+    Colon,
+    #[token("=")]
+    Equals,
+    #[regex(r"[!$%&*+\-./<>?@\\^|~`']")]
+    Punctuation,
+}
+
+impl ::core::convert::From<ScriptTokenKind> for TokenKind {
+    fn from(kind: ScriptTokenKind) -> Self {
+        match kind {
+            ScriptTokenKind::Whitespace => Self::Whitespace,
+            ScriptTokenKind::Newline => Self::Newline,
+            ScriptTokenKind::LineComment => Self::LineComment,
+            ScriptTokenKind::BlockComment => Self::BlockComment,
+            ScriptTokenKind::Preprocessor => Self::Preprocessor,
+
+            ScriptTokenKind::QuotedString => Self::QuotedString,
+            ScriptTokenKind::Number => Self::Number,
+            ScriptTokenKind::Name => Self::Name,
+
+            ScriptTokenKind::LeftBrace => Self::LeftBrace,
+            ScriptTokenKind::RightBrace => Self::RightBrace,
+            ScriptTokenKind::LeftParen => Self::LeftParen,
+            ScriptTokenKind::RightParen => Self::RightParen,
+            ScriptTokenKind::LeftBracket => Self::LeftBracket,
+            ScriptTokenKind::RightBracket => Self::RightBracket,
+            ScriptTokenKind::Comma => Self::Comma,
+            ScriptTokenKind::Semicolon => Self::Semicolon,
+            ScriptTokenKind::Colon => Self::Colon,
+            ScriptTokenKind::Equals => Self::Equals,
+            ScriptTokenKind::Punctuation => Self::Punctuation,
+        }
+    }
+}
+
+/// Lexer for a token dialect other than the default one.
+///
+/// Produces the same [`Token`]s as [`Lexer`], so dialects share the parser and sink.
+pub struct DialectLexer<'src, D: Logos<'src>> {
+    inner: logos::Lexer<'src, D>,
+}
+
+impl<'src, D: Logos<'src>> ::core::fmt::Debug for DialectLexer<'src, D> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        f.debug_struct("DialectLexer")
+            .field("span", &self.inner.span())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'src, D> DialectLexer<'src, D>
+where
+    D: Logos<'src, Source = str>,
+    D::Extras: Default,
+{
+    /// Creates a new lexer for the given source.
+    pub fn new(source: &'src str) -> Self {
+        Self {
+            inner: D::lexer(source),
+        }
+    }
+
+    /// Gets the source of this lexer.
+    pub fn source(&self) -> &'src str {
+        self.inner.source()
+    }
+}
+
+impl<'src, D> Iterator for DialectLexer<'src, D>
+where
+    D: Logos<'src, Source = str> + Into<TokenKind>,
+{
+    type Item = Token<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|kind| {
+            let span = self.inner.span();
+            let span = RawSpan::new(span.start as _, span.end as _);
+            let text = self.inner.slice();
+            match kind {
+                Ok(kind) => Token::new(kind.into(), span, text),
+                Err(_) => Token::new(TokenKind::Error, span, text),
+            }
+        })
+    }
+}
+
+/// Lexer for botlib scripts.
+pub type ScriptLexer<'src> = DialectLexer<'src, ScriptTokenKind>;
+
+token_kind! {
+    /// Kind of token lexed from player model skins.
+    ///
+    /// Skins are read by the renderer's `CommaParse`, which also ends tokens at `,`.
+    #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum SkinTokenKind {
+        String = r"[\x21-\x2B\x2D-\x7F]+";
+
+        #[token(",")]
+        Comma,
+    }
+}
+
+impl ::core::convert::From<SkinTokenKind> for TokenKind {
+    fn from(kind: SkinTokenKind) -> Self {
+        match kind {
+            SkinTokenKind::Whitespace => Self::Whitespace,
+            SkinTokenKind::Newline => Self::Newline,
+            SkinTokenKind::LineComment => Self::LineComment,
+            SkinTokenKind::BlockComment => Self::BlockComment,
+
+            SkinTokenKind::String => Self::String,
+            SkinTokenKind::QuotedString => Self::QuotedString,
+            SkinTokenKind::Comma => Self::Comma,
+        }
+    }
+}
+
+/// Lexer for player model skins.
+pub type SkinLexer<'src> = DialectLexer<'src, SkinTokenKind>;
+
+token_kind! {
+    lines;
+    /// Kind of token lexed from console configs.
+    ///
+    /// Like [`TokenKind`], but `;` separates commands, and quoted strings and `//` comments end at
+    /// the line like in `Cbuf_Execute` and `Cmd_TokenizeString`. A `/` ending a word is its own
+    /// [`CfgTokenKind::String`], since a following `/` would start a comment.
+    #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum CfgTokenKind {
+        String = r"([\x21\x23-\x2E\x30-\x3A\x3C-\x7F]|/[\x21\x23-\x29\x2B-\x2E\x30-\x3A\x3C-\x7F])+|/";
+
+        #[token(";")]
+        Semicolon,
+    }
+}
+
+impl ::core::convert::From<CfgTokenKind> for TokenKind {
+    fn from(kind: CfgTokenKind) -> Self {
+        match kind {
+            CfgTokenKind::Whitespace => Self::Whitespace,
+            CfgTokenKind::Newline => Self::Newline,
+            CfgTokenKind::LineComment => Self::LineComment,
+            CfgTokenKind::BlockComment => Self::BlockComment,
+
+            CfgTokenKind::String => Self::String,
+            CfgTokenKind::QuotedString => Self::QuotedString,
+            CfgTokenKind::Semicolon => Self::Semicolon,
+        }
+    }
+}
+
+/// Lexer for console configs.
+pub type CfgLexer<'src> = DialectLexer<'src, CfgTokenKind>;
+
+token_kind! {
+    /// Kind of token lexed from Radiant map sources.
+    ///
+    /// q3map's `GetToken` only splits on whitespace, but braces and parentheses are always written
+    /// apart from other words in `.map` files.
+    #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum MapTokenKind {
+        String = r"[\x21\x23-\x27\x2A-\x7A\x7C\x7E\x7F]+";
+
+        #[token("{")]
+        LeftBrace,
+        #[token("}")]
+        RightBrace,
+        #[token("(")]
+        LeftParen,
+        #[token(")")]
+        RightParen,
+    }
+}
+
+impl ::core::convert::From<MapTokenKind> for TokenKind {
+    fn from(kind: MapTokenKind) -> Self {
+        match kind {
+            MapTokenKind::Whitespace => Self::Whitespace,
+            MapTokenKind::Newline => Self::Newline,
+            MapTokenKind::LineComment => Self::LineComment,
+            MapTokenKind::BlockComment => Self::BlockComment,
+
+            MapTokenKind::String => Self::String,
+            MapTokenKind::QuotedString => Self::QuotedString,
+
+            MapTokenKind::LeftBrace => Self::LeftBrace,
+            MapTokenKind::RightBrace => Self::RightBrace,
+            MapTokenKind::LeftParen => Self::LeftParen,
+            MapTokenKind::RightParen => Self::RightParen,
+        }
+    }
+}
+
+/// Lexer for Radiant map sources.
+pub type MapLexer<'src> = DialectLexer<'src, MapTokenKind>;
+
 #[cfg(test)]
 mod tests {
     use logos::source::Source;
@@ -180,6 +516,7 @@ mod tests {
     use super::*;
 
     // https://github.com/maciejhirsz/logos/blob/master/tests/src/lib.rs
+    #[allow(clippy::type_complexity)]
     pub fn assert_lex<'a, Token>(
         source: &'a Token::Source,
         token_kind: &[(
@@ -219,6 +556,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cfg() {
+        assert_lex(
+            "bind a/b \"say; hi\";echo x//c\n\"open",
+            &[
+                (Ok(CfgTokenKind::String), "bind", 0..4),
+                (Ok(CfgTokenKind::Whitespace), " ", 4..5),
+                (Ok(CfgTokenKind::String), "a/b", 5..8),
+                (Ok(CfgTokenKind::Whitespace), " ", 8..9),
+                (Ok(CfgTokenKind::QuotedString), "\"say; hi\"", 9..18),
+                (Ok(CfgTokenKind::Semicolon), ";", 18..19),
+                (Ok(CfgTokenKind::String), "echo", 19..23),
+                (Ok(CfgTokenKind::Whitespace), " ", 23..24),
+                (Ok(CfgTokenKind::String), "x", 24..25),
+                (Ok(CfgTokenKind::LineComment), "//c", 25..28),
+                (Ok(CfgTokenKind::Newline), "\n", 28..29),
+                (Ok(CfgTokenKind::QuotedString), "\"open", 29..34),
+            ],
+        );
+    }
+
     #[test]
     fn newline() {
         assert_lex(
@@ -312,6 +670,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn script() {
+        assert_lex(
+            "#define X 1\ntype \"a\\\"\" { X, 0.5; }",
+            &[
+                (Ok(ScriptTokenKind::Preprocessor), "#define X 1", 0..11),
+                (Ok(ScriptTokenKind::Newline), "\n", 11..12),
+                (Ok(ScriptTokenKind::Name), "type", 12..16),
+                (Ok(ScriptTokenKind::Whitespace), " ", 16..17),
+                (Ok(ScriptTokenKind::QuotedString), "\"a\\\"\"", 17..22),
+                (Ok(ScriptTokenKind::Whitespace), " ", 22..23),
+                (Ok(ScriptTokenKind::LeftBrace), "{", 23..24),
+                (Ok(ScriptTokenKind::Whitespace), " ", 24..25),
+                (Ok(ScriptTokenKind::Name), "X", 25..26),
+                (Ok(ScriptTokenKind::Comma), ",", 26..27),
+                (Ok(ScriptTokenKind::Whitespace), " ", 27..28),
+                (Ok(ScriptTokenKind::Number), "0.5", 28..31),
+                (Ok(ScriptTokenKind::Semicolon), ";", 31..32),
+                (Ok(ScriptTokenKind::Whitespace), " ", 32..33),
+                (Ok(ScriptTokenKind::RightBrace), "}", 33..34),
+            ],
+        );
+    }
+
     #[test]
     fn test_token_new() {
         let token = Token::new(TokenKind::Error, RawSpan::new(0, 0), "");
@@ -323,14 +705,14 @@ mod tests {
     #[test]
     fn test_lexer_new() {
         let src = "hurz";
-        let lexer = Lexer::new(&src);
+        let lexer = Lexer::new(src);
         assert_eq!(lexer.source(), src);
     }
 
     #[test]
     fn test_lexer_iter() {
         let src = "abc\ndef_";
-        let lexer = Lexer::new(&src);
+        let lexer = Lexer::new(src);
         let tokens: Vec<_> = lexer.collect();
         assert_eq!(
             tokens,
@@ -1,8 +1,27 @@
+pub mod aas;
+pub mod animation;
+pub mod arena;
+mod binary;
+pub mod botlib;
+pub mod bsp;
+pub mod cfg;
+pub mod demo;
+pub mod diagnostic;
 pub mod event;
+pub mod info;
 pub mod lexer;
+pub mod map;
+pub mod md3;
 pub mod parse;
 pub mod parser;
+pub mod pk3;
+pub mod q3string;
+pub mod qvm;
+pub mod resolve;
+pub mod shader;
 pub mod sink;
+pub mod skin;
 pub mod source;
 pub mod span;
 pub mod syntax;
+pub mod vfs;
@@ -0,0 +1,645 @@
+//! Radiant map sources (`maps/*.map`).
+//!
+//! Entities hold key/value pairs, brushes in the original or the `brushDef` (brush primitives)
+//! format, and `patchDef2` meshes.
+
+use crate::{
+    arena::KeyValue,
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::MapLexer,
+    parse,
+    span::RawSpan,
+    syntax::{self, child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+/// Minimum number of planes enclosing a volume.
+pub const MIN_BRUSH_PLANES: usize = 4;
+
+/// Parsed and validated map source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapFile {
+    entities: Vec<Entity>,
+}
+
+impl MapFile {
+    /// Parses and validates a map source.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(
+            source,
+            MapLexer::new(source).collect(),
+            parse::map::map_file,
+        );
+        let mut diagnostics = parse.errors().to_vec();
+
+        let entities: Vec<_> = parse
+            .syntax()
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Entity))
+            .map(|node| Entity::from_node(node, source, &mut diagnostics))
+            .collect();
+        if let Some(first) = entities.first() {
+            if first.classname() != Some("worldspawn") {
+                diagnostics.push(Diagnostic::warning(
+                    "first entity is not worldspawn",
+                    first.span,
+                ));
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (Self { entities }, diagnostics)
+    }
+
+    /// Get the entities in file order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Get the `worldspawn` entity holding the structural brushes.
+    pub fn worldspawn(&self) -> Option<&Entity> {
+        self.entities_of_class("worldspawn").next()
+    }
+
+    /// Get the entities with the given `classname`, e.g. `info_player_deathmatch`.
+    pub fn entities_of_class<'a>(&'a self, classname: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |entity| entity.classname() == Some(classname))
+    }
+}
+
+/// `{ "classname" "func_door" { brush } }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entity {
+    pairs: Vec<KeyValue>,
+    brushes: Vec<Brush>,
+    patches: Vec<Patch>,
+    span: RawSpan,
+}
+
+impl Entity {
+    /// Creates an entity from a [`SyntaxKind::Entity`] node of a map or an entity lump.
+    pub(crate) fn from_node(
+        node: &SyntaxNode,
+        source: &str,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let mut entity = Self {
+            span: node.text_range().into(),
+            ..Self::default()
+        };
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::KeyValuePair => entity.pairs.extend(KeyValue::from_node(child, source)),
+                SyntaxKind::Brush => entity.brushes.push(Brush::from_node(
+                    child,
+                    source,
+                    BrushFormat::Standard,
+                    diagnostics,
+                )),
+                SyntaxKind::BrushDef => entity.brushes.push(Brush::from_node(
+                    child,
+                    source,
+                    BrushFormat::BrushDef,
+                    diagnostics,
+                )),
+                SyntaxKind::PatchDef => {
+                    entity
+                        .patches
+                        .push(Patch::from_node(child, source, diagnostics))
+                }
+                _ => {}
+            }
+        }
+        if entity.classname().is_none() {
+            diagnostics.push(Diagnostic::warning("entity without classname", entity.span));
+        }
+        entity
+    }
+
+    /// Get the key/value pairs in file order.
+    pub fn pairs(&self) -> &[KeyValue] {
+        &self.pairs
+    }
+
+    /// Get the pair for `key`; the last one wins like in q3map.
+    pub fn pair(&self, key: &str) -> Option<&KeyValue> {
+        self.pairs.iter().rev().find(|pair| pair.key() == key)
+    }
+
+    /// Get the value for `key`, see [`pair`](Self::pair).
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pair(key).map(|pair| pair.value().as_str())
+    }
+
+    /// Get the `classname`.
+    pub fn classname(&self) -> Option<&str> {
+        self.get("classname")
+    }
+
+    /// Get the `origin`, if it is three numbers.
+    pub fn origin(&self) -> Option<[f64; 3]> {
+        let mut numbers = self
+            .get("origin")?
+            .split_whitespace()
+            .map(|number| number.parse().ok());
+        let origin = [numbers.next()??, numbers.next()??, numbers.next()??];
+        numbers.next().is_none().then_some(origin)
+    }
+
+    /// Get the brushes in file order.
+    pub fn brushes(&self) -> &[Brush] {
+        &self.brushes
+    }
+
+    /// Get the patches in file order.
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    /// Get the entity's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Format a brush is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BrushFormat {
+    /// `{ ( … ) ( … ) ( … ) texture shift rotate scale … }`
+    #[default]
+    Standard,
+    /// `{ brushDef { ( … ) ( … ) ( … ) ( ( … ) ( … ) ) texture … } }`
+    BrushDef,
+}
+
+/// Convex volume bounded by planes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Brush {
+    format: BrushFormat,
+    planes: Vec<BrushPlane>,
+    span: RawSpan,
+}
+
+impl Brush {
+    fn from_node(
+        node: &SyntaxNode,
+        source: &str,
+        format: BrushFormat,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let planes: Vec<_> = child_nodes(node, SyntaxKind::Plane)
+            .map(|plane| BrushPlane::from_node(plane, source, format, diagnostics))
+            .collect();
+        let span = node.text_range().into();
+        if planes.len() < MIN_BRUSH_PLANES {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "brush has {} planes, at least {} are needed",
+                    planes.len(),
+                    MIN_BRUSH_PLANES
+                ),
+                span,
+            ));
+        }
+        Self {
+            format,
+            planes,
+            span,
+        }
+    }
+
+    /// Get the format the brush is written in.
+    pub const fn format(&self) -> BrushFormat {
+        self.format
+    }
+
+    /// Get the planes in file order.
+    pub fn planes(&self) -> &[BrushPlane] {
+        &self.planes
+    }
+
+    /// Get the brush's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Plane through three points with its texture.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BrushPlane {
+    points: [[f64; 3]; 3],
+    texture: String,
+    projection: TextureProjection,
+    contents: i64,
+    flags: i64,
+    value: i64,
+    span: RawSpan,
+}
+
+impl BrushPlane {
+    fn from_node(
+        node: &SyntaxNode,
+        source: &str,
+        format: BrushFormat,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let span = node.text_range().into();
+        let mut plane = Self {
+            span,
+            ..Self::default()
+        };
+        let mut numbers = Numbers {
+            source,
+            diagnostics,
+        };
+
+        let vectors: Vec<_> = child_nodes(node, SyntaxKind::Vector).collect();
+        let expected = match format {
+            BrushFormat::Standard => 3,
+            BrushFormat::BrushDef => 4,
+        };
+        if vectors.len() != expected {
+            numbers.diagnostics.push(Diagnostic::error(
+                format!("expect {} vectors, found {}", expected, vectors.len()),
+                span,
+            ));
+        }
+        for (point, vector) in plane.points.iter_mut().zip(&vectors) {
+            *point = numbers.array(vector);
+        }
+
+        let mut tokens = child_tokens(node).filter(|token| token.kind().is_string());
+        if let Some(texture) = tokens.next() {
+            plane.texture = unquote(&source[RawSpan::from(texture.text_range())]).to_owned();
+        }
+        let rest: Vec<_> = tokens
+            .map(|token| numbers.number(RawSpan::from(token.text_range())))
+            .collect();
+
+        let flags = match format {
+            BrushFormat::Standard => {
+                if rest.len() != 5 && rest.len() != 8 {
+                    numbers.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "expect 5 or 8 numbers after the texture, found {}",
+                            rest.len()
+                        ),
+                        span,
+                    ));
+                }
+                let at = |index: usize| rest.get(index).copied().unwrap_or_default();
+                plane.projection = TextureProjection::Standard {
+                    shift: [at(0), at(1)],
+                    rotate: at(2),
+                    scale: [at(3), at(4)],
+                };
+                rest.get(5..)
+            }
+            BrushFormat::BrushDef => {
+                if !rest.is_empty() && rest.len() != 3 {
+                    numbers.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "expect 0 or 3 numbers after the texture, found {}",
+                            rest.len()
+                        ),
+                        span,
+                    ));
+                }
+                let mut matrix = [[0.0; 3]; 2];
+                if let Some(vector) = vectors.get(3) {
+                    let rows: Vec<_> = child_nodes(vector, SyntaxKind::Vector).collect();
+                    if rows.len() != 2 {
+                        numbers.diagnostics.push(Diagnostic::error(
+                            format!("expect 2 texture matrix rows, found {}", rows.len()),
+                            vector.text_range().into(),
+                        ));
+                    }
+                    for (row, vector) in matrix.iter_mut().zip(rows) {
+                        *row = numbers.array(vector);
+                    }
+                }
+                plane.projection = TextureProjection::Matrix(matrix);
+                rest.get(..)
+            }
+        };
+        if let Some(&[contents, flags, value]) = flags {
+            plane.contents = contents as i64;
+            plane.flags = flags as i64;
+            plane.value = value as i64;
+        }
+
+        let [a, b, c] = plane.points;
+        let normal = cross(subtract(b, a), subtract(c, a));
+        if vectors.len() == expected && normal.iter().all(|component| *component == 0.0) {
+            numbers
+                .diagnostics
+                .push(Diagnostic::warning("plane points are collinear", span));
+        }
+        plane
+    }
+
+    /// Get the three points the plane goes through.
+    pub const fn points(&self) -> [[f64; 3]; 3] {
+        self.points
+    }
+
+    /// Get the texture without `textures/`.
+    pub fn texture(&self) -> &str {
+        &self.texture
+    }
+
+    /// Get how the texture is projected.
+    pub const fn projection(&self) -> TextureProjection {
+        self.projection
+    }
+
+    /// Get the content flags, 0 for the shader's.
+    pub const fn contents(&self) -> i64 {
+        self.contents
+    }
+
+    /// Get the surface flags, 0 for the shader's.
+    pub const fn flags(&self) -> i64 {
+        self.flags
+    }
+
+    /// Get the light value.
+    pub const fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Get the plane's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// How a texture is projected onto a plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureProjection {
+    /// Shift, rotation and scale of the original format.
+    Standard {
+        shift: [f64; 2],
+        rotate: f64,
+        scale: [f64; 2],
+    },
+    /// Texture matrix of brush primitives.
+    Matrix([[f64; 3]; 2]),
+}
+
+impl Default for TextureProjection {
+    fn default() -> Self {
+        Self::Standard {
+            shift: [0.0; 2],
+            rotate: 0.0,
+            scale: [0.5; 2],
+        }
+    }
+}
+
+/// `patchDef2` Bézier mesh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    texture: String,
+    width: usize,
+    height: usize,
+    contents: i64,
+    flags: i64,
+    value: i64,
+    control_points: Vec<[f64; 5]>,
+    span: RawSpan,
+}
+
+impl Patch {
+    fn from_node(node: &SyntaxNode, source: &str, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let span = node.text_range().into();
+        let mut patch = Self {
+            span,
+            ..Self::default()
+        };
+        if let Some(texture) = child_tokens(node).find(|token| {
+            token.kind().is_string() && &source[RawSpan::from(token.text_range())] != "patchDef2"
+        }) {
+            patch.texture = unquote(&source[RawSpan::from(texture.text_range())]).to_owned();
+        }
+
+        let mut numbers = Numbers {
+            source,
+            diagnostics,
+        };
+        let mut vectors = child_nodes(node, SyntaxKind::Vector);
+        if let Some(info) = vectors.next() {
+            let info: [f64; 5] = numbers.array(info);
+            patch.width = info[0] as usize;
+            patch.height = info[1] as usize;
+            patch.contents = info[2] as i64;
+            patch.flags = info[3] as i64;
+            patch.value = info[4] as i64;
+        }
+        if patch.width < 3
+            || patch.height < 3
+            || patch.width.is_multiple_of(2)
+            || patch.height.is_multiple_of(2)
+        {
+            numbers.diagnostics.push(Diagnostic::error(
+                format!(
+                    "patch is {}x{}, dimensions must be odd and at least 3",
+                    patch.width, patch.height
+                ),
+                span,
+            ));
+        }
+
+        if let Some(matrix) = vectors.next() {
+            let rows: Vec<_> = child_nodes(matrix, SyntaxKind::Vector).collect();
+            let columns = rows
+                .iter()
+                .map(|row| child_nodes(row, SyntaxKind::Vector).count());
+            if rows.len() != patch.width || columns.clone().any(|count| count != patch.height) {
+                numbers.diagnostics.push(Diagnostic::error(
+                    format!(
+                        "patch matrix doesn't have {} rows of {} points",
+                        patch.width, patch.height
+                    ),
+                    matrix.text_range().into(),
+                ));
+            }
+            patch.control_points = rows
+                .iter()
+                .flat_map(|row| child_nodes(row, SyntaxKind::Vector))
+                .map(|point| numbers.array(point))
+                .collect();
+        }
+        patch
+    }
+
+    /// Get the texture without `textures/`.
+    pub fn texture(&self) -> &str {
+        &self.texture
+    }
+
+    /// Get the number of rows.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the number of points per row.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the content flags.
+    pub const fn contents(&self) -> i64 {
+        self.contents
+    }
+
+    /// Get the surface flags.
+    pub const fn flags(&self) -> i64 {
+        self.flags
+    }
+
+    /// Get the light value.
+    pub const fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Get the `x y z s t` control points row by row.
+    pub fn control_points(&self) -> &[[f64; 5]] {
+        &self.control_points
+    }
+
+    /// Get the control point in `row` and `column`.
+    pub fn control_point(&self, row: usize, column: usize) -> Option<[f64; 5]> {
+        if column >= self.height {
+            return None;
+        }
+        self.control_points.get(row * self.height + column).copied()
+    }
+
+    /// Get the patch's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Reads numbers, reporting invalid ones.
+struct Numbers<'a> {
+    source: &'a str,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Numbers<'_> {
+    fn number(&mut self, span: RawSpan) -> f64 {
+        let text = &self.source[span];
+        text.parse().unwrap_or_else(|_| {
+            self.diagnostics.push(Diagnostic::error(
+                format!("invalid number '{}'", text),
+                span,
+            ));
+            0.0
+        })
+    }
+
+    /// `( 1 2 3 )`, reporting a different count of numbers.
+    fn array<const N: usize>(&mut self, vector: &SyntaxNode) -> [f64; N] {
+        let mut array = [0.0; N];
+        let spans: Vec<_> = child_tokens(vector)
+            .filter(|token| token.kind().is_string())
+            .map(|token| RawSpan::from(token.text_range()))
+            .collect();
+        if spans.len() != N || vector.children().next().is_some() {
+            self.diagnostics.push(Diagnostic::error(
+                format!("expect {} numbers", N),
+                vector.text_range().into(),
+            ));
+        }
+        for (number, span) in array.iter_mut().zip(spans) {
+            *number = self.number(span);
+        }
+        array
+    }
+}
+
+fn subtract(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write as _;
+
+    use super::*;
+
+    const BOX: &str = "( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) common/caulk 0 0 0 0.5 0.5 0 0 0\n\
+( 0 0 64 ) ( 1 0 64 ) ( 0 1 64 ) base_floor/concrete 0 0 0 0.5 0.5 0 0 0\n\
+( 0 0 0 ) ( 1 0 0 ) ( 0 0 1 ) common/caulk 0 0 0 0.5 0.5 0 0 0\n\
+( 0 64 0 ) ( 0 64 1 ) ( 1 64 0 ) common/caulk 0 0 0 0.5 0.5 0 0 0\n";
+
+    #[test]
+    fn map() {
+        let source = format!(
+            "{{\n\"classname\" \"worldspawn\"\n{{\n{}}}\n{{\npatchDef2\n{{\nbase_trim/border\n( 3 3 0 0 0 )\n(\n( ( 0 0 0 0 0 ) ( 0 64 0 0 1 ) ( 0 128 0 0 2 ) )\n( ( 64 0 0 1 0 ) ( 64 64 0 1 1 ) ( 64 128 0 1 2 ) )\n( ( 128 0 0 2 0 ) ( 128 64 0 2 1 ) ( 128 128 0 2 2 ) )\n)\n}}\n}}\n}}\n{{\n\"classname\" \"info_player_deathmatch\"\n\"origin\" \"16 32 24\"\n}}\n",
+            BOX
+        );
+        let (map, diagnostics) = MapFile::parse(&source);
+        assert_eq!(diagnostics, &[]);
+
+        let world = map.worldspawn().unwrap();
+        assert_eq!(world.brushes()[0].planes().len(), 4);
+        assert_eq!(
+            world.brushes()[0].planes()[1].texture(),
+            "base_floor/concrete"
+        );
+        assert_eq!(
+            world.patches()[0].control_point(1, 2),
+            Some([64.0, 128.0, 0.0, 1.0, 2.0])
+        );
+
+        let spawns: Vec<_> = map
+            .entities_of_class("info_player_deathmatch")
+            .map(Entity::origin)
+            .collect();
+        assert_eq!(spawns, &[Some([16.0, 32.0, 24.0])]);
+    }
+
+    #[test]
+    fn validation() {
+        let source = "{\n\"classname\" \"worldspawn\"\n{\n( 0 0 0 ) ( 1 1 1 ) ( 2 2 2 ) a 0 0 0 1\n}\n{\nbrushDef\n{\n( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) ( ( 1 0 0 ) ( 0 1 x ) ) b 0 0 0\n}\n}\n}\n";
+        let (_, diagnostics) = MapFile::parse(source);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "brush has 1 planes, at least 4 are needed",
+                "expect 5 or 8 numbers after the texture, found 4",
+                "plane points are collinear",
+                "brush has 1 planes, at least 4 are needed",
+                "invalid number 'x'",
+            ]
+        );
+    }
+
+    #[test]
+    fn large() {
+        let mut source = String::from("{\n\"classname\" \"worldspawn\"\n");
+        for brush in 0..4_000 {
+            let _ = write!(source, "// brush {}\n{{\n{}}}\n", brush, BOX);
+        }
+        source.push_str("}\n");
+        assert!(source.len() > 1_000_000);
+
+        let (map, diagnostics) = MapFile::parse(&source);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(map.worldspawn().unwrap().brushes().len(), 4_000);
+    }
+}
@@ -0,0 +1,690 @@
+//! Models (`models/**/*.md3`) in the IDP3 version 15 format, e.g. players and items.
+//!
+//! The model borrows the file's data, records are decoded when iterated. Spans are byte ranges in
+//! the file.
+
+use std::borrow::Cow;
+
+use crate::{
+    binary::{self, float, index, int, short, span, vector, MAX_QPATH},
+    diagnostic::Diagnostic,
+    span::RawSpan,
+};
+
+/// Magic at the start of the file and of each surface.
+pub const MD3_IDENT: &[u8; 4] = b"IDP3";
+/// Version written by the exporters and read by the engine.
+pub const MD3_VERSION: i32 = 15;
+/// Maximum number of frames.
+pub const MD3_MAX_FRAMES: usize = 1024;
+/// Maximum number of tags per frame.
+pub const MD3_MAX_TAGS: usize = 16;
+/// Maximum number of surfaces.
+pub const MD3_MAX_SURFACES: usize = 32;
+/// Maximum number of shaders per surface.
+pub const MD3_MAX_SHADERS: usize = 256;
+/// Maximum number of vertexes per surface.
+pub const MD3_MAX_VERTS: usize = 4096;
+/// Maximum number of triangles per surface.
+pub const MD3_MAX_TRIANGLES: usize = 8192;
+/// Scale of the compressed vertex positions.
+pub const MD3_XYZ_SCALE: f32 = 1.0 / 64.0;
+
+const HEADER_SIZE: usize = 108;
+const FRAME_SIZE: usize = 56;
+const TAG_SIZE: usize = MAX_QPATH + 48;
+const SURFACE_SIZE: usize = 108;
+const SHADER_SIZE: usize = MAX_QPATH + 4;
+const TRIANGLE_SIZE: usize = 12;
+const TEX_COORD_SIZE: usize = 8;
+const VERTEX_SIZE: usize = 8;
+
+/// Read and validated model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Md3<'a> {
+    name: Cow<'a, str>,
+    frames: &'a [u8],
+    frame_count: usize,
+    tags: &'a [u8],
+    tag_count: usize,
+    surfaces: Vec<Surface<'a>>,
+}
+
+impl<'a> Md3<'a> {
+    /// Reads and validates a model, nothing but the diagnostics if the header is invalid.
+    pub fn parse(data: &'a [u8]) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut md3 = Self::default();
+
+        if data.len() < HEADER_SIZE {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "file of {} bytes is too short for the {} bytes header",
+                    data.len(),
+                    HEADER_SIZE
+                ),
+                span(0, data.len()),
+            ));
+            return (md3, diagnostics);
+        }
+        if &data[..4] != MD3_IDENT {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "expect ident 'IDP3', found '{}'",
+                    String::from_utf8_lossy(&data[..4])
+                ),
+                span(0, 4),
+            ));
+            return (md3, diagnostics);
+        }
+        let version = int(data, 4);
+        if version != MD3_VERSION {
+            diagnostics.push(Diagnostic::error(
+                format!("unsupported version {}, expect {}", version, MD3_VERSION),
+                span(4, 8),
+            ));
+            return (md3, diagnostics);
+        }
+
+        let header = span(0, HEADER_SIZE);
+        md3.name = read_name(&data[8..8 + MAX_QPATH], header, &mut diagnostics);
+        let frame_count = index(data, 76);
+        let tag_count = index(data, 80);
+        let surface_count = index(data, 84);
+        let frames_offset = index(data, 92);
+        let tags_offset = index(data, 96);
+        let surfaces_offset = index(data, 100);
+        let end = index(data, 104);
+        if end < HEADER_SIZE {
+            diagnostics.push(Diagnostic::error(
+                format!("end {} is inside of the {} bytes header", end, HEADER_SIZE),
+                span(104, 108),
+            ));
+            return (md3, diagnostics);
+        }
+        let data = if end > data.len() {
+            diagnostics.push(Diagnostic::error(
+                format!("end {} is outside of the file's {} bytes", end, data.len()),
+                span(104, 108),
+            ));
+            data
+        } else {
+            &data[..end]
+        };
+        check_limit(
+            "frames",
+            frame_count,
+            MD3_MAX_FRAMES,
+            header,
+            &mut diagnostics,
+        );
+        check_limit("tags", tag_count, MD3_MAX_TAGS, header, &mut diagnostics);
+        check_limit(
+            "surfaces",
+            surface_count,
+            MD3_MAX_SURFACES,
+            header,
+            &mut diagnostics,
+        );
+
+        match binary::region(data, frames_offset, frame_count, FRAME_SIZE) {
+            Some(frames) => {
+                md3.frames = frames;
+                md3.frame_count = frame_count;
+            }
+            None => diagnostics.push(Diagnostic::error(
+                "frames are outside of the model",
+                span(92, 96),
+            )),
+        }
+        let tags = frame_count
+            .checked_mul(tag_count)
+            .and_then(|count| binary::region(data, tags_offset, count, TAG_SIZE));
+        match tags {
+            Some(tags) => {
+                md3.tags = tags;
+                md3.tag_count = tag_count;
+            }
+            None => diagnostics.push(Diagnostic::error(
+                "tags are outside of the model",
+                span(96, 100),
+            )),
+        }
+        for (number, tag) in md3.tags.chunks_exact(TAG_SIZE).enumerate() {
+            let offset = tags_offset + number * TAG_SIZE;
+            read_name(
+                &tag[..MAX_QPATH],
+                span(offset, offset + TAG_SIZE),
+                &mut diagnostics,
+            );
+        }
+
+        let mut offset = surfaces_offset;
+        for _ in 0..surface_count.min(MD3_MAX_SURFACES) {
+            match Surface::read(data, offset, frame_count, &mut diagnostics) {
+                Some((surface, end)) => {
+                    md3.surfaces.push(surface);
+                    offset = end;
+                }
+                None => break,
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (md3, diagnostics)
+    }
+
+    /// Get the model's name, usually the path it was exported to.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of animation frames.
+    pub const fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Get the frames.
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'a>> {
+        self.frames
+            .chunks_exact(FRAME_SIZE)
+            .map(|record| Frame { record })
+    }
+
+    /// Get the number of tags per frame.
+    pub const fn tag_count(&self) -> usize {
+        self.tag_count
+    }
+
+    /// Get the tags of the `frame`, e.g. `tag_head`.
+    pub fn tags(&self, frame: usize) -> impl Iterator<Item = Tag<'a>> {
+        let start = (frame * self.tag_count * TAG_SIZE).min(self.tags.len());
+        let end = ((frame + 1) * self.tag_count * TAG_SIZE).min(self.tags.len());
+        self.tags[start..end]
+            .chunks_exact(TAG_SIZE)
+            .map(|record| Tag { record })
+    }
+
+    /// Get the surfaces, named like in `.skin` files.
+    pub fn surfaces(&self) -> &[Surface<'a>] {
+        &self.surfaces
+    }
+
+    /// Get the surface with the name, ignoring case like skins.
+    pub fn surface(&self, name: &str) -> Option<&Surface<'a>> {
+        self.surfaces
+            .iter()
+            .find(|surface| surface.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the names of the shaders of all surfaces.
+    pub fn shader_names(&self) -> impl Iterator<Item = Cow<'a, str>> + '_ {
+        self.surfaces
+            .iter()
+            .flat_map(|surface| surface.shaders().map(|shader| shader.name()))
+    }
+}
+
+/// `md3Frame_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Frame<'a> {
+    record: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Get the minimum corner of the bounds.
+    pub fn mins(&self) -> [f32; 3] {
+        vector(self.record, 0)
+    }
+
+    /// Get the maximum corner of the bounds.
+    pub fn maxs(&self) -> [f32; 3] {
+        vector(self.record, 12)
+    }
+
+    /// Get the local origin.
+    pub fn local_origin(&self) -> [f32; 3] {
+        vector(self.record, 24)
+    }
+
+    /// Get the radius of the bounding sphere.
+    pub fn radius(&self) -> f32 {
+        float(self.record, 36)
+    }
+
+    /// Get the frame's name, often the exporter's.
+    pub fn name(&self) -> Cow<'a, str> {
+        lossy_name(&self.record[40..56])
+    }
+}
+
+/// `md3Tag_t`, attachment point like `tag_weapon`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tag<'a> {
+    record: &'a [u8],
+}
+
+impl<'a> Tag<'a> {
+    /// Get the tag's name.
+    pub fn name(&self) -> Cow<'a, str> {
+        lossy_name(&self.record[..MAX_QPATH])
+    }
+
+    /// Get the tag's origin.
+    pub fn origin(&self) -> [f32; 3] {
+        vector(self.record, MAX_QPATH)
+    }
+
+    /// Get the tag's axis.
+    pub fn axis(&self) -> [[f32; 3]; 3] {
+        [
+            vector(self.record, MAX_QPATH + 12),
+            vector(self.record, MAX_QPATH + 24),
+            vector(self.record, MAX_QPATH + 36),
+        ]
+    }
+}
+
+/// `md3Surface_t`, a mesh with its own shaders.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Surface<'a> {
+    name: Cow<'a, str>,
+    flags: i32,
+    vertex_count: usize,
+    shaders: &'a [u8],
+    triangles: &'a [u8],
+    tex_coords: &'a [u8],
+    vertices: &'a [u8],
+    span: RawSpan,
+}
+
+impl<'a> Surface<'a> {
+    /// Reads the surface at `offset`, returning where the next one starts.
+    fn read(
+        data: &'a [u8],
+        offset: usize,
+        frame_count: usize,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<(Self, usize)> {
+        let header = match binary::region(data, offset, 1, SURFACE_SIZE) {
+            Some(header) => header,
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    format!("surface at {} is outside of the model", offset),
+                    span(offset, data.len().max(offset)),
+                ));
+                return None;
+            }
+        };
+        let header_span = span(offset, offset + SURFACE_SIZE);
+        if &header[..4] != MD3_IDENT {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "expect surface ident 'IDP3', found '{}'",
+                    String::from_utf8_lossy(&header[..4])
+                ),
+                span(offset, offset + 4),
+            ));
+            return None;
+        }
+        let end = index(header, 104);
+        let record = match binary::region(data, offset, 1, end) {
+            Some(record) if end >= SURFACE_SIZE => record,
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    format!("surface end {} is outside of the model", end),
+                    header_span,
+                ));
+                return None;
+            }
+        };
+
+        let mut surface = Self {
+            name: read_name(&header[4..4 + MAX_QPATH], header_span, diagnostics),
+            flags: int(header, 68),
+            span: span(offset, offset + end),
+            ..Self::default()
+        };
+        let surface_frames = index(header, 72);
+        let shader_count = index(header, 76);
+        surface.vertex_count = index(header, 80);
+        let triangle_count = index(header, 84);
+        let name = surface.name.clone();
+        let what = |part: &str| format!("surface '{}' {}", name, part);
+
+        if surface_frames != frame_count {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "{} {} frames, the model has {}",
+                    what("has"),
+                    surface_frames,
+                    frame_count
+                ),
+                header_span,
+            ));
+        }
+        check_limit(
+            &what("shaders"),
+            shader_count,
+            MD3_MAX_SHADERS,
+            header_span,
+            diagnostics,
+        );
+        check_limit(
+            &what("vertexes"),
+            surface.vertex_count,
+            MD3_MAX_VERTS,
+            header_span,
+            diagnostics,
+        );
+        check_limit(
+            &what("triangles"),
+            triangle_count,
+            MD3_MAX_TRIANGLES,
+            header_span,
+            diagnostics,
+        );
+
+        let mut section = |part: &str, offset: usize, count: Option<usize>, size: usize| {
+            let section = count.and_then(|count| binary::region(record, offset, count, size));
+            section.unwrap_or_else(|| {
+                diagnostics.push(Diagnostic::error(
+                    format!("{} are outside of the surface", what(part)),
+                    header_span,
+                ));
+                &[]
+            })
+        };
+        surface.triangles = section(
+            "triangles",
+            index(header, 88),
+            Some(triangle_count),
+            TRIANGLE_SIZE,
+        );
+        surface.shaders = section(
+            "shaders",
+            index(header, 92),
+            Some(shader_count),
+            SHADER_SIZE,
+        );
+        surface.tex_coords = section(
+            "texture coordinates",
+            index(header, 96),
+            Some(surface.vertex_count),
+            TEX_COORD_SIZE,
+        );
+        surface.vertices = section(
+            "vertexes",
+            index(header, 100),
+            surface.vertex_count.checked_mul(surface_frames),
+            VERTEX_SIZE,
+        );
+        if surface.vertices.is_empty() {
+            surface.vertex_count = 0;
+        }
+
+        for shader in surface.shaders.chunks_exact(SHADER_SIZE) {
+            read_name(&shader[..MAX_QPATH], header_span, diagnostics);
+        }
+        if let Some(triangle) = surface.triangles().find(|triangle| {
+            triangle
+                .iter()
+                .any(|vertex| *vertex >= surface.vertex_count)
+        }) {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "{} {:?} out of {}",
+                    what("triangle"),
+                    triangle,
+                    surface.vertex_count
+                ),
+                header_span,
+            ));
+        }
+
+        Some((surface, offset + end))
+    }
+
+    /// Get the surface's name, e.g. `h_head`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the surface's flags.
+    pub const fn flags(&self) -> i32 {
+        self.flags
+    }
+
+    /// Get the shaders, the first being used without a skin.
+    pub fn shaders(&self) -> impl Iterator<Item = SurfaceShader<'a>> {
+        self.shaders
+            .chunks_exact(SHADER_SIZE)
+            .map(|record| SurfaceShader { record })
+    }
+
+    /// Get the number of vertexes per frame.
+    pub const fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// Get the vertex indexes of the triangles.
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + 'a {
+        self.triangles
+            .chunks_exact(TRIANGLE_SIZE)
+            .map(|record| [index(record, 0), index(record, 4), index(record, 8)])
+    }
+
+    /// Get the texture coordinates of the vertexes.
+    pub fn tex_coords(&self) -> impl Iterator<Item = [f32; 2]> + 'a {
+        self.tex_coords
+            .chunks_exact(TEX_COORD_SIZE)
+            .map(|record| [float(record, 0), float(record, 4)])
+    }
+
+    /// Get the vertexes of the `frame`.
+    pub fn vertices(&self, frame: usize) -> impl Iterator<Item = Vertex> + 'a {
+        let size = self.vertex_count * VERTEX_SIZE;
+        let start = (frame * size).min(self.vertices.len());
+        let end = (start + size).min(self.vertices.len());
+        self.vertices[start..end]
+            .chunks_exact(VERTEX_SIZE)
+            .map(|record| Vertex {
+                position: [short(record, 0), short(record, 2), short(record, 4)],
+                normal: [record[7], record[6]],
+            })
+    }
+
+    /// Get the surface's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `md3Shader_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceShader<'a> {
+    record: &'a [u8],
+}
+
+impl<'a> SurfaceShader<'a> {
+    /// Get the shader's name, e.g. `models/players/sarge/band.tga`.
+    pub fn name(&self) -> Cow<'a, str> {
+        lossy_name(&self.record[..MAX_QPATH])
+    }
+}
+
+/// `md3XyzNormal_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Vertex {
+    position: [i16; 3],
+    normal: [u8; 2],
+}
+
+impl Vertex {
+    /// Get the position, scaled by [`MD3_XYZ_SCALE`].
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+            .map(|component| component as f32 * MD3_XYZ_SCALE)
+    }
+
+    /// Get the unit normal decompressed from its latitude and longitude.
+    pub fn normal(&self) -> [f32; 3] {
+        let [latitude, longitude] = self
+            .normal
+            .map(|angle| angle as f32 * std::f32::consts::TAU / 256.0);
+        [
+            latitude.cos() * longitude.sin(),
+            latitude.sin() * longitude.sin(),
+            longitude.cos(),
+        ]
+    }
+}
+
+/// Name validated by [`read_name`].
+fn lossy_name(field: &[u8]) -> Cow<'_, str> {
+    binary::name(field).unwrap_or_else(|| String::from_utf8_lossy(field))
+}
+
+fn read_name<'a>(
+    field: &'a [u8],
+    span: RawSpan,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Cow<'a, str> {
+    binary::name(field).unwrap_or_else(|| {
+        diagnostics.push(Diagnostic::error("name isn't terminated", span));
+        String::from_utf8_lossy(field)
+    })
+}
+
+fn check_limit(
+    what: &str,
+    count: usize,
+    limit: usize,
+    span: RawSpan,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if count > limit {
+        diagnostics.push(Diagnostic::error(
+            format!("{} {} exceed the limit of {}", count, what, limit),
+            span,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary::ints;
+
+    use super::*;
+
+    fn name(name: &str, size: usize) -> Vec<u8> {
+        let mut field = name.as_bytes().to_vec();
+        field.resize(size, 0);
+        field
+    }
+
+    /// Writes a model of one frame, one tag and a surface with a triangle.
+    fn write(triangle: [i32; 3]) -> Vec<u8> {
+        let mut surface = MD3_IDENT.to_vec();
+        surface.extend(name("h_head", MAX_QPATH));
+        let shaders = SURFACE_SIZE;
+        let triangles = shaders + SHADER_SIZE;
+        let tex_coords = triangles + TRIANGLE_SIZE;
+        let vertices = tex_coords + 3 * TEX_COORD_SIZE;
+        let end = vertices + 3 * VERTEX_SIZE;
+        surface.extend(ints(&[
+            0,
+            1,
+            1,
+            3,
+            1,
+            triangles as i32,
+            shaders as i32,
+            tex_coords as i32,
+            vertices as i32,
+            end as i32,
+        ]));
+        surface.extend(name("models/players/sarge/cigar", MAX_QPATH));
+        surface.extend(ints(&[0]));
+        surface.extend(ints(&triangle));
+        surface.extend(vec![0; 3 * TEX_COORD_SIZE]);
+        for (x, normal) in [(64i16, 0u16), (128, 64), (-64, 0x4040)] {
+            surface.extend(x.to_le_bytes());
+            surface.extend([0; 4]);
+            surface.extend(normal.to_le_bytes());
+        }
+
+        let frames = HEADER_SIZE;
+        let tags = frames + FRAME_SIZE;
+        let surfaces = tags + TAG_SIZE;
+        let end = surfaces + surface.len();
+        let mut data = MD3_IDENT.to_vec();
+        data.extend(ints(&[MD3_VERSION]));
+        data.extend(name("models/players/sarge/head.md3", MAX_QPATH));
+        data.extend(ints(&[
+            0,
+            1,
+            1,
+            1,
+            0,
+            frames as i32,
+            tags as i32,
+            surfaces as i32,
+            end as i32,
+        ]));
+        data.extend(vec![0; 40]);
+        data.extend(name("frame0", 16));
+        data.extend(name("tag_head", MAX_QPATH));
+        data.extend(vec![0; 48]);
+        data.extend(surface);
+        data
+    }
+
+    #[test]
+    fn read() {
+        let data = write([0, 1, 2]);
+        let (md3, diagnostics) = Md3::parse(&data);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(md3.name(), "models/players/sarge/head.md3");
+        assert_eq!(md3.frames().next().unwrap().name(), "frame0");
+        assert_eq!(md3.tags(0).next().unwrap().name(), "tag_head");
+
+        let surface = md3.surface("H_HEAD").unwrap();
+        assert_eq!(
+            md3.shader_names().collect::<Vec<_>>(),
+            &["models/players/sarge/cigar"]
+        );
+        assert_eq!(surface.triangles().collect::<Vec<_>>(), &[[0, 1, 2]]);
+        let vertices: Vec<_> = surface.vertices(0).collect();
+        assert_eq!(vertices[1].position(), [2.0, 0.0, 0.0]);
+        assert_eq!(vertices[0].normal(), [0.0, 0.0, 1.0]);
+        let normal = vertices[1].normal();
+        assert!((normal[0] - 1.0).abs() < 1e-6 && normal[2].abs() < 1e-6);
+    }
+
+    #[test]
+    fn validation() {
+        let mut data = write([0, 1, 3]);
+        data[84..88].copy_from_slice(&40i32.to_le_bytes());
+        let (md3, diagnostics) = Md3::parse(&data);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "40 surfaces exceed the limit of 32",
+                "surface 'h_head' triangle [0, 1, 3] out of 3",
+                "surface at 512 is outside of the model",
+            ]
+        );
+        assert_eq!(md3.surfaces().len(), 1);
+
+        let mut data = MD3_IDENT.to_vec();
+        data.extend(ints(&[MD3_VERSION]));
+        data.resize(HEADER_SIZE, 0);
+        let (_, diagnostics) = Md3::parse(&data);
+        assert_eq!(
+            diagnostics[0].message(),
+            "end 0 is inside of the 108 bytes header"
+        );
+    }
+}
@@ -1,9 +1,22 @@
 use crate::{
     lexer::TokenKind,
-    parser::{CompletedMarker, Marker, Parser},
+    parser::{CompletedMarker, Parser},
     syntax::SyntaxKind,
 };
 
+pub mod animation;
+pub mod bsp;
+pub mod cfg;
+pub mod chat;
+pub mod map;
+pub mod match_template;
+pub mod reply_chat;
+pub mod shader;
+pub mod skin;
+pub mod structure;
+pub mod synonym;
+pub mod weight;
+
 pub fn arenas(parser: &mut Parser) -> Option<CompletedMarker> {
     let arenas = parser.start();
 
@@ -0,0 +1,83 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Player animations, e.g. `models/players/sarge/animation.cfg`.
+///
+/// Header lines like `sex m` come first, then one line of numbers per animation.
+pub fn animation_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        if parser
+            .peek_text()
+            .is_some_and(|text| text.starts_with(|c: char| c.is_ascii_digit() || c == '-'))
+        {
+            animation(parser);
+        } else {
+            header(parser);
+        }
+    }
+
+    Some(file.complete(parser, SyntaxKind::AnimationFile))
+}
+
+/// `sex m`, `footsteps boot` or `headoffset 0 0 0`
+pub fn header(parser: &mut Parser) -> Option<CompletedMarker> {
+    line(parser, SyntaxKind::Directive)
+}
+
+/// `0 30 0 25 // BOTH_DEATH1`
+pub fn animation(parser: &mut Parser) -> Option<CompletedMarker> {
+    line(parser, SyntaxKind::Animation)
+}
+
+fn line(parser: &mut Parser, kind: SyntaxKind) -> Option<CompletedMarker> {
+    let line = parser.start();
+
+    if !parser.expect_any(TokenKind::String | TokenKind::QuotedString) {
+        line.abandon(parser);
+        return None;
+    }
+    while !parser.at_end() && !parser.at_line_start() {
+        parser.bump();
+    }
+
+    Some(line.complete(parser, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::Lexer,
+        syntax::{parse_tokens, SyntaxKind},
+    };
+
+    use super::animation_file;
+
+    #[test]
+    fn animation_file_lines() {
+        let source = "// animation config file\nsex m\nheadoffset 0 0 0\n\n0\t30\t0\t25\t\t// BOTH_DEATH1\n29 1 0 25 // BOTH_DEAD1\n";
+        let parse = parse_tokens(source, Lexer::new(source).collect(), animation_file);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+        assert_eq!(parse.errors(), &[]);
+
+        let kinds: Vec<_> = root
+            .children()
+            .flat_map(|file| file.children())
+            .map(|node| node.kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            &[
+                SyntaxKind::Directive,
+                SyntaxKind::Directive,
+                SyntaxKind::Animation,
+                SyntaxKind::Animation
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+use super::key_value;
+
+/// Entity lump of a compiled map, the entities' key/value pairs without brushes.
+pub fn entity_lump(parser: &mut Parser) -> Option<CompletedMarker> {
+    let lump = parser.start();
+
+    while !parser.at_end() {
+        if parser.at(TokenKind::LeftBrace) {
+            entity(parser);
+        } else {
+            parser.error(format!("expect {:?}", TokenKind::LeftBrace));
+        }
+    }
+
+    Some(lump.complete(parser, SyntaxKind::EntityLump))
+}
+
+/// `{ "classname" "info_player_deathmatch" "origin" "16 32 24" }`
+pub fn entity(parser: &mut Parser) -> Option<CompletedMarker> {
+    let entity = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        entity.abandon(parser);
+        return None;
+    }
+    loop {
+        if parser.eat(TokenKind::RightBrace) {
+            break;
+        }
+        if parser.at_end() || parser.at(TokenKind::LeftBrace) {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+            break;
+        }
+        if parser.at_any(TokenKind::String | TokenKind::QuotedString) {
+            key_value(parser);
+        } else {
+            parser.error(format!("expect {:?}", TokenKind::RightBrace));
+        }
+    }
+
+    Some(entity.complete(parser, SyntaxKind::Entity))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::Lexer,
+        syntax::{child_nodes, parse_tokens, SyntaxKind},
+    };
+
+    use super::entity_lump;
+
+    #[test]
+    fn entities() {
+        let source = "{\n\"classname\" \"worldspawn\"\n\"message\" \"Arena Gate\"\n}\n{\n\"classname\" \"info_player_deathmatch\"\n\"origin\" \"16 32 24\"\n{\n\"classname\" \"light\"\n}\n";
+        let parse = parse_tokens(source, Lexer::new(source).collect(), entity_lump);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+
+        let pairs: Vec<_> = root
+            .children()
+            .flat_map(|lump| child_nodes(lump, SyntaxKind::Entity))
+            .map(|entity| child_nodes(entity, SyntaxKind::KeyValuePair).count())
+            .collect();
+        assert_eq!(pairs, &[2, 2, 1]);
+        let errors: Vec<_> = parse
+            .errors()
+            .iter()
+            .map(|error| error.span().start())
+            .collect();
+        assert_eq!(
+            errors,
+            &[source.find("{\n\"classname\" \"light").unwrap() as u32]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Console config, e.g. `autoexec.cfg` or a server's `server.cfg`.
+///
+/// Commands end at a newline or at a `;` outside of quotes.
+pub fn cfg_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        if !parser.eat(TokenKind::Semicolon) {
+            command(parser);
+        }
+    }
+
+    Some(file.complete(parser, SyntaxKind::CfgFile))
+}
+
+/// `seta r_mode "3"`, a command name and its arguments.
+pub fn command(parser: &mut Parser) -> Option<CompletedMarker> {
+    let command = parser.start();
+
+    if !parser.expect_any(TokenKind::String | TokenKind::QuotedString) {
+        command.abandon(parser);
+        return None;
+    }
+    while !parser.at_end() && !parser.at_line_start() && !parser.at(TokenKind::Semicolon) {
+        parser.bump();
+    }
+
+    Some(command.complete(parser, SyntaxKind::Command))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::CfgLexer,
+        syntax::{child_nodes, parse_tokens, SyntaxKind},
+    };
+
+    use super::cfg_file;
+
+    #[test]
+    fn commands() {
+        let source = "// rotation\nset d1 \"map q3dm1; set nextmap vstr d2\" ; vstr d1\nbind x \"say hi\"//say\r\n;;exec server.cfg";
+        let parse = parse_tokens(source, CfgLexer::new(source).collect(), cfg_file);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+        assert_eq!(parse.errors(), &[]);
+
+        let commands: Vec<_> = root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Command))
+            .map(|command| {
+                command
+                    .resolve_text(parse.resolver())
+                    .to_string()
+                    .trim()
+                    .to_owned()
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            &[
+                "set d1 \"map q3dm1; set nextmap vstr d2\"",
+                "vstr d1",
+                "bind x \"say hi\"",
+                "exec server.cfg",
+            ]
+        );
+    }
+}
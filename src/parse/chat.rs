@@ -0,0 +1,142 @@
+use enumflags2::make_bitflags;
+
+use crate::{
+    lexer::{TokenKind, TokenSet},
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Tokens a chat message is made of: text, variables and random strings.
+const MESSAGE_PIECE: TokenSet = make_bitflags!(TokenKind::{QuotedString | Number | Name});
+
+/// Bot chat file, e.g. `botfiles/bots/sarge_t.c`.
+pub fn chat_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        chat(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::ChatFile))
+}
+
+/// `chat "name" { type … }`
+pub fn chat(parser: &mut Parser) -> Option<CompletedMarker> {
+    let chat = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "chat") {
+        chat.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::QuotedString) {
+        chat.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftBrace) {
+        chat.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        chat_type(parser);
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        chat.abandon(parser);
+        return None;
+    }
+
+    Some(chat.complete(parser, SyntaxKind::Chat))
+}
+
+/// Chat types without an enclosing chat, e.g. an `#include`d `teamplay.h`.
+pub fn chat_types(parser: &mut Parser) -> Option<CompletedMarker> {
+    let types = parser.start();
+
+    while !parser.at_end() {
+        chat_type(parser);
+    }
+
+    Some(types.complete(parser, SyntaxKind::ChatTypes))
+}
+
+/// `type "game_enter" { "message", VARIABLE; … }`
+pub fn chat_type(parser: &mut Parser) -> Option<CompletedMarker> {
+    let chat_type = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "type") {
+        chat_type.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::QuotedString) {
+        chat_type.abandon(parser);
+        return None;
+    }
+    if !messages(parser) {
+        chat_type.abandon(parser);
+        return None;
+    }
+
+    Some(chat_type.complete(parser, SyntaxKind::ChatType))
+}
+
+/// Random strings file, e.g. `botfiles/rnd.c`.
+pub fn random_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        random(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::RandomFile))
+}
+
+/// `name { "message"; … }`
+pub fn random(parser: &mut Parser) -> Option<CompletedMarker> {
+    let random = parser.start();
+
+    if !parser.expect(TokenKind::Name) {
+        random.abandon(parser);
+        return None;
+    }
+    if !messages(parser) {
+        random.abandon(parser);
+        return None;
+    }
+
+    Some(random.complete(parser, SyntaxKind::Random))
+}
+
+/// `{ "message"; … }`
+pub fn messages(parser: &mut Parser) -> bool {
+    if !parser.expect(TokenKind::LeftBrace) {
+        return false;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        message(parser);
+    }
+    parser.expect(TokenKind::RightBrace)
+}
+
+/// `"text", VARIABLE, random;`
+pub fn message(parser: &mut Parser) -> Option<CompletedMarker> {
+    let message = parser.start();
+
+    loop {
+        if parser.at(TokenKind::RightBrace) {
+            parser.push_error(format!("expect_any {:?}", MESSAGE_PIECE));
+            break;
+        }
+        if !parser.expect_any(MESSAGE_PIECE) {
+            break;
+        }
+        if !parser.eat(TokenKind::Comma) {
+            break;
+        }
+    }
+    if parser.at(TokenKind::RightBrace) {
+        parser.push_error(format!("expect {:?}", TokenKind::Semicolon));
+    } else {
+        parser.expect(TokenKind::Semicolon);
+    }
+
+    Some(message.complete(parser, SyntaxKind::ChatMessage))
+}
@@ -0,0 +1,268 @@
+use enumflags2::make_bitflags;
+
+use crate::{
+    lexer::{TokenKind, TokenSet},
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+use super::key_value;
+
+/// Tokens a key, value, texture name or number can be.
+const WORD: TokenSet = make_bitflags!(TokenKind::{String | QuotedString});
+
+/// Radiant map source, e.g. `maps/q3dm1.map`.
+pub fn map_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        if parser.at(TokenKind::LeftBrace) {
+            entity(parser);
+        } else {
+            parser.error(format!("expect {:?}", TokenKind::LeftBrace));
+        }
+    }
+
+    Some(file.complete(parser, SyntaxKind::MapFile))
+}
+
+/// `{ "classname" "worldspawn" { brush } … }`
+pub fn entity(parser: &mut Parser) -> Option<CompletedMarker> {
+    let entity = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        entity.abandon(parser);
+        return None;
+    }
+    loop {
+        if parser.eat(TokenKind::RightBrace) {
+            break;
+        }
+        if parser.at_end() {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+            break;
+        }
+        if parser.at(TokenKind::LeftBrace) {
+            primitive(parser);
+        } else if parser.at_any(WORD) {
+            key_value(parser);
+        } else {
+            parser.error(format!("expect {:?}", TokenKind::RightBrace));
+        }
+    }
+
+    Some(entity.complete(parser, SyntaxKind::Entity))
+}
+
+/// Brush in the original or the `brushDef` format, or a `patchDef2` mesh.
+pub fn primitive(parser: &mut Parser) -> Option<CompletedMarker> {
+    match parser.nth_text(1) {
+        Some("brushDef") => brush_def(parser),
+        Some("patchDef2") => patch_def(parser),
+        _ => brush(parser),
+    }
+}
+
+/// `{ ( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) common/caulk 0 0 0 0.5 0.5 0 0 0 … }`
+pub fn brush(parser: &mut Parser) -> Option<CompletedMarker> {
+    let brush = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        brush.abandon(parser);
+        return None;
+    }
+    planes(parser);
+
+    Some(brush.complete(parser, SyntaxKind::Brush))
+}
+
+/// `{ brushDef { ( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) ( ( 0.0078125 0 0 ) ( 0 0.0078125 0 ) ) common/caulk 0 0 0 … } }`
+pub fn brush_def(parser: &mut Parser) -> Option<CompletedMarker> {
+    let brush = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        brush.abandon(parser);
+        return None;
+    }
+    parser.expect_text(TokenKind::String, "brushDef");
+    if parser.expect(TokenKind::LeftBrace) {
+        planes(parser);
+    }
+    if !parser.eat(TokenKind::RightBrace) {
+        parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+    }
+
+    Some(brush.complete(parser, SyntaxKind::BrushDef))
+}
+
+/// `{ patchDef2 { texture ( 3 3 0 0 0 ) ( ( ( x y z u v ) … ) … ) } }`
+pub fn patch_def(parser: &mut Parser) -> Option<CompletedMarker> {
+    let patch = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        patch.abandon(parser);
+        return None;
+    }
+    parser.expect_text(TokenKind::String, "patchDef2");
+    if parser.expect(TokenKind::LeftBrace) {
+        parser.expect_any(WORD);
+        vector(parser);
+        vector(parser);
+        if !parser.eat(TokenKind::RightBrace) {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+        }
+    }
+    if !parser.eat(TokenKind::RightBrace) {
+        parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+    }
+
+    Some(patch.complete(parser, SyntaxKind::PatchDef))
+}
+
+/// Planes up to the closing `}`.
+///
+/// A `{` means the `}` is missing, since primitives can't be nested.
+fn planes(parser: &mut Parser) {
+    loop {
+        if parser.eat(TokenKind::RightBrace) {
+            break;
+        }
+        if parser.at_end() || parser.at(TokenKind::LeftBrace) {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+            break;
+        }
+        if parser.at(TokenKind::LeftParen) {
+            plane(parser);
+        } else {
+            parser.error(format!("expect {:?}", TokenKind::LeftParen));
+        }
+    }
+}
+
+/// `( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) texture` followed by the texture's numbers.
+pub fn plane(parser: &mut Parser) -> Option<CompletedMarker> {
+    let plane = parser.start();
+
+    while parser.at(TokenKind::LeftParen) {
+        vector(parser);
+    }
+    if !parser.expect_any(WORD) {
+        plane.abandon(parser);
+        return None;
+    }
+    while parser.at_any(WORD) {
+        parser.bump();
+    }
+
+    Some(plane.complete(parser, SyntaxKind::Plane))
+}
+
+/// `( 0 0 0 )` or nested like `( ( 1 0 0 ) ( 0 1 0 ) )`
+pub fn vector(parser: &mut Parser) -> Option<CompletedMarker> {
+    let group = parser.start();
+
+    if !parser.expect(TokenKind::LeftParen) {
+        group.abandon(parser);
+        return None;
+    }
+    loop {
+        if parser.eat(TokenKind::RightParen) {
+            break;
+        }
+        if parser.at(TokenKind::LeftParen) {
+            vector(parser);
+        } else if parser.at_any(WORD) {
+            parser.bump();
+        } else {
+            parser.push_error(format!("expect {:?}", TokenKind::RightParen));
+            break;
+        }
+    }
+
+    Some(group.complete(parser, SyntaxKind::Vector))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::MapLexer,
+        syntax::{parse_tokens, SyntaxKind},
+    };
+
+    use super::map_file;
+
+    #[test]
+    fn primitives() {
+        let source = r#"// entity 0
+{
+"classname" "worldspawn"
+// brush 0
+{
+( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) common/caulk 0 0 0 0.5 0.5 0 0 0
+}
+{
+brushDef
+{
+( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) ( ( 0.0078125 0 0 ) ( 0 0.0078125 0 ) ) base_wall/concrete 0 0 0
+}
+}
+{
+patchDef2
+{
+base_trim/border
+( 3 3 0 0 0 )
+(
+( ( 0 0 0 0 0 ) ( 0 64 0 0 1 ) ( 0 128 0 0 2 ) )
+)
+}
+}
+}
+"#;
+        let parse = parse_tokens(source, MapLexer::new(source).collect(), map_file);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+        assert_eq!(parse.errors(), &[]);
+
+        let kinds: Vec<_> = root
+            .descendants()
+            .map(|node| node.kind())
+            .filter(|kind| {
+                matches!(
+                    kind,
+                    SyntaxKind::Entity
+                        | SyntaxKind::Brush
+                        | SyntaxKind::BrushDef
+                        | SyntaxKind::PatchDef
+                        | SyntaxKind::Plane
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            &[
+                SyntaxKind::Entity,
+                SyntaxKind::Brush,
+                SyntaxKind::Plane,
+                SyntaxKind::BrushDef,
+                SyntaxKind::Plane,
+                SyntaxKind::PatchDef,
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery() {
+        // The first brush lacks its `}`
+        let source = "{\n\"classname\" \"worldspawn\"\n{\n( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) a 0 0 0 1 1\n{\n( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) b 0 0 0 1 1\n}\n}\n";
+        let parse = parse_tokens(source, MapLexer::new(source).collect(), map_file);
+        assert_eq!(parse.syntax().resolve_text(parse.resolver()), source);
+        let brushes = parse
+            .syntax()
+            .descendants()
+            .filter(|node| node.kind() == SyntaxKind::Brush)
+            .count();
+        assert_eq!(brushes, 2);
+        let messages: Vec<_> = parse.errors().iter().map(|error| error.message()).collect();
+        assert_eq!(messages, &["expect RightBrace"]);
+    }
+}
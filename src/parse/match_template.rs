@@ -0,0 +1,100 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+use super::weight::expression;
+
+/// Match templates, e.g. `botfiles/match.c`.
+pub fn match_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        match_context(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::MatchFile))
+}
+
+/// `MTCONTEXT_MISC { "help ", TEAMMATE = (MSG_HELP, ST_SOMEONE); … }`
+pub fn match_context(parser: &mut Parser) -> Option<CompletedMarker> {
+    let context = parser.start();
+
+    expression(parser);
+    if !parser.expect(TokenKind::LeftBrace) {
+        context.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        match_template(parser);
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        context.abandon(parser);
+        return None;
+    }
+
+    Some(context.complete(parser, SyntaxKind::MatchContext))
+}
+
+/// `"help ", TEAMMATE = (MSG_HELP, ST_SOMEONE);`
+pub fn match_template(parser: &mut Parser) -> Option<CompletedMarker> {
+    let template = parser.start();
+
+    match_pieces(parser);
+    if !parser.expect(TokenKind::Equals) {
+        template.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftParen) {
+        template.abandon(parser);
+        return None;
+    }
+    expression(parser);
+    if !parser.expect(TokenKind::Comma) {
+        template.abandon(parser);
+        return None;
+    }
+    expression(parser);
+    if !parser.expect(TokenKind::RightParen) {
+        template.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::Semicolon) {
+        template.abandon(parser);
+        return None;
+    }
+
+    Some(template.complete(parser, SyntaxKind::MatchTemplate))
+}
+
+/// `"help ", TEAMMATE`
+pub fn match_pieces(parser: &mut Parser) {
+    loop {
+        if match_piece(parser).is_none() {
+            break;
+        }
+        if !parser.eat(TokenKind::Comma) {
+            break;
+        }
+    }
+}
+
+/// `"where are you" | "where r u"` or a variable like `NETNAME`.
+pub fn match_piece(parser: &mut Parser) -> Option<CompletedMarker> {
+    let piece = parser.start();
+
+    if parser.eat(TokenKind::QuotedString) {
+        while parser.at_text(TokenKind::Punctuation, "|") {
+            parser.bump();
+            if !parser.expect(TokenKind::QuotedString) {
+                break;
+            }
+        }
+    } else if !parser.expect_any(TokenKind::Number | TokenKind::Name) {
+        piece.abandon(parser);
+        return None;
+    }
+
+    Some(piece.complete(parser, SyntaxKind::MatchPiece))
+}
@@ -0,0 +1,84 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+use super::{chat::messages, match_template::match_pieces, weight::expression};
+
+/// Reply chats, e.g. `botfiles/rchat.c`.
+pub fn reply_chat_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        reply_chat(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::ReplyChatFile))
+}
+
+/// `["hello", name, !"bye"] = 5 { "hi ", 0, "!"; … }`
+pub fn reply_chat(parser: &mut Parser) -> Option<CompletedMarker> {
+    let chat = parser.start();
+
+    if !parser.expect(TokenKind::LeftBracket) {
+        chat.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBracket) && !parser.at_end() {
+        if reply_key(parser).is_none() {
+            break;
+        }
+        parser.eat(TokenKind::Comma);
+    }
+    if !parser.expect(TokenKind::RightBracket) {
+        chat.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::Equals) {
+        chat.abandon(parser);
+        return None;
+    }
+    expression(parser);
+    if !messages(parser) {
+        chat.abandon(parser);
+        return None;
+    }
+
+    Some(chat.complete(parser, SyntaxKind::ReplyChat))
+}
+
+/// `"text"`, `&name`, `!female`, `("i hate ", 0)` or `<"sarge", "visor">`, optionally prefixed by `&` or `!`.
+pub fn reply_key(parser: &mut Parser) -> Option<CompletedMarker> {
+    let key = parser.start();
+
+    if parser.at_text(TokenKind::Punctuation, "&") || parser.at_text(TokenKind::Punctuation, "!") {
+        parser.bump();
+    }
+    if parser.eat(TokenKind::LeftParen) {
+        match_pieces(parser);
+        if !parser.expect(TokenKind::RightParen) {
+            key.abandon(parser);
+            return None;
+        }
+    } else if parser.at_text(TokenKind::Punctuation, "<") {
+        parser.bump();
+        loop {
+            if !parser.expect(TokenKind::QuotedString) {
+                break;
+            }
+            if !parser.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        if !parser.expect_text(TokenKind::Punctuation, ">") {
+            key.abandon(parser);
+            return None;
+        }
+    } else if !parser.expect_any(TokenKind::QuotedString | TokenKind::Name) {
+        key.abandon(parser);
+        return None;
+    }
+
+    Some(key.complete(parser, SyntaxKind::ReplyKey))
+}
@@ -0,0 +1,173 @@
+use enumflags2::make_bitflags;
+
+use crate::{
+    lexer::{TokenKind, TokenSet},
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Tokens a shader name or directive word can be.
+const WORD: TokenSet = make_bitflags!(TokenKind::{String | QuotedString});
+
+/// Shader script, e.g. `scripts/base_wall.shader`.
+pub fn shader_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        shader(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::ShaderFile))
+}
+
+/// `textures/base_wall/concrete { directive … { stage } }`
+///
+/// A missing `{` or `}` is reported and the shader is kept, see [`at_shader_start`].
+pub fn shader(parser: &mut Parser) -> Option<CompletedMarker> {
+    let shader = parser.start();
+
+    if parser.at(TokenKind::LeftBrace) {
+        parser.push_error(format!("expect_any {:?}", WORD));
+    } else if !parser.expect_any(WORD) {
+        shader.abandon(parser);
+        return None;
+    }
+    if !parser.eat(TokenKind::LeftBrace) {
+        parser.push_error(format!("expect {:?}", TokenKind::LeftBrace));
+    }
+    loop {
+        if parser.eat(TokenKind::RightBrace) {
+            break;
+        }
+        if parser.at_end() || at_shader_start(parser) {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+            break;
+        }
+        if parser.at(TokenKind::LeftBrace) {
+            stage(parser);
+        } else {
+            directive(parser);
+        }
+    }
+
+    Some(shader.complete(parser, SyntaxKind::Shader))
+}
+
+/// `{ map $lightmap  rgbGen identity }`
+///
+/// Stages can't be nested, so a `{` inside a stage means its `}` is missing.
+pub fn stage(parser: &mut Parser) -> Option<CompletedMarker> {
+    let stage = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        stage.abandon(parser);
+        return None;
+    }
+    loop {
+        if parser.eat(TokenKind::RightBrace) {
+            break;
+        }
+        if parser.at_end() || parser.at(TokenKind::LeftBrace) || at_shader_start(parser) {
+            parser.push_error(format!("expect {:?}", TokenKind::RightBrace));
+            break;
+        }
+        directive(parser);
+    }
+
+    Some(stage.complete(parser, SyntaxKind::Stage))
+}
+
+/// `blendFunc GL_ONE GL_ONE`, a keyword and its arguments up to the end of the line.
+pub fn directive(parser: &mut Parser) -> Option<CompletedMarker> {
+    let directive = parser.start();
+
+    if !parser.expect_any(WORD) {
+        directive.abandon(parser);
+        return None;
+    }
+    while !parser.at_end()
+        && !parser.at_line_start()
+        && !parser.at(TokenKind::LeftBrace)
+        && !parser.at(TokenKind::RightBrace)
+    {
+        parser.bump();
+    }
+
+    Some(directive.complete(parser, SyntaxKind::Directive))
+}
+
+/// Next tokens look like the start of another shader: a path like `textures/x` at the start of a
+/// line followed by `{`.
+///
+/// Directive keywords never contain `/`, so this lets a shader with a missing `}` end before the
+/// next one instead of swallowing every shader after it.
+fn at_shader_start(parser: &mut Parser) -> bool {
+    parser.at_any(WORD)
+        && parser.peek_text().is_some_and(|text| text.contains('/'))
+        && parser.at_line_start()
+        && parser.nth_at(1, TokenKind::LeftBrace)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostic::Diagnostic,
+        lexer::Lexer,
+        syntax::{child_nodes, parse_tokens, SyntaxKind},
+    };
+
+    use super::shader_file;
+
+    fn shaders(source: &str) -> (Vec<(String, usize)>, Vec<Diagnostic>) {
+        let parse = parse_tokens(source, Lexer::new(source).collect(), shader_file);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+
+        let shaders = root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Shader))
+            .map(|shader| {
+                let name = shader
+                    .first_token()
+                    .map(|token| token.resolve_text(parse.resolver()).to_owned())
+                    .unwrap_or_default();
+                (name, child_nodes(shader, SyntaxKind::Stage).count())
+            })
+            .collect();
+        (shaders, parse.errors().to_vec())
+    }
+
+    #[test]
+    fn shader() {
+        let source = "// walls\ntextures/base_wall/a\n{\n\tsurfaceparm nomarks // no decals\n\tq3map_lightimage textures/a.tga\n\t{\n\t\tmap $lightmap\n\t\trgbGen identity\n\t}\n\t{ map textures/a.tga\n\t\tblendFunc GL_DST_COLOR GL_ZERO }\n}\ntextures/b { }\n";
+        let (shaders, errors) = shaders(source);
+        assert_eq!(errors, &[]);
+        assert_eq!(
+            shaders,
+            &[
+                ("textures/base_wall/a".to_owned(), 2),
+                ("textures/b".to_owned(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery() {
+        // The first stage and shader lack their `}`, the third shader its `{`
+        let source = "textures/a\n{\n\t{\n\t\tmap a.tga\n\t{\n\t\tmap b.tga\n\t}\ntextures/b\n{\n\tcull none\n}\ntextures/c\n\tcull back\n}\n";
+        let (shaders, errors) = shaders(source);
+        assert_eq!(
+            shaders,
+            &[
+                ("textures/a".to_owned(), 2),
+                ("textures/b".to_owned(), 0),
+                ("textures/c".to_owned(), 0)
+            ]
+        );
+        let messages: Vec<_> = errors.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &["expect RightBrace", "expect RightBrace", "expect LeftBrace"]
+        );
+    }
+}
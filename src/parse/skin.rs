@@ -0,0 +1,92 @@
+use enumflags2::make_bitflags;
+
+use crate::{
+    lexer::{TokenKind, TokenSet},
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Tokens a surface name or shader path can be.
+const WORD: TokenSet = make_bitflags!(TokenKind::{String | QuotedString});
+
+/// Player model skin, e.g. `models/players/sarge/head_default.skin`.
+pub fn skin_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        skin_surface(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::SkinFile))
+}
+
+/// `h_head,models/players/sarge/cigar.tga` or `tag_head,`
+///
+/// The shader path has to be on the same line, the engine would take the next surface name.
+pub fn skin_surface(parser: &mut Parser) -> Option<CompletedMarker> {
+    let surface = parser.start();
+
+    let name = parser.start();
+    if !parser.expect_any(WORD) {
+        name.abandon(parser);
+        surface.abandon(parser);
+        return None;
+    }
+    name.complete(parser, SyntaxKind::Key);
+
+    if !parser.at_line_start() {
+        parser.eat(TokenKind::Comma);
+    }
+    if !parser.at_line_start() && parser.at_any(WORD) {
+        let path = parser.start();
+        parser.bump();
+        path.complete(parser, SyntaxKind::Value);
+    }
+
+    Some(surface.complete(parser, SyntaxKind::SkinSurface))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::SkinLexer,
+        syntax::{child_nodes, parse_tokens, SyntaxKind},
+    };
+
+    use super::skin_file;
+
+    #[test]
+    fn skin() {
+        let source = "tag_head,\nh_head,models/players/sarge/cigar.tga \r\n// comment\nh_visor,\nh_eyes \"a b.tga\"\n,x\n";
+        let parse = parse_tokens(source, SkinLexer::new(source).collect(), skin_file);
+        let root = parse.syntax();
+        assert_eq!(root.resolve_text(parse.resolver()), source);
+
+        let surfaces: Vec<_> = root
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::SkinSurface))
+            .map(|surface| {
+                surface
+                    .children()
+                    .map(|node| node.resolve_text(parse.resolver()).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(
+            surfaces,
+            &[
+                vec!["tag_head"],
+                vec!["h_head", "models/players/sarge/cigar.tga"],
+                vec!["h_visor"],
+                vec!["h_eyes", "\"a b.tga\""],
+                vec!["x"],
+            ]
+        );
+        let errors: Vec<_> = parse
+            .errors()
+            .iter()
+            .map(|error| error.span().start())
+            .collect();
+        assert_eq!(errors, &[source.find(",x").unwrap() as u32]);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Structure definitions, e.g. `botfiles/weapons.c` or `botfiles/items.c`.
+pub fn structure_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        structure(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::StructureFile))
+}
+
+/// `weaponinfo { … }` or `iteminfo "classname" { … }`
+pub fn structure(parser: &mut Parser) -> Option<CompletedMarker> {
+    let structure = parser.start();
+
+    if !parser.expect(TokenKind::Name) {
+        structure.abandon(parser);
+        return None;
+    }
+    parser.eat(TokenKind::QuotedString);
+    if !parser.expect(TokenKind::LeftBrace) {
+        structure.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        field(parser);
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        structure.abandon(parser);
+        return None;
+    }
+
+    Some(structure.complete(parser, SyntaxKind::Structure))
+}
+
+/// `name "Rocket Launcher"`, `speed 900`, `offset {0, 0, 0}`
+pub fn field(parser: &mut Parser) -> Option<CompletedMarker> {
+    let field = parser.start();
+
+    if !parser.expect(TokenKind::Name) {
+        field.abandon(parser);
+        return None;
+    }
+    if field_value(parser).is_none() {
+        field.abandon(parser);
+        return None;
+    }
+
+    Some(field.complete(parser, SyntaxKind::Field))
+}
+
+fn field_value(parser: &mut Parser) -> Option<CompletedMarker> {
+    if parser.at(TokenKind::LeftBrace) {
+        array(parser)
+    } else if parser.at(TokenKind::QuotedString) {
+        let value = parser.start();
+        parser.bump();
+        Some(value.complete(parser, SyntaxKind::Value))
+    } else {
+        atom(parser)
+    }
+}
+
+/// `{0, 0, 0}`
+pub fn array(parser: &mut Parser) -> Option<CompletedMarker> {
+    let array = parser.start();
+
+    if !parser.expect(TokenKind::LeftBrace) {
+        array.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        if field_value(parser).is_none() {
+            break;
+        }
+        if !parser.eat(TokenKind::Comma) {
+            break;
+        }
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        array.abandon(parser);
+        return None;
+    }
+
+    Some(array.complete(parser, SyntaxKind::Array))
+}
+
+/// Single number, possibly negated, defined or computed: `-1`, `INVENTORY_ROCKETS`, `$evalint(…)`.
+pub fn atom(parser: &mut Parser) -> Option<CompletedMarker> {
+    let atom = parser.start();
+
+    while parser.at_text(TokenKind::Punctuation, "-") || parser.at_text(TokenKind::Punctuation, "+")
+    {
+        parser.bump();
+    }
+    if parser.at_text(TokenKind::Punctuation, "$") {
+        parser.bump();
+        if !parser.expect(TokenKind::Name) {
+            atom.abandon(parser);
+            return None;
+        }
+        if !parenthesized(parser) {
+            atom.abandon(parser);
+            return None;
+        }
+    } else if parser.at(TokenKind::LeftParen) {
+        if !parenthesized(parser) {
+            atom.abandon(parser);
+            return None;
+        }
+    } else if !parser.expect_any(TokenKind::Number | TokenKind::Name) {
+        atom.abandon(parser);
+        return None;
+    }
+
+    Some(atom.complete(parser, SyntaxKind::Expression))
+}
+
+fn parenthesized(parser: &mut Parser) -> bool {
+    if !parser.expect(TokenKind::LeftParen) {
+        return false;
+    }
+    let mut depth = 1usize;
+    while !parser.at_end() && !parser.at(TokenKind::LeftBrace) && !parser.at(TokenKind::RightBrace)
+    {
+        if parser.at(TokenKind::LeftParen) {
+            depth += 1;
+        } else if parser.at(TokenKind::RightParen) {
+            depth -= 1;
+        }
+        parser.bump();
+        if depth == 0 {
+            return true;
+        }
+    }
+    parser.expect(TokenKind::RightParen)
+}
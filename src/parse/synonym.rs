@@ -0,0 +1,89 @@
+use crate::{
+    lexer::TokenKind,
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+use super::weight::expression;
+
+/// Synonyms, e.g. `botfiles/syn.c`.
+pub fn synonym_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        synonym_context(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::SynonymFile))
+}
+
+/// `CONTEXT_NEARBYITEM { [("Rocket Launcher", 1), ("RL", 0.5)] … }`
+pub fn synonym_context(parser: &mut Parser) -> Option<CompletedMarker> {
+    let context = parser.start();
+
+    expression(parser);
+    if !parser.expect(TokenKind::LeftBrace) {
+        context.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        if synonym_group(parser).is_none() {
+            break;
+        }
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        context.abandon(parser);
+        return None;
+    }
+
+    Some(context.complete(parser, SyntaxKind::SynonymContext))
+}
+
+/// `[("Rocket Launcher", 1), ("RL", 0.5)]`
+pub fn synonym_group(parser: &mut Parser) -> Option<CompletedMarker> {
+    let group = parser.start();
+
+    if !parser.expect(TokenKind::LeftBracket) {
+        group.abandon(parser);
+        return None;
+    }
+    loop {
+        if synonym(parser).is_none() {
+            break;
+        }
+        if !parser.eat(TokenKind::Comma) {
+            break;
+        }
+    }
+    if !parser.expect(TokenKind::RightBracket) {
+        group.abandon(parser);
+        return None;
+    }
+
+    Some(group.complete(parser, SyntaxKind::SynonymGroup))
+}
+
+/// `("Rocket Launcher", 1)`
+pub fn synonym(parser: &mut Parser) -> Option<CompletedMarker> {
+    let synonym = parser.start();
+
+    if !parser.expect(TokenKind::LeftParen) {
+        synonym.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::QuotedString) {
+        synonym.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::Comma) {
+        synonym.abandon(parser);
+        return None;
+    }
+    expression(parser);
+    if !parser.expect(TokenKind::RightParen) {
+        synonym.abandon(parser);
+        return None;
+    }
+
+    Some(synonym.complete(parser, SyntaxKind::Synonym))
+}
@@ -0,0 +1,186 @@
+use enumflags2::make_bitflags;
+
+use crate::{
+    lexer::{TokenKind, TokenSet},
+    parser::{CompletedMarker, Parser},
+    syntax::SyntaxKind,
+};
+
+/// Tokens that end an expression.
+const EXPRESSION_END: TokenSet = make_bitflags!(TokenKind::{Semicolon | LeftBrace | RightBrace});
+/// Tokens that end an expression outside of parentheses.
+const ARGUMENT_END: TokenSet = make_bitflags!(TokenKind::{Comma | Colon | RightParen});
+
+/// Fuzzy weight config, e.g. `botfiles/bots/sarge_w.c` or `botfiles/fw_items.c`.
+pub fn weight_file(parser: &mut Parser) -> Option<CompletedMarker> {
+    let file = parser.start();
+
+    while !parser.at_end() {
+        weight(parser);
+    }
+
+    Some(file.complete(parser, SyntaxKind::WeightFile))
+}
+
+/// `weight "name" { switch … }` or `weight "name" { return …; }`
+pub fn weight(parser: &mut Parser) -> Option<CompletedMarker> {
+    let weight = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "weight") {
+        weight.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::QuotedString) {
+        weight.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftBrace) {
+        weight.abandon(parser);
+        return None;
+    }
+    weight_body(parser);
+    if !parser.expect(TokenKind::RightBrace) {
+        weight.abandon(parser);
+        return None;
+    }
+
+    Some(weight.complete(parser, SyntaxKind::Weight))
+}
+
+fn weight_body(parser: &mut Parser) -> Option<CompletedMarker> {
+    if parser.at_text(TokenKind::Name, "switch") {
+        switch(parser)
+    } else {
+        weight_return(parser)
+    }
+}
+
+/// `switch (INVENTORY_X) { case …: … default: … }`
+pub fn switch(parser: &mut Parser) -> Option<CompletedMarker> {
+    let switch = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "switch") {
+        switch.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftParen) {
+        switch.abandon(parser);
+        return None;
+    }
+    expression(parser);
+    if !parser.expect(TokenKind::RightParen) {
+        switch.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftBrace) {
+        switch.abandon(parser);
+        return None;
+    }
+    while !parser.at(TokenKind::RightBrace) && !parser.at_end() {
+        case(parser);
+    }
+    if !parser.expect(TokenKind::RightBrace) {
+        switch.abandon(parser);
+        return None;
+    }
+
+    Some(switch.complete(parser, SyntaxKind::Switch))
+}
+
+/// `case 1: return …;`, `default: { switch … }`
+pub fn case(parser: &mut Parser) -> Option<CompletedMarker> {
+    let case = parser.start();
+
+    if parser.at_text(TokenKind::Name, "default") {
+        parser.bump();
+    } else {
+        if !parser.expect_text(TokenKind::Name, "case") {
+            case.abandon(parser);
+            return None;
+        }
+        expression(parser);
+    }
+    if !parser.expect(TokenKind::Colon) {
+        case.abandon(parser);
+        return None;
+    }
+    if parser.eat(TokenKind::LeftBrace) {
+        switch(parser);
+        if !parser.expect(TokenKind::RightBrace) {
+            case.abandon(parser);
+            return None;
+        }
+    } else {
+        weight_return(parser);
+    }
+
+    Some(case.complete(parser, SyntaxKind::Case))
+}
+
+/// `return 100;`, `return balance(W_X, 80, 120);`
+pub fn weight_return(parser: &mut Parser) -> Option<CompletedMarker> {
+    let weight_return = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "return") {
+        weight_return.abandon(parser);
+        return None;
+    }
+    if parser.at_text(TokenKind::Name, "balance") {
+        balance(parser);
+    } else {
+        expression(parser);
+    }
+    if !parser.expect(TokenKind::Semicolon) {
+        weight_return.abandon(parser);
+        return None;
+    }
+
+    Some(weight_return.complete(parser, SyntaxKind::Return))
+}
+
+/// `balance(weight, min, max)`
+pub fn balance(parser: &mut Parser) -> Option<CompletedMarker> {
+    let balance = parser.start();
+
+    if !parser.expect_text(TokenKind::Name, "balance") {
+        balance.abandon(parser);
+        return None;
+    }
+    if !parser.expect(TokenKind::LeftParen) {
+        balance.abandon(parser);
+        return None;
+    }
+    for i in 0..3 {
+        if i > 0 && !parser.expect(TokenKind::Comma) {
+            balance.abandon(parser);
+            return None;
+        }
+        expression(parser);
+    }
+    if !parser.expect(TokenKind::RightParen) {
+        balance.abandon(parser);
+        return None;
+    }
+
+    Some(balance.complete(parser, SyntaxKind::Balance))
+}
+
+/// Constant expression, evaluated with the precompiler's defines.
+pub fn expression(parser: &mut Parser) -> Option<CompletedMarker> {
+    let expression = parser.start();
+
+    let mut depth = 0usize;
+    while !parser.at_end() {
+        if parser.at_any(EXPRESSION_END) || (depth == 0 && parser.at_any(ARGUMENT_END)) {
+            break;
+        }
+        if parser.at(TokenKind::LeftParen) {
+            depth += 1;
+        } else if parser.at(TokenKind::RightParen) {
+            depth -= 1;
+        }
+        parser.bump();
+    }
+
+    Some(expression.complete(parser, SyntaxKind::Expression))
+}
@@ -1,8 +1,9 @@
 use std::num::NonZeroUsize;
 
 use crate::{
+    diagnostic::Diagnostic,
     event::Event,
-    lexer::{Lexer, Token, TokenKind, TokenSet},
+    lexer::{Token, TokenKind, TokenSet},
     source::Source,
     syntax::SyntaxKind,
 };
@@ -11,7 +12,7 @@ use crate::{
 pub struct Parser<'src, 'token> {
     source: Source<'src, 'token>,
     events: Vec<Event>,
-    errors: Vec<String>,
+    errors: Vec<Diagnostic>,
 }
 
 impl<'src, 'token> Parser<'src, 'token> {
@@ -34,6 +35,41 @@ impl<'src, 'token> Parser<'src, 'token> {
         self.source.try_peek_kind() == Some(kind)
     }
 
+    pub fn at_any(&mut self, kind: TokenSet) -> bool {
+        self.source
+            .try_peek_kind()
+            .is_some_and(|peek| kind.intersects(peek))
+    }
+
+    /// Next token is of the given kind and has exactly the given text, e.g. a keyword.
+    pub fn at_text(&mut self, kind: TokenKind, text: &str) -> bool {
+        self.source
+            .try_peek_nth(0)
+            .is_some_and(|token| token.kind() == kind && token.text() == text)
+    }
+
+    /// `n`th next token is of the given kind.
+    pub fn nth_at(&mut self, n: usize, kind: TokenKind) -> bool {
+        self.source
+            .try_peek_nth(n)
+            .is_some_and(|token| token.kind() == kind)
+    }
+
+    /// Next token is the first one on its line, for line based formats.
+    pub fn at_line_start(&mut self) -> bool {
+        self.source.at_line_start()
+    }
+
+    /// Text of the next token.
+    pub fn peek_text(&mut self) -> Option<&'src str> {
+        self.source.try_peek_nth(0).map(|token| token.text())
+    }
+
+    /// Text of the `n`th next token.
+    pub fn nth_text(&mut self, n: usize) -> Option<&'src str> {
+        self.source.try_peek_nth(n).map(|token| token.text())
+    }
+
     pub fn bump(&mut self) {
         if let Some(token) = self.source.next() {
             self.push_event(Event::Token {
@@ -62,6 +98,17 @@ impl<'src, 'token> Parser<'src, 'token> {
         false
     }
 
+    pub fn expect_text(&mut self, kind: TokenKind, text: &str) -> bool {
+        if self.at_text(kind, text) {
+            self.bump();
+            return true;
+        }
+
+        let error = format!("expect {:?} {:?}", kind, text);
+        self.error(error);
+        false
+    }
+
     pub fn expect_any(&mut self, kind: TokenSet) -> bool {
         if let Some(peek) = self.source.try_peek_kind() {
             //println!("expect_any peeked: {:?}", peek);
@@ -80,16 +127,19 @@ impl<'src, 'token> Parser<'src, 'token> {
     }
 
     pub fn error(&mut self, error: String) {
+        let span = self.source.peek_span();
         if !self.at_end() {
             let marker = self.start();
             self.bump();
             marker.complete(self, SyntaxKind::Error);
         }
-        self.push_error(error);
+        self.errors.push(Diagnostic::error(error, span));
     }
 
+    /// Reports an error at the next token without consuming it.
     pub fn push_error(&mut self, error: String) {
-        self.errors.push(error);
+        let span = self.source.peek_span();
+        self.errors.push(Diagnostic::error(error, span));
     }
 
     pub fn push_event(&mut self, event: Event) {
@@ -99,7 +149,7 @@ impl<'src, 'token> Parser<'src, 'token> {
     pub fn parse(
         mut self,
         parse: impl FnOnce(&mut Self) -> Option<CompletedMarker>,
-    ) -> (Vec<Event>, Vec<String>) {
+    ) -> (Vec<Event>, Vec<Diagnostic>) {
         let root = self.start();
 
         parse(&mut self);
@@ -177,9 +227,9 @@ mod tests {
 
     use crate::span::RawSpan;
 
-    //#[test]
+    #[test]
     fn test_parser() {
-        let tokens = vec![
+        let tokens = [
             Token::new(TokenKind::LeftBrace, RawSpan::new(0, 1), "{"),
             Token::new(TokenKind::Newline, RawSpan::new(1, 2), "\n"),
             Token::new(TokenKind::String, RawSpan::new(2, 5), "foo"),
@@ -189,7 +239,14 @@ mod tests {
         ];
         let mut parser = Parser::new(&tokens[..]);
         crate::parse::arenas(&mut parser);
-        dbg!(parser);
-        assert!(false);
+
+        assert!(parser.errors.is_empty());
+        assert_eq!(
+            parser.events.first(),
+            Some(&Event::Enter {
+                kind: SyntaxKind::Arenas,
+                preceded_by: None
+            })
+        );
     }
 }
@@ -0,0 +1,349 @@
+//! Paks (`*.pk3`), zip archives of stored or deflated game files.
+//!
+//! Only the central directory is read up front, files are read from the archive on demand. Spans
+//! are byte ranges in the archive.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    binary::{index, int, short, span},
+    diagnostic::Diagnostic,
+    span::RawSpan,
+};
+
+/// `PK\5\6`
+const END_SIGNATURE: i32 = 0x0605_4b50;
+/// `PK\1\2`
+const ENTRY_SIGNATURE: i32 = 0x0201_4b50;
+/// `PK\3\4`
+const LOCAL_SIGNATURE: i32 = 0x0403_4b50;
+const END_SIZE: usize = 22;
+const ENTRY_SIZE: usize = 46;
+const LOCAL_SIZE: usize = 30;
+/// The end of central directory is followed by a comment of up to 65535 bytes.
+const MAX_END_SEARCH: usize = END_SIZE + 0xffff;
+
+/// Stored without compression.
+pub const METHOD_STORED: u16 = 0;
+/// Deflated, the only compression the engine reads.
+pub const METHOD_DEFLATED: u16 = 8;
+
+/// File in the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    name: String,
+    method: u16,
+    compressed_size: usize,
+    size: usize,
+    offset: usize,
+    span: RawSpan,
+}
+
+impl Entry {
+    /// Get the path in the archive, e.g. `scripts/arenas.txt`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the compression method, [`METHOD_STORED`] or [`METHOD_DEFLATED`] for readable files.
+    pub const fn method(&self) -> u16 {
+        self.method
+    }
+
+    /// Get the size of the file once read.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the span of the central directory record.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Directory of a read and validated archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pk3 {
+    entries: Vec<Entry>,
+    /// Lowercase names to entries, the last of duplicate names winning.
+    names: HashMap<String, usize>,
+}
+
+impl Pk3 {
+    /// Reads the directory of an archive in memory, nothing but the diagnostics if it has none.
+    pub fn parse(data: &[u8]) -> (Self, Vec<Diagnostic>) {
+        Self::read_directory(io::Cursor::new(data)).unwrap_or_else(|error| {
+            (
+                Self::default(),
+                vec![Diagnostic::error(error.to_string(), span(0, data.len()))],
+            )
+        })
+    }
+
+    /// Reads the directory of the archive at `path`, reading no more of the file than needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<(Self, Vec<Diagnostic>)> {
+        Self::read_directory(File::open(path)?)
+    }
+
+    fn read_directory(mut archive: impl Read + Seek) -> io::Result<(Self, Vec<Diagnostic>)> {
+        let mut diagnostics = Vec::new();
+        let mut pk3 = Self::default();
+
+        let length = archive.seek(SeekFrom::End(0))? as usize;
+        let tail_offset = length.saturating_sub(MAX_END_SEARCH);
+        let tail = read_at(&mut archive, tail_offset, length - tail_offset)?;
+        let end = match (0..tail.len().saturating_sub(END_SIZE - 1))
+            .rev()
+            .find(|offset| int(&tail, *offset) == END_SIGNATURE)
+        {
+            Some(end) => end,
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    "no end of central directory, not a zip archive",
+                    span(0, length),
+                ));
+                return Ok((pk3, diagnostics));
+            }
+        };
+        let end_span = span(tail_offset + end, tail_offset + end + END_SIZE);
+        let count = short(&tail, end + 10) as u16 as usize;
+        let directory_size = index(&tail, end + 12);
+        let directory_offset = index(&tail, end + 16);
+        if directory_offset.saturating_add(directory_size) > tail_offset + end {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "central directory {}..{} is outside of the file's {} bytes",
+                    directory_offset,
+                    directory_offset.saturating_add(directory_size),
+                    tail_offset + end
+                ),
+                end_span,
+            ));
+            return Ok((pk3, diagnostics));
+        }
+        let directory = read_at(&mut archive, directory_offset, directory_size)?;
+
+        let mut offset = 0;
+        for _ in 0..count {
+            let start = directory_offset + offset;
+            if offset + ENTRY_SIZE > directory.len() || int(&directory, offset) != ENTRY_SIGNATURE {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "expect {} entries in the central directory, found {}",
+                        count,
+                        pk3.entries.len()
+                    ),
+                    span(start, directory_offset + directory.len()),
+                ));
+                break;
+            }
+            let record = &directory[offset..];
+            let field = |at| short(record, at) as u16 as usize;
+            let name_length = field(28);
+            let size = ENTRY_SIZE + name_length + field(30) + field(32);
+            let name = match record.get(ENTRY_SIZE..ENTRY_SIZE + name_length) {
+                Some(name) => String::from_utf8_lossy(name).replace('\\', "/"),
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        "entry name is truncated",
+                        span(start, directory_offset + directory.len()),
+                    ));
+                    break;
+                }
+            };
+            let entry = Entry {
+                name,
+                method: field(10) as u16,
+                compressed_size: index(record, 20),
+                size: index(record, 24),
+                offset: index(record, 42),
+                span: span(start, start + size.min(record.len())),
+            };
+            offset += size;
+
+            if entry.name.ends_with('/') {
+                continue;
+            }
+            if field(8) & 1 != 0 {
+                diagnostics.push(Diagnostic::warning(
+                    format!("'{}' is encrypted", entry.name),
+                    entry.span,
+                ));
+                continue;
+            }
+            if entry.method != METHOD_STORED && entry.method != METHOD_DEFLATED {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "'{}' uses compression method {}, only stored and deflated files are read",
+                        entry.name, entry.method
+                    ),
+                    entry.span,
+                ));
+            }
+            pk3.names
+                .insert(entry.name.to_lowercase(), pk3.entries.len());
+            pk3.entries.push(entry);
+        }
+
+        Ok((pk3, diagnostics))
+    }
+
+    /// Get the files in archive order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Get the file at `path`, ignoring case like the engine.
+    pub fn entry(&self, path: &str) -> Option<&Entry> {
+        self.names
+            .get(&path.to_lowercase())
+            .map(|index| &self.entries[*index])
+    }
+
+    /// Reads the file from the `archive` this directory was read from.
+    pub fn read(&self, mut archive: impl Read + Seek, entry: &Entry) -> io::Result<Vec<u8>> {
+        let header = read_at(&mut archive, entry.offset, LOCAL_SIZE)?;
+        if int(&header, 0) != LOCAL_SIGNATURE {
+            return Err(invalid(format!(
+                "expect local header of '{}' at {}",
+                entry.name, entry.offset
+            )));
+        }
+        let data_offset = entry.offset
+            + LOCAL_SIZE
+            + short(&header, 26) as u16 as usize
+            + short(&header, 28) as u16 as usize;
+        let data = read_at(&mut archive, data_offset, entry.compressed_size)?;
+        let data = match entry.method {
+            METHOD_STORED => data,
+            METHOD_DEFLATED => miniz_oxide::inflate::decompress_to_vec_with_limit(
+                &data, entry.size,
+            )
+            .map_err(|error| invalid(format!("can't inflate '{}': {}", entry.name, error)))?,
+            method => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("compression method {} of '{}'", method, entry.name),
+                ))
+            }
+        };
+        if data.len() != entry.size {
+            return Err(invalid(format!(
+                "'{}' has {} bytes, expect {}",
+                entry.name,
+                data.len(),
+                entry.size
+            )));
+        }
+        Ok(data)
+    }
+}
+
+fn read_at(mut archive: impl Read + Seek, offset: usize, length: usize) -> io::Result<Vec<u8>> {
+    // Lengths come from the archive itself, so check them before allocating
+    let size = archive.seek(SeekFrom::End(0))?;
+    let end = offset as u64 + length as u64;
+    if end > size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("{}..{} is outside of the archive", offset, end),
+        ));
+    }
+    archive.seek(SeekFrom::Start(offset as u64))?;
+    let mut data = vec![0; length];
+    archive.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes zip archives, for tests.
+#[cfg(test)]
+pub(crate) fn write(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data, deflate) in files {
+        let (method, compressed) = if *deflate {
+            (
+                METHOD_DEFLATED,
+                miniz_oxide::deflate::compress_to_vec(data, 6),
+            )
+        } else {
+            (METHOD_STORED, data.to_vec())
+        };
+        let sizes = [compressed.len() as u32, data.len() as u32];
+        let offset = archive.len() as u32;
+
+        archive.extend(LOCAL_SIGNATURE.to_le_bytes());
+        archive.extend([20, 0, 0, 0]);
+        archive.extend(method.to_le_bytes());
+        archive.extend([0; 8]);
+        archive.extend(sizes.iter().flat_map(|size| size.to_le_bytes()));
+        archive.extend((name.len() as u16).to_le_bytes());
+        archive.extend([0; 2]);
+        archive.extend(name.as_bytes());
+        archive.extend(&compressed);
+
+        directory.extend(ENTRY_SIGNATURE.to_le_bytes());
+        directory.extend([20, 0, 20, 0, 0, 0]);
+        directory.extend(method.to_le_bytes());
+        directory.extend([0; 8]);
+        directory.extend(sizes.iter().flat_map(|size| size.to_le_bytes()));
+        directory.extend((name.len() as u16).to_le_bytes());
+        directory.extend([0; 12]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    archive.extend(&directory);
+    archive.extend(END_SIGNATURE.to_le_bytes());
+    archive.extend([0; 4]);
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend([0; 2]);
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let arenas = b"{\nmap \"q3dm17\"\nbots \"sarge\"\n}\n".repeat(8);
+        let data = write(&[
+            ("scripts/arenas.txt", &arenas, true),
+            ("maps/q3dm17.bsp", b"IBSP", false),
+        ]);
+        let (pk3, diagnostics) = Pk3::parse(&data);
+        assert_eq!(diagnostics, &[]);
+        let names: Vec<_> = pk3.entries().iter().map(Entry::name).collect();
+        assert_eq!(names, &["scripts/arenas.txt", "maps/q3dm17.bsp"]);
+
+        let entry = pk3.entry("Scripts/Arenas.TXT").unwrap();
+        assert_eq!(entry.method(), METHOD_DEFLATED);
+        assert_eq!(pk3.read(io::Cursor::new(&data), entry).unwrap(), arenas);
+        let entry = pk3.entry("maps/q3dm17.bsp").unwrap();
+        assert_eq!(pk3.read(io::Cursor::new(&data), entry).unwrap(), b"IBSP");
+
+        let mut huge = entry.clone();
+        huge.compressed_size = u32::MAX as usize;
+        let error = pk3.read(io::Cursor::new(&data), &huge).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let (_, diagnostics) = Pk3::parse(&data[..data.len() - 1]);
+        assert_eq!(
+            diagnostics[0].message(),
+            "no end of central directory, not a zip archive"
+        );
+    }
+}
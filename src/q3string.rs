@@ -0,0 +1,263 @@
+//! Text with `^1`-style colour escapes, e.g. `longname`, bot names and chat.
+//!
+//! An escape is `^` followed by any character but `^` or the end, like `Q_IsColorString`, which
+//! picks the colour with `ColorIndex`, so `^a` is as valid as `^1`.
+
+use std::{fmt, ops::Deref};
+
+/// `Q_COLOR_ESCAPE`
+pub const COLOR_ESCAPE: char = '^';
+
+/// Colour of `g_color_table`, white unless an escape sets another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Color {
+    /// `^0`
+    Black,
+    /// `^1`
+    Red,
+    /// `^2`
+    Green,
+    /// `^3`
+    Yellow,
+    /// `^4`
+    Blue,
+    /// `^5`
+    Cyan,
+    /// `^6`
+    Magenta,
+    /// `^7`
+    #[default]
+    White,
+}
+
+impl Color {
+    const ALL: [Self; 8] = [
+        Self::Black,
+        Self::Red,
+        Self::Green,
+        Self::Yellow,
+        Self::Blue,
+        Self::Cyan,
+        Self::Magenta,
+        Self::White,
+    ];
+
+    /// Get the colour of the character after the escape like `ColorIndex`, `(c - '0') & 7`.
+    pub fn from_code(code: char) -> Self {
+        Self::ALL[(code as u32).wrapping_sub('0' as u32) as usize & 7]
+    }
+
+    /// Get the SGR foreground code of the terminal colour, 30 to 37.
+    pub fn ansi(self) -> u8 {
+        30 + self as u8
+    }
+
+    /// Get the CSS colour, e.g. `#ff0000`.
+    pub const fn html(self) -> &'static str {
+        match self {
+            Self::Black => "#000000",
+            Self::Red => "#ff0000",
+            Self::Green => "#00ff00",
+            Self::Yellow => "#ffff00",
+            Self::Blue => "#0000ff",
+            Self::Cyan => "#00ffff",
+            Self::Magenta => "#ff00ff",
+            Self::White => "#ffffff",
+        }
+    }
+}
+
+/// Run of text printed in one colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<'a> {
+    color: Color,
+    text: &'a str,
+}
+
+impl<'a> Segment<'a> {
+    /// Get the colour.
+    pub const fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Get the printed text, without escapes.
+    pub const fn text(&self) -> &'a str {
+        self.text
+    }
+}
+
+/// Text with colour escapes, dereferencing to the raw text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q3String(String);
+
+impl Q3String {
+    /// Creates a new string from raw text with escapes.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self(text.into())
+    }
+
+    /// Get the raw text with escapes.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the text without escapes like `Q_CleanStr`, e.g. to sort names.
+    pub fn stripped(&self) -> String {
+        self.segments().map(|segment| segment.text).collect()
+    }
+
+    /// Get the number of printed characters like `Q_PrintStrlen`.
+    pub fn printable_len(&self) -> usize {
+        self.segments()
+            .map(|segment| segment.text.chars().count())
+            .sum()
+    }
+
+    /// Iterates over the non-empty runs of text in their colour.
+    pub fn segments(&self) -> impl Iterator<Item = Segment<'_>> {
+        let text = self.as_str();
+        let mut chars = text.char_indices().peekable();
+        let mut color = Color::default();
+        let mut start = 0;
+        std::iter::from_fn(move || loop {
+            let (index, c) = match chars.next() {
+                Some(next) => next,
+                None => {
+                    let segment = Segment {
+                        color,
+                        text: &text[start..],
+                    };
+                    start = text.len();
+                    return Some(segment).filter(|segment| !segment.text.is_empty());
+                }
+            };
+            let code = match chars.peek() {
+                Some(&(_, code)) if c == COLOR_ESCAPE && code != COLOR_ESCAPE => code,
+                _ => continue,
+            };
+            chars.next();
+            let segment = Segment {
+                color,
+                text: &text[start..index],
+            };
+            color = Color::from_code(code);
+            start = index + c.len_utf8() + code.len_utf8();
+            if !segment.text.is_empty() {
+                return Some(segment);
+            }
+        })
+    }
+
+    /// Ends with a `^` starting no escape, which the engine prints as is.
+    pub fn has_dangling_escape(&self) -> bool {
+        self.0.ends_with(COLOR_ESCAPE)
+    }
+
+    /// Renders with terminal colours, resetting them at the end.
+    pub fn to_ansi(&self) -> String {
+        let mut ansi = String::new();
+        for segment in self.segments() {
+            ansi.push_str(&format!("\x1b[{}m{}", segment.color.ansi(), segment.text));
+        }
+        if !ansi.is_empty() {
+            ansi.push_str("\x1b[0m");
+        }
+        ansi
+    }
+
+    /// Renders as HTML `<span>`s with escaped text.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        for segment in self.segments() {
+            html.push_str(&format!("<span style=\"color:{}\">", segment.color.html()));
+            for c in segment.text.chars() {
+                match c {
+                    '&' => html.push_str("&amp;"),
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    '"' => html.push_str("&quot;"),
+                    c => html.push(c),
+                }
+            }
+            html.push_str("</span>");
+        }
+        html
+    }
+}
+
+impl Deref for Q3String {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Q3String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Q3String {
+    fn from(text: String) -> Self {
+        Self(text)
+    }
+}
+
+impl From<&str> for Q3String {
+    fn from(text: &str) -> Self {
+        Self(text.to_owned())
+    }
+}
+
+impl PartialEq<str> for Q3String {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Q3String {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        let text = Q3String::new("^1Red^^7^2Green ^a^");
+        let segments: Vec<_> = text
+            .segments()
+            .map(|segment| (segment.color(), segment.text()))
+            .collect();
+        assert_eq!(
+            segments,
+            &[
+                (Color::Red, "Red^"),
+                (Color::Green, "Green "),
+                (Color::Red, "^")
+            ]
+        );
+        assert_eq!(text.stripped(), "Red^Green ^");
+        assert_eq!(text.printable_len(), 11);
+        assert!(text.has_dangling_escape());
+        assert!(!Q3String::new("^^7").has_dangling_escape());
+        assert_eq!(Q3String::new("^7").segments().count(), 0);
+    }
+
+    #[test]
+    fn render() {
+        let text = Q3String::new("^3<Sarge>^7 & co");
+        assert_eq!(text.to_ansi(), "\x1b[33m<Sarge>\x1b[37m & co\x1b[0m");
+        assert_eq!(
+            text.to_html(),
+            "<span style=\"color:#ffff00\">&lt;Sarge&gt;</span>\
+             <span style=\"color:#ffffff\"> &amp; co</span>"
+        );
+        assert_eq!(Q3String::default().to_ansi(), "");
+    }
+}
@@ -0,0 +1,649 @@
+//! Compiled mod code (`vm/*.qvm`) for the engine's virtual machine, e.g. `ui.qvm`.
+//!
+//! The program borrows the file's data. Spans are byte ranges in the file.
+
+use std::{borrow::Cow, fmt, fmt::Write as _};
+
+use crate::{
+    binary::{self, index, int, span},
+    diagnostic::Diagnostic,
+    span::RawSpan,
+};
+
+/// Magic at the start of the file.
+pub const VM_MAGIC: i32 = 0x1272_1444;
+/// Magic of files with jump table targets after the literals.
+pub const VM_MAGIC_VER2: i32 = 0x1272_1445;
+
+const HEADER_SIZE: usize = 32;
+const HEADER_SIZE_VER2: usize = 36;
+/// Minimum length of strings in the data segment, where anything else is words.
+const MIN_DATA_STRING: usize = 4;
+
+/// `opcode_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Undef,
+    Ignore,
+    Break,
+    Enter,
+    Leave,
+    Call,
+    Push,
+    Pop,
+    Const,
+    Local,
+    Jump,
+    Eq,
+    Ne,
+    Lti,
+    Lei,
+    Gti,
+    Gei,
+    Ltu,
+    Leu,
+    Gtu,
+    Geu,
+    Eqf,
+    Nef,
+    Ltf,
+    Lef,
+    Gtf,
+    Gef,
+    Load1,
+    Load2,
+    Load4,
+    Store1,
+    Store2,
+    Store4,
+    Arg,
+    BlockCopy,
+    Sex8,
+    Sex16,
+    Negi,
+    Add,
+    Sub,
+    Divi,
+    Divu,
+    Modi,
+    Modu,
+    Muli,
+    Mulu,
+    Band,
+    Bor,
+    Bxor,
+    Bcom,
+    Lsh,
+    Rshi,
+    Rshu,
+    Negf,
+    Addf,
+    Subf,
+    Divf,
+    Mulf,
+    Cvif,
+    Cvfi,
+}
+
+impl Opcode {
+    /// Opcodes by value.
+    pub const ALL: [Self; 60] = [
+        Self::Undef,
+        Self::Ignore,
+        Self::Break,
+        Self::Enter,
+        Self::Leave,
+        Self::Call,
+        Self::Push,
+        Self::Pop,
+        Self::Const,
+        Self::Local,
+        Self::Jump,
+        Self::Eq,
+        Self::Ne,
+        Self::Lti,
+        Self::Lei,
+        Self::Gti,
+        Self::Gei,
+        Self::Ltu,
+        Self::Leu,
+        Self::Gtu,
+        Self::Geu,
+        Self::Eqf,
+        Self::Nef,
+        Self::Ltf,
+        Self::Lef,
+        Self::Gtf,
+        Self::Gef,
+        Self::Load1,
+        Self::Load2,
+        Self::Load4,
+        Self::Store1,
+        Self::Store2,
+        Self::Store4,
+        Self::Arg,
+        Self::BlockCopy,
+        Self::Sex8,
+        Self::Sex16,
+        Self::Negi,
+        Self::Add,
+        Self::Sub,
+        Self::Divi,
+        Self::Divu,
+        Self::Modi,
+        Self::Modu,
+        Self::Muli,
+        Self::Mulu,
+        Self::Band,
+        Self::Bor,
+        Self::Bxor,
+        Self::Bcom,
+        Self::Lsh,
+        Self::Rshi,
+        Self::Rshu,
+        Self::Negf,
+        Self::Addf,
+        Self::Subf,
+        Self::Divf,
+        Self::Mulf,
+        Self::Cvif,
+        Self::Cvfi,
+    ];
+
+    /// Get the opcode of a byte, `None` if it is unknown.
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get(usize::from(byte)).copied()
+    }
+
+    /// Get the size of the operand following the opcode in bytes.
+    pub const fn operand_size(self) -> usize {
+        match self {
+            Self::Arg => 1,
+            Self::Enter | Self::Leave | Self::Const | Self::Local | Self::BlockCopy => 4,
+            _ if self.is_branch() => 4,
+            _ => 0,
+        }
+    }
+
+    /// Get whether the opcode is a conditional branch to the instruction in its operand.
+    pub const fn is_branch(self) -> bool {
+        matches!(
+            self,
+            Self::Eq
+                | Self::Ne
+                | Self::Lti
+                | Self::Lei
+                | Self::Gti
+                | Self::Gei
+                | Self::Ltu
+                | Self::Leu
+                | Self::Gtu
+                | Self::Geu
+                | Self::Eqf
+                | Self::Nef
+                | Self::Ltf
+                | Self::Lef
+                | Self::Gtf
+                | Self::Gef
+        )
+    }
+}
+
+impl fmt::Display for Opcode {
+    /// Writes the mnemonic like q3asm, e.g. `BLOCK_COPY`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::BlockCopy => "BLOCK_COPY".to_owned(),
+            _ => format!("{:?}", self).to_uppercase(),
+        };
+        f.pad(&name)
+    }
+}
+
+/// Decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    number: usize,
+    opcode: Opcode,
+    operand: Option<i32>,
+    span: RawSpan,
+}
+
+impl Instruction {
+    /// Get the instruction's number, what calls, jumps and branches refer to.
+    pub const fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Get the opcode.
+    pub const fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    /// Get the operand, `None` for opcodes without.
+    pub const fn operand(&self) -> Option<i32> {
+        self.operand
+    }
+
+    /// Get the span of the opcode and operand.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operand {
+            Some(operand) => write!(f, "{} {}", self.opcode, operand),
+            None => write!(f, "{}", self.opcode),
+        }
+    }
+}
+
+/// String in the data or literal segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral<'a> {
+    address: u32,
+    text: Cow<'a, str>,
+    span: RawSpan,
+}
+
+impl StringLiteral<'_> {
+    /// Get the address in the virtual machine's memory.
+    pub const fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Get the text without the terminating NUL.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the span of the text.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Read and validated program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Qvm<'a> {
+    magic: i32,
+    data_offset: usize,
+    data: &'a [u8],
+    lit_offset: usize,
+    lit: &'a [u8],
+    bss_length: usize,
+    jump_targets: &'a [u8],
+    instructions: Vec<Instruction>,
+}
+
+impl<'a> Qvm<'a> {
+    /// Reads and validates a program, nothing but the diagnostics if the header is invalid.
+    pub fn parse(data: &'a [u8]) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut qvm = Self::default();
+
+        if data.len() >= 4 {
+            qvm.magic = int(data, 0);
+        }
+        let header_size = if qvm.magic == VM_MAGIC_VER2 {
+            HEADER_SIZE_VER2
+        } else {
+            HEADER_SIZE
+        };
+        if data.len() < header_size {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "file of {} bytes is too short for the {} bytes header",
+                    data.len(),
+                    header_size
+                ),
+                span(0, data.len()),
+            ));
+            return (qvm, diagnostics);
+        }
+        if qvm.magic != VM_MAGIC && qvm.magic != VM_MAGIC_VER2 {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "expect magic {:#x} or {:#x}, found {:#x}",
+                    VM_MAGIC, VM_MAGIC_VER2, qvm.magic
+                ),
+                span(0, 4),
+            ));
+            return (qvm, diagnostics);
+        }
+
+        let instruction_count = index(data, 4);
+        let code_offset = index(data, 8);
+        let code_length = index(data, 12);
+        qvm.data_offset = index(data, 16);
+        let data_length = index(data, 20);
+        let lit_length = index(data, 24);
+        qvm.bss_length = index(data, 28);
+
+        let mut segment = |name, offset: usize, length: usize, header: RawSpan| {
+            binary::region(data, offset, length, 1).unwrap_or_else(|| {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "{} segment {}..{} is outside of the file's {} bytes",
+                        name,
+                        offset,
+                        offset.saturating_add(length),
+                        data.len()
+                    ),
+                    header,
+                ));
+                &[]
+            })
+        };
+        let code = segment("code", code_offset, code_length, span(8, 16));
+        qvm.data = segment("data", qvm.data_offset, data_length, span(16, 24));
+        qvm.lit_offset = qvm.data_offset.saturating_add(data_length);
+        qvm.lit = segment("literal", qvm.lit_offset, lit_length, span(24, 28));
+        if qvm.magic == VM_MAGIC_VER2 {
+            qvm.jump_targets = segment(
+                "jump target",
+                qvm.lit_offset.saturating_add(lit_length),
+                index(data, 32),
+                span(32, 36),
+            );
+        }
+        if !data_length.is_multiple_of(4) {
+            diagnostics.push(Diagnostic::error(
+                format!("data segment length {} isn't a multiple of 4", data_length),
+                span(20, 24),
+            ));
+        }
+
+        qvm.decode(code, code_offset, &mut diagnostics);
+        if qvm.instructions.len() != instruction_count {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "header has {} instructions, code has {}",
+                    instruction_count,
+                    qvm.instructions.len()
+                ),
+                span(4, 8),
+            ));
+        }
+        let count = qvm.instructions.len();
+        for instruction in &qvm.instructions {
+            let target = instruction.operand.unwrap_or_default() as u32 as usize;
+            if instruction.opcode.is_branch() && target >= count {
+                diagnostics.push(Diagnostic::error(
+                    format!("branch to instruction {} out of {}", target, count),
+                    instruction.span,
+                ));
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (qvm, diagnostics)
+    }
+
+    /// Decodes the instructions until the end of the code or an invalid one.
+    fn decode(&mut self, code: &[u8], code_offset: usize, diagnostics: &mut Vec<Diagnostic>) {
+        let mut offset = 0;
+        while offset < code.len() {
+            let start = code_offset + offset;
+            let opcode = match Opcode::from_byte(code[offset]) {
+                Some(opcode) => opcode,
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("unknown opcode {}", code[offset]),
+                        span(start, start + 1),
+                    ));
+                    return;
+                }
+            };
+            let size = opcode.operand_size();
+            let operand = match code.get(offset + 1..offset + 1 + size) {
+                Some([]) => None,
+                Some([byte]) => Some(i32::from(*byte)),
+                Some(operand) => Some(int(operand, 0)),
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("operand of {} is truncated", opcode),
+                        span(start, code_offset + code.len()),
+                    ));
+                    return;
+                }
+            };
+            offset += 1 + size;
+            self.instructions.push(Instruction {
+                number: self.instructions.len(),
+                opcode,
+                operand,
+                span: span(start, code_offset + offset),
+            });
+        }
+    }
+
+    /// Get whether the file has jump table targets, [`VM_MAGIC_VER2`].
+    pub const fn has_jump_targets(&self) -> bool {
+        self.magic == VM_MAGIC_VER2
+    }
+
+    /// Get the instructions.
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Get the initialized data, words at addresses from 0.
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the literals following the data, bytes like strings.
+    pub const fn lit(&self) -> &'a [u8] {
+        self.lit
+    }
+
+    /// Get the length of the zeroed memory following the literals.
+    pub const fn bss_length(&self) -> usize {
+        self.bss_length
+    }
+
+    /// Get the instruction numbers of `switch` jump tables, empty before [`VM_MAGIC_VER2`].
+    pub fn jump_targets(&self) -> impl Iterator<Item = usize> + 'a {
+        self.jump_targets
+            .chunks_exact(4)
+            .map(|target| index(target, 0))
+    }
+
+    /// Get the NUL terminated strings of printable characters.
+    ///
+    /// Every string of the literal segment is included, while strings in the data segment, usually
+    /// initialized `char` arrays, need at least 4 characters to tell them from numbers.
+    pub fn strings(&self) -> Vec<StringLiteral<'a>> {
+        let mut strings = strings(self.data, 0, self.data_offset, MIN_DATA_STRING);
+        strings.extend(strings_of(
+            self.lit,
+            self.lit_offset - self.data_offset,
+            self.lit_offset,
+        ));
+        strings
+    }
+
+    /// Get the listing of the instructions, one per line with procedures starting at `ENTER`.
+    ///
+    /// Constants are annotated with the literal string at their address, and with the trap number
+    /// when followed by a `CALL` of a negative address, a system call.
+    pub fn disassemble(&self) -> String {
+        let literals: Vec<_> = strings_of(
+            self.lit,
+            self.lit_offset - self.data_offset,
+            self.lit_offset,
+        );
+        let mut listing = String::new();
+        for (number, instruction) in self.instructions.iter().enumerate() {
+            if instruction.opcode == Opcode::Enter {
+                if number > 0 {
+                    listing.push('\n');
+                }
+                let _ = writeln!(listing, "proc {}:", number);
+            }
+            let _ = write!(listing, "{:8}  {}", number, instruction);
+
+            let next = self.instructions.get(number + 1).map(Instruction::opcode);
+            if let (Opcode::Const, Some(value)) = (instruction.opcode, instruction.operand) {
+                if value < 0 && next == Some(Opcode::Call) {
+                    let _ = write!(listing, "  ; trap {}", -1 - value);
+                } else if let Some(string) = literals
+                    .iter()
+                    .find(|string| string.address == value as u32)
+                {
+                    let _ = write!(listing, "  ; {:?}", string.text);
+                }
+            }
+            listing.push('\n');
+        }
+        listing
+    }
+}
+
+/// Every NUL terminated string of the segment.
+fn strings_of(segment: &[u8], address: usize, offset: usize) -> Vec<StringLiteral<'_>> {
+    strings(segment, address, offset, 1)
+}
+
+/// Strings of at least `min` printable characters followed by a NUL.
+fn strings(segment: &[u8], address: usize, offset: usize, min: usize) -> Vec<StringLiteral<'_>> {
+    let printable = |byte: &u8| matches!(byte, b' '..=b'~' | b'\t' | b'\n' | b'\r');
+    let mut strings = Vec::new();
+    let mut start = 0;
+    for (end, byte) in segment.iter().enumerate() {
+        if *byte == 0 && end - start >= min {
+            strings.push(StringLiteral {
+                address: (address + start) as u32,
+                text: String::from_utf8_lossy(&segment[start..end]),
+                span: span(offset + start, offset + end),
+            });
+        }
+        if !printable(byte) {
+            start = end + 1;
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a program with the header's instruction count.
+    fn write(instruction_count: i32, code: &[u8], data: &[u8], lit: &[u8]) -> Vec<u8> {
+        let code_offset = HEADER_SIZE as i32;
+        let data_offset = code_offset + code.len() as i32;
+        let header = [
+            VM_MAGIC,
+            instruction_count,
+            code_offset,
+            code.len() as i32,
+            data_offset,
+            data.len() as i32,
+            lit.len() as i32,
+            256,
+        ];
+        let mut file: Vec<u8> = header
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        file.extend_from_slice(code);
+        file.extend_from_slice(data);
+        file.extend_from_slice(lit);
+        file
+    }
+
+    fn instruction(opcode: Opcode, operand: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![opcode as u8];
+        bytes.extend_from_slice(operand);
+        bytes
+    }
+
+    #[test]
+    fn disassemble() {
+        // trap_Cvar_VariableStringBuffer("g_arenasFile", buffer, 64), the buffer at 0
+        let code = [
+            instruction(Opcode::Enter, &16i32.to_le_bytes()),
+            instruction(Opcode::Const, &4i32.to_le_bytes()),
+            instruction(Opcode::Arg, &[8]),
+            instruction(Opcode::Const, &0i32.to_le_bytes()),
+            instruction(Opcode::Arg, &[12]),
+            instruction(Opcode::Const, &(-32i32).to_le_bytes()),
+            instruction(Opcode::Call, &[]),
+            instruction(Opcode::Pop, &[]),
+            instruction(Opcode::Leave, &16i32.to_le_bytes()),
+        ]
+        .concat();
+        let data = b"arenas.txt\0\0";
+        let file = write(9, &code, &data[..4], b"g_arenasFile\0");
+        let (qvm, diagnostics) = Qvm::parse(&file);
+        assert_eq!(diagnostics, &[]);
+        assert_eq!(qvm.instructions()[2].operand(), Some(8));
+        assert_eq!(
+            qvm.disassemble(),
+            "proc 0:\n       0  ENTER 16\n       1  CONST 4  ; \"g_arenasFile\"\n       \
+             2  ARG 8\n       3  CONST 0\n       4  ARG 12\n       5  CONST -32  ; trap 31\n       \
+             6  CALL\n       7  POP\n       8  LEAVE 16\n"
+        );
+
+        let file = write(9, &code, data, b"g_arenasFile\0ui_\x01\0");
+        let (qvm, _) = Qvm::parse(&file);
+        let strings: Vec<_> = qvm
+            .strings()
+            .iter()
+            .map(|string| (string.address(), string.text().to_owned()))
+            .collect();
+        assert_eq!(
+            strings,
+            &[
+                (0, "arenas.txt".to_owned()),
+                (12, "g_arenasFile".to_owned())
+            ]
+        );
+        let literal = &qvm.strings()[1];
+        assert_eq!(
+            &file[std::ops::Range::from(literal.span())],
+            b"g_arenasFile"
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let (_, diagnostics) = Qvm::parse(&[0x44, 0x14, 0x72, 0x13, 0, 0, 0, 0]);
+        assert_eq!(
+            diagnostics[0].message(),
+            "file of 8 bytes is too short for the 32 bytes header"
+        );
+        let mut file = write(0, &[], &[], &[]);
+        file[0] = 0;
+        let (_, diagnostics) = Qvm::parse(&file);
+        assert_eq!(
+            diagnostics[0].message(),
+            "expect magic 0x12721444 or 0x12721445, found 0x12721400"
+        );
+
+        let code = [
+            instruction(Opcode::Eq, &7i32.to_le_bytes()),
+            vec![60, Opcode::Const as u8],
+        ]
+        .concat();
+        let mut file = write(3, &code, &[0; 6], &[]);
+        file[20..24].copy_from_slice(&7i32.to_le_bytes());
+        let (_, diagnostics) = Qvm::parse(&file);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "header has 3 instructions, code has 1",
+                "data segment 39..46 is outside of the file's 45 bytes",
+                "data segment length 7 isn't a multiple of 4",
+                "literal segment 46..46 is outside of the file's 45 bytes",
+                "branch to instruction 7 out of 1",
+                "unknown opcode 60",
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Source of files referenced by other files, e.g. `#include` or `exec`.
+///
+/// Paths are game paths with `/` separators, relative to the resolver's root.
+pub trait FileResolver {
+    /// Reads the whole file at `path`.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// Reads the whole file at `path` as text.
+    ///
+    /// Game files are not guaranteed to be UTF-8, invalid sequences are replaced.
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        let bytes = self.read(path)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// File at `path` exists.
+    fn exists(&self, path: &str) -> bool {
+        self.read(path).is_ok()
+    }
+
+    /// Get the paths of the files directly in `directory` whose name ends with `extension`, e.g.
+    /// `scripts` and `.arena`, like `FS_GetFileList`.
+    ///
+    /// Resolvers that can't list files have none.
+    fn list(&self, _directory: &str, _extension: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Decodes a game file as text, replacing each invalid byte with `?` so offsets in the text are
+/// offsets in the file.
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(std::iter::repeat_n('?', chunk.invalid().len()));
+    }
+    text
+}
+
+/// File at `path` is directly in `directory` and ends with `extension`, ignoring case.
+pub(crate) fn is_listed(path: &str, directory: &str, extension: &str) -> bool {
+    let path = path.replace('\\', "/").to_lowercase();
+    let directory = directory
+        .replace('\\', "/")
+        .trim_matches('/')
+        .to_lowercase();
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    parent == directory && path.ends_with(&extension.to_lowercase())
+}
+
+impl<R: FileResolver + ?Sized> FileResolver for &R {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        (**self).exists(path)
+    }
+
+    fn list(&self, directory: &str, extension: &str) -> Vec<String> {
+        (**self).list(directory, extension)
+    }
+}
+
+/// In-memory files, mostly useful for tests.
+impl FileResolver for HashMap<String, String> {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.get(path)
+            .map(|text| text.as_bytes().to_vec())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_owned()))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.contains_key(path)
+    }
+
+    fn list(&self, directory: &str, extension: &str) -> Vec<String> {
+        let mut paths: Vec<_> = self
+            .keys()
+            .filter(|path| is_listed(path, directory, extension))
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// Files below a directory on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    /// Creates a new resolver for files below `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Get the root directory.
+    pub fn root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    /// Path below the root, `None` if a `..` component would leave it like `FS_FOpenFileRead`
+    /// refuses.
+    fn path(&self, path: &str) -> Option<PathBuf> {
+        let mut full = self.root.clone();
+        for part in path.split(['/', '\\']).filter(|part| !part.is_empty()) {
+            if part == ".." {
+                return None;
+            }
+            full.push(part);
+        }
+        Some(full)
+    }
+}
+
+impl FileResolver for Directory {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        match self.path(path) {
+            Some(full) => std::fs::read(full),
+            None => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("path leaves the root directory: {}", path),
+            )),
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.path(path).is_some_and(|full| full.is_file())
+    }
+
+    fn list(&self, directory: &str, extension: &str) -> Vec<String> {
+        let entries = match self.path(directory).map(std::fs::read_dir) {
+            Some(Ok(entries)) => entries,
+            _ => return Vec::new(),
+        };
+        let prefix = directory.trim_matches(['/', '\\']);
+        let mut paths: Vec<_> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                match prefix {
+                    "" => name,
+                    _ => format!("{}/{}", prefix, name),
+                }
+            })
+            .filter(|path| is_listed(path, directory, extension))
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory() {
+        let directory = Directory::new("fixtures");
+        assert!(directory.exists("arenas.txt"));
+        assert!(directory.read("/arenas.txt").is_ok());
+        assert_eq!(directory.list("", ".arena").len(), 5);
+
+        assert!(!directory.exists("../Cargo.toml"));
+        let error = directory.read("scripts/../../Cargo.toml").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(directory.list("..", ".toml"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,1449 @@
+//! Shader scripts (`scripts/*.shader`).
+//!
+//! Validation follows the renderer's `ParseShader`/`ParseStage`: unknown general parameters are
+//! ignored with a warning, while an unknown stage parameter makes the engine drop the shader.
+
+use crate::{
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    parse,
+    span::RawSpan,
+    syntax::{self, child_nodes, child_tokens, SyntaxKind, SyntaxNode},
+};
+
+pub mod index;
+pub mod list;
+
+/// Maximum number of stages of a shader, `MAX_SHADER_STAGES`.
+pub const MAX_SHADER_STAGES: usize = 8;
+
+/// Maximum number of `animMap` frames, `MAX_IMAGE_ANIMATIONS`.
+pub const MAX_IMAGE_ANIMATIONS: usize = 8;
+
+/// Surface parameters known to the engine and q3map, `infoParms` in `tr_shader.c`.
+pub const SURFACE_PARMS: &[&str] = &[
+    "water",
+    "slime",
+    "lava",
+    "playerclip",
+    "monsterclip",
+    "nodrop",
+    "nonsolid",
+    "origin",
+    "trans",
+    "detail",
+    "structural",
+    "areaportal",
+    "clusterportal",
+    "donotenter",
+    "fog",
+    "sky",
+    "lightfilter",
+    "alphashadow",
+    "hint",
+    "slick",
+    "noimpact",
+    "nomarks",
+    "ladder",
+    "nodamage",
+    "metalsteps",
+    "flesh",
+    "nosteps",
+    "nodraw",
+    "pointlight",
+    "nolightmap",
+    "nodlight",
+    "dust",
+];
+
+/// Parsed shader script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderFile {
+    shaders: Vec<Shader>,
+}
+
+impl ShaderFile {
+    /// Parses and validates a shader script.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(
+            source,
+            Lexer::new(source).collect(),
+            parse::shader::shader_file,
+        );
+        let mut diagnostics = parse.errors().to_vec();
+
+        let shaders = parse
+            .syntax()
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::Shader))
+            .map(|node| Shader::from_node(node, source, &mut diagnostics))
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (Self { shaders }, diagnostics)
+    }
+
+    /// Get the shaders in file order.
+    pub fn shaders(&self) -> &[Shader] {
+        &self.shaders
+    }
+
+    /// Get the first shader with the given name, ignoring case like the engine.
+    pub fn shader(&self, name: &str) -> Option<&Shader> {
+        self.shaders
+            .iter()
+            .find(|shader| shader.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// `textures/base_wall/concrete { … }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shader {
+    name: String,
+    name_span: RawSpan,
+    span: RawSpan,
+    directives: Vec<Directive>,
+    surface_parms: Vec<String>,
+    cull: Cull,
+    deforms: Vec<Deform>,
+    sort: Option<Sort>,
+    sky_parms: Option<SkyParms>,
+    fog_parms: Option<FogParms>,
+    no_picmip: bool,
+    no_mipmaps: bool,
+    polygon_offset: bool,
+    portal: bool,
+    stages: Vec<Stage>,
+}
+
+impl Shader {
+    fn from_node(node: &SyntaxNode, source: &str, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let mut shader = Self {
+            span: node.text_range().into(),
+            ..Self::default()
+        };
+        if let Some(name) = child_tokens(node)
+            .find(|token| matches!(token.kind(), SyntaxKind::String | SyntaxKind::QuotedString))
+        {
+            shader.name_span = name.text_range().into();
+            shader.name = unquote(&source[shader.name_span]).to_owned();
+        }
+
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::Directive => {
+                    let directive = Directive::from_node(child, source);
+                    shader.directive(&directive, diagnostics);
+                    shader.directives.push(directive);
+                }
+                SyntaxKind::Stage => {
+                    let stage = Stage::from_node(child, source, diagnostics);
+                    if shader.stages.len() == MAX_SHADER_STAGES {
+                        diagnostics.push(Diagnostic::error(
+                            format!(
+                                "too many stages in shader '{}', the maximum is {}",
+                                shader.name, MAX_SHADER_STAGES
+                            ),
+                            stage.span,
+                        ));
+                    }
+                    shader.stages.push(stage);
+                }
+                _ => {}
+            }
+        }
+
+        shader
+    }
+
+    fn directive(&mut self, directive: &Directive, diagnostics: &mut Vec<Diagnostic>) {
+        let mut arguments = Arguments::new(directive, diagnostics);
+        match directive.keyword.to_ascii_lowercase().as_str() {
+            // Skipped by the renderer, see `compile_directives`
+            _ if directive.is_compile_time() => return,
+            "surfaceparm" => {
+                if let Some(parm) = arguments.word() {
+                    if !SURFACE_PARMS
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(parm))
+                    {
+                        let span = arguments.last_span();
+                        arguments.warning(format!("unknown surfaceparm '{}'", parm), span);
+                    }
+                    self.surface_parms.push(parm.to_owned());
+                }
+            }
+            "cull" => {
+                if let Some(cull) = arguments.word() {
+                    match Cull::parse(cull) {
+                        Some(cull) => self.cull = cull,
+                        None => {
+                            let span = arguments.last_span();
+                            arguments.warning(format!("invalid cull parm '{}'", cull), span);
+                        }
+                    }
+                }
+            }
+            "deformvertexes" => {
+                if let Some(deform) = Deform::parse(&mut arguments) {
+                    self.deforms.push(deform);
+                }
+            }
+            "sort" => {
+                if let Some(sort) = arguments.word() {
+                    match Sort::parse(sort) {
+                        Some(sort) => self.sort = Some(sort),
+                        None => {
+                            let span = arguments.last_span();
+                            arguments.warning(format!("invalid sort '{}'", sort), span);
+                        }
+                    }
+                }
+            }
+            "skyparms" => {
+                let far_box = arguments.word();
+                let cloud_height = arguments.word();
+                let near_box = arguments.word();
+                let image =
+                    |name: Option<&str>| name.filter(|name| *name != "-").map(str::to_owned);
+                self.sky_parms = Some(SkyParms {
+                    far_box: image(far_box),
+                    cloud_height: cloud_height
+                        .filter(|height| *height != "-")
+                        .and_then(|height| height.parse().ok()),
+                    near_box: image(near_box),
+                });
+            }
+            "fogparms" => {
+                if let (Some(color), Some(distance)) = (arguments.vector::<3>(), arguments.number())
+                {
+                    self.fog_parms = Some(FogParms { color, distance });
+                }
+            }
+            "nopicmip" => self.no_picmip = true,
+            "nomipmaps" => self.no_mipmaps = true,
+            "polygonoffset" => self.polygon_offset = true,
+            "portal" => self.portal = true,
+            "entitymergable" | "fogonly" | "light" => {
+                // Accepted but without effect on the model
+                arguments.rest();
+            }
+            "tesssize" | "clamptime" => {
+                arguments.number();
+            }
+            _ => {
+                arguments.rest();
+                arguments.warning(
+                    format!("unknown general shader parameter '{}'", directive.keyword),
+                    directive.keyword_span,
+                );
+            }
+        }
+        arguments.finish();
+    }
+
+    /// Get the shader's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the span of the shader's name.
+    pub const fn name_span(&self) -> RawSpan {
+        self.name_span
+    }
+
+    /// Get the shader's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get all general directives, including compile-time ones.
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// Get the `q3map_*` and `qer_*` directives, which the renderer skips.
+    pub fn compile_directives(&self) -> impl Iterator<Item = &Directive> {
+        self.directives
+            .iter()
+            .filter(|directive| directive.is_compile_time())
+    }
+
+    /// Get the first argument of the general directive with the given keyword.
+    pub fn directive_argument(&self, keyword: &str) -> Option<&str> {
+        self.directives
+            .iter()
+            .find(|directive| directive.keyword.eq_ignore_ascii_case(keyword))
+            .and_then(|directive| directive.arguments.first())
+            .map(Argument::text)
+    }
+
+    /// Get the `qer_editorimage` shown by the editor.
+    pub fn editor_image(&self) -> Option<&str> {
+        self.directive_argument("qer_editorimage")
+    }
+
+    /// Get the `q3map_lightimage` used for surface light colour.
+    pub fn light_image(&self) -> Option<&str> {
+        self.directive_argument("q3map_lightimage")
+    }
+
+    /// Get the surface parameters.
+    pub fn surface_parms(&self) -> &[String] {
+        &self.surface_parms
+    }
+
+    /// Shader has the given surface parameter.
+    pub fn has_surface_parm(&self, parm: &str) -> bool {
+        self.surface_parms
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(parm))
+    }
+
+    /// Get the face culling.
+    pub const fn cull(&self) -> Cull {
+        self.cull
+    }
+
+    /// Get the vertex deformations.
+    pub fn deforms(&self) -> &[Deform] {
+        &self.deforms
+    }
+
+    /// Get the explicit sort.
+    pub const fn sort(&self) -> Option<Sort> {
+        self.sort
+    }
+
+    /// Get the sky parameters.
+    pub const fn sky_parms(&self) -> Option<&SkyParms> {
+        self.sky_parms.as_ref()
+    }
+
+    /// Get the fog parameters.
+    pub const fn fog_parms(&self) -> Option<&FogParms> {
+        self.fog_parms.as_ref()
+    }
+
+    /// Shader has `nopicmip`.
+    pub const fn no_picmip(&self) -> bool {
+        self.no_picmip
+    }
+
+    /// Shader has `nomipmaps`.
+    pub const fn no_mipmaps(&self) -> bool {
+        self.no_mipmaps
+    }
+
+    /// Shader has `polygonOffset`.
+    pub const fn polygon_offset(&self) -> bool {
+        self.polygon_offset
+    }
+
+    /// Shader has `portal`.
+    pub const fn portal(&self) -> bool {
+        self.portal
+    }
+
+    /// Get the stages.
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+}
+
+/// `keyword arguments…` on one line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Directive {
+    keyword: String,
+    keyword_span: RawSpan,
+    arguments: Vec<Argument>,
+    span: RawSpan,
+}
+
+impl Directive {
+    fn from_node(node: &SyntaxNode, source: &str) -> Self {
+        let mut tokens = child_tokens(node).map(|token| {
+            let span = RawSpan::from(token.text_range());
+            Argument {
+                text: unquote(&source[span]).to_owned(),
+                span,
+            }
+        });
+        let keyword = tokens.next().unwrap_or_default();
+
+        Self {
+            keyword: keyword.text,
+            keyword_span: keyword.span,
+            arguments: tokens.collect(),
+            span: node.text_range().into(),
+        }
+    }
+
+    /// Get the directive's keyword as written.
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    /// Get the span of the keyword.
+    pub const fn keyword_span(&self) -> RawSpan {
+        self.keyword_span
+    }
+
+    /// Get the arguments.
+    pub fn arguments(&self) -> &[Argument] {
+        &self.arguments
+    }
+
+    /// Get the directive's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Directive is for q3map (`q3map_*`) or the editor (`qer*`).
+    pub fn is_compile_time(&self) -> bool {
+        let keyword = self.keyword.to_ascii_lowercase();
+        keyword.starts_with("q3map") || keyword.starts_with("qer")
+    }
+}
+
+/// Argument of a directive, without quotes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Argument {
+    text: String,
+    span: RawSpan,
+}
+
+impl Argument {
+    /// Get the argument's text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the argument's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// `{ map … blendFunc … }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stage {
+    span: RawSpan,
+    directives: Vec<Directive>,
+    map: Option<StageMap>,
+    blend_func: Option<BlendFunc>,
+    rgb_gen: Option<RgbGen>,
+    alpha_gen: Option<AlphaGen>,
+    tc_gen: Option<TcGen>,
+    tc_mods: Vec<TcMod>,
+    alpha_func: Option<AlphaFunc>,
+    depth_func: DepthFunc,
+    depth_write: bool,
+    detail: bool,
+}
+
+impl Stage {
+    fn from_node(node: &SyntaxNode, source: &str, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let mut stage = Self {
+            span: node.text_range().into(),
+            ..Self::default()
+        };
+        for directive in child_nodes(node, SyntaxKind::Directive) {
+            let directive = Directive::from_node(directive, source);
+            stage.directive(&directive, diagnostics);
+            stage.directives.push(directive);
+        }
+        if stage.map.is_none() {
+            diagnostics.push(Diagnostic::warning("stage without an image", stage.span));
+        }
+        stage
+    }
+
+    fn directive(&mut self, directive: &Directive, diagnostics: &mut Vec<Diagnostic>) {
+        let mut arguments = Arguments::new(directive, diagnostics);
+        match directive.keyword.to_ascii_lowercase().as_str() {
+            "map" => {
+                arguments.severity_error();
+                self.map = arguments.word().map(|path| match path {
+                    path if path.eq_ignore_ascii_case("$lightmap") => StageMap::Lightmap,
+                    path if path.eq_ignore_ascii_case("$whiteimage") => StageMap::WhiteImage,
+                    path => StageMap::Map(path.to_owned()),
+                });
+            }
+            "clampmap" => {
+                arguments.severity_error();
+                self.map = arguments
+                    .word()
+                    .map(|path| StageMap::ClampMap(path.to_owned()));
+            }
+            "animmap" => {
+                arguments.severity_error();
+                let frequency = arguments.number();
+                let mut frames = Vec::new();
+                while let Some(frame) = arguments.optional_word() {
+                    if frames.len() == MAX_IMAGE_ANIMATIONS {
+                        let span = arguments.last_span();
+                        arguments.warning(
+                            format!(
+                                "too many animMap frames, only {} are used",
+                                MAX_IMAGE_ANIMATIONS
+                            ),
+                            span,
+                        );
+                        arguments.rest();
+                        break;
+                    }
+                    frames.push(frame.to_owned());
+                }
+                if frames.is_empty() {
+                    arguments.missing();
+                }
+                if let Some(frequency) = frequency {
+                    self.map = Some(StageMap::AnimMap { frequency, frames });
+                }
+            }
+            "videomap" => {
+                arguments.severity_error();
+                self.map = arguments
+                    .word()
+                    .map(|path| StageMap::VideoMap(path.to_owned()));
+            }
+            "blendfunc" => self.blend_func = BlendFunc::parse(&mut arguments),
+            "rgbgen" => self.rgb_gen = RgbGen::parse(&mut arguments),
+            "alphagen" => self.alpha_gen = AlphaGen::parse(&mut arguments),
+            "tcgen" | "texgen" => self.tc_gen = TcGen::parse(&mut arguments),
+            "tcmod" => self.tc_mods.extend(TcMod::parse(&mut arguments)),
+            "alphafunc" => {
+                if let Some(name) = arguments.word() {
+                    self.alpha_func = arguments.enumeration(name, "alphaFunc", AlphaFunc::parse);
+                }
+            }
+            "depthfunc" => {
+                if let Some(name) = arguments.word() {
+                    self.depth_func = arguments
+                        .enumeration(name, "depthFunc", DepthFunc::parse)
+                        .unwrap_or_default();
+                }
+            }
+            "depthwrite" => self.depth_write = true,
+            "detail" => self.detail = true,
+            _ => {
+                arguments.rest();
+                arguments.error(
+                    format!(
+                        "unknown stage parameter '{}', the engine drops the shader",
+                        directive.keyword
+                    ),
+                    directive.keyword_span,
+                );
+            }
+        }
+        arguments.finish();
+    }
+
+    /// Get the stage's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+
+    /// Get the stage's directives.
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// Get the stage's image source.
+    pub const fn map(&self) -> Option<&StageMap> {
+        self.map.as_ref()
+    }
+
+    /// Get the blend function.
+    pub const fn blend_func(&self) -> Option<BlendFunc> {
+        self.blend_func
+    }
+
+    /// Get the colour generator.
+    pub const fn rgb_gen(&self) -> Option<&RgbGen> {
+        self.rgb_gen.as_ref()
+    }
+
+    /// Get the alpha generator.
+    pub const fn alpha_gen(&self) -> Option<&AlphaGen> {
+        self.alpha_gen.as_ref()
+    }
+
+    /// Get the texture coordinate generator.
+    pub const fn tc_gen(&self) -> Option<&TcGen> {
+        self.tc_gen.as_ref()
+    }
+
+    /// Get the texture coordinate modifiers in order.
+    pub fn tc_mods(&self) -> &[TcMod] {
+        &self.tc_mods
+    }
+
+    /// Get the alpha test.
+    pub const fn alpha_func(&self) -> Option<AlphaFunc> {
+        self.alpha_func
+    }
+
+    /// Get the depth test.
+    pub const fn depth_func(&self) -> DepthFunc {
+        self.depth_func
+    }
+
+    /// Stage has `depthWrite`.
+    pub const fn depth_write(&self) -> bool {
+        self.depth_write
+    }
+
+    /// Stage has `detail`.
+    pub const fn detail(&self) -> bool {
+        self.detail
+    }
+}
+
+/// Image source of a stage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StageMap {
+    /// `map path`
+    Map(String),
+    /// `clampMap path`
+    ClampMap(String),
+    /// `animMap frequency path…`
+    AnimMap { frequency: f64, frames: Vec<String> },
+    /// `videoMap file`, a RoQ video below `video/`.
+    VideoMap(String),
+    /// `map $lightmap`
+    Lightmap,
+    /// `map $whiteimage`
+    WhiteImage,
+}
+
+impl StageMap {
+    /// Get the image paths, empty for `$lightmap`, `$whiteimage` and videos.
+    pub fn images(&self) -> Vec<&str> {
+        match self {
+            Self::Map(path) | Self::ClampMap(path) => vec![path],
+            Self::AnimMap { frames, .. } => frames.iter().map(String::as_str).collect(),
+            Self::VideoMap(_) | Self::Lightmap | Self::WhiteImage => Vec::new(),
+        }
+    }
+}
+
+/// Face culling, `cull`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Cull {
+    #[default]
+    Front,
+    Back,
+    None,
+}
+
+impl Cull {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "twosided" | "disable" => Some(Self::None),
+            "back" | "backside" | "backsided" => Some(Self::Back),
+            _ => None,
+        }
+    }
+}
+
+/// `sort` value, `SS_*`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Portal,
+    Sky,
+    Opaque,
+    Decal,
+    SeeThrough,
+    Banner,
+    Additive,
+    Nearest,
+    Underwater,
+    Value(f64),
+}
+
+impl Sort {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "portal" => Self::Portal,
+            "sky" => Self::Sky,
+            "opaque" => Self::Opaque,
+            "decal" => Self::Decal,
+            "seethrough" => Self::SeeThrough,
+            "banner" => Self::Banner,
+            "additive" => Self::Additive,
+            "nearest" => Self::Nearest,
+            "underwater" => Self::Underwater,
+            value => Self::Value(value.parse().ok()?),
+        })
+    }
+
+    /// Numeric sort value used by the renderer.
+    pub const fn value(self) -> f64 {
+        match self {
+            Self::Portal => 1.0,
+            Self::Sky => 2.0,
+            Self::Opaque => 3.0,
+            Self::Decal => 4.0,
+            Self::SeeThrough => 5.0,
+            Self::Banner => 6.0,
+            Self::Underwater => 8.0,
+            Self::Additive => 10.0,
+            Self::Nearest => 16.0,
+            Self::Value(value) => value,
+        }
+    }
+}
+
+/// `skyParms farbox cloudheight nearbox`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkyParms {
+    far_box: Option<String>,
+    cloud_height: Option<f64>,
+    near_box: Option<String>,
+}
+
+impl SkyParms {
+    /// Suffixes of the six box images, in the engine's order.
+    pub const SUFFIXES: [&'static str; 6] = ["rt", "bk", "lf", "ft", "up", "dn"];
+
+    /// Get the far box base path, `None` for `-`.
+    pub fn far_box(&self) -> Option<&str> {
+        self.far_box.as_deref()
+    }
+
+    /// Get the cloud height, `None` for `-`.
+    pub const fn cloud_height(&self) -> Option<f64> {
+        self.cloud_height
+    }
+
+    /// Get the near box base path, `None` for `-`.
+    pub fn near_box(&self) -> Option<&str> {
+        self.near_box.as_deref()
+    }
+
+    /// Get the image paths of the far and near boxes, e.g. `env/sky_rt.tga`.
+    pub fn images(&self) -> Vec<String> {
+        [&self.far_box, &self.near_box]
+            .iter()
+            .filter_map(|base| base.as_deref())
+            .flat_map(|base| {
+                Self::SUFFIXES
+                    .iter()
+                    .map(move |suffix| format!("{}_{}.tga", base, suffix))
+            })
+            .collect()
+    }
+}
+
+/// `fogParms ( r g b ) distance`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FogParms {
+    color: [f64; 3],
+    distance: f64,
+}
+
+impl FogParms {
+    /// Get the fog colour.
+    pub const fn color(&self) -> [f64; 3] {
+        self.color
+    }
+
+    /// Get the distance to opaque.
+    pub const fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+/// Periodic function, `GF_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WaveFunc {
+    Sin,
+    Triangle,
+    Square,
+    Sawtooth,
+    InverseSawtooth,
+    Noise,
+}
+
+impl WaveFunc {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sin" => Some(Self::Sin),
+            "triangle" => Some(Self::Triangle),
+            "square" => Some(Self::Square),
+            "sawtooth" => Some(Self::Sawtooth),
+            "inversesawtooth" => Some(Self::InverseSawtooth),
+            "noise" => Some(Self::Noise),
+            _ => None,
+        }
+    }
+}
+
+/// `func base amplitude phase frequency`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    func: WaveFunc,
+    base: f64,
+    amplitude: f64,
+    phase: f64,
+    frequency: f64,
+}
+
+impl Wave {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        let func = arguments.enumeration(name, "wave function", WaveFunc::parse);
+        let [base, amplitude, phase, frequency] = arguments.numbers::<4>()?;
+        Some(Self {
+            func: func?,
+            base,
+            amplitude,
+            phase,
+            frequency,
+        })
+    }
+
+    /// Get the function.
+    pub const fn func(&self) -> WaveFunc {
+        self.func
+    }
+
+    /// Get the base value.
+    pub const fn base(&self) -> f64 {
+        self.base
+    }
+
+    /// Get the amplitude.
+    pub const fn amplitude(&self) -> f64 {
+        self.amplitude
+    }
+
+    /// Get the phase.
+    pub const fn phase(&self) -> f64 {
+        self.phase
+    }
+
+    /// Get the frequency.
+    pub const fn frequency(&self) -> f64 {
+        self.frequency
+    }
+}
+
+/// `deformVertexes …`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deform {
+    Wave { spread: f64, wave: Wave },
+    Normal { amplitude: f64, frequency: f64 },
+    Bulge { width: f64, height: f64, speed: f64 },
+    Move { vector: [f64; 3], wave: Wave },
+    Autosprite,
+    Autosprite2,
+    ProjectionShadow,
+    Text(u8),
+}
+
+impl Deform {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        let lower = name.to_ascii_lowercase();
+        Some(match lower.as_str() {
+            "wave" => {
+                let spread = arguments.number()?;
+                if spread == 0.0 {
+                    let span = arguments.last_span();
+                    arguments.warning("illegal div value of 0 in deformVertexes wave", span);
+                }
+                Self::Wave {
+                    spread,
+                    wave: Wave::parse(arguments)?,
+                }
+            }
+            "normal" => {
+                let [amplitude, frequency] = arguments.numbers::<2>()?;
+                Self::Normal {
+                    amplitude,
+                    frequency,
+                }
+            }
+            "bulge" => {
+                let [width, height, speed] = arguments.numbers::<3>()?;
+                Self::Bulge {
+                    width,
+                    height,
+                    speed,
+                }
+            }
+            "move" => Self::Move {
+                vector: arguments.numbers::<3>()?,
+                wave: Wave::parse(arguments)?,
+            },
+            "autosprite" => Self::Autosprite,
+            "autosprite2" => Self::Autosprite2,
+            "projectionshadow" => Self::ProjectionShadow,
+            text if text.len() == 5
+                && text.starts_with("text")
+                && (b'0'..=b'7').contains(&text.as_bytes()[4]) =>
+            {
+                Self::Text(text.as_bytes()[4] - b'0')
+            }
+            _ => {
+                arguments.rest();
+                let span = arguments.first_span();
+                arguments.warning(format!("unknown deformVertexes subtype '{}'", name), span);
+                return None;
+            }
+        })
+    }
+}
+
+/// Blend factor of `blendFunc`, `GL_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    One,
+    Zero,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "GL_ONE" => Some(Self::One),
+            "GL_ZERO" => Some(Self::Zero),
+            "GL_SRC_COLOR" => Some(Self::SrcColor),
+            "GL_ONE_MINUS_SRC_COLOR" => Some(Self::OneMinusSrcColor),
+            "GL_DST_COLOR" => Some(Self::DstColor),
+            "GL_ONE_MINUS_DST_COLOR" => Some(Self::OneMinusDstColor),
+            "GL_SRC_ALPHA" => Some(Self::SrcAlpha),
+            "GL_ONE_MINUS_SRC_ALPHA" => Some(Self::OneMinusSrcAlpha),
+            "GL_DST_ALPHA" => Some(Self::DstAlpha),
+            "GL_ONE_MINUS_DST_ALPHA" => Some(Self::OneMinusDstAlpha),
+            "GL_SRC_ALPHA_SATURATE" => Some(Self::SrcAlphaSaturate),
+            _ => None,
+        }
+    }
+
+    /// Factor is valid as source factor, see `NameToSrcBlendMode`.
+    pub const fn is_source(self) -> bool {
+        !matches!(self, Self::SrcColor | Self::OneMinusSrcColor)
+    }
+
+    /// Factor is valid as destination factor, see `NameToDstBlendMode`.
+    pub const fn is_destination(self) -> bool {
+        !matches!(
+            self,
+            Self::DstColor | Self::OneMinusDstColor | Self::SrcAlphaSaturate
+        )
+    }
+}
+
+/// `blendFunc src dst` or one of the `add`, `filter` and `blend` shorthands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendFunc {
+    source: BlendFactor,
+    destination: BlendFactor,
+}
+
+impl BlendFunc {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        let (source, destination) = match name.to_ascii_lowercase().as_str() {
+            "add" => (BlendFactor::One, BlendFactor::One),
+            "filter" => (BlendFactor::DstColor, BlendFactor::Zero),
+            "blend" => (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            _ => {
+                let source = BlendFactor::parse(name).filter(|factor| factor.is_source());
+                if source.is_none() {
+                    let span = arguments.last_span();
+                    arguments.warning(
+                        format!("unknown blend mode '{}', substituting GL_ONE", name),
+                        span,
+                    );
+                }
+                let name = arguments.word()?;
+                let destination = BlendFactor::parse(name).filter(|factor| factor.is_destination());
+                if destination.is_none() {
+                    let span = arguments.last_span();
+                    arguments.warning(
+                        format!("unknown blend mode '{}', substituting GL_ONE", name),
+                        span,
+                    );
+                }
+                (
+                    source.unwrap_or(BlendFactor::One),
+                    destination.unwrap_or(BlendFactor::One),
+                )
+            }
+        };
+        Some(Self {
+            source,
+            destination,
+        })
+    }
+
+    /// Get the source factor.
+    pub const fn source(&self) -> BlendFactor {
+        self.source
+    }
+
+    /// Get the destination factor.
+    pub const fn destination(&self) -> BlendFactor {
+        self.destination
+    }
+}
+
+/// `rgbGen …`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RgbGen {
+    Identity,
+    IdentityLighting,
+    Wave(Wave),
+    Const([f64; 3]),
+    Entity,
+    OneMinusEntity,
+    Vertex,
+    ExactVertex,
+    OneMinusVertex,
+    LightingDiffuse,
+}
+
+impl RgbGen {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        Some(match name.to_ascii_lowercase().as_str() {
+            "identity" => Self::Identity,
+            "identitylighting" => Self::IdentityLighting,
+            "wave" => Self::Wave(Wave::parse(arguments)?),
+            "const" => Self::Const(arguments.vector::<3>()?),
+            "entity" => Self::Entity,
+            "oneminusentity" => Self::OneMinusEntity,
+            "vertex" => Self::Vertex,
+            "exactvertex" => Self::ExactVertex,
+            "oneminusvertex" => Self::OneMinusVertex,
+            "lightingdiffuse" => Self::LightingDiffuse,
+            _ => {
+                arguments.rest();
+                let span = arguments.first_span();
+                arguments.warning(format!("unknown rgbGen parameter '{}'", name), span);
+                return None;
+            }
+        })
+    }
+}
+
+/// `alphaGen …`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaGen {
+    Identity,
+    Wave(Wave),
+    Const(f64),
+    Entity,
+    OneMinusEntity,
+    Vertex,
+    OneMinusVertex,
+    LightingSpecular,
+    Portal(f64),
+}
+
+impl AlphaGen {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        Some(match name.to_ascii_lowercase().as_str() {
+            "identity" => Self::Identity,
+            "wave" => Self::Wave(Wave::parse(arguments)?),
+            "const" => Self::Const(arguments.number()?),
+            "entity" => Self::Entity,
+            "oneminusentity" => Self::OneMinusEntity,
+            "vertex" => Self::Vertex,
+            "oneminusvertex" => Self::OneMinusVertex,
+            "lightingspecular" => Self::LightingSpecular,
+            "portal" => Self::Portal(arguments.number()?),
+            _ => {
+                arguments.rest();
+                let span = arguments.first_span();
+                arguments.warning(format!("unknown alphaGen parameter '{}'", name), span);
+                return None;
+            }
+        })
+    }
+}
+
+/// `tcGen …`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TcGen {
+    Texture,
+    Lightmap,
+    Environment,
+    Vector([f64; 3], [f64; 3]),
+}
+
+impl TcGen {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        Some(match name.to_ascii_lowercase().as_str() {
+            "texture" | "base" => Self::Texture,
+            "lightmap" => Self::Lightmap,
+            "environment" => Self::Environment,
+            "vector" => Self::Vector(arguments.vector::<3>()?, arguments.vector::<3>()?),
+            _ => {
+                arguments.rest();
+                let span = arguments.first_span();
+                arguments.warning(format!("unknown tcGen parameter '{}'", name), span);
+                return None;
+            }
+        })
+    }
+}
+
+/// `tcMod …`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TcMod {
+    Turb {
+        base: f64,
+        amplitude: f64,
+        phase: f64,
+        frequency: f64,
+    },
+    Scale([f64; 2]),
+    Scroll([f64; 2]),
+    Stretch(Wave),
+    Transform([f64; 6]),
+    Rotate(f64),
+    EntityTranslate,
+}
+
+impl TcMod {
+    fn parse(arguments: &mut Arguments) -> Option<Self> {
+        let name = arguments.word()?;
+        Some(match name.to_ascii_lowercase().as_str() {
+            "turb" => {
+                let [base, amplitude, phase, frequency] = arguments.numbers::<4>()?;
+                Self::Turb {
+                    base,
+                    amplitude,
+                    phase,
+                    frequency,
+                }
+            }
+            "scale" => Self::Scale(arguments.numbers::<2>()?),
+            "scroll" => Self::Scroll(arguments.numbers::<2>()?),
+            "stretch" => Self::Stretch(Wave::parse(arguments)?),
+            "transform" => Self::Transform(arguments.numbers::<6>()?),
+            "rotate" => Self::Rotate(arguments.number()?),
+            "entitytranslate" => Self::EntityTranslate,
+            _ => {
+                arguments.rest();
+                let span = arguments.first_span();
+                arguments.warning(format!("unknown tcMod '{}'", name), span);
+                return None;
+            }
+        })
+    }
+}
+
+/// `alphaFunc …`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlphaFunc {
+    Gt0,
+    Lt128,
+    Ge128,
+}
+
+impl AlphaFunc {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "GT0" => Some(Self::Gt0),
+            "LT128" => Some(Self::Lt128),
+            "GE128" => Some(Self::Ge128),
+            _ => None,
+        }
+    }
+}
+
+/// `depthFunc …`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DepthFunc {
+    #[default]
+    Lequal,
+    Equal,
+}
+
+impl DepthFunc {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lequal" => Some(Self::Lequal),
+            "equal" => Some(Self::Equal),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the arguments of a directive, reporting missing, invalid and extra ones.
+struct Arguments<'a> {
+    directive: &'a Directive,
+    next: usize,
+    missing: bool,
+    error: bool,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Arguments<'a> {
+    fn new(directive: &'a Directive, diagnostics: &'a mut Vec<Diagnostic>) -> Self {
+        Self {
+            directive,
+            next: 0,
+            missing: false,
+            error: false,
+            diagnostics,
+        }
+    }
+
+    /// Report missing arguments as errors, the engine drops the shader then.
+    fn severity_error(&mut self) {
+        self.error = true;
+    }
+
+    fn optional_word(&mut self) -> Option<&'a str> {
+        let argument = self.directive.arguments.get(self.next)?;
+        self.next += 1;
+        Some(&argument.text)
+    }
+
+    fn word(&mut self) -> Option<&'a str> {
+        let word = self.optional_word();
+        if word.is_none() {
+            self.missing();
+        }
+        word
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let word = self.word()?;
+        let number = word.parse().ok();
+        if number.is_none() {
+            let span = self.last_span();
+            self.warning(
+                format!("invalid number '{}' for '{}'", word, self.directive.keyword),
+                span,
+            );
+        }
+        number
+    }
+
+    fn numbers<const N: usize>(&mut self) -> Option<[f64; N]> {
+        let mut numbers = [0.0; N];
+        for number in &mut numbers {
+            *number = self.number()?;
+        }
+        Some(numbers)
+    }
+
+    /// `( x y z )`
+    fn vector<const N: usize>(&mut self) -> Option<[f64; N]> {
+        self.parenthesis("(")?;
+        let numbers = self.numbers::<N>()?;
+        self.parenthesis(")")?;
+        Some(numbers)
+    }
+
+    fn parenthesis(&mut self, expected: &str) -> Option<()> {
+        let word = self.word()?;
+        if word != expected {
+            let span = self.last_span();
+            self.warning(
+                format!(
+                    "expected '{}' found '{}' for '{}'",
+                    expected, word, self.directive.keyword
+                ),
+                span,
+            );
+            return None;
+        }
+        Some(())
+    }
+
+    fn enumeration<T>(
+        &mut self,
+        name: &str,
+        what: &str,
+        parse: fn(&str) -> Option<T>,
+    ) -> Option<T> {
+        let value = parse(name);
+        if value.is_none() {
+            let span = self.last_span();
+            self.warning(format!("invalid {} '{}'", what, name), span);
+        }
+        value
+    }
+
+    /// Skips the remaining arguments.
+    fn rest(&mut self) {
+        self.next = self.directive.arguments.len();
+    }
+
+    fn missing(&mut self) {
+        if self.missing {
+            return;
+        }
+        self.missing = true;
+        let message = format!("missing parameter for '{}'", self.directive.keyword);
+        let span = self.directive.span;
+        if self.error {
+            self.error(message, span);
+        } else {
+            self.warning(message, span);
+        }
+    }
+
+    fn first_span(&self) -> RawSpan {
+        self.directive
+            .arguments
+            .first()
+            .map_or(self.directive.span, Argument::span)
+    }
+
+    fn last_span(&self) -> RawSpan {
+        self.next
+            .checked_sub(1)
+            .and_then(|index| self.directive.arguments.get(index))
+            .map_or(self.directive.span, Argument::span)
+    }
+
+    fn warning(&mut self, message: impl Into<String>, span: RawSpan) {
+        self.diagnostics.push(Diagnostic::warning(message, span));
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: RawSpan) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    /// Reports arguments left over, which the engine ignores.
+    fn finish(self) {
+        if self.missing {
+            return;
+        }
+        if let Some(extra) = self.directive.arguments.get(self.next) {
+            self.diagnostics.push(Diagnostic::warning(
+                format!(
+                    "unexpected parameter '{}' for '{}' is ignored",
+                    extra.text, self.directive.keyword
+                ),
+                extra.span,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADERS: &str = r#"textures/base_wall/comp3
+{
+	qer_editorimage textures/base_wall/comp3.tga
+	q3map_lightimage textures/base_wall/comp3env.tga
+	surfaceparm nomarks
+	cull none
+	deformVertexes wave 100 sin 0 2 0 0.5
+	{
+		map $lightmap
+		rgbGen identity
+	}
+	{
+		map textures/base_wall/comp3.tga
+		blendFunc filter
+		tcMod scroll 0 0.5
+		tcMod scale 2 2
+	}
+	{
+		animMap 10 textures/a1.tga textures/a2.tga
+		blendFunc GL_ONE GL_ONE_MINUS_SRC_ALPHA
+		rgbGen const ( 1 0.5 0 )
+		alphaGen wave sin 0 1 0 1
+	}
+}
+
+textures/skies/space
+{
+	skyParms env/space 512 -
+	sort sky
+}
+"#;
+
+    #[test]
+    fn model() {
+        let (file, diagnostics) = ShaderFile::parse(SHADERS);
+        assert_eq!(diagnostics, &[]);
+
+        let shader = file.shader("TEXTURES/base_wall/comp3").unwrap();
+        assert_eq!(shader.editor_image(), Some("textures/base_wall/comp3.tga"));
+        assert_eq!(
+            shader.light_image(),
+            Some("textures/base_wall/comp3env.tga")
+        );
+        assert_eq!(shader.compile_directives().count(), 2);
+        assert!(shader.has_surface_parm("nomarks"));
+        assert_eq!(shader.cull(), Cull::None);
+        assert!(matches!(shader.deforms()[0], Deform::Wave { spread, .. } if spread == 100.0));
+
+        let stages = shader.stages();
+        assert_eq!(stages[0].map(), Some(&StageMap::Lightmap));
+        assert_eq!(
+            stages[1].blend_func().unwrap().source(),
+            BlendFactor::DstColor
+        );
+        assert_eq!(
+            stages[1].tc_mods(),
+            &[TcMod::Scroll([0.0, 0.5]), TcMod::Scale([2.0, 2.0])]
+        );
+        assert_eq!(
+            stages[2].map().unwrap().images(),
+            &["textures/a1.tga", "textures/a2.tga"]
+        );
+        assert_eq!(stages[2].rgb_gen(), Some(&RgbGen::Const([1.0, 0.5, 0.0])));
+
+        let sky = file.shader("textures/skies/space").unwrap();
+        assert_eq!(sky.sort().map(Sort::value), Some(2.0));
+        let sky_parms = sky.sky_parms().unwrap();
+        assert_eq!(sky_parms.cloud_height(), Some(512.0));
+        assert_eq!(sky_parms.near_box(), None);
+        assert_eq!(sky_parms.images()[0], "env/space_rt.tga");
+    }
+
+    #[test]
+    fn validation() {
+        let (_, diagnostics) = ShaderFile::parse(
+            "textures/a\n{\n\tsurfaceparm nomark\n\tcull front\n\tshinyness 5\n\t{\n\t\tmap\n\t\tblendFunc GL_ONE GL_DST_COLOR\n\t\trgbGen wave sine 0 1 0 1\n\t\talphaFunc GT0 extra\n\t\tclampTexCoords\n\t}\n}\n",
+        );
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "unknown surfaceparm 'nomark'",
+                "invalid cull parm 'front'",
+                "unknown general shader parameter 'shinyness'",
+                "stage without an image",
+                "missing parameter for 'map'",
+                "unknown blend mode 'GL_DST_COLOR', substituting GL_ONE",
+                "invalid wave function 'sine'",
+                "unexpected parameter 'extra' for 'alphaFunc' is ignored",
+                "unknown stage parameter 'clampTexCoords', the engine drops the shader",
+            ]
+        );
+        assert_eq!(
+            diagnostics[4].severity(),
+            crate::diagnostic::Severity::Error
+        );
+    }
+}
@@ -0,0 +1,573 @@
+//! Index over the shader scripts of a game, e.g. all `scripts/*.shader` files.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use crate::{
+    diagnostic::Diagnostic,
+    resolve::{self, FileResolver},
+    span::RawSpan,
+};
+
+use super::{list::LoadOrder, Argument, Directive, Shader, ShaderFile, StageMap};
+
+/// Image extensions the engine tries in place of the one written, in order.
+pub const IMAGE_EXTENSIONS: &[&str] = &["tga", "jpg"];
+
+/// Shaders of several files in load order.
+///
+/// For each name, compared ignoring case, the first definition added wins. The engine adds the
+/// files in reverse, so a later file wins there, see [`LoadOrder`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderIndex {
+    files: Vec<String>,
+    definitions: Vec<Definition>,
+    by_name: HashMap<String, usize>,
+    references: HashSet<String>,
+}
+
+impl ShaderIndex {
+    /// Creates a new empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every `scripts/*.shader` file listed by `resolver` like the engine, see
+    /// [`LoadOrder::engine_index`].
+    ///
+    /// Returns the problems by file.
+    pub fn load(resolver: &dyn FileResolver) -> (Self, Vec<(String, Diagnostic)>) {
+        let mut files = Vec::new();
+        let mut problems = Vec::new();
+        for path in resolver.list("scripts", ".shader") {
+            let data = match resolver.read(&path) {
+                Ok(data) => data,
+                Err(error) => {
+                    problems.push((
+                        path.clone(),
+                        Diagnostic::error(
+                            format!("cannot read {}: {}", path, error),
+                            RawSpan::default(),
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            if let Err(error) = std::str::from_utf8(&data) {
+                let start = error.valid_up_to();
+                let end = start + error.error_len().unwrap_or(data.len() - start);
+                problems.push((
+                    path.clone(),
+                    Diagnostic::warning(
+                        format!("{}, read as '?'", error),
+                        RawSpan::new(start as u32, end as u32),
+                    ),
+                ));
+            }
+            let (file, diagnostics) = ShaderFile::parse(&resolve::decode(&data));
+            problems.extend(
+                diagnostics
+                    .into_iter()
+                    .map(|diagnostic| (path.clone(), diagnostic)),
+            );
+            files.push((path, file));
+        }
+        (LoadOrder::engine_index(files), problems)
+    }
+
+    /// Parses and adds the shaders of the file at `path`, after all files added before.
+    pub fn add(&mut self, path: impl Into<String>, source: &str) -> Vec<Diagnostic> {
+        let (file, diagnostics) = ShaderFile::parse(source);
+        self.add_file(path, file);
+        diagnostics
+    }
+
+    /// Adds the already parsed shaders of the file at `path`, after all files added before.
+    pub fn add_file(&mut self, path: impl Into<String>, file: ShaderFile) {
+        let file_index = self.files.len();
+        self.files.push(path.into());
+        for shader in file.shaders {
+            let index = self.definitions.len();
+            self.by_name
+                .entry(shader.name.to_ascii_lowercase())
+                .or_insert(index);
+            self.definitions.push(Definition {
+                file: file_index,
+                shader,
+            });
+        }
+    }
+
+    /// Records that a map, model or other file uses the shader `name`.
+    pub fn add_reference(&mut self, name: &str) {
+        self.references.insert(name.to_ascii_lowercase());
+    }
+
+    /// Get the files in load order.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Iterates over all definitions in load order, including shadowed ones.
+    pub fn definitions(&self) -> impl Iterator<Item = ShaderDefinition<'_>> {
+        self.definitions
+            .iter()
+            .map(move |definition| self.definition(definition))
+    }
+
+    /// Get the definition the engine uses for `name`.
+    pub fn get(&self, name: &str) -> Option<ShaderDefinition<'_>> {
+        let index = *self.by_name.get(&name.to_ascii_lowercase())?;
+        Some(self.definition(&self.definitions[index]))
+    }
+
+    /// Get the names defined more than once, with the winning and the shadowed definitions.
+    pub fn duplicates(&self) -> Vec<Duplicate<'_>> {
+        let mut duplicates: Vec<Duplicate> = Vec::new();
+        let mut positions = HashMap::new();
+        for (index, definition) in self.definitions.iter().enumerate() {
+            let key = definition.shader.name.to_ascii_lowercase();
+            let winner = self.by_name[&key];
+            if winner == index {
+                continue;
+            }
+            let position = *positions.entry(key).or_insert_with(|| {
+                duplicates.push(Duplicate {
+                    winner: self.definition(&self.definitions[winner]),
+                    shadowed: Vec::new(),
+                });
+                duplicates.len() - 1
+            });
+            duplicates[position]
+                .shadowed
+                .push(self.definition(definition));
+        }
+        duplicates
+    }
+
+    /// Get the winning definitions whose name was never passed to [`add_reference`](Self::add_reference).
+    pub fn unreferenced(&self) -> Vec<ShaderDefinition<'_>> {
+        self.definitions
+            .iter()
+            .enumerate()
+            .filter(|(index, definition)| {
+                let key = definition.shader.name.to_ascii_lowercase();
+                self.by_name[&key] == *index && !self.references.contains(&key)
+            })
+            .map(|(_, definition)| self.definition(definition))
+            .collect()
+    }
+
+    /// Get the images the game needs for winning definitions, that `resolver` doesn't have in
+    /// any supported format.
+    pub fn missing_images(
+        &self,
+        resolver: &dyn FileResolver,
+    ) -> Vec<(ShaderDefinition<'_>, ImageDependency)> {
+        self.missing(resolver, true)
+    }
+
+    /// Get the images only the editor or q3map need for winning definitions, that `resolver`
+    /// doesn't have in any supported format.
+    pub fn missing_tool_images(
+        &self,
+        resolver: &dyn FileResolver,
+    ) -> Vec<(ShaderDefinition<'_>, ImageDependency)> {
+        self.missing(resolver, false)
+    }
+
+    fn missing(
+        &self,
+        resolver: &dyn FileResolver,
+        runtime: bool,
+    ) -> Vec<(ShaderDefinition<'_>, ImageDependency)> {
+        self.definitions()
+            .filter(ShaderDefinition::is_winner)
+            .flat_map(|definition| {
+                image_dependencies(definition.shader)
+                    .into_iter()
+                    .filter(|image| {
+                        image.kind.is_runtime() == runtime && !image_exists(resolver, &image.path)
+                    })
+                    .map(move |image| (definition, image))
+            })
+            .collect()
+    }
+
+    /// Exports the definitions, their images, duplicates and unreferenced shaders as JSON.
+    pub fn to_json(&self) -> Value {
+        let location = |definition: &ShaderDefinition| {
+            json!({
+                "file": definition.file,
+                "span": span_json(definition.shader.span),
+            })
+        };
+        let shaders: Vec<_> = self
+            .definitions()
+            .map(|definition| {
+                let images: Vec<_> = image_dependencies(definition.shader)
+                    .iter()
+                    .map(|image| {
+                        json!({
+                            "path": image.path,
+                            "kind": image.kind.as_str(),
+                            "span": span_json(image.span),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "name": definition.shader.name,
+                    "file": definition.file,
+                    "span": span_json(definition.shader.span),
+                    "winner": definition.is_winner(),
+                    "images": images,
+                })
+            })
+            .collect();
+        let duplicates: Vec<_> = self
+            .duplicates()
+            .iter()
+            .map(|duplicate| {
+                json!({
+                    "name": duplicate.winner.shader.name,
+                    "winner": location(&duplicate.winner),
+                    "shadowed": duplicate.shadowed.iter().map(location).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let unreferenced: Vec<_> = self
+            .unreferenced()
+            .iter()
+            .map(|definition| definition.shader.name.as_str())
+            .collect();
+
+        json!({
+            "files": self.files,
+            "shaders": shaders,
+            "duplicates": duplicates,
+            "unreferenced": unreferenced,
+        })
+    }
+
+    fn definition<'a>(&'a self, definition: &'a Definition) -> ShaderDefinition<'a> {
+        let key = definition.shader.name.to_ascii_lowercase();
+        let winner = &self.definitions[self.by_name[&key]];
+        ShaderDefinition {
+            file: &self.files[definition.file],
+            shader: &definition.shader,
+            winner: std::ptr::eq(winner, definition),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Definition {
+    file: usize,
+    shader: Shader,
+}
+
+/// Shader and the file defining it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShaderDefinition<'a> {
+    file: &'a str,
+    shader: &'a Shader,
+    winner: bool,
+}
+
+impl<'a> ShaderDefinition<'a> {
+    /// Get the path of the defining file.
+    pub const fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// Get the shader.
+    pub const fn shader(&self) -> &'a Shader {
+        self.shader
+    }
+
+    /// Definition is the one the engine uses.
+    pub const fn is_winner(&self) -> bool {
+        self.winner
+    }
+}
+
+/// Shader name defined more than once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate<'a> {
+    winner: ShaderDefinition<'a>,
+    shadowed: Vec<ShaderDefinition<'a>>,
+}
+
+impl<'a> Duplicate<'a> {
+    /// Get the definition the engine uses.
+    pub const fn winner(&self) -> &ShaderDefinition<'a> {
+        &self.winner
+    }
+
+    /// Get the later definitions that are ignored.
+    pub fn shadowed(&self) -> &[ShaderDefinition<'a>] {
+        &self.shadowed
+    }
+}
+
+/// Image a shader needs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageDependency {
+    path: String,
+    kind: ImageKind,
+    span: RawSpan,
+}
+
+impl ImageDependency {
+    /// Get the image path as written, or derived for sky boxes.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get where the image is used.
+    pub const fn kind(&self) -> ImageKind {
+        self.kind
+    }
+
+    /// Get the span of the argument naming the image.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Directive an image is used by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageKind {
+    /// `map` or `clampMap`.
+    Map,
+    /// Frame of `animMap`.
+    AnimMapFrame,
+    /// Side of a `skyParms` far or near box.
+    Sky,
+    /// `qer_editorimage`, only needed by the editor.
+    EditorImage,
+    /// `q3map_lightimage`, only needed by q3map.
+    LightImage,
+}
+
+impl ImageKind {
+    /// Image is loaded by the game, not only by the editor or q3map.
+    pub const fn is_runtime(self) -> bool {
+        !matches!(self, Self::EditorImage | Self::LightImage)
+    }
+
+    /// Name used in the JSON export.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Map => "map",
+            Self::AnimMapFrame => "animMap",
+            Self::Sky => "sky",
+            Self::EditorImage => "editorImage",
+            Self::LightImage => "lightImage",
+        }
+    }
+}
+
+/// Get the images `shader` depends on, in order of appearance.
+pub fn image_dependencies(shader: &Shader) -> Vec<ImageDependency> {
+    let mut images = Vec::new();
+    let mut push = |path: &str, kind, span| {
+        images.push(ImageDependency {
+            path: path.to_owned(),
+            kind,
+            span,
+        })
+    };
+
+    for directive in &shader.directives {
+        let kind = match directive.keyword.to_ascii_lowercase().as_str() {
+            "qer_editorimage" => ImageKind::EditorImage,
+            "q3map_lightimage" => ImageKind::LightImage,
+            _ => continue,
+        };
+        if let Some(argument) = directive.arguments.first() {
+            push(&argument.text, kind, argument.span);
+        }
+    }
+
+    if let Some(sky) = &shader.sky_parms {
+        let arguments = arguments(&shader.directives, &["skyparms"]);
+        for path in sky.images() {
+            // Far and near box images are the argument followed by a suffix
+            let argument = arguments.iter().find(|argument| {
+                path.strip_prefix(argument.text.as_str())
+                    .is_some_and(|suffix| suffix.starts_with('_'))
+            });
+            let span = argument.map_or(shader.span, |argument| argument.span);
+            push(&path, ImageKind::Sky, span);
+        }
+    }
+
+    for stage in &shader.stages {
+        let map = match &stage.map {
+            Some(map) => map,
+            None => continue,
+        };
+        let kind = match map {
+            StageMap::AnimMap { .. } => ImageKind::AnimMapFrame,
+            _ => ImageKind::Map,
+        };
+        let arguments = arguments(&stage.directives, &["map", "clampmap", "animmap"]);
+        for path in map.images() {
+            let argument = arguments.iter().find(|argument| argument.text == path);
+            push(
+                path,
+                kind,
+                argument.map_or(stage.span, |argument| argument.span),
+            );
+        }
+    }
+
+    images
+}
+
+/// Arguments of the directives with one of the `keywords`, ignoring case.
+fn arguments<'a>(directives: &'a [Directive], keywords: &[&str]) -> Vec<&'a Argument> {
+    directives
+        .iter()
+        .filter(|directive| {
+            keywords
+                .iter()
+                .any(|keyword| directive.keyword.eq_ignore_ascii_case(keyword))
+        })
+        .flat_map(|directive| &directive.arguments)
+        .collect()
+}
+
+/// Image exists as written or with one of the [`IMAGE_EXTENSIONS`].
+pub fn image_exists(resolver: &dyn FileResolver, path: &str) -> bool {
+    if resolver.exists(path) {
+        return true;
+    }
+    let stem = match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => &path[..dot],
+        _ => path,
+    };
+    IMAGE_EXTENSIONS
+        .iter()
+        .any(|extension| resolver.exists(&format!("{}.{}", stem, extension)))
+}
+
+fn span_json(span: RawSpan) -> Value {
+    json!({ "start": span.start(), "end": span.end() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> ShaderIndex {
+        let mut index = ShaderIndex::new();
+        let diagnostics = index.add(
+            "scripts/base.shader",
+            "textures/base/a\n{\n\tqer_editorimage textures/base/a_ed.tga\n\t{\n\t\tmap textures/base/a.tga\n\t}\n\t{\n\t\tanimMap 2 textures/base/f1.tga textures/base/f2.tga\n\t}\n}\ntextures/base/sky\n{\n\tskyParms env/sky - -\n}\n",
+        );
+        assert_eq!(diagnostics, &[]);
+        let diagnostics = index.add(
+            "scripts/custom.shader",
+            "TEXTURES/BASE/A\n{\n\t{\n\t\tmap textures/custom/a.tga\n\t}\n}\n",
+        );
+        assert_eq!(diagnostics, &[]);
+        index
+    }
+
+    #[test]
+    fn load() {
+        let files = HashMap::from([
+            (
+                "scripts/base.shader".to_owned(),
+                "textures/base/a\n{\n}\n".to_owned(),
+            ),
+            (
+                "scripts/custom.shader".to_owned(),
+                "textures/base/a\n{\n".to_owned(),
+            ),
+            ("scripts/shaderlist.txt".to_owned(), "base\n".to_owned()),
+        ]);
+        let (index, problems) = ShaderIndex::load(&files);
+        // The later file is added first and wins
+        assert_eq!(
+            index.files(),
+            &["scripts/custom.shader", "scripts/base.shader"]
+        );
+        let winner = index.get("textures/base/a").unwrap();
+        assert_eq!(winner.file(), "scripts/custom.shader");
+        assert_eq!(index.duplicates().len(), 1);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, "scripts/custom.shader");
+    }
+
+    #[test]
+    fn duplicates() {
+        let index = index();
+        let winner = index.get("textures/base/a").unwrap();
+        assert_eq!(winner.file(), "scripts/base.shader");
+
+        let duplicates = index.duplicates();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].winner().file(), "scripts/base.shader");
+        assert_eq!(duplicates[0].shadowed()[0].file(), "scripts/custom.shader");
+    }
+
+    #[test]
+    fn images() {
+        let mut index = index();
+        let images: Vec<_> = image_dependencies(index.get("textures/base/a").unwrap().shader())
+            .into_iter()
+            .map(|image| (image.path, image.kind))
+            .collect();
+        assert_eq!(
+            images,
+            &[
+                ("textures/base/a_ed.tga".to_owned(), ImageKind::EditorImage),
+                ("textures/base/a.tga".to_owned(), ImageKind::Map),
+                ("textures/base/f1.tga".to_owned(), ImageKind::AnimMapFrame),
+                ("textures/base/f2.tga".to_owned(), ImageKind::AnimMapFrame),
+            ]
+        );
+
+        let files = HashMap::from([
+            ("textures/base/a_ed.tga".to_owned(), String::new()),
+            ("textures/base/a.jpg".to_owned(), String::new()),
+            ("textures/base/f1.tga".to_owned(), String::new()),
+        ]);
+        let missing: Vec<_> = index
+            .missing_images(&files)
+            .into_iter()
+            .map(|(_, image)| image.path)
+            .collect();
+        assert_eq!(missing.len(), 7);
+        assert_eq!(missing[0], "textures/base/f2.tga");
+        assert_eq!(missing[1], "env/sky_rt.tga");
+        assert_eq!(index.missing_tool_images(&files), &[]);
+        let files = HashMap::new();
+        let missing: Vec<_> = index
+            .missing_tool_images(&files)
+            .into_iter()
+            .map(|(_, image)| image.path)
+            .collect();
+        assert_eq!(missing, &["textures/base/a_ed.tga"]);
+
+        index.add_reference("Textures/Base/A");
+        let unreferenced: Vec<_> = index
+            .unreferenced()
+            .iter()
+            .map(|definition| definition.shader().name())
+            .collect();
+        assert_eq!(unreferenced, &["textures/base/sky"]);
+
+        let json = index.to_json();
+        assert_eq!(json["shaders"][0]["images"][1]["kind"], "map");
+        assert_eq!(
+            json["duplicates"][0]["shadowed"][0]["file"],
+            "scripts/custom.shader"
+        );
+        assert_eq!(json["unreferenced"][0], "textures/base/sky");
+    }
+}
@@ -0,0 +1,346 @@
+//! `scripts/shaderlist.txt` and the order tools load shader files in.
+//!
+//! The engine loads every `.shader` file, while Radiant and q3map2 only load the files named in
+//! the list, so a shader can work in game and still be missing in the editor.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::{Lexer, TokenKind},
+    span::RawSpan,
+};
+
+use super::{
+    index::{ShaderDefinition, ShaderIndex},
+    ShaderFile,
+};
+
+/// Shader files named in `scripts/shaderlist.txt`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShaderList {
+    entries: Vec<ShaderListEntry>,
+}
+
+impl ShaderList {
+    /// Parses a shader list, one file name without extension per line.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut entries = Vec::new();
+        let mut seen = HashMap::new();
+
+        for token in Lexer::new(source) {
+            let name = match token.kind() {
+                TokenKind::String => token.text(),
+                TokenKind::QuotedString => token.text().trim_matches('"'),
+                kind if kind.is_trivia() => continue,
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("unexpected '{}'", token.text()),
+                        token.span(),
+                    ));
+                    continue;
+                }
+            };
+            let entry = ShaderListEntry {
+                name: name.to_owned(),
+                span: token.span(),
+            };
+
+            if entry.name.to_ascii_lowercase().ends_with(".shader") {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "entry '{}' has the '.shader' extension, tools look for '{}.shader'",
+                        entry.name, entry.name
+                    ),
+                    entry.span,
+                ));
+            }
+            if let Some(previous) = seen.insert(entry.name.to_ascii_lowercase(), entry.span) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "shader file '{}' already listed at {}",
+                        entry.name,
+                        previous.start()
+                    ),
+                    entry.span,
+                ));
+                continue;
+            }
+            entries.push(entry);
+        }
+
+        (Self { entries }, diagnostics)
+    }
+
+    /// Get the entries in list order, without repeated ones.
+    pub fn entries(&self) -> &[ShaderListEntry] {
+        &self.entries
+    }
+
+    /// List names the file at `path`, e.g. `scripts/base_wall.shader` for `base_wall`.
+    pub fn contains(&self, path: &str) -> bool {
+        self.entry(path).is_some()
+    }
+
+    /// Compares the list with the shader files present at `paths`.
+    pub fn compare<'a>(&'a self, paths: &[&'a str]) -> ListComparison<'a> {
+        let missing = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                !paths
+                    .iter()
+                    .any(|path| file_name(path).eq_ignore_ascii_case(&entry.name))
+            })
+            .collect();
+        let unlisted = paths
+            .iter()
+            .copied()
+            .filter(|path| !self.contains(path))
+            .collect();
+        ListComparison { missing, unlisted }
+    }
+
+    fn entry(&self, path: &str) -> Option<&ShaderListEntry> {
+        let name = file_name(path);
+        self.entries
+            .iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// `base_wall`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderListEntry {
+    name: String,
+    span: RawSpan,
+}
+
+impl ShaderListEntry {
+    /// Get the file name without directory and extension.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the entry's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+/// Differences between a shader list and the shader files present.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListComparison<'a> {
+    missing: Vec<&'a ShaderListEntry>,
+    unlisted: Vec<&'a str>,
+}
+
+impl<'a> ListComparison<'a> {
+    /// Get the entries without a shader file, tools report them as missing.
+    pub fn missing(&self) -> &[&'a ShaderListEntry] {
+        &self.missing
+    }
+
+    /// Get the shader files only the engine loads.
+    pub fn unlisted(&self) -> &[&'a str] {
+        &self.unlisted
+    }
+}
+
+/// Shader definitions each tool sees.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadOrder {
+    engine: ShaderIndex,
+    editor: ShaderIndex,
+}
+
+impl LoadOrder {
+    /// Loads `files` like the engine and like Radiant and q3map2 given `list`.
+    ///
+    /// The engine sorts the `.shader` files by name and concatenates them in reverse, so a shader in
+    /// a later file overrides one in an earlier file. The tools load the listed files in list order
+    /// and keep the first definition.
+    pub fn new(list: &ShaderList, mut files: Vec<(String, ShaderFile)>) -> Self {
+        files.sort_by_cached_key(|(path, _)| path.to_ascii_lowercase());
+
+        let mut editor = ShaderIndex::new();
+        for entry in list.entries() {
+            if let Some((path, file)) = files
+                .iter()
+                .find(|(path, _)| file_name(path).eq_ignore_ascii_case(&entry.name))
+            {
+                editor.add_file(path.clone(), file.clone());
+            }
+        }
+
+        let engine = Self::engine_index(files);
+
+        Self { engine, editor }
+    }
+
+    /// Loads `files` like the engine, sorted by name and the later file winning.
+    pub fn engine_index(mut files: Vec<(String, ShaderFile)>) -> ShaderIndex {
+        files.sort_by_cached_key(|(path, _)| path.to_ascii_lowercase());
+
+        let mut engine = ShaderIndex::new();
+        for (path, file) in files.into_iter().rev() {
+            engine.add_file(path, file);
+        }
+        engine
+    }
+
+    /// Get the shaders as the engine loads them.
+    pub const fn engine(&self) -> &ShaderIndex {
+        &self.engine
+    }
+
+    /// Get the shaders as Radiant and q3map2 load them.
+    pub const fn editor(&self) -> &ShaderIndex {
+        &self.editor
+    }
+
+    /// Get the definition each tool uses for every shader name, sorted by name.
+    pub fn visibility(&self) -> Vec<Visibility<'_>> {
+        let mut names = BTreeMap::new();
+        for definition in self.engine.definitions().chain(self.editor.definitions()) {
+            names
+                .entry(definition.shader().name().to_ascii_lowercase())
+                .or_insert_with(|| definition.shader().name());
+        }
+        names
+            .into_values()
+            .map(|name| Visibility {
+                engine: self.engine.get(name),
+                editor: self.editor.get(name),
+            })
+            .collect()
+    }
+
+    /// Get the shader names the engine and the tools resolve to different definitions.
+    pub fn mismatches(&self) -> Vec<Visibility<'_>> {
+        self.visibility()
+            .into_iter()
+            .filter(|visibility| !visibility.is_consistent())
+            .collect()
+    }
+}
+
+/// Definitions of a shader name in the engine and in the tools.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Visibility<'a> {
+    engine: Option<ShaderDefinition<'a>>,
+    editor: Option<ShaderDefinition<'a>>,
+}
+
+impl<'a> Visibility<'a> {
+    /// Get the shader name as first defined.
+    pub fn name(&self) -> &'a str {
+        self.engine
+            .or(self.editor)
+            .map(|definition| definition.shader().name())
+            .unwrap_or_default()
+    }
+
+    /// Get the definition the engine uses.
+    pub const fn engine(&self) -> Option<ShaderDefinition<'a>> {
+        self.engine
+    }
+
+    /// Get the definition Radiant and q3map2 use, `None` when no listed file defines it.
+    pub const fn editor(&self) -> Option<ShaderDefinition<'a>> {
+        self.editor
+    }
+
+    /// Engine and tools use the same definition.
+    pub fn is_consistent(&self) -> bool {
+        match (self.engine, self.editor) {
+            (Some(engine), Some(editor)) => {
+                engine.file() == editor.file() && engine.shader().span() == editor.shader().span()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `scripts/base_wall.shader` → `base_wall`
+fn file_name(path: &str) -> &str {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match name.len().checked_sub(".shader".len()) {
+        Some(end) if name[end..].eq_ignore_ascii_case(".shader") => &name[..end],
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let (list, diagnostics) =
+            ShaderList::parse("// list\nbase_wall\nsfx\r\nbase_wall\ngothic.shader\n");
+        let names: Vec<_> = list.entries().iter().map(ShaderListEntry::name).collect();
+        assert_eq!(names, &["base_wall", "sfx", "gothic.shader"]);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "shader file 'base_wall' already listed at 8",
+                "entry 'gothic.shader' has the '.shader' extension, tools look for 'gothic.shader.shader'",
+            ]
+        );
+
+        let comparison = list.compare(&["scripts/base_wall.shader", "scripts/custom.shader"]);
+        let missing: Vec<_> = comparison
+            .missing()
+            .iter()
+            .map(|entry| entry.name())
+            .collect();
+        assert_eq!(missing, &["sfx", "gothic.shader"]);
+        assert_eq!(comparison.unlisted(), &["scripts/custom.shader"]);
+    }
+
+    #[test]
+    fn load_order() {
+        let (list, _) = ShaderList::parse("b\na\n");
+        let files: Vec<_> = [
+            ("scripts/a.shader", "textures/x { }\ntextures/y { }\n"),
+            ("scripts/b.shader", "textures/x { cull none }\n"),
+            ("scripts/c.shader", "textures/y { }\ntextures/z { }\n"),
+        ]
+        .iter()
+        .map(|(path, source)| (path.to_string(), ShaderFile::parse(source).0))
+        .collect();
+        let order = LoadOrder::new(&list, files);
+
+        let visibility: Vec<_> = order
+            .visibility()
+            .iter()
+            .map(|visibility| {
+                (
+                    visibility.name(),
+                    visibility.engine().map(|definition| definition.file()),
+                    visibility.editor().map(|definition| definition.file()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            visibility,
+            &[
+                (
+                    "textures/x",
+                    Some("scripts/b.shader"),
+                    Some("scripts/b.shader")
+                ),
+                (
+                    "textures/y",
+                    Some("scripts/c.shader"),
+                    Some("scripts/a.shader")
+                ),
+                ("textures/z", Some("scripts/c.shader"), None),
+            ]
+        );
+        assert_eq!(order.mismatches().len(), 2);
+    }
+}
@@ -0,0 +1,181 @@
+//! Player model skins (`models/players/*/*.skin`).
+//!
+//! The renderer's `RE_RegisterSkin` lowercases surface names, skips `tag_*` entries and keeps the
+//! first shader of a surface when it is listed twice.
+
+use std::collections::HashMap;
+
+use crate::{
+    botlib::precomp::unquote,
+    diagnostic::Diagnostic,
+    lexer::SkinLexer,
+    parse,
+    span::RawSpan,
+    syntax::{self, child_nodes, SyntaxKind, SyntaxNode},
+};
+
+/// Parsed skin mapping model surfaces to shaders.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Skin {
+    surfaces: Vec<SkinSurface>,
+    tags: Vec<SkinSurface>,
+}
+
+impl Skin {
+    /// Parses and validates a skin.
+    pub fn parse(source: &str) -> (Self, Vec<Diagnostic>) {
+        let parse = syntax::parse_tokens(
+            source,
+            SkinLexer::new(source).collect(),
+            parse::skin::skin_file,
+        );
+        let mut diagnostics = parse.errors().to_vec();
+
+        let mut skin = Self::default();
+        let mut seen = HashMap::new();
+        for node in parse
+            .syntax()
+            .children()
+            .flat_map(|file| child_nodes(file, SyntaxKind::SkinSurface))
+        {
+            let surface = SkinSurface::from_node(node, source);
+            if surface.name.contains("tag_") {
+                skin.tags.push(surface);
+                continue;
+            }
+
+            if surface.shader.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    format!("empty shader path for surface '{}'", surface.name),
+                    surface.span,
+                ));
+            } else if surface.shader.contains('\\') {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "shader path '{}' uses '\\' separators, the engine only looks up '/'",
+                        surface.shader
+                    ),
+                    surface.shader_span,
+                ));
+            }
+            if let Some(previous) = seen.insert(surface.name.clone(), surface.name_span) {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "surface '{}' already mapped at {}, the first mapping wins",
+                        surface.name,
+                        previous.start()
+                    ),
+                    surface.name_span,
+                ));
+            }
+            skin.surfaces.push(surface);
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start());
+
+        (skin, diagnostics)
+    }
+
+    /// Get the surfaces in file order, including repeated ones.
+    pub fn surfaces(&self) -> &[SkinSurface] {
+        &self.surfaces
+    }
+
+    /// Get the `tag_*` entries, which the engine skips.
+    pub fn tags(&self) -> &[SkinSurface] {
+        &self.tags
+    }
+
+    /// Get the shader the engine uses for the surface, ignoring case.
+    pub fn shader(&self, surface: &str) -> Option<&str> {
+        self.surfaces
+            .iter()
+            .find(|skin_surface| skin_surface.name.eq_ignore_ascii_case(surface))
+            .map(SkinSurface::shader)
+    }
+}
+
+/// `h_head,models/players/sarge/cigar.tga`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkinSurface {
+    name: String,
+    name_span: RawSpan,
+    shader: String,
+    shader_span: RawSpan,
+    span: RawSpan,
+}
+
+impl SkinSurface {
+    fn from_node(node: &SyntaxNode, source: &str) -> Self {
+        let mut surface = Self {
+            span: node.text_range().into(),
+            ..Self::default()
+        };
+        if let Some(name) = child_nodes(node, SyntaxKind::Key).next() {
+            surface.name_span = name.text_range().into();
+            surface.name = unquote(&source[surface.name_span]).to_ascii_lowercase();
+        }
+        if let Some(shader) = child_nodes(node, SyntaxKind::Value).next() {
+            surface.shader_span = shader.text_range().into();
+            surface.shader = unquote(&source[surface.shader_span]).to_owned();
+        } else {
+            surface.shader_span = RawSpan::new(surface.span.end(), surface.span.end());
+        }
+        surface
+    }
+
+    /// Get the lowercased surface name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the span of the surface name.
+    pub const fn name_span(&self) -> RawSpan {
+        self.name_span
+    }
+
+    /// Get the shader or image path, empty for most tags.
+    pub fn shader(&self) -> &str {
+        &self.shader
+    }
+
+    /// Get the span of the shader path, empty after the name when it is missing.
+    pub const fn shader_span(&self) -> RawSpan {
+        self.shader_span
+    }
+
+    /// Get the entry's span.
+    pub const fn span(&self) -> RawSpan {
+        self.span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skin() {
+        let source = "tag_head,\nH_Head,models/players/sarge/cigar.tga\nh_visor,\nh_eyes,models\\players\\sarge\\eyes.tga\nh_head,models/players/sarge/other.tga\n";
+        let (skin, diagnostics) = Skin::parse(source);
+        assert_eq!(skin.tags().len(), 1);
+        assert_eq!(skin.surfaces().len(), 4);
+        assert_eq!(
+            skin.shader("h_head"),
+            Some("models/players/sarge/cigar.tga")
+        );
+
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::message).collect();
+        assert_eq!(
+            messages,
+            &[
+                "empty shader path for surface 'h_visor'",
+                "shader path 'models\\players\\sarge\\eyes.tga' uses '\\' separators, the engine only looks up '/'",
+                "surface 'h_head' already mapped at 10, the first mapping wins",
+            ]
+        );
+        assert_eq!(
+            &source[diagnostics[1].span()],
+            "models\\players\\sarge\\eyes.tga"
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::lexer::{Lexer, Token, TokenKind};
+use crate::{
+    lexer::{Token, TokenKind},
+    span::RawSpan,
+};
 
 #[derive(Debug)]
 pub struct Source<'src, 'token> {
@@ -11,6 +14,7 @@ impl<'src, 'token> Source<'src, 'token> {
         Self { tokens, cursor: 0 }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<Token<'src>> {
         self.eat_trivia();
 
@@ -25,11 +29,46 @@ impl<'src, 'token> Source<'src, 'token> {
         self.tokens.get(self.cursor).map(Token::kind)
     }
 
+    /// Peeks the `n`th next token, skipping trivia.
+    ///
+    /// Trivia between the peeked tokens is skipped as well, so `n` counts only the tokens
+    /// [`next`](Self::next) would return.
     pub fn try_peek_nth(&mut self, n: usize) -> Option<Token<'src>> {
         debug_assert!(n <= 4);
         self.eat_trivia();
 
-        self.tokens.get(self.cursor + n).copied()
+        self.tokens[self.cursor..]
+            .iter()
+            .filter(|token| !token.kind().is_trivia())
+            .nth(n)
+            .copied()
+    }
+
+    /// Next token is the first one on its line.
+    pub fn at_line_start(&mut self) -> bool {
+        self.eat_trivia();
+
+        self.tokens[..self.cursor]
+            .iter()
+            .rev()
+            .take_while(|token| token.kind().is_trivia())
+            .any(|token| ends_line(token))
+            || self.tokens[..self.cursor]
+                .iter()
+                .all(|token| token.kind().is_trivia())
+    }
+
+    /// Span of the next token, or an empty span at the end of the source.
+    pub fn peek_span(&mut self) -> RawSpan {
+        self.eat_trivia();
+
+        match self.tokens.get(self.cursor) {
+            Some(token) => token.span(),
+            None => {
+                let end = self.tokens.last().map_or(0, |token| token.span().end());
+                RawSpan::new(end, end)
+            }
+        }
     }
 
     fn eat_trivia(&mut self) {
@@ -42,3 +81,38 @@ impl<'src, 'token> Source<'src, 'token> {
         }
     }
 }
+
+/// Token is a line break, including line comments which end in one.
+fn ends_line(token: &Token) -> bool {
+    match token.kind() {
+        TokenKind::Newline => true,
+        TokenKind::LineComment => token.text().ends_with('\n'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Lexer;
+
+    use super::*;
+
+    #[test]
+    fn try_peek_nth() {
+        let tokens: Vec<_> = Lexer::new("{ // comment\n  foo /* block */ bar\n}").collect();
+        let mut source = Source::new(&tokens);
+        let peeked: Vec<_> = (0..5)
+            .map(|n| source.try_peek_nth(n).map(|token| token.text()))
+            .collect();
+        assert_eq!(
+            peeked,
+            &[Some("{"), Some("foo"), Some("bar"), Some("}"), None]
+        );
+
+        source.next();
+        assert_eq!(
+            source.try_peek_nth(1).map(|token| token.text()),
+            Some("bar")
+        );
+    }
+}
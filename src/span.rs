@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct RawSpan {
     start: u32,
     end: u32,
@@ -18,6 +18,12 @@ impl RawSpan {
     }
 }
 
+impl From<cstree::text::TextRange> for RawSpan {
+    fn from(range: cstree::text::TextRange) -> Self {
+        Self::new(range.start().into(), range.end().into())
+    }
+}
+
 impl From<RawSpan> for std::ops::Range<usize> {
     fn from(span: RawSpan) -> Self {
         span.start() as usize..span.end() as usize
@@ -0,0 +1,256 @@
+//! Game files as the engine's `FS_` layer sees them, through the search path of loose files and
+//! paks of the mounted game directories.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    pk3::Pk3,
+    resolve::{is_listed, FileResolver},
+};
+
+/// Where a file is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin<'a> {
+    /// Loose file below the game directory.
+    Directory(&'a Path),
+    /// File in the pak.
+    Pak(&'a Path),
+}
+
+#[derive(Debug, Clone)]
+enum SearchPath {
+    Directory {
+        root: PathBuf,
+        /// Lowercase game paths to the paths on disk.
+        files: BTreeMap<String, String>,
+    },
+    Pak {
+        path: PathBuf,
+        pk3: Pk3,
+    },
+}
+
+impl SearchPath {
+    fn origin(&self) -> Origin<'_> {
+        match self {
+            Self::Directory { root, .. } => Origin::Directory(root),
+            Self::Pak { path, .. } => Origin::Pak(path),
+        }
+    }
+
+    /// Get the game paths of the files, as named in the directory or pak.
+    fn names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Self::Directory { files, .. } => Box::new(files.values().map(String::as_str)),
+            Self::Pak { pk3, .. } => Box::new(pk3.entries().iter().map(|entry| entry.name())),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        match self {
+            Self::Directory { files, .. } => files.contains_key(key),
+            Self::Pak { pk3, .. } => pk3.entry(key).is_some(),
+        }
+    }
+}
+
+/// Search path of mounted game directories, the last mounted first.
+///
+/// Paths are resolved ignoring case, with `/` or `\` separators.
+#[derive(Debug, Clone, Default)]
+pub struct Vfs {
+    search_paths: Vec<SearchPath>,
+}
+
+impl Vfs {
+    /// Creates a new empty search path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a game directory like `FS_AddGameDirectory`, e.g. `baseq3` and then a mod's.
+    ///
+    /// The directory's `*.pk3` files are added in the order of their names ignoring case, so
+    /// later paks override earlier ones, and then its loose files which override every pak.
+    /// Returns the problems of the paks, whose files may be missing.
+    pub fn mount(&mut self, directory: impl AsRef<Path>) -> io::Result<Vec<(PathBuf, Diagnostic)>> {
+        let directory = directory.as_ref();
+        let mut paks = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if is_pak(&path) && path.is_file() {
+                paks.push(path);
+            }
+        }
+        paks.sort_by_key(|path| path.file_name().map(|name| name.to_ascii_lowercase()));
+
+        let mut problems = Vec::new();
+        for path in paks {
+            let diagnostics = self.mount_pak(&path)?;
+            problems.extend(
+                diagnostics
+                    .into_iter()
+                    .map(|diagnostic| (path.clone(), diagnostic)),
+            );
+        }
+
+        let mut files = BTreeMap::new();
+        list_directory(directory, "", &mut files)?;
+        self.search_paths.insert(
+            0,
+            SearchPath::Directory {
+                root: directory.to_owned(),
+                files,
+            },
+        );
+        Ok(problems)
+    }
+
+    /// Adds a single pak above everything mounted before.
+    pub fn mount_pak(&mut self, path: impl AsRef<Path>) -> io::Result<Vec<Diagnostic>> {
+        let path = path.as_ref();
+        let (pk3, diagnostics) = Pk3::open(path)?;
+        self.search_paths.insert(
+            0,
+            SearchPath::Pak {
+                path: path.to_owned(),
+                pk3,
+            },
+        );
+        Ok(diagnostics)
+    }
+
+    /// Get where the file at `path` is read from, `None` if it doesn't exist.
+    pub fn origin(&self, path: &str) -> Option<Origin<'_>> {
+        let key = key(path);
+        self.search_paths
+            .iter()
+            .find(|search_path| search_path.contains(&key))
+            .map(SearchPath::origin)
+    }
+}
+
+impl FileResolver for Vfs {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let key = key(path);
+        for search_path in &self.search_paths {
+            match search_path {
+                SearchPath::Directory { root, files } => {
+                    if let Some(name) = files.get(&key) {
+                        return std::fs::read(root.join(name));
+                    }
+                }
+                SearchPath::Pak { path, pk3 } => {
+                    if let Some(entry) = pk3.entry(&key) {
+                        return pk3.read(File::open(path)?, entry);
+                    }
+                }
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::NotFound, path.to_owned()))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.origin(path).is_some()
+    }
+
+    /// Lists in the order of the search path and then of the directory or pak, without the
+    /// duplicates.
+    fn list(&self, directory: &str, extension: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.search_paths
+            .iter()
+            .flat_map(SearchPath::names)
+            .filter(|name| is_listed(name, directory, extension) && seen.insert(key(name)))
+            .map(|name| name.replace('\\', "/"))
+            .collect()
+    }
+}
+
+/// Normalized game path to look files up by.
+fn key(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_lowercase()
+}
+
+/// Adds the files below `directory` by their lowercase game path.
+/// File is a `*.pk3` archive by its extension.
+fn is_pak(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pk3"))
+}
+
+fn list_directory(
+    directory: &Path,
+    prefix: &str,
+    files: &mut BTreeMap<String, String>,
+) -> io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            list_directory(&entry.path(), &format!("{}/", path), files)?;
+        } else if !(prefix.is_empty() && is_pak(&entry.path())) {
+            // The paks of the game directory are mounted apart
+            files.insert(path.to_lowercase(), path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pk3;
+
+    use super::*;
+
+    #[test]
+    fn priority() {
+        let root = std::env::temp_dir().join(format!("vfs-priority-{}", std::process::id()));
+        let baseq3 = root.join("baseq3");
+        std::fs::create_dir_all(baseq3.join("scripts")).unwrap();
+        let pak = |name: &str, files: &[(&str, &[u8], bool)]| {
+            std::fs::write(baseq3.join(name), pk3::write(files)).unwrap();
+        };
+        pak(
+            "pak0.pk3",
+            &[
+                ("scripts/arenas.txt", b"pak0", true),
+                ("maps/q3dm17.bsp", b"IBSP", false),
+            ],
+        );
+        pak("pak1.pk3", &[("Scripts/Arenas.txt", b"pak1", true)]);
+        pak("Pak10.pk3", &[("scripts/ctf.arena", b"pak10", false)]);
+        std::fs::write(baseq3.join("scripts/CTF.arena"), "loose").unwrap();
+
+        let mut vfs = Vfs::new();
+        let problems = vfs.mount(&baseq3).unwrap();
+        assert_eq!(problems, &[]);
+
+        assert_eq!(vfs.read_to_string("scripts/arenas.txt").unwrap(), "pak1");
+        assert_eq!(
+            vfs.origin("SCRIPTS\\ARENAS.TXT"),
+            Some(Origin::Pak(&baseq3.join("pak1.pk3")))
+        );
+        assert_eq!(
+            vfs.origin("maps/q3dm17.bsp"),
+            Some(Origin::Pak(&baseq3.join("pak0.pk3")))
+        );
+        assert_eq!(vfs.read_to_string("scripts/ctf.arena").unwrap(), "loose");
+        assert_eq!(
+            vfs.origin("scripts/ctf.arena"),
+            Some(Origin::Directory(&baseq3))
+        );
+        assert_eq!(vfs.list("scripts", ".arena"), &["scripts/CTF.arena"]);
+        assert!(!vfs.exists("maps/q3dm1.bsp"));
+        assert!(!vfs.exists("pak0.pk3"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}